@@ -0,0 +1,20 @@
+-- Add up migration script here
+
+-- files (PDF, MusicXML, images) attached to a partition
+-- the file itself is stored on the local filesystem
+-- under <PARTITION_FILES_ROOT>/<partition_id>/<stored_name>
+CREATE TABLE
+    "partition_files" (
+        id SERIAL PRIMARY KEY,
+        partition_id INTEGER NOT NULL REFERENCES partitions (id) ON DELETE CASCADE,
+        file_name VARCHAR(255) NOT NULL,
+        stored_name VARCHAR(255) NOT NULL UNIQUE,
+        mime_type VARCHAR(100) NOT NULL,
+        size_bytes BIGINT NOT NULL,
+        checksum VARCHAR(64) NOT NULL,
+        uploaded_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW()
+    );
+
+CREATE INDEX partition_files_partition_id_idx ON partition_files (partition_id);
@@ -0,0 +1,13 @@
+-- Add down migration script here
+
+DROP TRIGGER IF EXISTS genres_search_document ON genres;
+DROP FUNCTION IF EXISTS genres_search_document_trigger();
+DROP TRIGGER IF EXISTS persons_search_document ON persons;
+DROP FUNCTION IF EXISTS persons_search_document_trigger();
+DROP TRIGGER IF EXISTS partitions_search_document ON partitions;
+DROP FUNCTION IF EXISTS partitions_search_document_trigger();
+DROP INDEX IF EXISTS partitions_search_document_idx;
+ALTER TABLE partitions DROP COLUMN IF EXISTS search_document;
+DROP FUNCTION IF EXISTS partition_search_document(partitions);
+
+DROP EXTENSION IF EXISTS "unaccent";
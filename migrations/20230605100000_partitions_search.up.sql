@@ -0,0 +1,65 @@
+-- Add up migration script here
+
+-- used by the full-text search on partitions :
+-- "Noel" must find "Noël"
+CREATE EXTENSION IF NOT EXISTS "unaccent";
+
+-- the search document of a partition : the title (weight A),
+-- the name of the musician (weight B) and the name of the genre (weight C)
+CREATE OR REPLACE FUNCTION partition_search_document(p partitions) RETURNS tsvector AS
+$$
+SELECT setweight(to_tsvector('simple', unaccent(p.title)), 'A')
+    || setweight(to_tsvector('simple', unaccent(coalesce(
+        (SELECT full_name FROM persons WHERE persons.id = p.person_id), ''))), 'B')
+    || setweight(to_tsvector('simple', unaccent(coalesce(
+        (SELECT name FROM genres WHERE genres.id = p.genre_id), ''))), 'C')
+$$ LANGUAGE sql STABLE;
+
+-- the document is stored in the partitions and indexed,
+-- the triggers below keep it up to date
+ALTER TABLE partitions ADD COLUMN IF NOT EXISTS search_document tsvector;
+
+UPDATE partitions SET search_document = partition_search_document(partitions);
+
+CREATE INDEX IF NOT EXISTS partitions_search_document_idx ON partitions USING gin (search_document);
+
+CREATE OR REPLACE FUNCTION partitions_search_document_trigger() RETURNS trigger AS
+$$
+BEGIN
+    NEW.search_document := partition_search_document(NEW);
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER partitions_search_document
+    BEFORE INSERT OR UPDATE OF title, person_id, genre_id ON partitions
+    FOR EACH ROW EXECUTE FUNCTION partitions_search_document_trigger();
+
+-- renaming a musician or a genre changes the document of its partitions
+CREATE OR REPLACE FUNCTION persons_search_document_trigger() RETURNS trigger AS
+$$
+BEGIN
+    UPDATE partitions SET search_document = partition_search_document(partitions)
+    WHERE person_id = NEW.id;
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER persons_search_document
+    AFTER UPDATE OF full_name ON persons
+    FOR EACH ROW WHEN (OLD.full_name IS DISTINCT FROM NEW.full_name)
+    EXECUTE FUNCTION persons_search_document_trigger();
+
+CREATE OR REPLACE FUNCTION genres_search_document_trigger() RETURNS trigger AS
+$$
+BEGIN
+    UPDATE partitions SET search_document = partition_search_document(partitions)
+    WHERE genre_id = NEW.id;
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER genres_search_document
+    AFTER UPDATE OF name ON genres
+    FOR EACH ROW WHEN (OLD.name IS DISTINCT FROM NEW.name)
+    EXECUTE FUNCTION genres_search_document_trigger();
@@ -0,0 +1,27 @@
+-- Add down migration script here
+DROP TRIGGER IF EXISTS partition_contributors_search_document ON partition_contributors;
+DROP FUNCTION IF EXISTS partition_contributors_search_document_trigger();
+
+-- the search document of the migration "partitions_search"
+CREATE OR REPLACE FUNCTION partition_search_document(p partitions) RETURNS tsvector AS
+$$
+SELECT setweight(to_tsvector('simple', unaccent(p.title)), 'A')
+    || setweight(to_tsvector('simple', unaccent(coalesce(
+        (SELECT full_name FROM persons WHERE persons.id = p.person_id), ''))), 'B')
+    || setweight(to_tsvector('simple', unaccent(coalesce(
+        (SELECT name FROM genres WHERE genres.id = p.genre_id), ''))), 'C')
+$$ LANGUAGE sql STABLE;
+
+CREATE OR REPLACE FUNCTION persons_search_document_trigger() RETURNS trigger AS
+$$
+BEGIN
+    UPDATE partitions SET search_document = partition_search_document(partitions)
+    WHERE person_id = NEW.id;
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TABLE IF EXISTS partition_contributors;
+DROP TYPE IF EXISTS contributor_role;
+
+UPDATE partitions SET search_document = partition_search_document(partitions);
@@ -0,0 +1,67 @@
+-- Add up migration script here
+
+-- several musicians per partition, each one with a role
+CREATE TYPE contributor_role AS ENUM ('composer', 'lyricist', 'arranger', 'editor');
+
+CREATE TABLE IF NOT EXISTS partition_contributors
+(
+    partition_id INT NOT NULL REFERENCES partitions (id) ON DELETE CASCADE,
+    person_id    INT NOT NULL REFERENCES persons (id),
+    role         contributor_role NOT NULL,
+    position     INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (partition_id, person_id, role)
+);
+
+CREATE INDEX IF NOT EXISTS partition_contributors_person_id_idx ON partition_contributors (person_id);
+
+-- the musician of the existing partitions becomes their composer
+INSERT INTO partition_contributors (partition_id, person_id, role, position)
+SELECT id, person_id, 'composer', 0
+FROM partitions
+ON CONFLICT DO NOTHING;
+
+-- the names of all the contributors are in the search document (weight B)
+CREATE OR REPLACE FUNCTION partition_search_document(p partitions) RETURNS tsvector AS
+$$
+SELECT setweight(to_tsvector('simple', unaccent(p.title)), 'A')
+    || setweight(to_tsvector('simple', unaccent(coalesce(
+        (SELECT full_name FROM persons WHERE persons.id = p.person_id), ''))), 'B')
+    || setweight(to_tsvector('simple', unaccent(coalesce(
+        (SELECT string_agg(persons.full_name, ' ')
+         FROM partition_contributors
+         INNER JOIN persons ON partition_contributors.person_id = persons.id
+         WHERE partition_contributors.partition_id = p.id), ''))), 'B')
+    || setweight(to_tsvector('simple', unaccent(coalesce(
+        (SELECT name FROM genres WHERE genres.id = p.genre_id), ''))), 'C')
+$$ LANGUAGE sql STABLE;
+
+UPDATE partitions SET search_document = partition_search_document(partitions);
+
+CREATE OR REPLACE FUNCTION persons_search_document_trigger() RETURNS trigger AS
+$$
+BEGIN
+    UPDATE partitions SET search_document = partition_search_document(partitions)
+    WHERE person_id = NEW.id OR id IN (
+        SELECT partition_id FROM partition_contributors WHERE person_id = NEW.id);
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION partition_contributors_search_document_trigger() RETURNS trigger AS
+$$
+BEGIN
+    IF TG_OP <> 'INSERT' THEN
+        UPDATE partitions SET search_document = partition_search_document(partitions)
+        WHERE id = OLD.partition_id;
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        UPDATE partitions SET search_document = partition_search_document(partitions)
+        WHERE id = NEW.partition_id;
+    END IF;
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER partition_contributors_search_document
+    AFTER INSERT OR UPDATE OR DELETE ON partition_contributors
+    FOR EACH ROW EXECUTE FUNCTION partition_contributors_search_document_trigger();
@@ -0,0 +1,20 @@
+-- Add up migration script here
+
+-- free-form tags of the partitions, in addition to the genre
+CREATE TABLE IF NOT EXISTS tags
+(
+    id   SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL
+);
+
+-- "Gospel" and "gospel" are the same tag
+CREATE UNIQUE INDEX IF NOT EXISTS tags_lower_name_idx ON tags (lower(name));
+
+CREATE TABLE IF NOT EXISTS partition_tags
+(
+    partition_id INT NOT NULL REFERENCES partitions (id) ON DELETE CASCADE,
+    tag_id       INT NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+    PRIMARY KEY (partition_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS partition_tags_tag_id_idx ON partition_tags (tag_id);
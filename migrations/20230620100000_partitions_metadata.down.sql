@@ -0,0 +1,8 @@
+-- Add down migration script here
+ALTER TABLE partitions
+    DROP COLUMN IF EXISTS musical_key,
+    DROP COLUMN IF EXISTS time_signature,
+    DROP COLUMN IF EXISTS tempo,
+    DROP COLUMN IF EXISTS duration_seconds,
+    DROP COLUMN IF EXISTS instrumentation,
+    DROP COLUMN IF EXISTS difficulty;
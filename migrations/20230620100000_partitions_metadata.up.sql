@@ -0,0 +1,11 @@
+-- Add up migration script here
+
+-- musical metadata of the partitions, all optional
+-- the values of the text columns are checked by the enums of models::partition
+ALTER TABLE partitions
+    ADD COLUMN IF NOT EXISTS musical_key      VARCHAR,
+    ADD COLUMN IF NOT EXISTS time_signature   VARCHAR,
+    ADD COLUMN IF NOT EXISTS tempo            VARCHAR,
+    ADD COLUMN IF NOT EXISTS duration_seconds INT CHECK (duration_seconds >= 0),
+    ADD COLUMN IF NOT EXISTS instrumentation  VARCHAR,
+    ADD COLUMN IF NOT EXISTS difficulty       SMALLINT CHECK (difficulty BETWEEN 1 AND 5);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS loans;
+ALTER TABLE partitions DROP COLUMN IF EXISTS copies;
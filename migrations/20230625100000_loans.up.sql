@@ -0,0 +1,22 @@
+-- Add up migration script here
+
+-- number of physical copies of a partition in the library
+ALTER TABLE partitions
+    ADD COLUMN IF NOT EXISTS copies INT NOT NULL DEFAULT 1 CHECK (copies >= 0);
+
+-- loans of the copies of the partitions to the users
+-- a loan is open while returned_at is NULL, the returned loans are the history
+CREATE TABLE IF NOT EXISTS loans
+(
+    id           SERIAL PRIMARY KEY,
+    partition_id INT                      NOT NULL REFERENCES partitions (id) ON DELETE CASCADE,
+    user_id      UUID                     NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    borrowed_at  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    due_date     DATE                     NOT NULL,
+    returned_at  TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS loans_partition_id_idx ON loans (partition_id);
+CREATE INDEX IF NOT EXISTS loans_user_id_idx ON loans (user_id);
+-- the open loans (copies lent, overdue list)
+CREATE INDEX IF NOT EXISTS loans_open_due_date_idx ON loans (due_date) WHERE returned_at IS NULL;
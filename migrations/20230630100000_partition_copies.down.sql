@@ -0,0 +1,12 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS inventory_audit_copies;
+DROP TABLE IF EXISTS inventory_audits;
+
+ALTER TABLE partitions
+    ADD COLUMN IF NOT EXISTS copies INT NOT NULL DEFAULT 1 CHECK (copies >= 0);
+UPDATE partitions p
+SET copies = (SELECT COUNT(*) FROM partition_copies c WHERE c.partition_id = p.id AND c.condition <> 'missing');
+
+ALTER TABLE loans DROP COLUMN IF EXISTS copy_id;
+DROP TABLE IF EXISTS partition_copies;
+DROP TYPE IF EXISTS copy_condition;
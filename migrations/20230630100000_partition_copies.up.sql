@@ -0,0 +1,66 @@
+-- Add up migration script here
+
+CREATE TYPE copy_condition AS ENUM ('good', 'damaged', 'missing');
+
+-- the printed copies of the partitions
+-- the id is the inventory number written on the copy
+CREATE TABLE IF NOT EXISTS partition_copies
+(
+    id            SERIAL PRIMARY KEY,
+    partition_id  INT            NOT NULL REFERENCES partitions (id) ON DELETE CASCADE,
+    location      VARCHAR        NOT NULL DEFAULT '',
+    condition     copy_condition NOT NULL DEFAULT 'good',
+    purchase_date DATE
+);
+
+CREATE INDEX IF NOT EXISTS partition_copies_partition_id_idx ON partition_copies (partition_id);
+
+-- the number of copies of the partitions becomes one row per copy
+INSERT INTO partition_copies (partition_id)
+SELECT p.id
+FROM partitions p
+         CROSS JOIN generate_series(1, p.copies)
+ORDER BY p.id;
+
+-- a loan is the loan of a copy
+-- the open loans are given to the copies of their partition
+ALTER TABLE loans
+    ADD COLUMN IF NOT EXISTS copy_id INT REFERENCES partition_copies (id) ON DELETE SET NULL;
+
+UPDATE loans
+SET copy_id = c.id
+FROM (SELECT id, partition_id, row_number() OVER (PARTITION BY partition_id ORDER BY borrowed_at, id) AS rank
+      FROM loans
+      WHERE returned_at IS NULL) o
+         INNER JOIN (SELECT id, partition_id, row_number() OVER (PARTITION BY partition_id ORDER BY id) AS rank
+                     FROM partition_copies) c
+                    ON c.partition_id = o.partition_id AND c.rank = o.rank
+WHERE loans.id = o.id;
+
+CREATE INDEX IF NOT EXISTS loans_open_copy_id_idx ON loans (copy_id) WHERE returned_at IS NULL;
+
+ALTER TABLE partitions
+    DROP COLUMN IF EXISTS copies;
+
+-- inventory audits : the copies seen on the shelves by the librarian
+-- only one audit can be in progress (closed_at IS NULL)
+CREATE TABLE IF NOT EXISTS inventory_audits
+(
+    id         SERIAL PRIMARY KEY,
+    started_by UUID                     REFERENCES users (id) ON DELETE SET NULL,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    closed_at  TIMESTAMP WITH TIME ZONE,
+    -- number of copies when the audit was closed
+    total      BIGINT
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS inventory_audits_in_progress_idx
+    ON inventory_audits ((closed_at IS NULL)) WHERE closed_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS inventory_audit_copies
+(
+    audit_id INT                      NOT NULL REFERENCES inventory_audits (id) ON DELETE CASCADE,
+    copy_id  INT                      NOT NULL REFERENCES partition_copies (id) ON DELETE CASCADE,
+    seen_at  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (audit_id, copy_id)
+);
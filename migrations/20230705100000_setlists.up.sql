@@ -0,0 +1,26 @@
+-- Add up migration script here
+
+-- a setlist : the pieces of a concert (or a rehearsal) in the order they are played
+CREATE TABLE IF NOT EXISTS setlists
+(
+    id               SERIAL PRIMARY KEY,
+    name             VARCHAR NOT NULL,
+    performance_date DATE    NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS setlists_performance_date_idx ON setlists (performance_date);
+
+-- the pieces of a setlist, position starts at 1
+-- the unique constraint is checked at the end of the transaction
+-- so that the positions can be shifted when an item is moved
+CREATE TABLE IF NOT EXISTS setlist_items
+(
+    id           SERIAL PRIMARY KEY,
+    setlist_id   INT     NOT NULL REFERENCES setlists (id) ON DELETE CASCADE,
+    partition_id INT     NOT NULL REFERENCES partitions (id) ON DELETE CASCADE,
+    position     INT     NOT NULL CHECK (position >= 1),
+    note         VARCHAR NOT NULL DEFAULT '',
+    CONSTRAINT setlist_items_position_key UNIQUE (setlist_id, position) DEFERRABLE INITIALLY DEFERRED
+);
+
+CREATE INDEX IF NOT EXISTS setlist_items_partition_id_idx ON setlist_items (partition_id);
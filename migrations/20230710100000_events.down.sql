@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS calendar_tokens;
+DROP TABLE IF EXISTS events;
+DROP TYPE IF EXISTS event_kind;
@@ -0,0 +1,28 @@
+-- Add up migration script here
+
+CREATE TYPE event_kind AS ENUM ('rehearsal', 'concert');
+
+-- rehearsals and concerts of the ensemble
+-- starts_at is the local time of the ensemble (no time zone)
+CREATE TABLE IF NOT EXISTS events
+(
+    id               SERIAL PRIMARY KEY,
+    kind             event_kind NOT NULL DEFAULT 'rehearsal',
+    title            VARCHAR    NOT NULL DEFAULT '',
+    starts_at        TIMESTAMP  NOT NULL,
+    duration_minutes INT        NOT NULL DEFAULT 120 CHECK (duration_minutes > 0),
+    place            VARCHAR    NOT NULL DEFAULT '',
+    setlist_id       INT REFERENCES setlists (id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS events_starts_at_idx ON events (starts_at);
+
+-- secret token of the iCalendar feed of a user (/calendar/<token>.ics)
+-- 64 hexadecimal characters from two random uuids
+CREATE TABLE IF NOT EXISTS calendar_tokens
+(
+    user_id    UUID PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    token      VARCHAR                  NOT NULL UNIQUE
+        DEFAULT replace(uuid_generate_v4()::TEXT || uuid_generate_v4()::TEXT, '-', ''),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS collection_items;
+DROP TABLE IF EXISTS collections;
+DROP TABLE IF EXISTS favourites;
@@ -0,0 +1,26 @@
+-- Add up migration script here
+-- the partitions starred by each user
+CREATE TABLE IF NOT EXISTS favourites (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    partition_id INTEGER NOT NULL REFERENCES partitions(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, partition_id)
+);
+
+-- the named personal collections of partitions of each user
+-- a shared collection can be read (not modified) by the other users
+CREATE TABLE IF NOT EXISTS collections (
+    id SERIAL PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    shared BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS collection_items (
+    collection_id INTEGER NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+    partition_id INTEGER NOT NULL REFERENCES partitions(id) ON DELETE CASCADE,
+    added_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (collection_id, partition_id)
+);
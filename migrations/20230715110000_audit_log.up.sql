@@ -0,0 +1,20 @@
+-- Add up migration script here
+CREATE TYPE audit_action AS ENUM ('create', 'update', 'delete', 'import');
+CREATE TYPE audit_entity AS ENUM ('partition', 'person', 'genre');
+
+-- the changes of the catalogue : who, what, when, the record before and after
+-- the name of the user is kept when the user is deleted
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    actor_name VARCHAR(100) NOT NULL,
+    action audit_action NOT NULL,
+    entity audit_entity NOT NULL,
+    entity_id INTEGER,
+    before JSONB,
+    after JSONB,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_created_at_idx ON audit_log (created_at);
+CREATE INDEX IF NOT EXISTS audit_log_entity_idx ON audit_log (entity, entity_id);
@@ -0,0 +1,22 @@
+-- Add down migration script here
+-- (the values 'restore' and 'purge' of audit_action can't be removed from the enum)
+
+-- without deleted_at the musicians and genres in the trash would come back
+-- as live data : they must be purged from the trash before going back
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM persons WHERE deleted_at IS NOT NULL)
+        OR EXISTS (SELECT 1 FROM genres WHERE deleted_at IS NOT NULL) THEN
+        RAISE EXCEPTION 'musicians or genres are in the trash : purge them before reverting the soft delete';
+    END IF;
+END
+$$;
+
+DROP INDEX IF EXISTS genres_deleted_at_idx;
+DROP INDEX IF EXISTS persons_deleted_at_idx;
+DROP INDEX IF EXISTS partitions_deleted_at_idx;
+
+DELETE FROM partitions WHERE deleted_at IS NOT NULL;
+ALTER TABLE partitions DROP COLUMN IF EXISTS deleted_at;
+ALTER TABLE persons DROP COLUMN IF EXISTS deleted_at;
+ALTER TABLE genres DROP COLUMN IF EXISTS deleted_at;
@@ -0,0 +1,19 @@
+-- Add up migration script here
+
+-- soft deletion : a deleted partition, musician or genre goes to the trash
+-- (deleted_at is set), it can be restored or purged (really deleted) later
+ALTER TABLE partitions
+    ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE persons
+    ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE genres
+    ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+
+-- the trash page and the automatic purge
+CREATE INDEX IF NOT EXISTS partitions_deleted_at_idx ON partitions (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS persons_deleted_at_idx ON persons (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS genres_deleted_at_idx ON genres (deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- the restorations and the purges are recorded in the audit log
+ALTER TYPE audit_action ADD VALUE IF NOT EXISTS 'restore';
+ALTER TYPE audit_action ADD VALUE IF NOT EXISTS 'purge';
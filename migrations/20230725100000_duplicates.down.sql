@@ -0,0 +1,6 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS genres_name_trgm_idx;
+DROP INDEX IF EXISTS persons_full_name_trgm_idx;
+DROP INDEX IF EXISTS partitions_title_trgm_idx;
+
+DROP EXTENSION IF EXISTS "pg_trgm";
@@ -0,0 +1,10 @@
+-- Add up migration script here
+
+-- duplicate detection : similarity of the titles and names by trigrams
+-- ("Brel, Jacques" and "Jacques Brel", "Ne me quitte pas" and "Ne me quittes pas")
+CREATE EXTENSION IF NOT EXISTS "pg_trgm";
+
+-- the % operator of pg_trgm uses these indexes
+CREATE INDEX IF NOT EXISTS partitions_title_trgm_idx ON partitions USING gin (title gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS persons_full_name_trgm_idx ON persons USING gin (full_name gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS genres_name_trgm_idx ON genres USING gin (name gin_trgm_ops);
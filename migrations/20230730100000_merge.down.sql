@@ -0,0 +1,2 @@
+-- Add down migration script here
+-- (the value 'merge' of audit_action can't be removed from the enum)
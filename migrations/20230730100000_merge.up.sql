@@ -0,0 +1,4 @@
+-- Add up migration script here
+
+-- the merges of two musicians or two genres are recorded in the audit log
+ALTER TYPE audit_action ADD VALUE IF NOT EXISTS 'merge';
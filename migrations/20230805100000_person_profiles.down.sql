@@ -0,0 +1,9 @@
+-- Add down migration script here
+ALTER TABLE persons DROP CONSTRAINT IF EXISTS persons_years_check;
+
+ALTER TABLE persons
+    DROP COLUMN IF EXISTS alternative_names,
+    DROP COLUMN IF EXISTS biography,
+    DROP COLUMN IF EXISTS nationality,
+    DROP COLUMN IF EXISTS death_year,
+    DROP COLUMN IF EXISTS birth_year;
@@ -0,0 +1,13 @@
+-- Add up migration script here
+
+-- profile of a musician : years of birth and death, nationality, a short biography
+-- and the other spellings of the name ("Brel, Jacques", "J. Brel")
+ALTER TABLE persons
+    ADD COLUMN IF NOT EXISTS birth_year INT,
+    ADD COLUMN IF NOT EXISTS death_year INT,
+    ADD COLUMN IF NOT EXISTS nationality VARCHAR NOT NULL DEFAULT '',
+    ADD COLUMN IF NOT EXISTS biography TEXT NOT NULL DEFAULT '',
+    ADD COLUMN IF NOT EXISTS alternative_names TEXT[] NOT NULL DEFAULT '{}';
+
+ALTER TABLE persons
+    ADD CONSTRAINT persons_years_check CHECK (death_year IS NULL OR birth_year IS NULL OR death_year >= birth_year);
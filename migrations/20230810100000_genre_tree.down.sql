@@ -0,0 +1,5 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS genres_parent_id_idx;
+
+ALTER TABLE genres DROP CONSTRAINT IF EXISTS genres_parent_check;
+ALTER TABLE genres DROP COLUMN IF EXISTS parent_id;
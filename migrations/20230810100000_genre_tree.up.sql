@@ -0,0 +1,11 @@
+-- Add up migration script here
+
+-- sub-genres : "Classique > Baroque > Cantate"
+-- a sub-genre whose parent is purged becomes a main genre
+ALTER TABLE genres
+    ADD COLUMN IF NOT EXISTS parent_id INT REFERENCES genres (id) ON DELETE SET NULL;
+
+ALTER TABLE genres
+    ADD CONSTRAINT genres_parent_check CHECK (parent_id <> id);
+
+CREATE INDEX IF NOT EXISTS genres_parent_id_idx ON genres (parent_id);
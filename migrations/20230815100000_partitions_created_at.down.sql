@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS partitions_created_at_idx;
+
+ALTER TABLE partitions DROP COLUMN IF EXISTS created_at;
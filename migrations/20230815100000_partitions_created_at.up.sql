@@ -0,0 +1,17 @@
+-- Add up migration script here
+
+-- the date the partition was added to the catalogue (statistics per month)
+ALTER TABLE partitions
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW();
+
+-- the existing partitions : the first change recorded in the audit log,
+-- the partitions added before the audit log keep the date of this migration
+UPDATE partitions
+SET created_at = a.first_change
+FROM (SELECT entity_id, MIN(created_at) AS first_change
+      FROM audit_log
+      WHERE entity = 'partition'
+      GROUP BY entity_id) a
+WHERE partitions.id = a.entity_id;
+
+CREATE INDEX IF NOT EXISTS partitions_created_at_idx ON partitions (created_at);
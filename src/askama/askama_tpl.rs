@@ -1,8 +1,27 @@
 //! src/askama.rs
 
-use crate::models::genre::Genre;
-use crate::models::musician::Person;
-use crate::models::partition::ShowPartition;
+use crate::models::audit::{AuditAction, AuditEntity, AuditEntry, AuditFilter, FieldChange};
+use crate::models::collection::{Collection, CollectionPartition};
+use crate::models::copy::{AuditCopy, CopyCondition, InventoryAudit, PartitionCopy};
+use crate::models::deletion::DeletionImpact;
+use crate::models::duplicate::{DuplicateCluster, DuplicateMatch};
+use crate::models::event::{CalendarMonth, Event, EventKind};
+use crate::models::facet::Facet;
+use crate::models::genre::{Genre, GenreNode};
+use crate::models::loan::{Loan, LoanFilter, PartitionAvailability};
+use crate::models::musician::{GenreWorks, Person, PersonProfile};
+use crate::models::pagination::Page;
+use crate::models::partition::{
+    ContributorRole, Difficulty, Instrumentation, MusicalKey, MusicalMetadata, ShowPartition,
+    Tempo, TimeSignature,
+};
+use crate::models::partition_file::PartitionFile;
+use crate::models::partition_import::{ImportOptions, ImportReport};
+use crate::models::revision::PartitionRevision;
+use crate::models::setlist::{PartitionChoice, Setlist, SetlistItem};
+use crate::models::statistics::{CatalogueTotals, Statistics};
+use crate::models::tag::Tag;
+use crate::models::trash::TrashItem;
 use crate::models::user::FilteredUser;
 use askama::Template;
 use axum::http::Uri;
@@ -55,24 +74,35 @@ pub struct SignupTemplate {
 #[template(path = "list_persons.html")]
 pub struct ListPersonsTemplate {
     pub list_persons: Vec<Person>,
+    pub page: Page,
 }
 
 #[derive(Template)] // this will generate the code...
 #[template(path = "list_genres.html")]
 pub struct ListGenresTemplate {
     pub list_genres: Vec<Genre>,
+    pub page: Page,
+}
+
+#[derive(Template)] // this will generate the code...
+#[template(path = "list_tags.html")]
+pub struct ListTagsTemplate {
+    pub list_tags: Vec<Tag>,
+    pub page: Page,
 }
 /// # ListPartitionsTemplate
 /// Askama Template to list partitions in text form
 /// arguments :
 /// * title : String
 /// * list_partitions : Vector of ShowPartition
+/// * page : the pagination of the list
 ///
 #[derive(Template)] // this will generate the code...
 #[template(path = "list_partitions.html")]
 pub struct ListPartitionsTemplate {
     pub title: String,
     pub list_partitions: Vec<ShowPartition>,
+    pub page: Page,
 }
 
 #[derive(Template)] // this will generate the code...
@@ -100,6 +130,22 @@ pub struct HandlePersonsTemplate {
     pub title: String,
     pub flash: Option<String>,
     pub persons: Vec<Person>,
+    pub page: Page,
+}
+
+///
+/// # PersonTemplate
+/// Profile of a musician and its partitions grouped by genre
+/// * total : the number of partitions
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "person.html")]
+pub struct PersonTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub profile: PersonProfile,
+    pub works: Vec<GenreWorks>,
+    pub total: usize,
 }
 
 #[derive(Template)] // this will generate the code...
@@ -108,6 +154,8 @@ pub struct HandleGenresTemplate {
     pub title: String,
     pub flash: Option<String>,
     pub genres: Vec<Genre>,
+    pub page: Page,
+    pub tree: Vec<GenreNode>,
 }
 
 #[derive(Template)] // this will generate the code...
@@ -118,6 +166,355 @@ pub struct HandlePartitionsTemplate {
     pub partitions: Vec<ShowPartition>,
     pub persons: Vec<Person>,
     pub genres: Vec<Genre>,
+    pub tags: Vec<Tag>,
+    pub files: Vec<PartitionFile>,
+    pub copies: Vec<PartitionAvailability>,
+    pub favourites: Vec<i32>,
+    pub collections: Vec<Collection>,
+    pub page: Page,
+}
+
+#[derive(Template)] // this will generate the code...
+#[template(path = "tags.html")]
+pub struct HandleTagsTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub tags: Vec<Tag>,
+    pub page: Page,
+}
+
+///
+/// # ImportPartitionsTemplate
+/// Page of the CSV import of partitions
+/// * report : the dry-run of the uploaded file (None before the upload)
+/// * csv, options : the uploaded file and its options, sent again with the confirmation
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "import_partitions.html")]
+pub struct ImportPartitionsTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub report: Option<ImportReport>,
+    pub csv: String,
+    pub options: ImportOptions,
+}
+
+///
+/// # HandleLoansTemplate
+/// Page of the loans : the loans matching the filter (one page of the list)
+/// * users : the borrowers proposed in the filter
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "loans.html")]
+pub struct HandleLoansTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub loans: Vec<Loan>,
+    pub users: Vec<FilteredUser>,
+    pub filter: LoanFilter,
+    pub page: Page,
+}
+
+///
+/// # AuditTemplate
+/// Page of the audit log (administrators) : the changes matching the filter
+/// (one page of the list)
+/// * users : the users proposed in the filter
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "audit.html")]
+pub struct AuditTemplate {
+    pub title: String,
+    pub entries: Vec<AuditEntry>,
+    pub users: Vec<FilteredUser>,
+    pub filter: AuditFilter,
+    pub page: Page,
+}
+
+///
+/// # TrashTemplate
+/// Page of the trash (administrators) : the deleted partitions, musicians and genres
+/// * retention_days : the records are purged automatically after that number of days
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "trash.html")]
+pub struct TrashTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub items: Vec<TrashItem>,
+    pub retention_days: i64,
+}
+
+///
+/// # DuplicatesTemplate
+/// Report of the suspected duplicates (administrators) : the clusters of partitions,
+/// musicians and genres whose titles or names are close
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "duplicates.html")]
+pub struct DuplicatesTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub clusters: Vec<DuplicateCluster>,
+}
+
+///
+/// # DuplicatesConfirmTemplate
+/// Confirmation step before adding a partition or a musician close to existing ones
+/// * name : the title or the name being added
+/// * matches : the close records of the catalogue
+/// * action, fields : the form posted again (with confirm=true) to add it anyway
+/// * cancel : the page to go back to
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "duplicates_confirm.html")]
+pub struct DuplicatesConfirmTemplate {
+    pub title: String,
+    pub name: String,
+    pub matches: Vec<DuplicateMatch>,
+    pub action: String,
+    pub fields: Vec<(String, String)>,
+    pub cancel: String,
+}
+
+///
+/// # DeletionTemplate
+/// Confirmation of the deletion of a musician or a genre : what would be affected
+/// and the policy for its partitions
+/// * choices : the other musicians or genres the partitions can be reassigned to
+/// * is_admin : only the administrators can reassign the partitions or put them in the trash
+/// * action : the deletion route, cancel : the page to go back to
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "deletion.html")]
+pub struct DeletionTemplate {
+    pub title: String,
+    pub impact: DeletionImpact,
+    pub choices: Vec<(i32, String)>,
+    pub is_admin: bool,
+    pub action: String,
+    pub cancel: String,
+}
+
+///
+/// # BrowsePartitionsTemplate
+/// Browse page of the partitions : the facets in the sidebar and the results
+/// * q, hidden : the search box and the other criteria kept when searching
+/// * criteria : the criteria in words (empty without criteria)
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "browse_partitions.html")]
+pub struct BrowsePartitionsTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub q: String,
+    pub hidden: Vec<(&'static str, String)>,
+    pub criteria: String,
+    pub facets: Vec<Facet>,
+    pub partitions: Vec<ShowPartition>,
+    pub page: Page,
+}
+
+///
+/// # PartitionHistoryTemplate
+/// Revision history of a partition : its versions (the last one first)
+/// * from, to : the numbers of the versions compared (0 if there is less than two versions)
+/// * changes : the fields that differ between these versions
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "partition_history.html")]
+pub struct PartitionHistoryTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub partition_id: i32,
+    pub partition_title: String,
+    pub revisions: Vec<PartitionRevision>,
+    pub from: usize,
+    pub to: usize,
+    pub changes: Vec<FieldChange>,
+}
+
+///
+/// # PartitionLoansTemplate
+/// Loans of a partition : copies, form to lend a copy and history
+/// * current_user_id : the borrower selected by default in the form
+/// * due_date : the due date proposed in the form ("2023-07-14")
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "partition_loans.html")]
+pub struct PartitionLoansTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub availability: PartitionAvailability,
+    pub loans: Vec<Loan>,
+    pub users: Vec<FilteredUser>,
+    pub current_user_id: String,
+    pub due_date: String,
+    pub page: Page,
+}
+
+#[derive(Template)] // this will generate the code...
+#[template(path = "overdue_loans.html")]
+pub struct OverdueLoansTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub loans: Vec<Loan>,
+}
+
+///
+/// # PartitionCopiesTemplate
+/// Copies of a partition : location, condition and purchase date of each copy
+/// * conditions : the conditions proposed in the forms
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "partition_copies.html")]
+pub struct PartitionCopiesTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub availability: PartitionAvailability,
+    pub copies: Vec<PartitionCopy>,
+    pub conditions: Vec<CopyCondition>,
+}
+
+///
+/// # InventoryTemplate
+/// Page of the inventory audit
+/// * audit : the audit in progress (None : the button to start an audit is shown)
+/// * copies : the copies with their state in the audit in progress, by location
+/// * audits : the previous audits
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "inventory.html")]
+pub struct InventoryTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub audit: Option<InventoryAudit>,
+    pub copies: Vec<AuditCopy>,
+    pub audits: Vec<InventoryAudit>,
+}
+
+///
+/// # InventoryReportTemplate
+/// Report of an audit : the copies that were not found
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "inventory_report.html")]
+pub struct InventoryReportTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub audit: InventoryAudit,
+    pub copies: Vec<AuditCopy>,
+}
+
+///
+/// # HandleSetlistsTemplate
+/// Page of the setlists : the form to create a setlist
+/// and one page of the list of setlists
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "setlists.html")]
+pub struct HandleSetlistsTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub setlists: Vec<Setlist>,
+    pub page: Page,
+}
+
+///
+/// # SetlistTemplate
+/// Page of a setlist : the pieces in order, the forms to move, annotate or remove them
+/// * partitions : the partitions proposed in the form to add a piece
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "setlist.html")]
+pub struct SetlistTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub setlist: Setlist,
+    pub items: Vec<SetlistItem>,
+    pub partitions: Vec<PartitionChoice>,
+}
+
+///
+/// # SetlistProgrammeTemplate
+/// Printable programme of a concert : the pieces with their composer and duration
+/// * ensemble : the name of the ensemble (ENSEMBLE_NAME)
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "setlist_programme.html")]
+pub struct SetlistProgrammeTemplate {
+    pub title: String,
+    pub ensemble: String,
+    pub setlist: Setlist,
+    pub items: Vec<SetlistItem>,
+}
+
+///
+/// # CalendarTemplate
+/// Monthly calendar of the events
+/// * events : the events of the month, in order
+/// * setlists : the setlists proposed in the form to add an event
+/// * date, duration : the values proposed in the form
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "calendar.html")]
+pub struct CalendarTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub month: CalendarMonth,
+    pub events: Vec<Event>,
+    pub setlists: Vec<Setlist>,
+    pub date: String,
+    pub duration: i32,
+}
+
+///
+/// # EventTemplate
+/// Page of an event with the form to modify it
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "event.html")]
+pub struct EventTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub event: Event,
+    pub setlists: Vec<Setlist>,
+}
+
+///
+/// # MeTemplate
+/// Page of the logged user : profile, loans (open ones first), favourites and collections
+/// * calendar_url : the address of the iCalendar feed of the user (webcal://...)
+/// * shared_collections : the collections shared by the other users
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "me.html")]
+pub struct MeTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub user: FilteredUser,
+    pub loans: Vec<Loan>,
+    pub calendar_url: String,
+    pub favourites: Vec<CollectionPartition>,
+    pub collections: Vec<Collection>,
+    pub shared_collections: Vec<Collection>,
+}
+
+///
+/// # CollectionTemplate
+/// Page of a collection : its partitions, read-only for the other users
+/// * is_owner : the logged user can modify the collection
+/// * partitions_choices : the partitions proposed in the form to add one (owner only)
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "collection.html")]
+pub struct CollectionTemplate {
+    pub title: String,
+    pub flash: Option<String>,
+    pub collection: Collection,
+    pub partitions: Vec<CollectionPartition>,
+    pub is_owner: bool,
+    pub partitions_choices: Vec<PartitionChoice>,
 }
 
 //*************************************************************************
@@ -134,6 +531,18 @@ pub struct StartTemplate {
 pub struct WelcomeTemplate {
     pub title: String,
     pub flash: Option<String>,
+    pub totals: CatalogueTotals,
+}
+
+///
+/// # StatisticsTemplate
+/// Statistics of the catalogue : the totals and the bar charts (SVG)
+///
+#[derive(Template)] // this will generate the code...
+#[template(path = "statistics.html")]
+pub struct StatisticsTemplate {
+    pub title: String,
+    pub statistics: Statistics,
 }
 
 //**************************************************************************
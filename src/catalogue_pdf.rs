@@ -0,0 +1,733 @@
+//! src/catalogue_pdf.rs
+//!
+//! Catalogue of the partitions in PDF (A4) generated on the server :
+//! grouped by genre or by composer, with a table of contents, a header
+//! with the name of the ensemble and the page numbers in the footer
+//!
+//! Only the standard PDF fonts are used (Helvetica), no font file is needed :
+//! the text is written in WinAnsi encoding (the French accents are supported),
+//! a character out of this encoding is replaced by "?"
+//!
+//! The pages are laid out first, then written : the table of contents
+//! and the footers ("Page 3 / 12") need the page numbers of the whole document
+//!
+
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use serde::Deserialize;
+
+use crate::models::partition::ShowPartition;
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+/// top of the text under the header
+const BODY_TOP: f32 = 772.0;
+/// bottom of the text above the footer
+const BODY_BOTTOM: f32 = 60.0;
+
+const ROW_HEIGHT: f32 = 14.0;
+const CONTRIBUTORS_HEIGHT: f32 = 10.0;
+const GROUP_HEADING_HEIGHT: f32 = 34.0;
+const TOC_ROW_HEIGHT: f32 = 16.0;
+
+/// x and width of the columns : title, composer or genre, musical information
+const COLUMNS: [(f32, f32); 3] = [(MARGIN, 230.0), (290.0, 130.0), (428.0, 117.0)];
+
+///
+/// Query string of the PDF catalogue
+/// * group : "genre" (default) or "composer"
+///
+/// the criteria of the search (PartitionFilter) are in the same query string
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CatalogueParams {
+    pub group: Option<String>,
+}
+
+impl CatalogueParams {
+    pub fn grouping(&self) -> CatalogueGrouping {
+        match self.group.as_deref() {
+            Some("composer") => CatalogueGrouping::Composer,
+            _ => CatalogueGrouping::Genre,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatalogueGrouping {
+    Genre,
+    Composer,
+}
+
+impl CatalogueGrouping {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CatalogueGrouping::Genre => "genre",
+            CatalogueGrouping::Composer => "compositeur",
+        }
+    }
+
+    /// name of the group of a partition
+    fn key<'a>(&self, partition: &'a ShowPartition) -> &'a str {
+        match self {
+            CatalogueGrouping::Genre => &partition.name,
+            CatalogueGrouping::Composer => &partition.full_name,
+        }
+    }
+
+    /// the second column : the composer in a genre, the genre for a composer
+    fn other<'a>(&self, partition: &'a ShowPartition) -> &'a str {
+        match self {
+            CatalogueGrouping::Genre => &partition.full_name,
+            CatalogueGrouping::Composer => &partition.name,
+        }
+    }
+
+    fn other_label(&self) -> &'static str {
+        match self {
+            CatalogueGrouping::Genre => "Compositeur",
+            CatalogueGrouping::Composer => "Genre",
+        }
+    }
+}
+
+///
+/// A chapter of the catalogue : a genre or a composer with its partitions
+///
+#[derive(Debug, Clone)]
+pub struct CatalogueGroup {
+    pub name: String,
+    pub partitions: Vec<ShowPartition>,
+}
+
+///
+/// # Catalogue
+/// * ensemble : the name of the ensemble (header of the pages)
+/// * title : the title of the catalogue
+/// * criteria : the criteria of the search in words (empty for the whole catalogue)
+///
+#[derive(Debug, Clone)]
+pub struct Catalogue {
+    pub ensemble: String,
+    pub title: String,
+    pub criteria: String,
+    pub grouping: CatalogueGrouping,
+    pub groups: Vec<CatalogueGroup>,
+}
+
+impl Catalogue {
+    ///
+    /// Builds the catalogue from partitions ordered by title :
+    /// the groups are ordered by name, the partitions keep their order
+    ///
+    pub fn new(
+        ensemble: String,
+        criteria: String,
+        grouping: CatalogueGrouping,
+        partitions: Vec<ShowPartition>,
+    ) -> Self {
+        let mut groups: Vec<CatalogueGroup> = Vec::new();
+        for partition in partitions {
+            let key = grouping.key(&partition).to_string();
+            match groups.iter_mut().find(|group| group.name == key) {
+                Some(group) => group.partitions.push(partition),
+                None => groups.push(CatalogueGroup {
+                    name: key,
+                    partitions: vec![partition],
+                }),
+            }
+        }
+        groups.sort_by_key(|group| group.name.to_lowercase());
+
+        Self {
+            ensemble,
+            title: format!("Catalogue des partitions par {}", grouping.label()),
+            criteria,
+            grouping,
+            groups,
+        }
+    }
+
+    fn partitions_count(&self) -> usize {
+        self.groups.iter().map(|group| group.partitions.len()).sum()
+    }
+}
+
+//*******************************************************************************
+// Layout of the pages
+//
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Font {
+    Regular,
+    Bold,
+    Italic,
+}
+
+impl Font {
+    const ALL: [Font; 3] = [Font::Regular, Font::Bold, Font::Italic];
+
+    fn resource_name(&self) -> Name<'static> {
+        match self {
+            Font::Regular => Name(b"F1"),
+            Font::Bold => Name(b"F2"),
+            Font::Italic => Name(b"F3"),
+        }
+    }
+
+    fn base_font(&self) -> Name<'static> {
+        match self {
+            Font::Regular => Name(b"Helvetica"),
+            Font::Bold => Name(b"Helvetica-Bold"),
+            Font::Italic => Name(b"Helvetica-Oblique"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TextItem {
+    x: f32,
+    y: f32,
+    size: f32,
+    font: Font,
+    gray: f32,
+    text: String,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PageLayout {
+    texts: Vec<TextItem>,
+    /// horizontal lines : (x1, x2, y)
+    lines: Vec<(f32, f32, f32)>,
+}
+
+impl PageLayout {
+    fn text(&mut self, x: f32, y: f32, size: f32, font: Font, text: &str) {
+        self.texts.push(TextItem {
+            x,
+            y,
+            size,
+            font,
+            gray: 0.0,
+            text: text.to_string(),
+        });
+    }
+
+    /// a text cut with "…" to fit in the width
+    fn text_fit(&mut self, x: f32, y: f32, size: f32, font: Font, text: &str, width: f32) {
+        self.text(x, y, size, font, &fit_text(text, font, size, width));
+    }
+
+    fn text_right(&mut self, right: f32, y: f32, size: f32, font: Font, text: &str) {
+        let x = right - text_width(text, font, size);
+        self.text(x, y, size, font, text);
+    }
+
+    fn gray_text(&mut self, x: f32, y: f32, size: f32, font: Font, text: &str) {
+        self.text(x, y, size, font, text);
+        if let Some(item) = self.texts.last_mut() {
+            item.gray = 0.45;
+        }
+    }
+}
+
+///
+/// Lays out the chapters from the page `first_page` (index in the document)
+/// returns the pages and the index of the first page of each group
+///
+fn layout_groups(catalogue: &Catalogue, first_page: usize) -> (Vec<PageLayout>, Vec<usize>) {
+    let grouping = catalogue.grouping;
+    let mut pages = vec![PageLayout::default()];
+    let mut group_pages = Vec::new();
+    let mut y = BODY_TOP;
+
+    for group in &catalogue.groups {
+        // the heading stays with at least 3 partitions
+        let first_rows = group.partitions.len().min(3) as f32 * ROW_HEIGHT;
+        if y - GROUP_HEADING_HEIGHT - first_rows < BODY_BOTTOM && y < BODY_TOP {
+            pages.push(PageLayout::default());
+            y = BODY_TOP;
+        }
+        group_pages.push(first_page + pages.len() - 1);
+        let heading = format!("{} ({})", group.name, group.partitions.len());
+        y = group_heading(pages.last_mut().unwrap(), y, &heading, grouping);
+
+        for partition in &group.partitions {
+            let contributors = contributors_text(partition);
+            let height = ROW_HEIGHT
+                + if contributors.is_empty() {
+                    0.0
+                } else {
+                    CONTRIBUTORS_HEIGHT
+                };
+            if y - height < BODY_BOTTOM {
+                pages.push(PageLayout::default());
+                let heading = format!("{} (suite)", group.name);
+                y = group_heading(pages.last_mut().unwrap(), BODY_TOP, &heading, grouping);
+            }
+            let page = pages.last_mut().unwrap();
+            let [title, other, infos] = COLUMNS;
+            page.text_fit(title.0, y, 10.0, Font::Regular, &partition.title, title.1);
+            page.text_fit(
+                other.0,
+                y,
+                9.0,
+                Font::Regular,
+                grouping.other(partition),
+                other.1,
+            );
+            page.text_fit(
+                infos.0,
+                y,
+                8.0,
+                Font::Italic,
+                &partition.metadata.summary(),
+                infos.1,
+            );
+            if !contributors.is_empty() {
+                page.text_fit(
+                    title.0 + 8.0,
+                    y - CONTRIBUTORS_HEIGHT,
+                    7.5,
+                    Font::Italic,
+                    &contributors,
+                    title.1 + other.1,
+                );
+            }
+            y -= height;
+        }
+        y -= ROW_HEIGHT;
+    }
+    (pages, group_pages)
+}
+
+///
+/// Writes the heading of a group and the names of the columns
+/// returns the y of the first row
+///
+fn group_heading(page: &mut PageLayout, y: f32, heading: &str, grouping: CatalogueGrouping) -> f32 {
+    let y = y - 4.0;
+    page.text_fit(
+        MARGIN,
+        y,
+        13.0,
+        Font::Bold,
+        heading,
+        PAGE_WIDTH - 2.0 * MARGIN,
+    );
+    page.lines.push((MARGIN, PAGE_WIDTH - MARGIN, y - 5.0));
+    let y = y - 17.0;
+    let [title, other, infos] = COLUMNS;
+    page.gray_text(title.0, y, 7.5, Font::Bold, "Titre");
+    page.gray_text(other.0, y, 7.5, Font::Bold, grouping.other_label());
+    page.gray_text(infos.0, y, 7.5, Font::Bold, "Informations");
+    y - ROW_HEIGHT + 1.0
+}
+
+/// "arrangeur : X · parolier : Y"
+fn contributors_text(partition: &ShowPartition) -> String {
+    partition
+        .other_contributors()
+        .iter()
+        .map(|c| format!("{} : {}", c.role.label(), c.full_name))
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+///
+/// Number of pages of the table of contents
+/// (the first page also has the title and the criteria)
+///
+fn toc_pages_count(catalogue: &Catalogue) -> usize {
+    let first_rows = toc_rows(first_toc_top(catalogue));
+    let other_rows = toc_rows(BODY_TOP);
+    let groups = catalogue.groups.len();
+    if groups <= first_rows {
+        1
+    } else {
+        1 + (groups - first_rows).div_ceil(other_rows)
+    }
+}
+
+fn toc_rows(top: f32) -> usize {
+    ((top - BODY_BOTTOM) / TOC_ROW_HEIGHT) as usize
+}
+
+/// y of the first entry of the table of contents on the first page
+fn first_toc_top(catalogue: &Catalogue) -> f32 {
+    let criteria = if catalogue.criteria.is_empty() {
+        0.0
+    } else {
+        14.0
+    };
+    BODY_TOP - 90.0 - criteria
+}
+
+///
+/// Lays out the first page (title, criteria) and the table of contents
+/// * group_pages : the index of the first page of each group
+///
+fn layout_toc(catalogue: &Catalogue, group_pages: &[usize]) -> Vec<PageLayout> {
+    let mut pages = vec![PageLayout::default()];
+    let page = &mut pages[0];
+    let width = PAGE_WIDTH - 2.0 * MARGIN;
+    page.text_fit(
+        MARGIN,
+        BODY_TOP - 10.0,
+        20.0,
+        Font::Bold,
+        &catalogue.title,
+        width,
+    );
+    page.text(
+        MARGIN,
+        BODY_TOP - 32.0,
+        10.0,
+        Font::Regular,
+        &format!(
+            "{} partition(s) en {} chapitre(s)",
+            catalogue.partitions_count(),
+            catalogue.groups.len()
+        ),
+    );
+    let mut y = BODY_TOP - 46.0;
+    if !catalogue.criteria.is_empty() {
+        let criteria = format!("Critères : {}", catalogue.criteria);
+        page.text_fit(MARGIN, y, 10.0, Font::Italic, &criteria, width);
+        y -= 14.0;
+    }
+    page.text(MARGIN, y - 20.0, 14.0, Font::Bold, "Table des matières");
+    page.lines.push((MARGIN, PAGE_WIDTH - MARGIN, y - 26.0));
+
+    let mut y = first_toc_top(catalogue);
+    for (group, page_index) in catalogue.groups.iter().zip(group_pages) {
+        if y < BODY_BOTTOM + TOC_ROW_HEIGHT {
+            pages.push(PageLayout::default());
+            y = BODY_TOP;
+        }
+        let page = pages.last_mut().unwrap();
+        let number = (page_index + 1).to_string();
+        let number_width = text_width(&number, Font::Regular, 10.0);
+        let name = fit_text(
+            &group.name,
+            Font::Regular,
+            10.0,
+            width - number_width - 40.0,
+        );
+        let name_width = text_width(&name, Font::Regular, 10.0);
+        page.text(MARGIN, y, 10.0, Font::Regular, &name);
+
+        // dot leaders between the name and the page number
+        let dot = text_width(" .", Font::Regular, 10.0);
+        let dots = ((width - name_width - number_width - 10.0) / dot).max(0.0) as usize;
+        page.gray_text(
+            PAGE_WIDTH - MARGIN - number_width - 5.0 - dots as f32 * dot,
+            y,
+            10.0,
+            Font::Regular,
+            &" .".repeat(dots),
+        );
+        page.text_right(PAGE_WIDTH - MARGIN, y, 10.0, Font::Regular, &number);
+        y -= TOC_ROW_HEIGHT;
+    }
+    pages
+}
+
+///
+/// Adds the header (ensemble, title) and the footer (page number) to the pages
+///
+fn add_headers(catalogue: &Catalogue, pages: &mut [PageLayout]) {
+    let total = pages.len();
+    let half = (PAGE_WIDTH - 2.0 * MARGIN) / 2.0;
+    for (index, page) in pages.iter_mut().enumerate() {
+        page.text_fit(MARGIN, 812.0, 9.0, Font::Bold, &catalogue.ensemble, half);
+        let title = fit_text(&catalogue.title, Font::Regular, 9.0, half - 10.0);
+        page.text_right(PAGE_WIDTH - MARGIN, 812.0, 9.0, Font::Regular, &title);
+        page.lines.push((MARGIN, PAGE_WIDTH - MARGIN, 805.0));
+
+        let footer = format!("Page {} / {}", index + 1, total);
+        let x = (PAGE_WIDTH - text_width(&footer, Font::Regular, 8.0)) / 2.0;
+        page.text(x, 30.0, 8.0, Font::Regular, &footer);
+    }
+}
+
+//*******************************************************************************
+// Writing of the PDF
+//
+
+///
+/// **Generates the PDF catalogue**<br>
+/// first page : title, criteria and table of contents, then one chapter per group
+/// (a chapter doesn't start at the bottom of a page)<br>
+/// the PDF also has bookmarks to the chapters
+///
+pub fn catalogue_pdf(catalogue: &Catalogue) -> Vec<u8> {
+    let toc_count = toc_pages_count(catalogue);
+    let (group_layouts, group_pages) = layout_groups(catalogue, toc_count);
+    let mut pages = layout_toc(catalogue, &group_pages);
+    if !catalogue.groups.is_empty() {
+        pages.extend(group_layouts);
+    }
+    add_headers(catalogue, &mut pages);
+
+    // ids : catalog, page tree, info, outline, fonts, then 2 per page, then the bookmarks
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let info_id = Ref::new(3);
+    let outline_id = Ref::new(4);
+    let font_ids = [Ref::new(5), Ref::new(6), Ref::new(7)];
+    let page_ids = (0..pages.len())
+        .map(|i| Ref::new(8 + 2 * i as i32))
+        .collect::<Vec<_>>();
+    let first_bookmark = 8 + 2 * pages.len() as i32;
+    let bookmark_ids = (0..catalogue.groups.len())
+        .map(|i| Ref::new(first_bookmark + i as i32))
+        .collect::<Vec<_>>();
+
+    let mut pdf = Pdf::new();
+    let mut catalog = pdf.catalog(catalog_id);
+    catalog.pages(page_tree_id);
+    if !bookmark_ids.is_empty() {
+        catalog.outlines(outline_id);
+    }
+    catalog.finish();
+    pdf.document_info(info_id)
+        .title(TextStr(&catalogue.title))
+        .author(TextStr(&catalogue.ensemble));
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().copied())
+        .count(page_ids.len() as i32);
+    for (font, id) in Font::ALL.iter().zip(font_ids) {
+        pdf.type1_font(id)
+            .base_font(font.base_font())
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+    }
+
+    for (layout, page_id) in pages.iter().zip(&page_ids) {
+        let content_id = Ref::new(page_id.get() + 1);
+        let mut page = pdf.page(*page_id);
+        page.parent(page_tree_id)
+            .media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+            .contents(content_id);
+        let mut resources = page.resources();
+        let mut fonts = resources.fonts();
+        for (font, id) in Font::ALL.iter().zip(font_ids) {
+            fonts.pair(font.resource_name(), id);
+        }
+        fonts.finish();
+        resources.finish();
+        page.finish();
+        pdf.stream(content_id, &page_content(layout));
+    }
+
+    if !bookmark_ids.is_empty() {
+        pdf.outline(outline_id)
+            .first(bookmark_ids[0])
+            .last(bookmark_ids[bookmark_ids.len() - 1])
+            .count(bookmark_ids.len() as i32);
+        for (index, group) in catalogue.groups.iter().enumerate() {
+            let mut item = pdf.outline_item(bookmark_ids[index]);
+            item.title(TextStr(&group.name)).parent(outline_id);
+            if index > 0 {
+                item.prev(bookmark_ids[index - 1]);
+            }
+            if index + 1 < bookmark_ids.len() {
+                item.next(bookmark_ids[index + 1]);
+            }
+            item.dest()
+                .page(page_ids[group_pages[index]])
+                .xyz(0.0, PAGE_HEIGHT, None);
+        }
+    }
+    pdf.finish()
+}
+
+fn page_content(layout: &PageLayout) -> Vec<u8> {
+    let mut content = Content::new();
+    content.set_line_width(0.5);
+    for (x1, x2, y) in &layout.lines {
+        content.move_to(*x1, *y).line_to(*x2, *y).stroke();
+    }
+    for item in &layout.texts {
+        content
+            .set_fill_gray(item.gray)
+            .begin_text()
+            .set_font(item.font.resource_name(), item.size)
+            .next_line(item.x, item.y)
+            .show(Str(&win_ansi(&item.text)))
+            .end_text();
+    }
+    content.finish()
+}
+
+//*******************************************************************************
+// Text in the standard fonts
+//
+
+/// widths of the characters 32 to 126 of Helvetica (1/1000 of the font size)
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// widths of the characters 32 to 126 of Helvetica-Bold
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667,
+    611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556,
+    278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+///
+/// Width of a character : the accented letters have the width of their letter
+///
+fn char_width(c: char, font: Font) -> u16 {
+    let widths = match font {
+        Font::Bold => &HELVETICA_BOLD_WIDTHS,
+        Font::Regular | Font::Italic => &HELVETICA_WIDTHS,
+    };
+    let base = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' => 'A',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'È' | 'É' | 'Ê' | 'Ë' => 'E',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'Ì' | 'Í' | 'Î' | 'Ï' => 'I',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => 'o',
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' => 'O',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'Ù' | 'Ú' | 'Û' | 'Ü' => 'U',
+        'ç' => 'c',
+        'Ç' => 'C',
+        'ñ' => 'n',
+        'Ñ' => 'N',
+        'ý' | 'ÿ' => 'y',
+        'Ý' | 'Ÿ' => 'Y',
+        'š' => 's',
+        'Š' => 'S',
+        'ž' => 'z',
+        'Ž' => 'Z',
+        '’' | '‘' => '\'',
+        '«' | '»' | '–' | '€' | '·' => '0',
+        'æ' | 'œ' => 'm',
+        'Æ' | 'Œ' | '…' | '—' => 'W',
+        c => c,
+    };
+    match base as u32 {
+        code @ 32..=126 => widths[(code - 32) as usize],
+        _ => 556,
+    }
+}
+
+fn text_width(text: &str, font: Font, size: f32) -> f32 {
+    text.chars()
+        .map(|c| char_width(c, font) as f32)
+        .sum::<f32>()
+        * size
+        / 1000.0
+}
+
+///
+/// Cuts the text with "…" if it's wider than `width`
+///
+fn fit_text(text: &str, font: Font, size: f32, width: f32) -> String {
+    if text_width(text, font, size) <= width {
+        return text.to_string();
+    }
+    let ellipsis = text_width("…", font, size);
+    let mut fitted = String::new();
+    let mut used = 0.0;
+    for c in text.chars() {
+        let w = char_width(c, font) as f32 * size / 1000.0;
+        if used + w + ellipsis > width {
+            break;
+        }
+        fitted.push(c);
+        used += w;
+    }
+    format!("{}…", fitted.trim_end())
+}
+
+///
+/// Encodes a text in WinAnsi (the encoding of the standard fonts)
+///
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            '€' => 0x80,
+            '…' => 0x85,
+            'Š' => 0x8A,
+            'Œ' => 0x8C,
+            'Ž' => 0x8E,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            'š' => 0x9A,
+            'œ' => 0x9C,
+            'ž' => 0x9E,
+            'Ÿ' => 0x9F,
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_not_cut() {
+        assert_eq!(
+            fit_text("Ave Maria", Font::Regular, 9.0, 200.0),
+            "Ave Maria"
+        );
+        assert_eq!(fit_text("", Font::Bold, 9.0, 10.0), "");
+    }
+
+    #[test]
+    fn long_text_is_cut_with_an_ellipsis() {
+        let title = "Messe pour les paroisses à l'usage des couvents de religieuses";
+        let fitted = fit_text(title, Font::Regular, 9.0, 100.0);
+        assert!(fitted.ends_with('…'));
+        assert!(fitted.chars().count() < title.chars().count());
+        assert!(title.starts_with(fitted.trim_end_matches('…')));
+        assert!(text_width(&fitted, Font::Regular, 9.0) <= 100.0);
+    }
+
+    #[test]
+    fn no_space_before_the_ellipsis() {
+        // "Te " fits but not "Te D" : the space is removed
+        let width = text_width("Te …", Font::Regular, 10.0);
+        assert_eq!(fit_text("Te Deum", Font::Regular, 10.0, width), "Te…");
+    }
+
+    #[test]
+    fn bold_text_is_wider() {
+        assert!(text_width("Gloria", Font::Bold, 9.0) > text_width("Gloria", Font::Regular, 9.0));
+        assert_eq!(
+            char_width('é', Font::Regular),
+            char_width('e', Font::Regular)
+        );
+    }
+
+    #[test]
+    fn win_ansi_encoding() {
+        assert_eq!(win_ansi("Ave"), b"Ave".to_vec());
+        assert_eq!(win_ansi("Noël"), vec![b'N', b'o', 0xEB, b'l']);
+        assert_eq!(win_ansi("Cœur…"), vec![b'C', 0x9C, b'u', b'r', 0x85]);
+        assert_eq!(
+            win_ansi("l’été – 5 €"),
+            vec![b'l', 0x92, 0xE9, b't', 0xE9, b' ', 0x96, b' ', b'5', b' ', 0x80]
+        );
+        assert_eq!(win_ansi("♪ 日本"), b"? ??".to_vec());
+    }
+}
@@ -4,6 +4,10 @@ fn get_env_var(var_name: &str) -> String {
     std::env::var(var_name).unwrap_or_else(|_| panic!("{} must be set", var_name))
 }
 
+fn get_env_var_or(var_name: &str, default: &str) -> String {
+    std::env::var(var_name).unwrap_or_else(|_| default.to_string())
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub database_url: String,
@@ -19,6 +23,10 @@ pub struct Config {
     pub refresh_token_public_key: String,
     pub refresh_token_expires_in: String,
     pub refresh_token_max_age: i64,
+
+    pub partition_files_root: String,
+    pub ensemble_name: String,
+    pub trash_retention_days: i64,
 }
 
 impl Config {
@@ -37,6 +45,13 @@ impl Config {
         let refresh_token_expires_in = get_env_var("REFRESH_TOKEN_EXPIRED_IN");
         let refresh_token_max_age = get_env_var("REFRESH_TOKEN_MAXAGE");
 
+        // directory where the files attached to the partitions are stored
+        let partition_files_root = get_env_var_or("PARTITION_FILES_ROOT", "partition_files");
+        // name of the ensemble in the header of the PDF catalogue
+        let ensemble_name = get_env_var_or("ENSEMBLE_NAME", "Ensemble");
+        // number of days a deleted record stays in the trash before it is purged
+        let trash_retention_days = get_env_var_or("TRASH_RETENTION_DAYS", "30");
+
         Config {
             database_url,
             redis_url,
@@ -50,6 +65,9 @@ impl Config {
             refresh_token_expires_in,
             access_token_max_age: access_token_max_age.parse::<i64>().unwrap(),
             refresh_token_max_age: refresh_token_max_age.parse::<i64>().unwrap(),
+            partition_files_root,
+            ensemble_name,
+            trash_retention_days: trash_retention_days.parse::<i64>().unwrap(),
         }
     }
 }
@@ -0,0 +1,167 @@
+//! src/db/audit.rs
+//!
+//! Audit log of the changes of the catalogue (table audit_log)
+//!
+//! The JSON of the records is written and read as text (casts to and from JSONB)
+//!
+
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use uuid::Uuid;
+
+use crate::db::partitions::find_show_partition_by_id;
+use crate::errors::MyAppError;
+use crate::models::audit::{AuditChange, AuditEntry, AuditFilter};
+use crate::models::pagination::PageParams;
+use crate::models::user::User;
+
+/// columns of an AuditEntry, the audit_log table is "a"
+const AUDIT_COLUMNS: &str = "SELECT a.id, a.actor_id, a.actor_name, a.action, a.entity, \
+     a.entity_id, a.before::TEXT AS before, a.after::TEXT AS after, a.created_at \
+     FROM audit_log a ";
+
+///
+/// **Records a change of the catalogue made by a user**<br>
+/// the change is already done : an error is only traced, the action of the user
+/// is not reported as failed because of the log
+///
+pub async fn record_audit(actor: &User, change: AuditChange, pool: &PgPool) {
+    insert_audit(Some(actor.id), &actor.name, change, pool).await;
+}
+
+///
+/// **Records a change of the catalogue made by the application itself**<br>
+/// (e.g. the automatic purge of the trash), there is no user
+///
+pub async fn record_system_audit(change: AuditChange, pool: &PgPool) {
+    insert_audit(None, SYSTEM_ACTOR, change, pool).await;
+}
+
+/// name of the actor of the changes made by the application itself
+const SYSTEM_ACTOR: &str = "Système";
+
+async fn insert_audit(
+    actor_id: Option<Uuid>,
+    actor_name: &str,
+    change: AuditChange,
+    pool: &PgPool,
+) {
+    let result = sqlx::query(
+        "INSERT INTO audit_log (actor_id, actor_name, action, entity, entity_id, before, after) \
+         VALUES ( $1, $2, $3, $4, $5, $6::JSONB, $7::JSONB )",
+    )
+    .bind(actor_id)
+    .bind(actor_name)
+    .bind(change.action)
+    .bind(change.entity)
+    .bind(change.entity_id)
+    .bind(change.before.map(|value| value.to_string()))
+    .bind(change.after.map(|value| value.to_string()))
+    .execute(pool)
+    .await;
+
+    if let Err(err) = result {
+        tracing::info!("error recording audit {:?} : {}", change.action, err);
+    }
+}
+
+///
+/// Returns one page of the audit log matching the filter, the last changes first
+///
+pub async fn list_audit_page(
+    filter: &AuditFilter,
+    page: &PageParams,
+    pool: &PgPool,
+) -> Result<Vec<AuditEntry>, MyAppError> {
+    let mut query = QueryBuilder::<Postgres>::new(AUDIT_COLUMNS);
+    push_audit_filter(&mut query, filter);
+    query
+        .push(" ORDER BY a.created_at DESC, a.id DESC LIMIT ")
+        .push_bind(page.per_page())
+        .push(" OFFSET ")
+        .push_bind(page.offset());
+    let entries = query.build_query_as::<AuditEntry>().fetch_all(pool).await?;
+    Ok(entries)
+}
+
+///
+/// Returns the number of changes matching the filter
+/// used for the pagination
+///
+pub async fn count_audit(filter: &AuditFilter, pool: &PgPool) -> Result<i64, MyAppError> {
+    let mut query = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM audit_log a ");
+    push_audit_filter(&mut query, filter);
+    let total: i64 = query.build().fetch_one(pool).await?.get(0);
+    Ok(total)
+}
+
+///
+/// Returns the changes of a partition, the oldest first
+/// (used for the revision history of the partition)
+///
+pub async fn list_partition_audit(id: i32, pool: &PgPool) -> Result<Vec<AuditEntry>, MyAppError> {
+    let entries = sqlx::query_as::<_, AuditEntry>(&format!(
+        "{AUDIT_COLUMNS} WHERE a.entity = 'partition' AND a.entity_id = $1 \
+         ORDER BY a.created_at, a.id"
+    ))
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+    Ok(entries)
+}
+
+//*******************************************************************************
+// Snapshots of the records
+//
+
+///
+/// Returns the JSON of a partition with its musicians, tags and metadata
+/// (None if the partition can't be read)
+///
+pub async fn partition_snapshot(id: i32, pool: &PgPool) -> Option<Value> {
+    match find_show_partition_by_id(id, pool).await {
+        Ok(partition) => snapshot(&partition),
+        Err(err) => {
+            tracing::info!("error reading partition {} for audit : {}", id, err);
+            None
+        }
+    }
+}
+
+///
+/// Returns the JSON of a record (a person, a genre ...)
+///
+pub fn snapshot<T: Serialize>(record: &T) -> Option<Value> {
+    serde_json::to_value(record).ok()
+}
+
+//*******************************************************************************
+// Helpers functions
+//
+
+fn push_audit_filter(query: &mut QueryBuilder<'_, Postgres>, filter: &AuditFilter) {
+    query.push(" WHERE TRUE");
+    if let Some(actor_id) = filter.actor_id() {
+        query.push(" AND a.actor_id = ").push_bind(actor_id);
+    }
+    if let Some(action) = filter.action() {
+        query.push(" AND a.action = ").push_bind(action);
+    }
+    if let Some(entity) = filter.entity() {
+        query.push(" AND a.entity = ").push_bind(entity);
+    }
+    if let Some(entity_id) = filter.entity_id() {
+        query.push(" AND a.entity_id = ").push_bind(entity_id);
+    }
+    if let Some(from) = filter.from() {
+        query.push(" AND a.created_at >= ").push_bind(from).push("::DATE");
+    }
+    if let Some(to) = filter.to() {
+        // the day "to" is included
+        query
+            .push(" AND a.created_at < ")
+            .push_bind(to)
+            .push("::DATE + 1");
+    }
+}
@@ -0,0 +1,345 @@
+//! src/db/collections.rs
+//!
+//! Favourites of the users (table favourites) and personal collections
+//! (tables collections and collection_items)
+//!
+//! Only the owner of a collection modifies it : the functions that modify
+//! a collection take the id of the logged user and return NOT_FOUND
+//! for the collections of the other users
+//!
+
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::MyAppError;
+use crate::models::collection::{Collection, CollectionPartition};
+
+/// columns of a Collection, the collections table is "c" (needs GROUP BY c.id, u.name)
+const COLLECTION_COLUMNS: &str = "SELECT c.id, c.user_id, u.name AS owner_name, c.name, c.shared, \
+     COUNT(i.partition_id) AS partitions_count \
+     FROM collections c \
+     INNER JOIN users u ON u.id = c.user_id \
+     LEFT JOIN collection_items i ON i.collection_id = c.id ";
+
+/// columns of a CollectionPartition, the partitions table is "p"
+const PARTITION_COLUMNS: &str = "SELECT p.id AS partition_id, p.title, pe.full_name, \
+     g.name AS genre";
+
+//*******************************************************************************************
+// Favourites
+//
+
+///
+/// **Stars or unstars a partition for a user**<br>
+/// returns true if the partition is now a favourite, or MyAppError
+///
+pub async fn toggle_favourite(
+    user_id: Uuid,
+    partition_id: i32,
+    pool: &PgPool,
+) -> Result<bool, MyAppError> {
+    let removed = sqlx::query("DELETE FROM favourites WHERE user_id = $1 AND partition_id = $2")
+        .bind(user_id)
+        .bind(partition_id)
+        .execute(pool)
+        .await?;
+    if removed.rows_affected() > 0 {
+        tracing::info!("db : favourite removed : {} {}", user_id, partition_id);
+        return Ok(false);
+    }
+
+    sqlx::query(
+        "INSERT INTO favourites (user_id, partition_id) VALUES ( $1, $2 ) ON CONFLICT DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(partition_id)
+    .execute(pool)
+    .await?;
+    tracing::info!("db : favourite added : {} {}", user_id, partition_id);
+    Ok(true)
+}
+
+///
+/// Returns the favourites of a user, the last starred first
+///
+pub async fn list_favourites(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Vec<CollectionPartition>, MyAppError> {
+    let partitions = sqlx::query_as::<_, CollectionPartition>(&format!(
+        "{PARTITION_COLUMNS}, f.created_at AS added_at \
+         FROM favourites f \
+         INNER JOIN partitions p ON p.id = f.partition_id \
+         INNER JOIN persons pe ON pe.id = p.person_id \
+         INNER JOIN genres g ON g.id = p.genre_id \
+         WHERE f.user_id = $1 AND p.deleted_at IS NULL ORDER BY f.created_at DESC, p.id"
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(partitions)
+}
+
+///
+/// Returns the ids of the favourites of a user among the partitions of a page
+///
+pub async fn list_favourite_ids(
+    user_id: Uuid,
+    partition_ids: &[i32],
+    pool: &PgPool,
+) -> Result<Vec<i32>, MyAppError> {
+    let ids: Vec<i32> = sqlx::query_scalar(
+        "SELECT partition_id FROM favourites WHERE user_id = $1 AND partition_id = ANY($2)",
+    )
+    .bind(user_id)
+    .bind(partition_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(ids)
+}
+
+//*******************************************************************************************
+// CRUD Operations on collections
+//
+
+///
+/// **Adds an (empty) collection to the user**<br>
+/// returns the added Collection or MyAppError (CONFLICT if the user has a collection with the name)
+///
+pub async fn add_collection(
+    user_id: Uuid,
+    name: &str,
+    shared: bool,
+    pool: &PgPool,
+) -> Result<Collection, MyAppError> {
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO collections (user_id, name, shared) VALUES ( $1, $2, $3 ) RETURNING id",
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(shared)
+    .fetch_one(pool)
+    .await?;
+
+    let collection = find_collection_by_id(id, pool).await?;
+    tracing::info!("db : collection added : {:?}", &collection);
+    Ok(collection)
+}
+
+pub async fn update_collection(
+    id: i32,
+    user_id: Uuid,
+    name: &str,
+    shared: bool,
+    pool: &PgPool,
+) -> Result<Collection, MyAppError> {
+    let result =
+        sqlx::query("UPDATE collections SET name = $1, shared = $2 WHERE id = $3 AND user_id = $4")
+            .bind(name)
+            .bind(shared)
+            .bind(id)
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+    if result.rows_affected() == 0 {
+        return Err(collection_not_found());
+    }
+
+    let collection = find_collection_by_id(id, pool).await?;
+    tracing::info!("db : collection modified : {:?}", &collection);
+    Ok(collection)
+}
+
+///
+/// **Deletes a collection of the user** (not the partitions)<br>
+/// returns the deleted Collection or MyAppError
+///
+pub async fn delete_collection(
+    id: i32,
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Collection, MyAppError> {
+    let collection = find_owned_collection(id, user_id, pool).await?;
+    sqlx::query("DELETE FROM collections WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    tracing::info!("db : collection deleted : {:?}", &collection);
+    Ok(collection)
+}
+
+pub async fn find_collection_by_id(id: i32, pool: &PgPool) -> Result<Collection, MyAppError> {
+    let collection = sqlx::query_as::<_, Collection>(&format!(
+        "{COLLECTION_COLUMNS} WHERE c.id = $1 GROUP BY c.id, u.name"
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(collection_not_found)?;
+    Ok(collection)
+}
+
+///
+/// Returns a collection the user can read : one of his collections or a shared one
+/// (NOT_FOUND for the private collections of the other users)
+///
+pub async fn find_readable_collection(
+    id: i32,
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Collection, MyAppError> {
+    let collection = find_collection_by_id(id, pool).await?;
+    if collection.is_owner(user_id) || collection.shared {
+        Ok(collection)
+    } else {
+        Err(collection_not_found())
+    }
+}
+
+///
+/// Returns the collections of a user, by name
+///
+pub async fn list_user_collections(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Vec<Collection>, MyAppError> {
+    let collections = sqlx::query_as::<_, Collection>(&format!(
+        "{COLLECTION_COLUMNS} WHERE c.user_id = $1 GROUP BY c.id, u.name ORDER BY lower(c.name)"
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(collections)
+}
+
+///
+/// Returns the collections shared by the other users, by owner and name
+///
+pub async fn list_shared_collections(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Vec<Collection>, MyAppError> {
+    let collections = sqlx::query_as::<_, Collection>(&format!(
+        "{COLLECTION_COLUMNS} WHERE c.shared AND c.user_id <> $1 \
+         GROUP BY c.id, u.name ORDER BY lower(u.name), lower(c.name)"
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(collections)
+}
+
+//*******************************************************************************************
+// Partitions of the collections
+//
+
+///
+/// Returns the partitions of a collection, by title
+///
+pub async fn list_collection_partitions(
+    collection_id: i32,
+    pool: &PgPool,
+) -> Result<Vec<CollectionPartition>, MyAppError> {
+    let partitions = sqlx::query_as::<_, CollectionPartition>(&format!(
+        "{PARTITION_COLUMNS}, i.added_at \
+         FROM collection_items i \
+         INNER JOIN partitions p ON p.id = i.partition_id \
+         INNER JOIN persons pe ON pe.id = p.person_id \
+         INNER JOIN genres g ON g.id = p.genre_id \
+         WHERE i.collection_id = $1 AND p.deleted_at IS NULL ORDER BY lower(p.title), p.id"
+    ))
+    .bind(collection_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(partitions)
+}
+
+///
+/// **Adds a partition to a collection of the user**<br>
+/// a partition already in the collection is kept once
+///
+/// returns the Collection or MyAppError
+///
+pub async fn add_collection_partition(
+    collection_id: i32,
+    user_id: Uuid,
+    partition_id: i32,
+    pool: &PgPool,
+) -> Result<Collection, MyAppError> {
+    find_owned_collection(collection_id, user_id, pool).await?;
+    sqlx::query(
+        "INSERT INTO collection_items (collection_id, partition_id) VALUES ( $1, $2 ) \
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(collection_id)
+    .bind(partition_id)
+    .execute(pool)
+    .await?;
+
+    let collection = find_collection_by_id(collection_id, pool).await?;
+    tracing::info!(
+        "db : partition {} added to collection : {:?}",
+        partition_id,
+        &collection
+    );
+    Ok(collection)
+}
+
+///
+/// **Removes a partition from a collection of the user**<br>
+/// returns the Collection or MyAppError
+///
+pub async fn remove_collection_partition(
+    collection_id: i32,
+    user_id: Uuid,
+    partition_id: i32,
+    pool: &PgPool,
+) -> Result<Collection, MyAppError> {
+    find_owned_collection(collection_id, user_id, pool).await?;
+    let result =
+        sqlx::query("DELETE FROM collection_items WHERE collection_id = $1 AND partition_id = $2")
+            .bind(collection_id)
+            .bind(partition_id)
+            .execute(pool)
+            .await?;
+    if result.rows_affected() == 0 {
+        return Err(MyAppError::new(
+            StatusCode::NOT_FOUND,
+            "Partition not in the collection !",
+        ));
+    }
+
+    let collection = find_collection_by_id(collection_id, pool).await?;
+    tracing::info!(
+        "db : partition {} removed from collection : {:?}",
+        partition_id,
+        &collection
+    );
+    Ok(collection)
+}
+
+//*******************************************************************************
+// Helpers functions
+//
+
+///
+/// Returns a collection of the user (NOT_FOUND for the collections of the other users)
+///
+async fn find_owned_collection(
+    id: i32,
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Collection, MyAppError> {
+    let collection = find_collection_by_id(id, pool).await?;
+    if collection.is_owner(user_id) {
+        Ok(collection)
+    } else {
+        Err(collection_not_found())
+    }
+}
+
+fn collection_not_found() -> MyAppError {
+    MyAppError::new(StatusCode::NOT_FOUND, "Collection with the ID not found !")
+}
@@ -0,0 +1,393 @@
+//! src/db/copies.rs
+//!
+//! Printed copies of the partitions (table partition_copies)
+//! and inventory audits of the copies (tables inventory_audits, inventory_audit_copies)
+//!
+
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::MyAppError;
+use crate::models::copy::{AuditCopy, CopyCondition, CopyValues, InventoryAudit, PartitionCopy};
+use crate::models::loan::PartitionAvailability;
+
+/// columns of a PartitionCopy, the copies table is "c"
+const COPY_COLUMNS: &str = "SELECT c.id, c.partition_id, p.title, c.location, c.condition, \
+     c.purchase_date, \
+     EXISTS (SELECT 1 FROM loans l WHERE l.copy_id = c.id AND l.returned_at IS NULL) AS lent \
+     FROM partition_copies c \
+     INNER JOIN partitions p ON p.id = c.partition_id ";
+
+/// number of copies checked by an inventory audit (the partitions in the trash are not counted)
+const AUDITED_COPIES_COUNT: &str = "(SELECT COUNT(*) FROM partition_copies c \
+     INNER JOIN partitions p ON p.id = c.partition_id WHERE p.deleted_at IS NULL)";
+
+/// columns of an InventoryAudit, the audits table is "a"
+const AUDIT_COLUMNS: &str = "SELECT a.id, u.name AS started_by, a.started_at, a.closed_at, \
+     (SELECT COUNT(*) FROM inventory_audit_copies s WHERE s.audit_id = a.id) AS seen, \
+     COALESCE(a.total, (SELECT COUNT(*) FROM partition_copies c \
+        INNER JOIN partitions p ON p.id = c.partition_id WHERE p.deleted_at IS NULL)) AS total \
+     FROM inventory_audits a \
+     LEFT JOIN users u ON u.id = a.started_by ";
+
+//*******************************************************************************************
+// CRUD Operations on copies
+//
+
+///
+/// **Adds copies to a partition**<br>
+/// `count` copies with the same location, condition and purchase date
+/// (e.g. the copies of a box bought together)
+///
+/// returns the added copies or MyAppError
+/// (NOT_FOUND if the partition does not exist or is in the trash)
+///
+pub async fn add_copies(
+    partition_id: i32,
+    count: i32,
+    values: &CopyValues,
+    pool: &PgPool,
+) -> Result<Vec<PartitionCopy>, MyAppError> {
+    let ids: Vec<i32> = sqlx::query_scalar(
+        "INSERT INTO partition_copies (partition_id, location, condition, purchase_date) \
+         SELECT p.id, $2, $3, $4 FROM partitions p CROSS JOIN generate_series(1, $5) \
+         WHERE p.id = $1 AND p.deleted_at IS NULL RETURNING id",
+    )
+    .bind(partition_id)
+    .bind(&values.location)
+    .bind(values.condition)
+    .bind(values.purchase_date)
+    .bind(count)
+    .fetch_all(pool)
+    .await?;
+    if ids.is_empty() && count > 0 {
+        return Err(MyAppError::new(
+            StatusCode::NOT_FOUND,
+            "Partition with the ID not found !",
+        ));
+    }
+
+    let copies = sqlx::query_as::<_, PartitionCopy>(&format!(
+        "{COPY_COLUMNS} WHERE c.id = ANY($1) ORDER BY c.id"
+    ))
+    .bind(ids)
+    .fetch_all(pool)
+    .await?;
+    tracing::info!("db : copies added : {:?}", &copies);
+    Ok(copies)
+}
+
+///
+/// **Modifies the location, the condition and the purchase date of a copy**<br>
+/// a lent copy can't be marked as missing
+///
+pub async fn update_copy(
+    id: i32,
+    values: &CopyValues,
+    pool: &PgPool,
+) -> Result<PartitionCopy, MyAppError> {
+    let copy = find_copy_by_id(id, pool).await?;
+    if copy.lent && values.condition == CopyCondition::Missing {
+        return Err(MyAppError::new(
+            StatusCode::CONFLICT,
+            format!("l'exemplaire n° {} est prêté", id),
+        ));
+    }
+    sqlx::query(
+        "UPDATE partition_copies SET location = $1, condition = $2, purchase_date = $3 \
+         WHERE id = $4",
+    )
+    .bind(&values.location)
+    .bind(values.condition)
+    .bind(values.purchase_date)
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    let copy = find_copy_by_id(id, pool).await?;
+    tracing::info!("db : copy modified : {:?}", &copy);
+    Ok(copy)
+}
+
+///
+/// **Deletes a copy**<br>
+/// returns the deleted copy or MyAppError (CONFLICT if the copy is lent)<br>
+/// the returned loans of the copy are kept (loans.copy_id is set to NULL)
+///
+pub async fn delete_copy(id: i32, pool: &PgPool) -> Result<PartitionCopy, MyAppError> {
+    let copy = find_copy_by_id(id, pool).await?;
+    if copy.lent {
+        return Err(MyAppError::new(
+            StatusCode::CONFLICT,
+            format!("l'exemplaire n° {} est prêté", id),
+        ));
+    }
+    sqlx::query("DELETE FROM partition_copies WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    tracing::info!("db : copy deleted : {:?}", &copy);
+    Ok(copy)
+}
+
+pub async fn find_copy_by_id(id: i32, pool: &PgPool) -> Result<PartitionCopy, MyAppError> {
+    let copy = sqlx::query_as::<_, PartitionCopy>(&format!("{COPY_COLUMNS} WHERE c.id = $1"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| MyAppError::new(StatusCode::NOT_FOUND, "Copy with the ID not found !"))?;
+    Ok(copy)
+}
+
+///
+/// Returns the copies of a partition ordered by inventory number
+///
+pub async fn list_partition_copies(
+    partition_id: i32,
+    pool: &PgPool,
+) -> Result<Vec<PartitionCopy>, MyAppError> {
+    let copies = sqlx::query_as::<_, PartitionCopy>(&format!(
+        "{COPY_COLUMNS} WHERE c.partition_id = $1 ORDER BY c.id"
+    ))
+    .bind(partition_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(copies)
+}
+
+//*******************************************************************************************
+// Availability of the partitions
+//
+
+///
+/// Returns the number of copies of a partition (the missing ones are not counted)
+/// and the number of lent copies<br>
+/// MyAppError NOT_FOUND if the partition does not exist or is in the trash
+///
+pub async fn partition_availability(
+    partition_id: i32,
+    pool: &PgPool,
+) -> Result<PartitionAvailability, MyAppError> {
+    list_availability_for_partitions(&[partition_id], pool)
+        .await?
+        .pop()
+        .ok_or_else(|| MyAppError::new(StatusCode::NOT_FOUND, "Partition with the ID not found !"))
+}
+
+///
+/// Returns the copies and the lent copies of the partitions
+/// (e.g. the partitions shown in a page), the partitions in the trash are left out
+///
+pub async fn list_availability_for_partitions(
+    partition_ids: &[i32],
+    pool: &PgPool,
+) -> Result<Vec<PartitionAvailability>, MyAppError> {
+    let availability = sqlx::query_as::<_, PartitionAvailability>(
+        "SELECT p.id AS partition_id, p.title, \
+         (SELECT COUNT(*) FROM partition_copies c \
+          WHERE c.partition_id = p.id AND c.condition <> 'missing') AS copies, \
+         (SELECT COUNT(*) FROM loans l \
+          WHERE l.partition_id = p.id AND l.returned_at IS NULL) AS lent \
+         FROM partitions p WHERE p.id = ANY($1) AND p.deleted_at IS NULL",
+    )
+    .bind(partition_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(availability)
+}
+
+//*******************************************************************************************
+// Inventory audits
+//
+
+///
+/// **Starts an inventory audit**<br>
+/// returns the audit or MyAppError (CONFLICT if an audit is in progress)
+///
+pub async fn start_audit(user_id: Uuid, pool: &PgPool) -> Result<InventoryAudit, MyAppError> {
+    if current_audit(pool).await?.is_some() {
+        return Err(MyAppError::new(
+            StatusCode::CONFLICT,
+            "un inventaire est déjà en cours",
+        ));
+    }
+    let id: i32 =
+        sqlx::query_scalar("INSERT INTO inventory_audits (started_by) VALUES ( $1 ) RETURNING id")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
+
+    let audit = find_audit_by_id(id, pool).await?;
+    tracing::info!("db : inventory audit started : {:?}", &audit);
+    Ok(audit)
+}
+
+///
+/// Returns the audit in progress, if any
+///
+pub async fn current_audit(pool: &PgPool) -> Result<Option<InventoryAudit>, MyAppError> {
+    let audit =
+        sqlx::query_as::<_, InventoryAudit>(&format!("{AUDIT_COLUMNS} WHERE a.closed_at IS NULL"))
+            .fetch_optional(pool)
+            .await?;
+    Ok(audit)
+}
+
+pub async fn find_audit_by_id(id: i32, pool: &PgPool) -> Result<InventoryAudit, MyAppError> {
+    let audit = sqlx::query_as::<_, InventoryAudit>(&format!("{AUDIT_COLUMNS} WHERE a.id = $1"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| {
+            MyAppError::new(
+                StatusCode::NOT_FOUND,
+                "Inventory audit with the ID not found !",
+            )
+        })?;
+    Ok(audit)
+}
+
+///
+/// Returns the audits, the last one first
+///
+pub async fn list_audits(pool: &PgPool) -> Result<Vec<InventoryAudit>, MyAppError> {
+    let audits = sqlx::query_as::<_, InventoryAudit>(&format!(
+        "{AUDIT_COLUMNS} ORDER BY a.started_at DESC, a.id DESC"
+    ))
+    .fetch_all(pool)
+    .await?;
+    Ok(audits)
+}
+
+///
+/// **Marks copies as seen during the audit in progress**<br>
+/// a copy seen twice is counted once, a missing copy that is seen
+/// is found again : its condition becomes "good"
+///
+/// returns the inventory numbers that are not copies (typing errors,
+/// copies of partitions in the trash) or MyAppError (CONFLICT if no audit is in progress)
+///
+pub async fn mark_copies_seen(copy_ids: &[i32], pool: &PgPool) -> Result<Vec<i32>, MyAppError> {
+    let audit = current_audit(pool)
+        .await?
+        .ok_or_else(|| MyAppError::new(StatusCode::CONFLICT, "aucun inventaire en cours"))?;
+
+    let mut tx = pool.begin().await?;
+    let known: Vec<i32> = sqlx::query_scalar(
+        "INSERT INTO inventory_audit_copies (audit_id, copy_id) \
+         SELECT $1, c.id FROM partition_copies c \
+         INNER JOIN partitions p ON p.id = c.partition_id \
+         WHERE c.id = ANY($2) AND p.deleted_at IS NULL \
+         ON CONFLICT (audit_id, copy_id) DO UPDATE SET seen_at = NOW() \
+         RETURNING copy_id",
+    )
+    .bind(audit.id)
+    .bind(copy_ids)
+    .fetch_all(&mut tx)
+    .await?;
+    sqlx::query(
+        "UPDATE partition_copies SET condition = 'good' \
+         WHERE id = ANY($1) AND condition = 'missing'",
+    )
+    .bind(&known)
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    tracing::info!("db : copies seen (audit {}) : {:?}", audit.id, &known);
+    let unknown = copy_ids
+        .iter()
+        .copied()
+        .filter(|id| !known.contains(id))
+        .collect();
+    Ok(unknown)
+}
+
+///
+/// **Closes the audit in progress**<br>
+/// returns the closed audit or MyAppError (CONFLICT if no audit is in progress)
+///
+pub async fn close_audit(pool: &PgPool) -> Result<InventoryAudit, MyAppError> {
+    let id: Option<i32> = sqlx::query_scalar(&format!(
+        "UPDATE inventory_audits \
+         SET closed_at = NOW(), total = {AUDITED_COPIES_COUNT} \
+         WHERE closed_at IS NULL RETURNING id"
+    ))
+    .fetch_optional(pool)
+    .await?;
+    let id =
+        id.ok_or_else(|| MyAppError::new(StatusCode::CONFLICT, "aucun inventaire en cours"))?;
+
+    let audit = find_audit_by_id(id, pool).await?;
+    tracing::info!("db : inventory audit closed : {:?}", &audit);
+    Ok(audit)
+}
+
+///
+/// Returns the copies with their state in the audit, ordered by location
+/// (the order of the walk along the shelves), the partitions in the trash are left out
+/// * only_not_seen : only the copies not seen, the report of the audit
+///
+pub async fn list_audit_copies(
+    audit_id: i32,
+    only_not_seen: bool,
+    pool: &PgPool,
+) -> Result<Vec<AuditCopy>, MyAppError> {
+    let copies = sqlx::query_as::<_, AuditCopy>(
+        "SELECT * FROM ( \
+         SELECT c.id, c.partition_id, p.title, c.location, c.condition, \
+         EXISTS (SELECT 1 FROM loans l WHERE l.copy_id = c.id AND l.returned_at IS NULL) AS lent, \
+         EXISTS (SELECT 1 FROM inventory_audit_copies s \
+                 WHERE s.audit_id = $1 AND s.copy_id = c.id) AS seen \
+         FROM partition_copies c \
+         INNER JOIN partitions p ON p.id = c.partition_id \
+         WHERE p.deleted_at IS NULL \
+         ) copies \
+         WHERE NOT ($2 AND seen) \
+         ORDER BY lower(location), lower(title), id",
+    )
+    .bind(audit_id)
+    .bind(only_not_seen)
+    .fetch_all(pool)
+    .await?;
+    Ok(copies)
+}
+
+///
+/// **Marks as missing the copies not found by a closed audit**<br>
+/// the lent copies are not on the shelves and the copies of the partitions
+/// in the trash are not checked : they are not marked
+///
+/// returns the number of copies marked as missing
+/// or MyAppError (CONFLICT if the audit is in progress)
+///
+pub async fn mark_not_found_missing(audit_id: i32, pool: &PgPool) -> Result<u64, MyAppError> {
+    let audit = find_audit_by_id(audit_id, pool).await?;
+    if !audit.is_closed() {
+        return Err(MyAppError::new(
+            StatusCode::CONFLICT,
+            "l'inventaire n'est pas terminé",
+        ));
+    }
+    let result = sqlx::query(
+        "UPDATE partition_copies c SET condition = 'missing' \
+         FROM partitions p \
+         WHERE p.id = c.partition_id AND p.deleted_at IS NULL \
+         AND c.condition <> 'missing' \
+         AND NOT EXISTS (SELECT 1 FROM inventory_audit_copies s \
+                         WHERE s.audit_id = $1 AND s.copy_id = c.id) \
+         AND NOT EXISTS (SELECT 1 FROM loans l WHERE l.copy_id = c.id AND l.returned_at IS NULL)",
+    )
+    .bind(audit_id)
+    .execute(pool)
+    .await?;
+
+    tracing::info!(
+        "db : copies not found by audit {} marked as missing : {}",
+        audit_id,
+        result.rows_affected()
+    );
+    Ok(result.rows_affected())
+}
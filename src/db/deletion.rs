@@ -0,0 +1,149 @@
+//! src/db/deletion.rs
+//!
+//! Deletion of a musician or a genre still used by partitions :
+//! the impact of the deletion and the policy applied to the partitions
+//! (block, reassign to another record, cascade to the trash)
+//!
+
+use axum::http::StatusCode;
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::db::loans::list_open_loans_for_partitions;
+use crate::db::merge::reassign_partitions;
+use crate::db::setlists::list_setlists_with_partitions;
+use crate::db::trash::columns_of;
+use crate::errors::MyAppError;
+use crate::models::audit::AuditEntity;
+use crate::models::deletion::{DeletePolicy, DeletionImpact, ImpactPartition};
+
+///
+/// Returns what the deletion of a musician or a genre would affect
+/// or MyAppError (NOT_FOUND if it does not exist or is in the trash)
+///
+pub async fn deletion_impact(
+    entity: AuditEntity,
+    id: i32,
+    pool: &PgPool,
+) -> Result<DeletionImpact, MyAppError> {
+    if entity == AuditEntity::Partition {
+        return Err(MyAppError::new(
+            StatusCode::BAD_REQUEST,
+            "seuls les musiciens et les genres ont un impact de suppression",
+        ));
+    }
+    let (table, name) = columns_of(entity);
+    let name: String = sqlx::query_scalar(&format!(
+        "SELECT {name} FROM {table} WHERE id = $1 AND deleted_at IS NULL"
+    ))
+    .bind(id)
+    .fetch_one(pool)
+    .await?;
+
+    let partitions = sqlx::query_as::<_, ImpactPartition>(&format!(
+        "{} ORDER BY lower(p.title), p.id",
+        using_partitions(entity, "p.id, p.title")
+    ))
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+
+    let partition_ids: Vec<i32> = partitions.iter().map(|partition| partition.id).collect();
+    let loans = list_open_loans_for_partitions(&partition_ids, pool).await?;
+    let setlists = list_setlists_with_partitions(&partition_ids, pool).await?;
+
+    Ok(DeletionImpact {
+        entity,
+        id,
+        name,
+        partitions,
+        loans,
+        setlists,
+    })
+}
+
+///
+/// **Applies the deletion policy to the partitions of a musician or a genre**
+/// (in the transaction of the deletion)<br>
+/// * Block : MyAppError CONFLICT if partitions (not in the trash) use the record
+/// * Reassign : the partitions go to the other record (NOT_FOUND if it does not
+///   exist or is in the trash, BAD_REQUEST if it is the deleted record)
+/// * Cascade : the partitions go to the trash
+///
+/// Returns the ids of the partitions reassigned or sent to the trash
+///
+pub async fn apply_delete_policy(
+    entity: AuditEntity,
+    id: i32,
+    name: &str,
+    policy: DeletePolicy,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<Vec<i32>, MyAppError> {
+    match policy {
+        DeletePolicy::Block => {
+            let used: i64 = sqlx::query_scalar(&using_partitions(entity, "COUNT(*)"))
+                .bind(id)
+                .fetch_one(&mut *tx)
+                .await?;
+            if used > 0 {
+                return Err(MyAppError::new(
+                    StatusCode::CONFLICT,
+                    format!("{name} est utilisé par {used} partition(s)"),
+                ));
+            }
+            Ok(Vec::new())
+        }
+        DeletePolicy::Reassign(to_id) => {
+            if to_id == id {
+                return Err(MyAppError::new(
+                    StatusCode::BAD_REQUEST,
+                    "impossible de réattribuer les partitions à l'enregistrement supprimé",
+                ));
+            }
+            let (table, _) = columns_of(entity);
+            let exists: bool = sqlx::query_scalar(&format!(
+                "SELECT EXISTS (SELECT 1 FROM {table} WHERE id = $1 AND deleted_at IS NULL)"
+            ))
+            .bind(to_id)
+            .fetch_one(&mut *tx)
+            .await?;
+            if !exists {
+                return Err(MyAppError::new(
+                    StatusCode::NOT_FOUND,
+                    format!("{} {to_id} introuvable", entity.label()),
+                ));
+            }
+            reassign_partitions(entity, id, to_id, tx).await
+        }
+        DeletePolicy::Cascade => {
+            let ids: Vec<i32> = sqlx::query_scalar(&format!(
+                "UPDATE partitions SET deleted_at = NOW() WHERE id IN ({}) RETURNING id",
+                using_partitions(entity, "p.id")
+            ))
+            .bind(id)
+            .fetch_all(&mut *tx)
+            .await?;
+            Ok(ids)
+        }
+    }
+}
+
+//*******************************************************************************
+// Helpers functions
+//
+
+///
+/// The query of the partitions (not in the trash, "p") using the musician
+/// or the genre $1, selecting `columns`
+///
+fn using_partitions(entity: AuditEntity, columns: &str) -> String {
+    let condition = match entity {
+        AuditEntity::Partition => "FALSE",
+        AuditEntity::Person => {
+            "(p.person_id = $1 OR EXISTS ( \
+                SELECT 1 FROM partition_contributors pc \
+                WHERE pc.partition_id = p.id AND pc.person_id = $1))"
+        }
+        AuditEntity::Genre => "p.genre_id = $1",
+    };
+    format!("SELECT {columns} FROM partitions p WHERE p.deleted_at IS NULL AND {condition}")
+}
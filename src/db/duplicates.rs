@@ -0,0 +1,134 @@
+//! src/db/duplicates.rs
+//!
+//! Duplicate detection with the trigram similarity of pg_trgm
+//! (the comparison ignores the case, the punctuation and the order of the words) :
+//! the records close to a title or a name before an insertion,
+//! and the clusters of suspected duplicates of the whole catalogue
+//!
+
+use sqlx::PgPool;
+
+use crate::errors::MyAppError;
+use crate::models::audit::AuditEntity;
+use crate::models::duplicate::{
+    DuplicateCluster, DuplicateMatch, DuplicatePair, DUPLICATE_THRESHOLD,
+};
+
+/// the number of close records shown before an insertion
+const MATCHES_LIMIT: i64 = 10;
+
+///
+/// Returns the partitions (not in the trash) whose title is close to `title`,
+/// the closest first, with their main musician
+///
+pub async fn find_similar_partitions(
+    title: &str,
+    pool: &PgPool,
+) -> Result<Vec<DuplicateMatch>, MyAppError> {
+    let matches = sqlx::query_as::<_, DuplicateMatch>(
+        "SELECT p.id, p.title AS name, pe.full_name AS detail, \
+            similarity(p.title, $1)::FLOAT8 AS similarity \
+         FROM partitions p \
+         LEFT JOIN persons pe ON pe.id = p.person_id \
+         WHERE p.deleted_at IS NULL AND p.title % $1 AND similarity(p.title, $1) >= $2 \
+         ORDER BY similarity DESC, p.title \
+         LIMIT $3",
+    )
+    .bind(title)
+    .bind(DUPLICATE_THRESHOLD)
+    .bind(MATCHES_LIMIT)
+    .fetch_all(pool)
+    .await?;
+    Ok(matches)
+}
+
+///
+/// Returns the musicians (not in the trash) whose name or one of the other
+/// spellings of the name is close to `full_name`, the closest first
+///
+pub async fn find_similar_persons(
+    full_name: &str,
+    pool: &PgPool,
+) -> Result<Vec<DuplicateMatch>, MyAppError> {
+    let matches = sqlx::query_as::<_, DuplicateMatch>(
+        "SELECT * FROM ( \
+            SELECT id, full_name AS name, NULL::TEXT AS detail, \
+                GREATEST(similarity(full_name, $1), \
+                    (SELECT MAX(similarity(alt, $1)) FROM unnest(alternative_names) AS alt) \
+                )::FLOAT8 AS similarity \
+            FROM persons \
+            WHERE deleted_at IS NULL AND (full_name % $1 OR $1 % ANY(alternative_names)) \
+         ) AS close \
+         WHERE similarity >= $2 \
+         ORDER BY similarity DESC, name \
+         LIMIT $3",
+    )
+    .bind(full_name)
+    .bind(DUPLICATE_THRESHOLD)
+    .bind(MATCHES_LIMIT)
+    .fetch_all(pool)
+    .await?;
+    Ok(matches)
+}
+
+///
+/// Returns the clusters of suspected duplicates of the catalogue :
+/// the partitions, then the musicians, then the genres
+///
+pub async fn list_duplicate_clusters(pool: &PgPool) -> Result<Vec<DuplicateCluster>, MyAppError> {
+    let mut clusters = Vec::new();
+    for entity in [AuditEntity::Partition, AuditEntity::Person, AuditEntity::Genre] {
+        let pairs = list_duplicate_pairs(entity, pool).await?;
+        clusters.extend(DuplicateCluster::from_pairs(entity, pairs));
+    }
+    Ok(clusters)
+}
+
+//*******************************************************************************
+// Helpers functions
+//
+
+///
+/// Returns the pairs of records of a table whose titles or names are close
+///
+async fn list_duplicate_pairs(
+    entity: AuditEntity,
+    pool: &PgPool,
+) -> Result<Vec<DuplicatePair>, MyAppError> {
+    let query = match entity {
+        AuditEntity::Partition => {
+            "SELECT a.id AS first_id, a.title AS first_name, pa.full_name AS first_detail, \
+                b.id AS second_id, b.title AS second_name, pb.full_name AS second_detail, \
+                similarity(a.title, b.title)::FLOAT8 AS similarity \
+             FROM partitions a \
+             INNER JOIN partitions b ON a.id < b.id AND a.title % b.title \
+             LEFT JOIN persons pa ON pa.id = a.person_id \
+             LEFT JOIN persons pb ON pb.id = b.person_id \
+             WHERE a.deleted_at IS NULL AND b.deleted_at IS NULL \
+                AND similarity(a.title, b.title) >= $1"
+        }
+        AuditEntity::Person => {
+            "SELECT a.id AS first_id, a.full_name AS first_name, NULL::TEXT AS first_detail, \
+                b.id AS second_id, b.full_name AS second_name, NULL::TEXT AS second_detail, \
+                similarity(a.full_name, b.full_name)::FLOAT8 AS similarity \
+             FROM persons a \
+             INNER JOIN persons b ON a.id < b.id AND a.full_name % b.full_name \
+             WHERE a.deleted_at IS NULL AND b.deleted_at IS NULL \
+                AND similarity(a.full_name, b.full_name) >= $1"
+        }
+        AuditEntity::Genre => {
+            "SELECT a.id AS first_id, a.name AS first_name, NULL::TEXT AS first_detail, \
+                b.id AS second_id, b.name AS second_name, NULL::TEXT AS second_detail, \
+                similarity(a.name, b.name)::FLOAT8 AS similarity \
+             FROM genres a \
+             INNER JOIN genres b ON a.id < b.id AND a.name % b.name \
+             WHERE a.deleted_at IS NULL AND b.deleted_at IS NULL \
+                AND similarity(a.name, b.name) >= $1"
+        }
+    };
+    let pairs = sqlx::query_as::<_, DuplicatePair>(query)
+        .bind(DUPLICATE_THRESHOLD)
+        .fetch_all(pool)
+        .await?;
+    Ok(pairs)
+}
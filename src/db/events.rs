@@ -0,0 +1,192 @@
+//! src/db/events.rs
+//!
+//! Events of the ensemble (table events)
+//! and the secret tokens of the iCalendar feeds of the users (table calendar_tokens)
+//!
+
+use axum::http::StatusCode;
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::errors::MyAppError;
+use crate::models::event::{Event, EventValues};
+
+/// columns of an Event, the events table is "e"
+const EVENT_COLUMNS: &str = "SELECT e.id, e.kind, e.title, e.starts_at, e.duration_minutes, \
+     e.place, e.setlist_id, s.name AS setlist_name \
+     FROM events e \
+     LEFT JOIN setlists s ON s.id = e.setlist_id ";
+
+//*******************************************************************************************
+// CRUD Operations on events
+//
+
+///
+/// **Adds an event to the DB**<br>
+/// returns the added Event or MyAppError
+///
+pub async fn add_event(values: &EventValues, pool: &PgPool) -> Result<Event, MyAppError> {
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO events (kind, title, starts_at, duration_minutes, place, setlist_id) \
+         VALUES ( $1, $2, $3, $4, $5, $6 ) RETURNING id",
+    )
+    .bind(values.kind)
+    .bind(&values.title)
+    .bind(values.starts_at)
+    .bind(values.duration_minutes)
+    .bind(&values.place)
+    .bind(values.setlist_id)
+    .fetch_one(pool)
+    .await?;
+
+    let event = find_event_by_id(id, pool).await?;
+    tracing::info!("db : event added : {:?}", &event);
+    Ok(event)
+}
+
+pub async fn update_event(
+    id: i32,
+    values: &EventValues,
+    pool: &PgPool,
+) -> Result<Event, MyAppError> {
+    let result = sqlx::query(
+        "UPDATE events SET kind = $1, title = $2, starts_at = $3, duration_minutes = $4, \
+         place = $5, setlist_id = $6 WHERE id = $7",
+    )
+    .bind(values.kind)
+    .bind(&values.title)
+    .bind(values.starts_at)
+    .bind(values.duration_minutes)
+    .bind(&values.place)
+    .bind(values.setlist_id)
+    .bind(id)
+    .execute(pool)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(MyAppError::new(
+            StatusCode::NOT_FOUND,
+            "Event with the ID not found !",
+        ));
+    }
+
+    let event = find_event_by_id(id, pool).await?;
+    tracing::info!("db : event modified : {:?}", &event);
+    Ok(event)
+}
+
+///
+/// **Deletes an event**<br>
+/// returns the deleted Event or MyAppError
+///
+pub async fn delete_event(id: i32, pool: &PgPool) -> Result<Event, MyAppError> {
+    let event = find_event_by_id(id, pool).await?;
+    sqlx::query("DELETE FROM events WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    tracing::info!("db : event deleted : {:?}", &event);
+    Ok(event)
+}
+
+pub async fn find_event_by_id(id: i32, pool: &PgPool) -> Result<Event, MyAppError> {
+    let event = sqlx::query_as::<_, Event>(&format!("{EVENT_COLUMNS} WHERE e.id = $1"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| MyAppError::new(StatusCode::NOT_FOUND, "Event with the ID not found !"))?;
+    Ok(event)
+}
+
+///
+/// Returns the events starting between from (included) and to (excluded)
+/// in the order of their start
+///
+pub async fn list_events_between(
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    pool: &PgPool,
+) -> Result<Vec<Event>, MyAppError> {
+    let events = sqlx::query_as::<_, Event>(&format!(
+        "{EVENT_COLUMNS} WHERE e.starts_at >= $1 AND e.starts_at < $2 ORDER BY e.starts_at, e.id"
+    ))
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+    Ok(events)
+}
+
+///
+/// Returns the events starting after since, for the iCalendar feeds
+///
+pub async fn list_events_since(
+    since: NaiveDateTime,
+    pool: &PgPool,
+) -> Result<Vec<Event>, MyAppError> {
+    let events = sqlx::query_as::<_, Event>(&format!(
+        "{EVENT_COLUMNS} WHERE e.starts_at >= $1 ORDER BY e.starts_at, e.id"
+    ))
+    .bind(since)
+    .fetch_all(pool)
+    .await?;
+    Ok(events)
+}
+
+//*******************************************************************************************
+// Secret tokens of the iCalendar feeds
+//
+
+///
+/// Returns the token of the feed of a user
+/// the token is created the first time it is asked
+///
+pub async fn get_calendar_token(user_id: Uuid, pool: &PgPool) -> Result<String, MyAppError> {
+    sqlx::query("INSERT INTO calendar_tokens (user_id) VALUES ( $1 ) ON CONFLICT DO NOTHING")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    let token: String = sqlx::query_scalar("SELECT token FROM calendar_tokens WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(token)
+}
+
+///
+/// **Replaces the token of the feed of a user**<br>
+/// the old subscription URL stops working (e.g. when it was shared by mistake)
+///
+/// returns the new token or MyAppError
+///
+pub async fn renew_calendar_token(user_id: Uuid, pool: &PgPool) -> Result<String, MyAppError> {
+    let token: String = sqlx::query_scalar(
+        "INSERT INTO calendar_tokens (user_id) VALUES ( $1 ) \
+         ON CONFLICT (user_id) DO UPDATE SET token = DEFAULT, created_at = NOW() \
+         RETURNING token",
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    tracing::info!("db : calendar token renewed for user {}", user_id);
+    Ok(token)
+}
+
+///
+/// Returns the name of the user of a token, None if the token is unknown
+///
+pub async fn find_calendar_token_user(
+    token: &str,
+    pool: &PgPool,
+) -> Result<Option<String>, MyAppError> {
+    let name: Option<String> = sqlx::query_scalar(
+        "SELECT u.name FROM calendar_tokens t \
+         INNER JOIN users u ON u.id = t.user_id WHERE t.token = $1",
+    )
+    .bind(token)
+    .fetch_optional(pool)
+    .await?;
+    Ok(name)
+}
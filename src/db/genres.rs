@@ -2,10 +2,15 @@
 
 use crate::errors::MyAppError;
 use axum::http::StatusCode;
+use futures::stream::{self, Stream};
 use sqlx::postgres::PgRow;
-use sqlx::{PgPool, Row};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row, Transaction};
 
-use crate::models::genre::Genre;
+use crate::db::deletion::apply_delete_policy;
+use crate::models::audit::AuditEntity;
+use crate::models::deletion::DeletePolicy;
+use crate::models::genre::{Genre, GenreNode};
+use crate::models::pagination::PageParams;
 
 //*******************************************************************************************
 // CRUD Operations on genres
@@ -30,7 +35,8 @@ pub async fn add_genre(pool: &PgPool, genre_name: String) -> Result<Genre, MyApp
 pub async fn update_genre(id: i32, genre_name: String, pool: &PgPool) -> Result<Genre, MyAppError> {
     let genre = sqlx::query_as!(
         Genre,
-        "UPDATE genres SET name = $1 WHERE id = $2 RETURNING id, name;",
+        "UPDATE genres SET name = $1 WHERE id = $2 AND deleted_at IS NULL \
+        RETURNING id, name;",
         genre_name,
         id,
     )
@@ -54,24 +60,39 @@ pub async fn update_genre(id: i32, genre_name: String, pool: &PgPool) -> Result<
          */
 }
 
-pub async fn delete_genre(id: i32, pool: &PgPool) -> Result<String, MyAppError> {
-    let genre = find_genre_by_id(id, pool).await?;
-    if genre.is_none() {
-        Err(MyAppError::new(
+///
+/// **Deletes a genre on basis of the ID**<br>
+/// the genre goes to the trash (soft deletion : deleted_at is set),
+/// it can be restored or purged from the trash page<br>
+/// the partitions still using the genre are handled by the policy
+/// (block, reassign to another genre, cascade to the trash) in the same transaction<br>
+/// returns the name of the genre, or MyAppError (NOT_FOUND, or CONFLICT if
+/// partitions still use the genre and the policy is Block)
+///
+pub async fn delete_genre(id: i32, policy: DeletePolicy, pool: &PgPool) -> Result<String, MyAppError> {
+    let mut tx = pool.begin().await?;
+    let name: Option<String> = sqlx::query_scalar(
+        "SELECT name FROM genres WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+    )
+    .bind(id)
+    .fetch_optional(&mut tx)
+    .await?;
+    match name {
+        None => Err(MyAppError::new(
             StatusCode::NOT_FOUND,
             "Genre with the ID not found !",
-        ))
-    } else {
-        let genre = genre.unwrap();
-        let name = genre.name;
+        )),
+        Some(name) => {
+            apply_delete_policy(AuditEntity::Genre, id, &name, policy, &mut tx).await?;
+            let _res = sqlx::query("UPDATE genres SET deleted_at = NOW() WHERE id = $1")
+                .bind(id)
+                .execute(&mut tx)
+                .await?;
+            tx.commit().await?;
 
-        let _res = sqlx::query("DELETE FROM genres WHERE id = $1")
-            .bind(id)
-            .execute(pool)
-            .await?;
-
-        tracing::info!("db : Genre deleted : {}", &name);
-        Ok(name)
+            tracing::info!("db : Genre deleted : {}", &name);
+            Ok(name)
+        }
     }
 }
 
@@ -81,7 +102,9 @@ pub async fn delete_genre(id: i32, pool: &PgPool) -> Result<String, MyAppError>
 /// or a sqlx Error
 ///
 pub async fn list_genres(pool: &PgPool) -> Result<Vec<Genre>, MyAppError> {
-    let genres: Vec<Genre> = sqlx::query("SELECT id, name FROM genres ORDER BY name;")
+    let genres: Vec<Genre> = sqlx::query(
+        "SELECT id, name FROM genres WHERE deleted_at IS NULL ORDER BY name;",
+    )
         .map(|row: PgRow| Genre {
             id: row.get(0),
             name: row.get(1),
@@ -91,10 +114,57 @@ pub async fn list_genres(pool: &PgPool) -> Result<Vec<Genre>, MyAppError> {
     Ok(genres)
 }
 
-pub async fn find_genre_by_id(id: i32, pool: &PgPool) -> Result<Option<Genre>, MyAppError> {
-    let genre = sqlx::query_as!(Genre, "SELECT * FROM genres WHERE id = $1;", id,)
-        .fetch_optional(pool)
+///
+/// Returns one page of the list of genres
+/// sorted by name (sort=name) or by id (sort=id)
+///
+pub async fn list_genres_page(page: &PageParams, pool: &PgPool) -> Result<Vec<Genre>, MyAppError> {
+    let column = page
+        .sort_column(&[("name", "name"), ("id", "id")])
+        .unwrap_or("name");
+    let mut query = QueryBuilder::<Postgres>::new(
+        "SELECT id, name FROM genres WHERE deleted_at IS NULL ORDER BY ",
+    );
+    query
+        .push(column)
+        .push(" ")
+        .push(page.sql_dir())
+        .push(", id LIMIT ")
+        .push_bind(page.per_page())
+        .push(" OFFSET ")
+        .push_bind(page.offset());
+    let genres = query
+        .build()
+        .map(|row: PgRow| Genre {
+            id: row.get(0),
+            name: row.get(1),
+        })
+        .fetch_all(pool)
         .await?;
+    Ok(genres)
+}
+
+///
+/// Returns the number of genres
+/// used for the pagination
+///
+pub async fn count_genres(pool: &PgPool) -> Result<i64, MyAppError> {
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM genres WHERE deleted_at IS NULL"#
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(total)
+}
+
+pub async fn find_genre_by_id(id: i32, pool: &PgPool) -> Result<Option<Genre>, MyAppError> {
+    let genre = sqlx::query_as!(
+        Genre,
+        "SELECT id, name FROM genres WHERE id = $1 AND deleted_at IS NULL;",
+        id,
+    )
+    .fetch_optional(pool)
+    .await?;
     //.map_err(|err| MyAppError::from(err))?;
     /*
         let genre = sqlx::query("SELECT * FROM genres WHERE id = $1;")
@@ -111,9 +181,13 @@ pub async fn find_genre_by_id(id: i32, pool: &PgPool) -> Result<Option<Genre>, M
 }
 
 pub async fn find_genre_by_name_strict(name: String, pool: &PgPool) -> Result<Genre, MyAppError> {
-    let genre = sqlx::query_as!(Genre, "SELECT * FROM genres WHERE name = $1;", name)
-        .fetch_one(pool)
-        .await?;
+    let genre = sqlx::query_as!(
+        Genre,
+        "SELECT id, name FROM genres WHERE name = $1 AND deleted_at IS NULL;",
+        name
+    )
+    .fetch_one(pool)
+    .await?;
     Ok(genre)
 }
 ///
@@ -133,13 +207,165 @@ pub async fn find_genre_by_name_parts(
     let mut part_name = name.clone();
     part_name.push('%');
 
-    let genre = sqlx::query("SELECT * FROM genres WHERE name LIKE $1;")
-        .bind(part_name)
-        .map(|row: PgRow| Genre {
-            id: row.get("id"),
-            name: row.get("name"),
-        })
-        .fetch_all(pool)
+    let genre = sqlx::query(
+        "SELECT id, name FROM genres WHERE name LIKE $1 AND deleted_at IS NULL;",
+    )
+    .bind(part_name)
+    .map(|row: PgRow| Genre {
+        id: row.get("id"),
+        name: row.get("name"),
+    })
+    .fetch_all(pool)
+    .await?;
+    Ok(genre)
+}
+
+//*******************************************************************************************
+// Tree of the genres
+//
+
+///
+/// **Returns the tree of the genres** (not in the trash), depth first :
+/// each main genre followed by its sub-genres, sorted by name at each level<br>
+/// a sub-genre whose parent is in the trash is shown as a main genre
+///
+pub async fn list_genre_tree(pool: &PgPool) -> Result<Vec<GenreNode>, MyAppError> {
+    let nodes = sqlx::query_as::<_, GenreNode>(
+        "WITH RECURSIVE tree AS ( \
+            SELECT g.id, g.name, g.parent_id, 0 AS depth, g.name::TEXT AS path, \
+                ARRAY[lower(g.name)]::TEXT[] AS sort_key \
+            FROM genres g \
+            LEFT JOIN genres p ON p.id = g.parent_id AND p.deleted_at IS NULL \
+            WHERE g.deleted_at IS NULL AND p.id IS NULL \
+            UNION ALL \
+            SELECT g.id, g.name, g.parent_id, t.depth + 1, t.path || ' > ' || g.name, \
+                t.sort_key || lower(g.name)::TEXT \
+            FROM genres g \
+            INNER JOIN tree t ON g.parent_id = t.id \
+            WHERE g.deleted_at IS NULL \
+         ) \
+         SELECT id, name, \
+            CASE WHEN depth = 0 THEN NULL ELSE parent_id END AS parent_id, depth, path \
+         FROM tree ORDER BY sort_key, id",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(nodes)
+}
+
+///
+/// **Moves a genre under another one** (None : the genre becomes a main genre)<br>
+/// a genre can't be moved under itself or one of its sub-genres (MyAppError CONFLICT),
+/// the moves are done one at a time so that two moves can't make a cycle together
+///
+/// Returns the moved Genre or MyAppError (NOT_FOUND if a genre does not exist
+/// or is in the trash)
+///
+pub async fn move_genre(
+    id: i32,
+    parent_id: Option<i32>,
+    pool: &PgPool,
+) -> Result<Genre, MyAppError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('genres_tree'))")
+        .execute(&mut tx)
         .await?;
+
+    if let Some(parent_id) = parent_id {
+        let parent: Option<String> =
+            sqlx::query_scalar("SELECT name FROM genres WHERE id = $1 AND deleted_at IS NULL")
+                .bind(parent_id)
+                .fetch_optional(&mut tx)
+                .await?;
+        let parent = parent
+            .ok_or_else(|| MyAppError::new(StatusCode::NOT_FOUND, "genre parent introuvable"))?;
+        if is_sub_genre(parent_id, id, &mut tx).await? {
+            return Err(MyAppError::new(
+                StatusCode::CONFLICT,
+                format!("{parent} est le genre lui-même ou un de ses sous-genres"),
+            ));
+        }
+    }
+
+    let genre = sqlx::query_as::<_, Genre>(
+        "UPDATE genres SET parent_id = $2 WHERE id = $1 AND deleted_at IS NULL \
+         RETURNING id, name",
+    )
+    .bind(id)
+    .bind(parent_id)
+    .fetch_one(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    tracing::info!("db : genre moved : {:?} under {:?}", &genre, parent_id);
     Ok(genre)
 }
+
+///
+/// Returns true if the genre `id` is the genre `ancestor_id` or one of its
+/// sub-genres (at any depth, in the trash or not)
+///
+pub async fn is_sub_genre(
+    id: i32,
+    ancestor_id: i32,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<bool, MyAppError> {
+    let found: bool = sqlx::query_scalar(
+        "WITH RECURSIVE sub_genres AS ( \
+            SELECT id FROM genres WHERE id = $1 \
+            UNION \
+            SELECT g.id FROM genres g INNER JOIN sub_genres s ON g.parent_id = s.id \
+         ) \
+         SELECT EXISTS (SELECT 1 FROM sub_genres WHERE id = $2)",
+    )
+    .bind(ancestor_id)
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+    Ok(found)
+}
+
+///
+/// Number of genres read at once by the export
+///
+const EXPORT_BATCH_SIZE: i64 = 500;
+
+///
+/// **Streams the genres whose name begins with the pattern** (e.g. "CH%")<br>
+/// ordered by name, in batches of EXPORT_BATCH_SIZE genres
+/// (keyset pagination on the name and the id)
+///
+pub fn stream_genres(
+    name_pattern: String,
+    pool: PgPool,
+) -> impl Stream<Item = Result<Vec<Genre>, MyAppError>> {
+    stream::try_unfold(Some((String::new(), 0)), move |cursor| {
+        let name_pattern = name_pattern.clone();
+        let pool = pool.clone();
+        async move {
+            let (name, id) = match cursor {
+                Some(cursor) => cursor,
+                None => return Ok(None),
+            };
+            let genres = sqlx::query_as::<_, Genre>(
+                "SELECT id, name FROM genres
+                WHERE name LIKE $1 AND deleted_at IS NULL AND (name, id) > ($2, $3)
+                ORDER BY name, id LIMIT $4",
+            )
+            .bind(name_pattern)
+            .bind(name)
+            .bind(id)
+            .bind(EXPORT_BATCH_SIZE)
+            .fetch_all(&pool)
+            .await?;
+
+            let next = match genres.last() {
+                Some(last) if genres.len() as i64 == EXPORT_BATCH_SIZE => {
+                    Some((last.name.clone(), last.id))
+                }
+                _ => None,
+            };
+            Ok((!genres.is_empty()).then_some((genres, next)))
+        }
+    })
+}
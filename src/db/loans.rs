@@ -0,0 +1,242 @@
+//! src/db/loans.rs
+//!
+//! Loans of the copies of the partitions (table loans)
+//!
+//! A partition can be lent while one of its copies is not lent nor missing :
+//! the row of the partition is locked while the copy is chosen so that
+//! two loans of the last copy at the same time are not possible
+//!
+
+use axum::http::StatusCode;
+use chrono::NaiveDate;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use uuid::Uuid;
+
+use crate::errors::MyAppError;
+use crate::models::loan::{Loan, LoanFilter};
+use crate::models::pagination::PageParams;
+
+/// columns of a Loan, the loans table is "l"
+const LOAN_COLUMNS: &str =
+    "SELECT l.id, l.partition_id, l.copy_id, p.title, l.user_id, u.name AS user_name, \
+     l.borrowed_at, l.due_date, l.returned_at \
+     FROM loans l \
+     INNER JOIN partitions p ON p.id = l.partition_id \
+     INNER JOIN users u ON u.id = l.user_id ";
+
+///
+/// **Lends a copy of a partition to a user**<br>
+/// the copy in good condition with the smallest number is chosen,
+/// a damaged copy only if there is no other one
+///
+/// returns the Loan or MyAppError
+/// (NOT_FOUND if the partition does not exist or is in the trash,
+/// CONFLICT if all the copies of the partition are lent or missing)
+///
+pub async fn lend_partition(
+    partition_id: i32,
+    user_id: Uuid,
+    due_date: NaiveDate,
+    pool: &PgPool,
+) -> Result<Loan, MyAppError> {
+    let mut tx = pool.begin().await?;
+    // a partition in the trash can't be lent
+    let partition: Option<i32> = sqlx::query_scalar(
+        "SELECT id FROM partitions WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+    )
+    .bind(partition_id)
+    .fetch_optional(&mut tx)
+    .await?;
+    if partition.is_none() {
+        return Err(MyAppError::new(
+            StatusCode::NOT_FOUND,
+            "Partition with the ID not found !",
+        ));
+    }
+    let copy_id: Option<i32> = sqlx::query_scalar(
+        "SELECT c.id FROM partition_copies c \
+         WHERE c.partition_id = $1 AND c.condition <> 'missing' \
+         AND NOT EXISTS (SELECT 1 FROM loans l WHERE l.copy_id = c.id AND l.returned_at IS NULL) \
+         ORDER BY c.condition = 'damaged', c.id LIMIT 1",
+    )
+    .bind(partition_id)
+    .fetch_optional(&mut tx)
+    .await?;
+    let copy_id = copy_id
+        .ok_or_else(|| MyAppError::new(StatusCode::CONFLICT, "aucun exemplaire disponible"))?;
+
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO loans (partition_id, copy_id, user_id, due_date) \
+         VALUES ( $1, $2, $3, $4 ) RETURNING id",
+    )
+    .bind(partition_id)
+    .bind(copy_id)
+    .bind(user_id)
+    .bind(due_date)
+    .fetch_one(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    let loan = find_loan_by_id(id, pool).await?;
+    tracing::info!("db : loan added : {:?}", &loan);
+    Ok(loan)
+}
+
+///
+/// **Returns a lent copy**<br>
+/// returns the Loan or MyAppError (NOT_FOUND if the loan is unknown or already returned)
+///
+pub async fn return_loan(id: i32, pool: &PgPool) -> Result<Loan, MyAppError> {
+    let result =
+        sqlx::query("UPDATE loans SET returned_at = NOW() WHERE id = $1 AND returned_at IS NULL")
+            .bind(id)
+            .execute(pool)
+            .await?;
+    if result.rows_affected() == 0 {
+        return Err(MyAppError::new(
+            StatusCode::NOT_FOUND,
+            "Loan with the ID not found or already returned !",
+        ));
+    }
+
+    let loan = find_loan_by_id(id, pool).await?;
+    tracing::info!("db : loan returned : {:?}", &loan);
+    Ok(loan)
+}
+
+pub async fn find_loan_by_id(id: i32, pool: &PgPool) -> Result<Loan, MyAppError> {
+    let loan = sqlx::query_as::<_, Loan>(&format!("{LOAN_COLUMNS} WHERE l.id = $1"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| MyAppError::new(StatusCode::NOT_FOUND, "Loan with the ID not found !"))?;
+    Ok(loan)
+}
+
+///
+/// Returns one page of the loans matching the filter
+/// sorted by date of loan (default, the last ones first), due date, title or borrower
+///
+pub async fn list_loans_page(
+    filter: &LoanFilter,
+    page: &PageParams,
+    pool: &PgPool,
+) -> Result<Vec<Loan>, MyAppError> {
+    let column = page
+        .sort_column(&[
+            ("borrowed", "l.borrowed_at"),
+            ("due", "l.due_date"),
+            ("title", "lower(p.title)"),
+            ("user", "lower(u.name)"),
+        ])
+        .unwrap_or("l.borrowed_at");
+    // the last loans first when no sort is asked
+    let dir = if page.sort.is_none() {
+        "DESC"
+    } else {
+        page.sql_dir()
+    };
+
+    let mut query = QueryBuilder::<Postgres>::new(LOAN_COLUMNS);
+    push_loan_filter(&mut query, filter);
+    query
+        .push(" ORDER BY ")
+        .push(column)
+        .push(" ")
+        .push(dir)
+        .push(", l.id DESC LIMIT ")
+        .push_bind(page.per_page())
+        .push(" OFFSET ")
+        .push_bind(page.offset());
+    let loans = query.build_query_as::<Loan>().fetch_all(pool).await?;
+    Ok(loans)
+}
+
+///
+/// Returns the number of loans matching the filter
+/// used for the pagination
+///
+pub async fn count_loans(filter: &LoanFilter, pool: &PgPool) -> Result<i64, MyAppError> {
+    let mut query = QueryBuilder::<Postgres>::new(
+        "SELECT COUNT(*) FROM loans l \
+         INNER JOIN partitions p ON p.id = l.partition_id \
+         INNER JOIN users u ON u.id = l.user_id ",
+    );
+    push_loan_filter(&mut query, filter);
+    let total: i64 = query.build().fetch_one(pool).await?.get(0);
+    Ok(total)
+}
+
+///
+/// Returns the loans of a user : the open ones first (by due date), then the history
+///
+pub async fn list_user_loans(user_id: Uuid, pool: &PgPool) -> Result<Vec<Loan>, MyAppError> {
+    let loans = sqlx::query_as::<_, Loan>(&format!(
+        "{LOAN_COLUMNS} WHERE l.user_id = $1 \
+         ORDER BY l.returned_at IS NOT NULL, l.due_date, l.returned_at DESC, l.id DESC"
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(loans)
+}
+
+///
+/// Returns the open loans past their due date, the oldest due date first
+///
+pub async fn list_overdue_loans(pool: &PgPool) -> Result<Vec<Loan>, MyAppError> {
+    let loans = sqlx::query_as::<_, Loan>(&format!(
+        "{LOAN_COLUMNS} WHERE l.returned_at IS NULL AND l.due_date < CURRENT_DATE \
+         ORDER BY l.due_date, lower(u.name)"
+    ))
+    .fetch_all(pool)
+    .await?;
+    Ok(loans)
+}
+
+///
+/// Returns the open loans of the partitions, the oldest due date first
+/// (impact of the deletion of a musician or a genre)
+///
+pub async fn list_open_loans_for_partitions(
+    partition_ids: &[i32],
+    pool: &PgPool,
+) -> Result<Vec<Loan>, MyAppError> {
+    let loans = sqlx::query_as::<_, Loan>(&format!(
+        "{LOAN_COLUMNS} WHERE l.returned_at IS NULL AND l.partition_id = ANY($1) \
+         ORDER BY l.due_date, lower(u.name)"
+    ))
+    .bind(partition_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(loans)
+}
+
+//*******************************************************************************
+// Helpers functions
+//
+
+///
+/// Adds the WHERE clause of the filter of the loans
+///
+fn push_loan_filter(query: &mut QueryBuilder<'_, Postgres>, filter: &LoanFilter) {
+    query.push(" WHERE TRUE");
+    match filter.status() {
+        Some("open") => {
+            query.push(" AND l.returned_at IS NULL");
+        }
+        Some("overdue") => {
+            query.push(" AND l.returned_at IS NULL AND l.due_date < CURRENT_DATE");
+        }
+        Some("returned") => {
+            query.push(" AND l.returned_at IS NOT NULL");
+        }
+        _ => {}
+    }
+    if let Some(user_id) = filter.user_id() {
+        query.push(" AND l.user_id = ").push_bind(user_id);
+    }
+    if let Some(partition_id) = filter.partition_id() {
+        query.push(" AND l.partition_id = ").push_bind(partition_id);
+    }
+}
@@ -0,0 +1,197 @@
+//! src/db/merge.rs
+//!
+//! Merge of two musicians or two genres (duplicates) : the partitions of the
+//! loser are moved to the survivor, then the loser is deleted, in one transaction
+//!
+
+use axum::http::StatusCode;
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::db::genres::is_sub_genre;
+use crate::db::trash::columns_of;
+use crate::errors::MyAppError;
+use crate::models::audit::AuditEntity;
+use crate::models::duplicate::{MergeReport, MergedRecord};
+
+///
+/// **Merges a musician or a genre (the loser) into another one (the survivor)**<br>
+/// every partition of the loser (in the trash or not) goes to the survivor :
+/// main musician, other musicians with their roles, genre<br>
+/// the loser is then deleted for good
+///
+/// Returns the MergeReport or MyAppError (NOT_FOUND if one of them does not exist
+/// or is in the trash, BAD_REQUEST for a partition or the same record twice)
+///
+pub async fn merge_records(
+    entity: AuditEntity,
+    loser_id: i32,
+    survivor_id: i32,
+    pool: &PgPool,
+) -> Result<MergeReport, MyAppError> {
+    if loser_id == survivor_id {
+        return Err(MyAppError::new(
+            StatusCode::BAD_REQUEST,
+            "impossible de fusionner un enregistrement avec lui-même",
+        ));
+    }
+    if entity == AuditEntity::Partition {
+        return Err(MyAppError::new(
+            StatusCode::BAD_REQUEST,
+            "seuls les musiciens et les genres peuvent être fusionnés",
+        ));
+    }
+    let (table, name) = columns_of(entity);
+
+    let mut tx = pool.begin().await?;
+    // the two records are locked until the end of the merge
+    let records = sqlx::query_as::<_, MergedRecord>(&format!(
+        "SELECT id, {name} AS name FROM {table} \
+         WHERE id IN ($1, $2) AND deleted_at IS NULL FOR UPDATE"
+    ))
+    .bind(loser_id)
+    .bind(survivor_id)
+    .fetch_all(&mut tx)
+    .await?;
+    let record = |id: i32| {
+        records
+            .iter()
+            .find(|record| record.id == id)
+            .cloned()
+            .ok_or_else(|| {
+                MyAppError::new(
+                    StatusCode::NOT_FOUND,
+                    format!("{} {id} introuvable", entity.label()),
+                )
+            })
+    };
+    let loser = record(loser_id)?;
+    let survivor = record(survivor_id)?;
+
+    let partition_ids = reassign_partitions(entity, loser_id, survivor_id, &mut tx).await?;
+    if entity == AuditEntity::Genre {
+        reassign_sub_genres(loser_id, survivor_id, &mut tx).await?;
+    }
+    sqlx::query(&format!("DELETE FROM {table} WHERE id = $1"))
+        .bind(loser_id)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+
+    let report = MergeReport {
+        entity,
+        loser,
+        survivor,
+        partition_ids,
+    };
+    tracing::info!("db : merged : {:?}", &report);
+    Ok(report)
+}
+
+///
+/// Returns the ids of the partitions (in the trash or not) using a musician
+/// (main musician or other musician) or a genre
+///
+pub async fn partitions_using(
+    entity: AuditEntity,
+    id: i32,
+    pool: &PgPool,
+) -> Result<Vec<i32>, MyAppError> {
+    let query = match entity {
+        AuditEntity::Partition => return Ok(Vec::new()),
+        AuditEntity::Person => {
+            "SELECT id FROM partitions WHERE person_id = $1 \
+             UNION SELECT partition_id FROM partition_contributors WHERE person_id = $1 \
+             ORDER BY 1"
+        }
+        AuditEntity::Genre => "SELECT id FROM partitions WHERE genre_id = $1 ORDER BY id",
+    };
+    let ids: Vec<i32> = sqlx::query_scalar(query).bind(id).fetch_all(pool).await?;
+    Ok(ids)
+}
+
+///
+/// **Moves the partitions of a musician or a genre to another one** (in a transaction)<br>
+/// a musician already contributing to a partition with the same role keeps one row
+///
+/// Returns the ids of the partitions moved
+///
+pub async fn reassign_partitions(
+    entity: AuditEntity,
+    from_id: i32,
+    to_id: i32,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<Vec<i32>, MyAppError> {
+    let partition_ids: Vec<i32> = match entity {
+        AuditEntity::Partition => Vec::new(),
+        AuditEntity::Person => {
+            let mut ids: Vec<i32> = sqlx::query_scalar(
+                "UPDATE partitions SET person_id = $2 WHERE person_id = $1 RETURNING id",
+            )
+            .bind(from_id)
+            .bind(to_id)
+            .fetch_all(&mut *tx)
+            .await?;
+            sqlx::query(
+                "INSERT INTO partition_contributors (partition_id, person_id, role, position) \
+                 SELECT partition_id, $2, role, position FROM partition_contributors \
+                 WHERE person_id = $1 \
+                 ON CONFLICT DO NOTHING",
+            )
+            .bind(from_id)
+            .bind(to_id)
+            .execute(&mut *tx)
+            .await?;
+            let contributed: Vec<i32> = sqlx::query_scalar(
+                "DELETE FROM partition_contributors WHERE person_id = $1 RETURNING partition_id",
+            )
+            .bind(from_id)
+            .fetch_all(&mut *tx)
+            .await?;
+            ids.extend(contributed);
+            ids.sort_unstable();
+            ids.dedup();
+            ids
+        }
+        AuditEntity::Genre => {
+            sqlx::query_scalar(
+                "UPDATE partitions SET genre_id = $2 WHERE genre_id = $1 RETURNING id",
+            )
+            .bind(from_id)
+            .bind(to_id)
+            .fetch_all(&mut *tx)
+            .await?
+        }
+    };
+    Ok(partition_ids)
+}
+
+///
+/// **Moves the sub-genres of a genre to another one** (merge of two genres)<br>
+/// a survivor that is a sub-genre of the loser first takes the place of the loser
+/// in the tree, so that the merge can't make a cycle
+///
+async fn reassign_sub_genres(
+    loser_id: i32,
+    survivor_id: i32,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<(), MyAppError> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('genres_tree'))")
+        .execute(&mut *tx)
+        .await?;
+    if is_sub_genre(survivor_id, loser_id, tx).await? {
+        sqlx::query(
+            "UPDATE genres SET parent_id = (SELECT parent_id FROM genres WHERE id = $1) \
+             WHERE id = $2",
+        )
+        .bind(loser_id)
+        .bind(survivor_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    sqlx::query("UPDATE genres SET parent_id = $2 WHERE parent_id = $1 AND id <> $2")
+        .bind(loser_id)
+        .bind(survivor_id)
+        .execute(&mut *tx)
+        .await?;
+    Ok(())
+}
@@ -1,6 +1,20 @@
 /// src/db/mod.rs
+pub mod audit;
+pub mod collections;
 pub mod connect;
+pub mod copies;
+pub mod deletion;
+pub mod duplicates;
+pub mod events;
 pub mod genres;
+pub mod loans;
+pub mod merge;
 pub mod musicians;
+pub mod partition_files;
+pub mod partition_import;
 pub mod partitions;
+pub mod setlists;
+pub mod statistics;
+pub mod tags;
+pub mod trash;
 pub mod users;
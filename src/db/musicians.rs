@@ -2,9 +2,14 @@
 
 use crate::errors::MyAppError;
 use axum::http::StatusCode;
-use sqlx::PgPool;
+use futures::stream::{self, Stream};
+use sqlx::{PgPool, Postgres, QueryBuilder};
 
-use crate::models::musician::Person;
+use crate::db::deletion::apply_delete_policy;
+use crate::models::audit::AuditEntity;
+use crate::models::deletion::DeletePolicy;
+use crate::models::musician::{Person, PersonProfile, PersonWork};
+use crate::models::pagination::PageParams;
 
 //*******************************************************************************************
 // CRUD Operations on persons - musicians
@@ -51,7 +56,8 @@ pub async fn update_person(
 ) -> Result<Person, MyAppError> {
     let person = sqlx::query_as!(
         Person,
-        "UPDATE persons SET full_name = $1 WHERE id = $2 RETURNING id, full_name;",
+        "UPDATE persons SET full_name = $1 WHERE id = $2 AND deleted_at IS NULL \
+        RETURNING id, full_name;",
         person_name,
         id,
     )
@@ -61,26 +67,42 @@ pub async fn update_person(
     Ok(person)
 }
 ///
-/// **deletes a person (musician) from the DB on basis of the ID**<br>
-/// first check if the person exists in the DB, then delete that person.<br>
-/// if the person doesn't exist in the DB return MyAppError<br>
-/// if the person cannot be deleted return MyAppError
+/// **deletes a person (musician) on basis of the ID**<br>
+/// the person goes to the trash (soft deletion : deleted_at is set),
+/// it can be restored or purged from the trash page<br>
+/// the partitions still using the person are handled by the policy
+/// (block, reassign to another person, cascade to the trash) in the same transaction<br>
+/// if the person doesn't exist in the DB return MyAppError (NOT_FOUND)<br>
+/// if partitions still use the person and the policy is Block return MyAppError (CONFLICT)
 ///
 /// Notice that the person normally exists since we start with the list of persons
 /// in the DB ...
 ///
 #[allow(dead_code)]
-pub async fn delete_person(id: i32, pool: &PgPool) -> Result<Person, MyAppError> {
-    let person = find_person_by_id(id, pool).await?;
+pub async fn delete_person(
+    id: i32,
+    policy: DeletePolicy,
+    pool: &PgPool,
+) -> Result<Person, MyAppError> {
+    let mut tx = pool.begin().await?;
+    let person = sqlx::query_as::<_, Person>(
+        "SELECT id, full_name FROM persons WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+    )
+    .bind(id)
+    .fetch_optional(&mut tx)
+    .await?;
     match person {
         Some(p) => {
-            sqlx::query_as!(Person, "DELETE FROM persons WHERE id= $1", id,)
-                .execute(pool)
+            apply_delete_policy(AuditEntity::Person, id, &p.full_name, policy, &mut tx).await?;
+            sqlx::query("UPDATE persons SET deleted_at = NOW() WHERE id = $1")
+                .bind(id)
+                .execute(&mut tx)
                 .await?;
+            tx.commit().await?;
             Ok(p)
         }
         None => Err(MyAppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::NOT_FOUND,
             "Error: Person doesn't exist",
         )),
     }
@@ -93,16 +115,20 @@ pub async fn delete_person(id: i32, pool: &PgPool) -> Result<Person, MyAppError>
 ///
 /// **Finds one person by id**<br>
 /// Returns an Option with the found Person<br>
-/// Or None is there is no Person with the id in the DB<br>
+/// Or None is there is no Person with the id in the DB (or it is in the trash)<br>
 /// In case of error (DB Error from sqlx) returns MyAppError
 ///
 /// used as help function for others
 ///
 #[allow(dead_code)]
 pub async fn find_person_by_id(id: i32, pool: &PgPool) -> Result<Option<Person>, MyAppError> {
-    let person = sqlx::query_as!(Person, "SELECT * FROM persons WHERE id = $1", id)
-        .fetch_optional(pool)
-        .await?;
+    let person = sqlx::query_as!(
+        Person,
+        "SELECT id, full_name FROM persons WHERE id = $1 AND deleted_at IS NULL",
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
     //.map_err(|err| MyAppError::from(err))?;
     Ok(person)
 }
@@ -120,7 +146,7 @@ pub async fn find_persons_by_name_strict(
 ) -> Result<Person, MyAppError> {
     let person = sqlx::query_as!(
         Person,
-        "SELECT * FROM persons WHERE full_name = $1",
+        "SELECT id, full_name FROM persons WHERE full_name = $1 AND deleted_at IS NULL",
         full_name
     )
     .fetch_one(pool)
@@ -150,7 +176,7 @@ pub async fn find_persons_by_name_parts(
 
     let persons = sqlx::query_as!(
         Person,
-        "SELECT * FROM persons WHERE full_name LIKE $1",
+        "SELECT id, full_name FROM persons WHERE full_name LIKE $1 AND deleted_at IS NULL",
         name
     )
     .fetch_all(pool)
@@ -158,6 +184,77 @@ pub async fn find_persons_by_name_parts(
     Ok(persons)
 }
 
+//**********************************************************************************
+// Profile of a musician and its partitions
+//
+
+/// columns of a PersonProfile
+const PROFILE_COLUMNS: &str = "id, full_name, birth_year, death_year, nationality, \
+     biography, alternative_names";
+
+///
+/// **Finds the profile of a musician by id**<br>
+/// Returns the PersonProfile or MyAppError (NOT_FOUND if it does not exist or is in the trash)
+///
+pub async fn find_person_profile(id: i32, pool: &PgPool) -> Result<PersonProfile, MyAppError> {
+    let profile = sqlx::query_as::<_, PersonProfile>(&format!(
+        "SELECT {PROFILE_COLUMNS} FROM persons WHERE id = $1 AND deleted_at IS NULL"
+    ))
+    .bind(id)
+    .fetch_one(pool)
+    .await?;
+    Ok(profile)
+}
+
+///
+/// **Updates the profile of a musician** (not its name, see update_person)<br>
+/// Returns the modified PersonProfile or MyAppError (NOT_FOUND)
+///
+pub async fn update_person_profile(
+    id: i32,
+    profile: &PersonProfile,
+    pool: &PgPool,
+) -> Result<PersonProfile, MyAppError> {
+    let profile = sqlx::query_as::<_, PersonProfile>(&format!(
+        "UPDATE persons SET birth_year = $2, death_year = $3, nationality = $4, \
+            biography = $5, alternative_names = $6 \
+         WHERE id = $1 AND deleted_at IS NULL \
+         RETURNING {PROFILE_COLUMNS}"
+    ))
+    .bind(id)
+    .bind(profile.birth_year)
+    .bind(profile.death_year)
+    .bind(&profile.nationality)
+    .bind(&profile.biography)
+    .bind(&profile.alternative_names)
+    .fetch_one(pool)
+    .await?;
+
+    tracing::info!("db : person profile updated : {:?}", &profile);
+    Ok(profile)
+}
+
+///
+/// **Returns the partitions of a musician** (main musician or other musician,
+/// not in the trash) with the roles of the musician, ordered by genre then title
+///
+pub async fn list_person_works(id: i32, pool: &PgPool) -> Result<Vec<PersonWork>, MyAppError> {
+    let works = sqlx::query_as::<_, PersonWork>(
+        "SELECT p.id, p.title, g.name AS genre, \
+            COALESCE(string_agg(DISTINCT pc.role::TEXT, ','), '') AS roles \
+         FROM partitions p \
+         INNER JOIN genres g ON g.id = p.genre_id \
+         LEFT JOIN partition_contributors pc ON pc.partition_id = p.id AND pc.person_id = $1 \
+         WHERE p.deleted_at IS NULL AND (p.person_id = $1 OR pc.person_id IS NOT NULL) \
+         GROUP BY p.id, p.title, g.name \
+         ORDER BY lower(g.name), lower(p.title), p.id",
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+    Ok(works)
+}
+
 ///
 /// **Returns a list of all musicians**<br>
 /// under the form of a Vec(Person)
@@ -167,9 +264,93 @@ pub async fn find_persons_by_name_parts(
 pub async fn list_persons(pool: &PgPool) -> Result<Vec<Person>, MyAppError> {
     let persons = sqlx::query_as!(
         Person,
-        "SELECT id, full_name FROM persons ORDER BY full_name"
+        "SELECT id, full_name FROM persons WHERE deleted_at IS NULL ORDER BY full_name"
     )
     .fetch_all(pool)
     .await?;
     Ok(persons)
 }
+
+///
+/// **Returns one page of the list of musicians**<br>
+/// sorted by name (sort=name) or by id (sort=id)
+///
+pub async fn list_persons_page(
+    page: &PageParams,
+    pool: &PgPool,
+) -> Result<Vec<Person>, MyAppError> {
+    let column = page
+        .sort_column(&[("name", "full_name"), ("id", "id")])
+        .unwrap_or("full_name");
+    let mut query = QueryBuilder::<Postgres>::new(
+        "SELECT id, full_name FROM persons WHERE deleted_at IS NULL ORDER BY ",
+    );
+    query
+        .push(column)
+        .push(" ")
+        .push(page.sql_dir())
+        .push(", id LIMIT ")
+        .push_bind(page.per_page())
+        .push(" OFFSET ")
+        .push_bind(page.offset());
+    let persons = query.build_query_as::<Person>().fetch_all(pool).await?;
+    Ok(persons)
+}
+
+///
+/// **Returns the number of musicians**<br>
+/// used for the pagination
+///
+pub async fn count_persons(pool: &PgPool) -> Result<i64, MyAppError> {
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM persons WHERE deleted_at IS NULL"#
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(total)
+}
+
+///
+/// Number of musicians read at once by the export
+///
+const EXPORT_BATCH_SIZE: i64 = 500;
+
+///
+/// **Streams the musicians whose name begins with the pattern** (e.g. "BR%")<br>
+/// ordered by name, in batches of EXPORT_BATCH_SIZE musicians
+/// (keyset pagination on the name and the id)
+///
+pub fn stream_persons(
+    name_pattern: String,
+    pool: PgPool,
+) -> impl Stream<Item = Result<Vec<Person>, MyAppError>> {
+    stream::try_unfold(Some((String::new(), 0)), move |cursor| {
+        let name_pattern = name_pattern.clone();
+        let pool = pool.clone();
+        async move {
+            let (full_name, id) = match cursor {
+                Some(cursor) => cursor,
+                None => return Ok(None),
+            };
+            let persons = sqlx::query_as::<_, Person>(
+                "SELECT id, full_name FROM persons
+                WHERE full_name LIKE $1 AND deleted_at IS NULL AND (full_name, id) > ($2, $3)
+                ORDER BY full_name, id LIMIT $4",
+            )
+            .bind(name_pattern)
+            .bind(full_name)
+            .bind(id)
+            .bind(EXPORT_BATCH_SIZE)
+            .fetch_all(&pool)
+            .await?;
+
+            let next = match persons.last() {
+                Some(last) if persons.len() as i64 == EXPORT_BATCH_SIZE => {
+                    Some((last.full_name.clone(), last.id))
+                }
+                _ => None,
+            };
+            Ok((!persons.is_empty()).then_some((persons, next)))
+        }
+    })
+}
@@ -0,0 +1,102 @@
+//! src/db/partition_files.rs
+//!
+//! Operations on the files attached to the partitions (table partition_files)
+//! Only the metadata is stored in the DB, the content is on the disk (see storage.rs)
+//!
+
+use sqlx::PgPool;
+
+use crate::errors::MyAppError;
+use crate::models::partition_file::PartitionFile;
+
+///
+/// **Adds the metadata of a file attached to a partition**<br>
+/// returns the added PartitionFile or MyAppError
+///
+pub async fn add_partition_file(
+    partition_id: i32,
+    file_name: String,
+    stored_name: String,
+    mime_type: String,
+    size_bytes: i64,
+    checksum: String,
+    pool: &PgPool,
+) -> Result<PartitionFile, MyAppError> {
+    let file = sqlx::query_as!(
+        PartitionFile,
+        r#"INSERT INTO partition_files
+                (partition_id, file_name, stored_name, mime_type, size_bytes, checksum)
+                VALUES ( $1, $2, $3, $4, $5, $6 )
+                RETURNING id, partition_id, file_name, stored_name, mime_type, size_bytes, checksum;"#,
+        partition_id,
+        file_name,
+        stored_name,
+        mime_type,
+        size_bytes,
+        checksum,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    tracing::info!("db : partition file added : {:?}", &file);
+    Ok(file)
+}
+
+///
+/// **Deletes the metadata of a file**<br>
+/// returns the deleted PartitionFile (to remove the file from the disk)
+/// or MyAppError
+///
+pub async fn delete_partition_file(id: i32, pool: &PgPool) -> Result<PartitionFile, MyAppError> {
+    let file = sqlx::query_as!(
+        PartitionFile,
+        r#"DELETE FROM partition_files WHERE id = $1
+                RETURNING id, partition_id, file_name, stored_name, mime_type, size_bytes, checksum;"#,
+        id,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    tracing::info!("db : partition file deleted : {}", &file.file_name);
+    Ok(file)
+}
+
+///
+/// **Finds one file by id**<br>
+/// Returns an Option with the PartitionFile or None
+///
+pub async fn find_partition_file_by_id(
+    id: i32,
+    pool: &PgPool,
+) -> Result<Option<PartitionFile>, MyAppError> {
+    let file = sqlx::query_as!(
+        PartitionFile,
+        r#"SELECT id, partition_id, file_name, stored_name, mime_type, size_bytes, checksum
+                FROM partition_files WHERE id = $1;"#,
+        id,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(file)
+}
+
+///
+/// **Returns the files attached to a list of partitions**<br>
+/// used to show the files next to the partitions in the templates
+///
+pub async fn list_files_for_partitions(
+    partition_ids: &[i32],
+    pool: &PgPool,
+) -> Result<Vec<PartitionFile>, MyAppError> {
+    let files = sqlx::query_as!(
+        PartitionFile,
+        r#"SELECT id, partition_id, file_name, stored_name, mime_type, size_bytes, checksum
+                FROM partition_files
+                WHERE partition_id = ANY($1)
+                ORDER BY file_name;"#,
+        partition_ids,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(files)
+}
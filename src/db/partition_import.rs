@@ -0,0 +1,206 @@
+//! src/db/partition_import.rs
+//!
+//! Bulk import of partitions from a CSV file :
+//! the dry-run (resolution of the musicians and genres, nothing is written)
+//! and the import itself (all the rows in one transaction)
+//!
+
+use axum::http::StatusCode;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashMap;
+
+use crate::db::partitions::insert_partition;
+use crate::errors::MyAppError;
+use crate::models::partition_import::{ImportOptions, ImportReport, ImportRow, ImportSummary};
+
+///
+/// **Dry-run of the import**<br>
+/// the musicians and the genres of the rows are looked for in the DB
+/// (name compared case insensitive, as in the spreadsheets the case is not reliable)<br>
+/// an unknown one is created by the import if the option allows it,
+/// otherwise it's an error of the row
+///
+/// Nothing is written in the DB
+///
+pub async fn preview_import(
+    rows: Vec<ImportRow>,
+    options: ImportOptions,
+    pool: &PgPool,
+) -> Result<ImportReport, MyAppError> {
+    let persons = existing_names("persons", "full_name", &rows, |row| &row.full_name, pool).await?;
+    let genres = existing_names("genres", "name", &rows, |row| &row.name, pool).await?;
+
+    let mut report = ImportReport::default();
+    for mut row in rows {
+        if !row.full_name.is_empty() && !persons.contains(&row.full_name.to_lowercase()) {
+            if options.create_persons {
+                row.new_person = true;
+                push_new_name(&mut report.new_persons, &row.full_name);
+            } else {
+                row.errors
+                    .push(format!("musicien inconnu : {}", row.full_name));
+            }
+        }
+        if !row.name.is_empty() && !genres.contains(&row.name.to_lowercase()) {
+            if options.create_genres {
+                row.new_genre = true;
+                push_new_name(&mut report.new_genres, &row.name);
+            } else {
+                row.errors.push(format!("genre inconnu : {}", row.name));
+            }
+        }
+        report.rows.push(row);
+    }
+    Ok(report)
+}
+
+///
+/// **Imports the rows of a valid report**<br>
+/// the partitions, and the musicians and genres to create,
+/// are inserted in one transaction : if one insert fails nothing is imported
+///
+/// Returns the numbers of inserted partitions, musicians and genres
+/// or MyAppError (UNPROCESSABLE_ENTITY if the report has errors)
+///
+pub async fn import_partitions(
+    report: &ImportReport,
+    options: ImportOptions,
+    pool: &PgPool,
+) -> Result<ImportSummary, MyAppError> {
+    if !report.is_valid() {
+        return Err(MyAppError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("{} ligne(s) en erreur", report.error_count()),
+        ));
+    }
+
+    let mut summary = ImportSummary::default();
+    let mut person_ids: HashMap<String, i32> = HashMap::new();
+    let mut genre_ids: HashMap<String, i32> = HashMap::new();
+
+    let mut tx = pool.begin().await?;
+    for row in &report.rows {
+        let person_key = row.full_name.to_lowercase();
+        let person_id = match person_ids.get(&person_key) {
+            Some(id) => *id,
+            None => {
+                let (id, created) = find_or_create_id(
+                    "persons",
+                    "full_name",
+                    &row.full_name,
+                    options.create_persons,
+                    &mut tx,
+                )
+                .await?;
+                summary.persons += usize::from(created);
+                person_ids.insert(person_key, id);
+                id
+            }
+        };
+
+        let genre_key = row.name.to_lowercase();
+        let genre_id = match genre_ids.get(&genre_key) {
+            Some(id) => *id,
+            None => {
+                let (id, created) =
+                    find_or_create_id("genres", "name", &row.name, options.create_genres, &mut tx)
+                        .await?;
+                summary.genres += usize::from(created);
+                genre_ids.insert(genre_key, id);
+                id
+            }
+        };
+
+        insert_partition(
+            row.title.clone(),
+            person_id,
+            genre_id,
+            &row.details,
+            &mut tx,
+        )
+        .await?;
+        summary.partitions += 1;
+    }
+    tx.commit().await?;
+
+    tracing::info!("db : partitions imported : {:?}", &summary);
+    Ok(summary)
+}
+
+//*******************************************************************************
+// Helpers functions
+//
+
+///
+/// Returns the names (lower case) of the rows found in a table
+/// (the rows in the trash are ignored)
+///
+async fn existing_names(
+    table: &'static str,
+    column: &'static str,
+    rows: &[ImportRow],
+    name_of: fn(&ImportRow) -> &String,
+    pool: &PgPool,
+) -> Result<Vec<String>, MyAppError> {
+    let names = rows
+        .iter()
+        .map(|row| name_of(row).to_lowercase())
+        .collect::<Vec<String>>();
+    let existing = sqlx::query_scalar(&format!(
+        "SELECT DISTINCT lower({column}) FROM {table} \
+         WHERE lower({column}) = ANY($1) AND deleted_at IS NULL"
+    ))
+    .bind(names)
+    .fetch_all(pool)
+    .await?;
+    Ok(existing)
+}
+
+///
+/// Adds a name to the list of the names to create, once (case insensitive)
+///
+fn push_new_name(names: &mut Vec<String>, name: &str) {
+    if !names
+        .iter()
+        .any(|n| n.to_lowercase() == name.to_lowercase())
+    {
+        names.push(name.to_string());
+    }
+}
+
+///
+/// Returns the id of the row of the table with the name (case insensitive)
+/// and false, or creates the row if `create` and returns its id and true
+///
+async fn find_or_create_id(
+    table: &'static str,
+    column: &'static str,
+    name: &str,
+    create: bool,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<(i32, bool), MyAppError> {
+    let id: Option<i32> = sqlx::query_scalar(&format!(
+        "SELECT id FROM {table} WHERE lower({column}) = lower($1) AND deleted_at IS NULL \
+         ORDER BY id LIMIT 1"
+    ))
+    .bind(name)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    match id {
+        Some(id) => Ok((id, false)),
+        None if create => {
+            let id: i32 = sqlx::query_scalar(&format!(
+                "INSERT INTO {table} ({column}) VALUES ( $1 ) RETURNING id"
+            ))
+            .bind(name)
+            .fetch_one(&mut *tx)
+            .await?;
+            Ok((id, true))
+        }
+        None => Err(MyAppError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("{name} n'existe pas"),
+        )),
+    }
+}
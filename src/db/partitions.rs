@@ -1,12 +1,19 @@
 //! src/db/partitions.rs
 
+use futures::stream::{self, Stream};
 use sqlx::postgres::PgRow;
-use sqlx::{PgPool, Row};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row, Transaction};
 
 use crate::db::genres::find_genre_by_name_strict;
 use crate::db::musicians::find_persons_by_name_strict;
+use crate::db::tags::{list_tags_for_partitions, set_partition_tags};
 use crate::errors::MyAppError;
-use crate::models::partition::{Partition, ShowPartition};
+use crate::models::facet::{FacetCount, FacetKind};
+use crate::models::pagination::PageParams;
+use crate::models::partition::{
+    Contributor, ContributorRole, Difficulty, MusicalMetadata, NewContributor, Partition,
+    PartitionDetails, PartitionFilter, ShowPartition,
+};
 
 //*******************************************************************************************
 // CRUD Operations on partitions
@@ -14,6 +21,9 @@ use crate::models::partition::{Partition, ShowPartition};
 ///
 /// **Adds a partition to the DB**<br>
 /// requires a title, the musician name and the genre<br>
+/// the other musicians (with their role), the tags and the musical metadata
+/// are given in `details`, the main musician is added as composer<br>
+/// the tags are created on the fly if they don't exist<br>
 /// requires a PgPool<br>
 /// uses sqlx::query_as! macro<br>
 /// in the sql query when returning the id to build the Person struct,<br>
@@ -24,6 +34,7 @@ pub async fn add_partition(
     title: String,
     person_name: String,
     genre_name: String,
+    details: &PartitionDetails,
     pool: &PgPool,
 ) -> Result<Partition, MyAppError> {
     //let person_id: i32;
@@ -35,6 +46,26 @@ pub async fn add_partition(
     let genre = find_genre_by_name_strict(genre_name, pool).await?;
     let genre_id = genre.id;
 
+    let mut tx = pool.begin().await?;
+    let partition = insert_partition(title, person_id, genre_id, details, &mut tx).await?;
+    tx.commit().await?;
+
+    tracing::info!("db : partition added : {:?}", &partition);
+    Ok(partition)
+}
+
+///
+/// **Inserts a partition with its details in a transaction**<br>
+/// the musician and the genre must exist (ids)<br>
+/// used by add_partition and by the CSV import (all the rows in one transaction)
+///
+pub async fn insert_partition(
+    title: String,
+    person_id: i32,
+    genre_id: i32,
+    details: &PartitionDetails,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<Partition, MyAppError> {
     let partition: Partition = sqlx::query_as!(
         Partition,
         r#"INSERT INTO partitions (title, person_id, genre_id)
@@ -44,26 +75,37 @@ pub async fn add_partition(
         person_id,
         genre_id,
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
     //.map_err(|err| MyAppError::from(err))?;
-
-    tracing::info!("db : partition added : {:?}", &partition);
+    if let Some(id) = partition.id {
+        insert_contributors(id, person_id, &details.contributors, tx).await?;
+        set_partition_tags(id, &details.tags, tx).await?;
+        set_partition_metadata(id, &details.metadata, tx).await?;
+    }
     Ok(partition)
 }
 
+///
+/// **Updates a partition**<br>
+/// the musicians of the partition are replaced by the main musician (as composer)
+/// and the contributors of `details`, the tags by its tags (created on the fly if needed)
+/// and the musical metadata by its metadata
+///
 pub async fn update_partition(
     id: i32,
     partition_title: String,
     person_id: i32,
     genre_id: i32,
+    details: &PartitionDetails,
     pool: &PgPool,
 ) -> Result<Partition, MyAppError> {
+    let mut tx = pool.begin().await?;
     let row = sqlx::query!(
         r#"
         UPDATE partitions
         SET title = $1, person_id = $2, genre_id = $3
-        WHERE id = $4
+        WHERE id = $4 AND deleted_at IS NULL
         RETURNING id, title, person_id, genre_id
         "#,
         partition_title,
@@ -71,8 +113,18 @@ pub async fn update_partition(
         genre_id,
         id,
     )
-    .fetch_one(pool)
+    .fetch_one(&mut tx)
     .await?;
+
+    sqlx::query("DELETE FROM partition_contributors WHERE partition_id = $1")
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    insert_contributors(id, person_id, &details.contributors, &mut tx).await?;
+    set_partition_tags(id, &details.tags, &mut tx).await?;
+    set_partition_metadata(id, &details.metadata, &mut tx).await?;
+    tx.commit().await?;
+
     let partition = Partition {
         id: Some(row.id),
         title: row.title,
@@ -82,11 +134,95 @@ pub async fn update_partition(
     Ok(partition)
 }
 
+///
+/// Inserts the musicians of a partition :
+/// the main musician as composer then the contributors in the given order
+/// (a musician given twice with the same role is added once)
+///
+async fn insert_contributors(
+    partition_id: i32,
+    main_person_id: i32,
+    contributors: &[NewContributor],
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<(), MyAppError> {
+    let main = NewContributor {
+        person_id: main_person_id,
+        role: ContributorRole::Composer,
+    };
+    let all = std::iter::once(&main)
+        .chain(contributors.iter())
+        .enumerate();
+
+    let mut query = QueryBuilder::<Postgres>::new(
+        "INSERT INTO partition_contributors (partition_id, person_id, role, position) ",
+    );
+    query.push_values(all, |mut row, (position, contributor)| {
+        row.push_bind(partition_id)
+            .push_bind(contributor.person_id)
+            .push_bind(contributor.role)
+            .push_bind(position as i32);
+    });
+    query.push(" ON CONFLICT DO NOTHING");
+    query.build().execute(&mut *tx).await?;
+    Ok(())
+}
+
+///
+/// Writes the musical metadata of a partition
+/// (the enums are stored as their value, the difficulty as its grade)
+///
+async fn set_partition_metadata(
+    partition_id: i32,
+    metadata: &MusicalMetadata,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<(), MyAppError> {
+    sqlx::query(
+        "UPDATE partitions
+        SET musical_key = $1, time_signature = $2, tempo = $3,
+            duration_seconds = $4, instrumentation = $5, difficulty = $6
+        WHERE id = $7",
+    )
+    .bind(metadata.key.map(|v| v.value()))
+    .bind(metadata.time_signature.map(|v| v.value()))
+    .bind(metadata.tempo.map(|v| v.value()))
+    .bind(metadata.duration_seconds)
+    .bind(metadata.instrumentation.map(|v| v.value()))
+    .bind(metadata.difficulty.map(|v| v.grade()))
+    .bind(partition_id)
+    .execute(&mut *tx)
+    .await?;
+    Ok(())
+}
+
+///
+/// Reads the musical metadata in a row with the metadata columns of partitions
+/// a value that is not known by the enums is ignored
+///
+fn metadata_from_row(row: &PgRow) -> MusicalMetadata {
+    let text = |column: &str| row.get::<Option<String>, _>(column);
+    MusicalMetadata {
+        key: text("musical_key").and_then(|v| v.parse().ok()),
+        time_signature: text("time_signature").and_then(|v| v.parse().ok()),
+        tempo: text("tempo").and_then(|v| v.parse().ok()),
+        duration_seconds: row.get("duration_seconds"),
+        instrumentation: text("instrumentation").and_then(|v| v.parse().ok()),
+        difficulty: row
+            .get::<Option<i16>, _>("difficulty")
+            .and_then(Difficulty::from_grade),
+    }
+}
+
+///
+/// **Deletes a partition**<br>
+/// the partition goes to the trash (soft deletion : deleted_at is set),
+/// it can be restored or purged from the trash page<br>
+/// its files, copies and loans are kept until it is purged
+///
 pub async fn delete_partition(id: i32, pool: &PgPool) -> Result<String, MyAppError> {
     let partition = find_partition_by_id(id, pool).await?;
     let name = partition.title;
 
-    let _res = sqlx::query("DELETE FROM partitions WHERE id = $1")
+    let _res = sqlx::query("UPDATE partitions SET deleted_at = NOW() WHERE id = $1")
         .bind(id)
         .execute(pool)
         .await?;
@@ -101,31 +237,48 @@ pub async fn delete_partition(id: i32, pool: &PgPool) -> Result<String, MyAppErr
 //
 
 ///
-/// **Returns a list of all partitions in the db**<br>
+/// **Returns one page of the list of all partitions in the db**<br>
 /// under the form of a Vec<ShowPartition>
 /// or a MyAppError
 ///
-pub async fn list_show_partitions(pool: &PgPool) -> Result<Vec<ShowPartition>, MyAppError> {
-    let rep: Vec<ShowPartition> = sqlx::query(
-        "
-    SELECT partitions.id, partitions.title, persons.full_name, genres.name
+/// the total is given by count_partitions with a default filter
+///
+pub async fn list_show_partitions(
+    page: &PageParams,
+    pool: &PgPool,
+) -> Result<Vec<ShowPartition>, MyAppError> {
+    search_partitions(&PartitionFilter::default(), page, pool).await
+}
+
+///
+/// Query of the ShowPartitions : the partition with the name of the main musician,
+/// the genre and the musical metadata
+///
+const SHOW_PARTITION_SELECT: &str = "
+    SELECT partitions.id, partitions.title, persons.full_name, genres.name,
+        partitions.musical_key, partitions.time_signature, partitions.tempo,
+        partitions.duration_seconds, partitions.instrumentation, partitions.difficulty
     FROM partitions
     INNER JOIN persons
     ON partitions.person_id = persons.id
     INNER JOIN genres
     ON partitions.genre_id = genres.id
-    ORDER BY partitions.title
-        ",
-    )
-    .map(|row: PgRow| ShowPartition {
+    ";
+
+///
+/// Builds a ShowPartition from a row of SHOW_PARTITION_SELECT
+/// the contributors and the tags are filled by attach_details
+///
+fn show_partition_from_row(row: PgRow) -> ShowPartition {
+    ShowPartition {
         id: row.get(0),
         title: row.get(1),
         full_name: row.get(2),
         name: row.get(3),
-    })
-    .fetch_all(pool)
-    .await?;
-    Ok(rep)
+        contributors: Vec::new(),
+        tags: Vec::new(),
+        metadata: metadata_from_row(&row),
+    }
 }
 
 ///
@@ -136,42 +289,50 @@ pub async fn show_one_partition(
     partition: Partition,
     pool: &PgPool,
 ) -> Result<ShowPartition, MyAppError> {
-    let show_partition = sqlx::query(
-        "
-    SELECT partitions.id, partitions.title, persons.full_name, genres.name
-    FROM partitions
-    INNER JOIN persons
-    ON partitions.person_id = persons.id
-    INNER JOIN genres
-    ON partitions.genre_id = genres.id
-    WHERE partitions.title = $1
-        ",
-    )
+    let show_partition = sqlx::query(&format!(
+        "{SHOW_PARTITION_SELECT} WHERE partitions.title = $1 AND partitions.deleted_at IS NULL"
+    ))
     .bind(partition.title)
-    .map(|row: PgRow| ShowPartition {
-        id: row.get(0),
-        title: row.get(1),
-        full_name: row.get(2),
-        name: row.get(3),
-    })
+    .map(show_partition_from_row)
     .fetch_one(pool)
     .await?;
     //.map_err(|err| MyAppError::from(err))?;
 
-    Ok(show_partition)
+    let mut show_partitions = vec![show_partition];
+    attach_details(&mut show_partitions, pool).await?;
+    Ok(show_partitions.remove(0))
+}
+
+///
+/// **Returns a readable partition (ShowPartition) by its id**<br>
+/// with its musicians, tags and metadata, or MyAppError (NOT_FOUND)
+///
+pub async fn find_show_partition_by_id(id: i32, pool: &PgPool) -> Result<ShowPartition, MyAppError> {
+    let show_partition = sqlx::query(&format!(
+        "{SHOW_PARTITION_SELECT} WHERE partitions.id = $1 AND partitions.deleted_at IS NULL"
+    ))
+    .bind(id)
+    .map(show_partition_from_row)
+    .fetch_one(pool)
+    .await?;
+
+    let mut show_partitions = vec![show_partition];
+    attach_details(&mut show_partitions, pool).await?;
+    Ok(show_partitions.remove(0))
 }
 
 pub async fn find_partition_by_id(id: i32, pool: &PgPool) -> Result<Partition, MyAppError> {
-    let partition = sqlx::query("SELECT * FROM partitions WHERE id = $1;")
-        .bind(id)
-        .map(|row: PgRow| Partition {
-            id: row.get("id"),
-            title: row.get("title"),
-            person_id: row.get("person_id"),
-            genre_id: row.get("genre_id"),
-        })
-        .fetch_one(pool)
-        .await?;
+    let partition =
+        sqlx::query("SELECT * FROM partitions WHERE id = $1 AND deleted_at IS NULL;")
+            .bind(id)
+            .map(|row: PgRow| Partition {
+                id: row.get("id"),
+                title: row.get("title"),
+                person_id: row.get("person_id"),
+                genre_id: row.get("genre_id"),
+            })
+            .fetch_one(pool)
+            .await?;
 
     tracing::info!("db : partition trouvée (titre) : {}", &partition.title);
     Ok(partition)
@@ -191,16 +352,17 @@ pub async fn find_partition_by_title(
     let mut part_title = title.clone();
     part_title.push('%');
 
-    let partitions = sqlx::query("SELECT * FROM partitions WHERE title LIKE $1;")
-        .bind(part_title)
-        .map(|row: PgRow| Partition {
-            id: row.get("id"),
-            title: row.get("title"),
-            person_id: row.get("person_id"),
-            genre_id: row.get("genre_id"),
-        })
-        .fetch_all(pool)
-        .await?;
+    let partitions =
+        sqlx::query("SELECT * FROM partitions WHERE title LIKE $1 AND deleted_at IS NULL;")
+            .bind(part_title)
+            .map(|row: PgRow| Partition {
+                id: row.get("id"),
+                title: row.get("title"),
+                person_id: row.get("person_id"),
+                genre_id: row.get("genre_id"),
+            })
+            .fetch_all(pool)
+            .await?;
 
     Ok(partitions)
 }
@@ -212,9 +374,16 @@ pub async fn find_partition_by_genre(
     let genre = find_genre_by_name_strict(genre_name.clone(), pool).await?;
     let genre_id = genre.id;
 
+    // the partitions of the genre and of all its sub-genres
     let partitions = sqlx::query(
-        "SELECT * FROM partitions \
-        WHERE genre_id = $1 \
+        "WITH RECURSIVE sub_genres AS ( \
+            SELECT id FROM genres WHERE id = $1 \
+            UNION \
+            SELECT g.id FROM genres g INNER JOIN sub_genres s ON g.parent_id = s.id \
+            WHERE g.deleted_at IS NULL \
+        ) \
+        SELECT * FROM partitions \
+        WHERE genre_id IN (SELECT id FROM sub_genres) AND deleted_at IS NULL \
         ORDER BY partitions.title",
     )
     .bind(genre_id)
@@ -231,17 +400,29 @@ pub async fn find_partition_by_genre(
     Ok(partitions)
 }
 
+///
+/// **Find_partition_by_author**
+///
+/// Returns the partitions where the musician is a contributor,
+/// with the given role only if `role` is Some ("arranged by X")
+///
 pub async fn find_partition_by_author(
     author_name: String,
+    role: Option<ContributorRole>,
     pool: &PgPool,
 ) -> Result<Vec<Partition>, MyAppError> {
     let author = find_persons_by_name_strict(author_name.clone(), pool).await?;
     let partitions = sqlx::query(
-        "SELECT * FROM partitions \
-        WHERE person_id = $1\
+        "SELECT partitions.* FROM partitions \
+        WHERE partitions.deleted_at IS NULL AND EXISTS ( \
+            SELECT 1 FROM partition_contributors \
+            WHERE partition_contributors.partition_id = partitions.id \
+            AND partition_contributors.person_id = $1 \
+            AND ($2::contributor_role IS NULL OR partition_contributors.role = $2) ) \
         ORDER BY partitions.title",
     )
     .bind(author.id)
+    .bind(role)
     .map(|row: PgRow| Partition {
         id: row.get("id"),
         title: row.get("title"),
@@ -258,6 +439,367 @@ pub async fn find_partition_by_author(
     Ok(partitions)
 }
 
+//******************************************************************************************
+// Full-text search
+//
+
+///
+/// Full-text document of a partition used by the search, stored in the column
+/// partitions.search_document (GIN index, kept up to date by triggers) :<br>
+/// the title (weight A), the names of the musicians (weight B) and the genre name (weight C)<br>
+/// the accents are removed so that "Noel" finds "Noël"
+///
+const PARTITION_SEARCH_DOCUMENT: &str = "partitions.search_document";
+
+///
+/// Sort keys allowed in the query string of the partitions lists
+/// and the corresponding SQL columns
+///
+const PARTITION_SORT_COLUMNS: [(&str, &str); 5] = [
+    ("title", "partitions.title"),
+    ("composer", "persons.full_name"),
+    ("genre", "genres.name"),
+    ("difficulty", "partitions.difficulty"),
+    ("duration", "partitions.duration_seconds"),
+];
+
+///
+/// Adds the WHERE clause corresponding to the filter to the query
+/// (the partitions in the trash are never shown)
+///
+fn push_partition_filter(query: &mut QueryBuilder<Postgres>, filter: &PartitionFilter) {
+    query.push(" WHERE partitions.deleted_at IS NULL");
+    if let Some(ts_query) = filter.ts_query() {
+        query
+            .push(" AND ")
+            .push(PARTITION_SEARCH_DOCUMENT)
+            .push(" @@ to_tsquery('simple', unaccent(")
+            .push_bind(ts_query)
+            .push("))");
+    }
+    if let Some(tag) = filter.tag() {
+        query
+            .push(
+                " AND EXISTS ( \
+                SELECT 1 FROM partition_tags \
+                INNER JOIN tags ON partition_tags.tag_id = tags.id \
+                WHERE partition_tags.partition_id = partitions.id \
+                AND lower(tags.name) = lower(",
+            )
+            .push_bind(tag.to_string())
+            .push("))");
+    }
+    if let Some(key) = filter.key() {
+        query
+            .push(" AND partitions.musical_key = ")
+            .push_bind(key.value());
+    }
+    if let Some(time_signature) = filter.time_signature() {
+        query
+            .push(" AND partitions.time_signature = ")
+            .push_bind(time_signature.value());
+    }
+    if let Some(tempo) = filter.tempo() {
+        query
+            .push(" AND partitions.tempo = ")
+            .push_bind(tempo.value());
+    }
+    if let Some(instrumentation) = filter.instrumentation() {
+        query
+            .push(" AND partitions.instrumentation = ")
+            .push_bind(instrumentation.value());
+    }
+    if let Some(difficulty) = filter.max_difficulty() {
+        query
+            .push(" AND partitions.difficulty <= ")
+            .push_bind(difficulty.grade());
+    }
+    if let Some(person) = filter.person() {
+        query
+            .push(" AND (partitions.person_id = ")
+            .push_bind(person)
+            .push(
+                " OR EXISTS ( \
+                SELECT 1 FROM partition_contributors \
+                WHERE partition_contributors.partition_id = partitions.id \
+                AND partition_contributors.person_id = ",
+            )
+            .push_bind(person)
+            .push("))");
+    }
+    if let Some(genre) = filter.genre() {
+        query
+            .push(
+                " AND partitions.genre_id IN ( \
+                WITH RECURSIVE sub_genres AS ( \
+                    SELECT id FROM genres WHERE id = ",
+            )
+            .push_bind(genre)
+            .push(
+                " UNION \
+                    SELECT g.id FROM genres g INNER JOIN sub_genres s ON g.parent_id = s.id \
+                    WHERE g.deleted_at IS NULL \
+                ) SELECT id FROM sub_genres)",
+            );
+    }
+    if let Some(composer) = filter.composer() {
+        query
+            .push(" AND partitions.person_id = ")
+            .push_bind(composer);
+    }
+    if let Some(difficulty) = filter.difficulty() {
+        query
+            .push(" AND partitions.difficulty = ")
+            .push_bind(difficulty.grade());
+    }
+}
+
+///
+/// **Searches the partitions**<br>
+/// Full-text search (Postgres tsvector) on the title, the musician and the genre<br>
+/// a word can be found anywhere in the title ("Nocturne" finds "Chopin – Nocturne op. 9")
+///
+/// The results are ranked by relevance (ts_rank) then by title,
+/// unless another sort is asked (title, composer, genre, difficulty or duration).<br>
+/// Without words to search, returns all the partitions ordered by title.
+///
+/// Returns only the asked page, the total is given by count_partitions
+///
+pub async fn search_partitions(
+    filter: &PartitionFilter,
+    page: &PageParams,
+    pool: &PgPool,
+) -> Result<Vec<ShowPartition>, MyAppError> {
+    let mut query = QueryBuilder::<Postgres>::new(SHOW_PARTITION_SELECT);
+    push_partition_filter(&mut query, filter);
+
+    match (page.sort_column(&PARTITION_SORT_COLUMNS), filter.ts_query()) {
+        (Some(column), _) => {
+            query
+                .push(" ORDER BY ")
+                .push(column)
+                .push(" ")
+                .push(page.sql_dir())
+                .push(", partitions.id");
+        }
+        (None, Some(ts_query)) => {
+            query
+                .push(" ORDER BY ts_rank(")
+                .push(PARTITION_SEARCH_DOCUMENT)
+                .push(", to_tsquery('simple', unaccent(")
+                .push_bind(ts_query)
+                .push("))) DESC, partitions.title, partitions.id");
+        }
+        (None, None) => {
+            query.push(" ORDER BY partitions.title, partitions.id");
+        }
+    }
+    query
+        .push(" LIMIT ")
+        .push_bind(page.per_page())
+        .push(" OFFSET ")
+        .push_bind(page.offset());
+
+    let mut partitions = query
+        .build()
+        .map(show_partition_from_row)
+        .fetch_all(pool)
+        .await?;
+    attach_details(&mut partitions, pool).await?;
+
+    tracing::info!(
+        "db : {} partition(s) trouvée(s) : {:?}",
+        partitions.len(),
+        filter
+    );
+    Ok(partitions)
+}
+
+///
+/// **Counts the partitions corresponding to the filter**<br>
+/// used for the pagination
+///
+pub async fn count_partitions(filter: &PartitionFilter, pool: &PgPool) -> Result<i64, MyAppError> {
+    let mut query = QueryBuilder::<Postgres>::new(
+        "
+    SELECT COUNT(*)
+    FROM partitions
+    INNER JOIN persons
+    ON partitions.person_id = persons.id
+    INNER JOIN genres
+    ON partitions.genre_id = genres.id
+        ",
+    );
+    push_partition_filter(&mut query, filter);
+    let total: i64 = query.build().fetch_one(pool).await?.get(0);
+    Ok(total)
+}
+
+//******************************************************************************************
+// Facets
+//
+
+///
+/// Number of values shown per facet (the most used first)
+///
+const FACET_LIMIT: i64 = 20;
+
+///
+/// **Counts the partitions per value of a facet** (genre, composer, key or difficulty)<br>
+/// among the partitions corresponding to the filter without the criterion of the facet,
+/// so that the other values of the facet stay available<br>
+/// the partitions without key or difficulty are not counted
+///
+pub async fn count_partition_facet(
+    kind: FacetKind,
+    filter: &PartitionFilter,
+    pool: &PgPool,
+) -> Result<Vec<FacetCount>, MyAppError> {
+    let (value, name, order) = match kind {
+        FacetKind::Genre => ("genres.id::TEXT", "genres.name", "count DESC, name"),
+        FacetKind::Composer => ("persons.id::TEXT", "persons.full_name", "count DESC, name"),
+        FacetKind::Key => (
+            "partitions.musical_key",
+            "partitions.musical_key",
+            "count DESC, name",
+        ),
+        FacetKind::Difficulty => (
+            "partitions.difficulty::TEXT",
+            "partitions.difficulty::TEXT",
+            "value",
+        ),
+    };
+    let mut query = QueryBuilder::<Postgres>::new(format!(
+        "
+    SELECT {value} AS value, {name} AS name, COUNT(*) AS count
+    FROM partitions
+    INNER JOIN persons
+    ON partitions.person_id = persons.id
+    INNER JOIN genres
+    ON partitions.genre_id = genres.id
+        "
+    ));
+    push_partition_filter(&mut query, &kind.with_value(filter, None));
+    query
+        .push(format!(
+            " AND {value} IS NOT NULL GROUP BY 1, 2 ORDER BY {order} LIMIT "
+        ))
+        .push_bind(FACET_LIMIT);
+
+    let counts = query.build_query_as::<FacetCount>().fetch_all(pool).await?;
+    Ok(counts)
+}
+
+//******************************************************************************************
+// Export
+//
+
+///
+/// Number of partitions read at once by the export
+///
+const EXPORT_BATCH_SIZE: i64 = 500;
+
+///
+/// **Streams the partitions corresponding to the filter**<br>
+/// ordered by title, in batches of EXPORT_BATCH_SIZE partitions with their contributors and tags<br>
+/// a batch is read when the previous one has been sent (keyset pagination on the title and the id)
+/// so that a large catalogue is never loaded in memory at once
+///
+pub fn stream_show_partitions(
+    filter: PartitionFilter,
+    pool: PgPool,
+) -> impl Stream<Item = Result<Vec<ShowPartition>, MyAppError>> {
+    stream::try_unfold(Some((String::new(), 0)), move |cursor| {
+        let filter = filter.clone();
+        let pool = pool.clone();
+        async move {
+            let (title, id) = match cursor {
+                Some(cursor) => cursor,
+                None => return Ok(None),
+            };
+            let mut query = QueryBuilder::<Postgres>::new(SHOW_PARTITION_SELECT);
+            push_partition_filter(&mut query, &filter);
+            query
+                .push(" AND (partitions.title, partitions.id) > (")
+                .push_bind(title)
+                .push(", ")
+                .push_bind(id)
+                .push(") ORDER BY partitions.title, partitions.id LIMIT ")
+                .push_bind(EXPORT_BATCH_SIZE);
+
+            let mut partitions = query
+                .build()
+                .map(show_partition_from_row)
+                .fetch_all(&pool)
+                .await?;
+            if partitions.is_empty() {
+                return Ok(None);
+            }
+            attach_details(&mut partitions, &pool).await?;
+
+            let next = match partitions.last() {
+                Some(last) if partitions.len() as i64 == EXPORT_BATCH_SIZE => {
+                    Some((last.title.clone(), last.id))
+                }
+                _ => None,
+            };
+            Ok(Some((partitions, next)))
+        }
+    })
+}
+
+//******************************************************************************************
+// Contributors of the partitions
+//
+
+///
+/// **Returns the musicians (with their role) of a list of partitions**<br>
+/// ordered by partition and position
+///
+pub async fn list_contributors_for_partitions(
+    partition_ids: &[i32],
+    pool: &PgPool,
+) -> Result<Vec<Contributor>, MyAppError> {
+    let contributors = sqlx::query_as::<_, Contributor>(
+        "
+    SELECT partition_contributors.partition_id, partition_contributors.person_id,
+        persons.full_name, partition_contributors.role
+    FROM partition_contributors
+    INNER JOIN persons
+    ON partition_contributors.person_id = persons.id
+    WHERE partition_contributors.partition_id = ANY($1)
+    ORDER BY partition_contributors.partition_id, partition_contributors.position
+        ",
+    )
+    .bind(partition_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(contributors)
+}
+
+///
+/// Fills the contributors and the tags of the ShowPartitions
+/// (one query for all the partitions)
+///
+async fn attach_details(partitions: &mut [ShowPartition], pool: &PgPool) -> Result<(), MyAppError> {
+    let ids = partitions.iter().map(|p| p.id).collect::<Vec<i32>>();
+    let contributors = list_contributors_for_partitions(&ids, pool).await?;
+    let tags = list_tags_for_partitions(&ids, pool).await?;
+    for partition in partitions.iter_mut() {
+        partition.contributors = contributors
+            .iter()
+            .filter(|c| c.partition_id == partition.id)
+            .cloned()
+            .collect();
+        partition.tags = tags
+            .iter()
+            .filter(|t| t.partition_id == partition.id)
+            .cloned()
+            .collect();
+    }
+    Ok(())
+}
+
 #[allow(dead_code)]
 pub async fn vec_showpartitions_from_vec_partitions(
     partitions: Vec<Partition>,
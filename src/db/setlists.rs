@@ -0,0 +1,399 @@
+//! src/db/setlists.rs
+//!
+//! Setlists (table setlists) and their pieces (table setlist_items)
+//!
+//! The positions of the pieces of a setlist are kept as 1, 2, 3 ... :
+//! they are numbered again when a piece is moved or removed
+//! (a partition deleted from the catalogue leaves a gap)
+//!
+
+use axum::http::StatusCode;
+use chrono::NaiveDate;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row, Transaction};
+
+use crate::errors::MyAppError;
+use crate::models::pagination::PageParams;
+use crate::models::setlist::{PartitionChoice, Setlist, SetlistItem};
+
+/// columns of a Setlist, the setlists table is "s"
+const SETLIST_COLUMNS: &str = "SELECT s.id, s.name, s.performance_date, \
+     COUNT(i.id) AS items, \
+     COALESCE(SUM(p.duration_seconds), 0)::BIGINT AS total_seconds, \
+     COUNT(i.id) - COUNT(p.duration_seconds) AS unknown_durations \
+     FROM setlists s \
+     LEFT JOIN setlist_items i ON i.setlist_id = s.id \
+     LEFT JOIN partitions p ON p.id = i.partition_id ";
+
+/// columns of a SetlistItem, the setlist_items table is "i"
+const ITEM_COLUMNS: &str = "SELECT i.id, i.setlist_id, i.position, i.partition_id, \
+     p.title, pe.full_name, p.duration_seconds, i.note \
+     FROM setlist_items i \
+     INNER JOIN partitions p ON p.id = i.partition_id \
+     INNER JOIN persons pe ON pe.id = p.person_id ";
+
+//*******************************************************************************************
+// CRUD Operations on setlists
+//
+
+///
+/// **Adds a setlist to the DB**<br>
+/// returns the added (empty) Setlist or MyAppError
+///
+pub async fn add_setlist(
+    name: &str,
+    performance_date: NaiveDate,
+    pool: &PgPool,
+) -> Result<Setlist, MyAppError> {
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO setlists (name, performance_date) VALUES ( $1, $2 ) RETURNING id",
+    )
+    .bind(name)
+    .bind(performance_date)
+    .fetch_one(pool)
+    .await?;
+
+    let setlist = find_setlist_by_id(id, pool).await?;
+    tracing::info!("db : setlist added : {:?}", &setlist);
+    Ok(setlist)
+}
+
+pub async fn update_setlist(
+    id: i32,
+    name: &str,
+    performance_date: NaiveDate,
+    pool: &PgPool,
+) -> Result<Setlist, MyAppError> {
+    let result = sqlx::query("UPDATE setlists SET name = $1, performance_date = $2 WHERE id = $3")
+        .bind(name)
+        .bind(performance_date)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(MyAppError::new(
+            StatusCode::NOT_FOUND,
+            "Setlist with the ID not found !",
+        ));
+    }
+
+    let setlist = find_setlist_by_id(id, pool).await?;
+    tracing::info!("db : setlist modified : {:?}", &setlist);
+    Ok(setlist)
+}
+
+///
+/// **Deletes a setlist and its pieces**<br>
+/// returns the name of the deleted setlist or MyAppError
+///
+pub async fn delete_setlist(id: i32, pool: &PgPool) -> Result<String, MyAppError> {
+    let name: Option<String> =
+        sqlx::query_scalar("DELETE FROM setlists WHERE id = $1 RETURNING name")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+    let name = name
+        .ok_or_else(|| MyAppError::new(StatusCode::NOT_FOUND, "Setlist with the ID not found !"))?;
+
+    tracing::info!("db : setlist deleted : {}", &name);
+    Ok(name)
+}
+
+pub async fn find_setlist_by_id(id: i32, pool: &PgPool) -> Result<Setlist, MyAppError> {
+    let setlist =
+        sqlx::query_as::<_, Setlist>(&format!("{SETLIST_COLUMNS} WHERE s.id = $1 GROUP BY s.id"))
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| {
+                MyAppError::new(StatusCode::NOT_FOUND, "Setlist with the ID not found !")
+            })?;
+    Ok(setlist)
+}
+
+///
+/// Returns one page of the list of setlists
+/// sorted by date (default, the last ones first) or by name (sort=name)
+///
+pub async fn list_setlists_page(
+    page: &PageParams,
+    pool: &PgPool,
+) -> Result<Vec<Setlist>, MyAppError> {
+    let column = page
+        .sort_column(&[("date", "s.performance_date"), ("name", "lower(s.name)")])
+        .unwrap_or("s.performance_date");
+    // the next concerts first when no sort is asked
+    let dir = if page.sort.is_none() {
+        "DESC"
+    } else {
+        page.sql_dir()
+    };
+
+    let mut query = QueryBuilder::<Postgres>::new(SETLIST_COLUMNS);
+    query
+        .push(" GROUP BY s.id ORDER BY ")
+        .push(column)
+        .push(" ")
+        .push(dir)
+        .push(", s.id DESC LIMIT ")
+        .push_bind(page.per_page())
+        .push(" OFFSET ")
+        .push_bind(page.offset());
+    let setlists = query.build_query_as::<Setlist>().fetch_all(pool).await?;
+    Ok(setlists)
+}
+
+///
+/// Returns all the setlists, the last ones first
+/// (proposed in the forms of the events)
+///
+pub async fn list_setlists(pool: &PgPool) -> Result<Vec<Setlist>, MyAppError> {
+    let setlists = sqlx::query_as::<_, Setlist>(&format!(
+        "{SETLIST_COLUMNS} GROUP BY s.id ORDER BY s.performance_date DESC, s.id DESC"
+    ))
+    .fetch_all(pool)
+    .await?;
+    Ok(setlists)
+}
+
+///
+/// Returns the setlists playing one of the partitions, the last ones first
+/// (impact of the deletion of a musician or a genre)
+///
+pub async fn list_setlists_with_partitions(
+    partition_ids: &[i32],
+    pool: &PgPool,
+) -> Result<Vec<Setlist>, MyAppError> {
+    let setlists = sqlx::query_as::<_, Setlist>(&format!(
+        "{SETLIST_COLUMNS} WHERE s.id IN ( \
+            SELECT setlist_id FROM setlist_items WHERE partition_id = ANY($1)) \
+         GROUP BY s.id ORDER BY s.performance_date DESC, s.id DESC"
+    ))
+    .bind(partition_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(setlists)
+}
+
+///
+/// Returns the number of setlists, used for the pagination
+///
+pub async fn count_setlists(pool: &PgPool) -> Result<i64, MyAppError> {
+    let total: i64 = sqlx::query("SELECT COUNT(*) FROM setlists")
+        .fetch_one(pool)
+        .await?
+        .get(0);
+    Ok(total)
+}
+
+//*******************************************************************************************
+// Pieces of a setlist
+//
+
+///
+/// Returns the pieces of a setlist in the order they are played
+///
+pub async fn list_setlist_items(
+    setlist_id: i32,
+    pool: &PgPool,
+) -> Result<Vec<SetlistItem>, MyAppError> {
+    let items = sqlx::query_as::<_, SetlistItem>(&format!(
+        "{ITEM_COLUMNS} WHERE i.setlist_id = $1 ORDER BY i.position, i.id"
+    ))
+    .bind(setlist_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(items)
+}
+
+pub async fn find_setlist_item_by_id(id: i32, pool: &PgPool) -> Result<SetlistItem, MyAppError> {
+    let item = sqlx::query_as::<_, SetlistItem>(&format!("{ITEM_COLUMNS} WHERE i.id = $1"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| {
+            MyAppError::new(
+                StatusCode::NOT_FOUND,
+                "Setlist item with the ID not found !",
+            )
+        })?;
+    Ok(item)
+}
+
+///
+/// **Adds a partition at the end of a setlist**<br>
+/// a partition can be played several times in a concert (e.g. as an encore)
+///
+/// returns the added SetlistItem or MyAppError
+///
+pub async fn add_setlist_item(
+    setlist_id: i32,
+    partition_id: i32,
+    note: &str,
+    pool: &PgPool,
+) -> Result<SetlistItem, MyAppError> {
+    let mut tx = pool.begin().await?;
+    // the setlist row is locked : two pieces added at the same time get different positions
+    lock_setlist(setlist_id, &mut tx).await?;
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO setlist_items (setlist_id, partition_id, position, note) \
+         SELECT $1, $2, COALESCE(MAX(position), 0) + 1, $3 \
+         FROM setlist_items WHERE setlist_id = $1 RETURNING id",
+    )
+    .bind(setlist_id)
+    .bind(partition_id)
+    .bind(note)
+    .fetch_one(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    let item = find_setlist_item_by_id(id, pool).await?;
+    tracing::info!("db : setlist item added : {:?}", &item);
+    Ok(item)
+}
+
+pub async fn update_setlist_item_note(
+    id: i32,
+    note: &str,
+    pool: &PgPool,
+) -> Result<SetlistItem, MyAppError> {
+    sqlx::query("UPDATE setlist_items SET note = $1 WHERE id = $2")
+        .bind(note)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    let item = find_setlist_item_by_id(id, pool).await?;
+    tracing::info!("db : setlist item modified : {:?}", &item);
+    Ok(item)
+}
+
+///
+/// **Moves a piece to a new position**<br>
+/// the pieces between the old and the new position are shifted,
+/// a position out of the setlist moves the piece to the first or the last place
+///
+/// returns the moved SetlistItem or MyAppError
+///
+pub async fn move_setlist_item(
+    id: i32,
+    position: i32,
+    pool: &PgPool,
+) -> Result<SetlistItem, MyAppError> {
+    let item = find_setlist_item_by_id(id, pool).await?;
+    let mut tx = pool.begin().await?;
+    lock_setlist(item.setlist_id, &mut tx).await?;
+    let count = renumber_setlist_items(item.setlist_id, &mut tx).await?;
+    let old: i32 = sqlx::query_scalar("SELECT position FROM setlist_items WHERE id = $1")
+        .bind(id)
+        .fetch_one(&mut tx)
+        .await?;
+    let new = position.clamp(1, count.max(1));
+
+    if new < old {
+        sqlx::query(
+            "UPDATE setlist_items SET position = position + 1 \
+             WHERE setlist_id = $1 AND position >= $2 AND position < $3",
+        )
+        .bind(item.setlist_id)
+        .bind(new)
+        .bind(old)
+        .execute(&mut tx)
+        .await?;
+    } else if new > old {
+        sqlx::query(
+            "UPDATE setlist_items SET position = position - 1 \
+             WHERE setlist_id = $1 AND position > $2 AND position <= $3",
+        )
+        .bind(item.setlist_id)
+        .bind(old)
+        .bind(new)
+        .execute(&mut tx)
+        .await?;
+    }
+    sqlx::query("UPDATE setlist_items SET position = $1 WHERE id = $2")
+        .bind(new)
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+
+    let item = find_setlist_item_by_id(id, pool).await?;
+    tracing::info!("db : setlist item moved : {:?}", &item);
+    Ok(item)
+}
+
+///
+/// **Removes a piece from a setlist**<br>
+/// returns the removed SetlistItem or MyAppError
+///
+pub async fn remove_setlist_item(id: i32, pool: &PgPool) -> Result<SetlistItem, MyAppError> {
+    let item = find_setlist_item_by_id(id, pool).await?;
+    let mut tx = pool.begin().await?;
+    lock_setlist(item.setlist_id, &mut tx).await?;
+    sqlx::query("DELETE FROM setlist_items WHERE id = $1")
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    renumber_setlist_items(item.setlist_id, &mut tx).await?;
+    tx.commit().await?;
+
+    tracing::info!("db : setlist item removed : {:?}", &item);
+    Ok(item)
+}
+
+///
+/// Returns the partitions that can be added to a setlist, by title
+///
+pub async fn list_partition_choices(pool: &PgPool) -> Result<Vec<PartitionChoice>, MyAppError> {
+    let partitions = sqlx::query_as::<_, PartitionChoice>(
+        "SELECT p.id, p.title, pe.full_name FROM partitions p \
+         INNER JOIN persons pe ON pe.id = p.person_id \
+         WHERE p.deleted_at IS NULL \
+         ORDER BY lower(p.title), p.id",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(partitions)
+}
+
+//*******************************************************************************
+// Helpers functions
+//
+
+///
+/// Locks the row of the setlist until the end of the transaction
+/// returns MyAppError (NOT_FOUND) if the setlist is unknown
+///
+async fn lock_setlist(
+    setlist_id: i32,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<(), MyAppError> {
+    let setlist: Option<i32> =
+        sqlx::query_scalar("SELECT id FROM setlists WHERE id = $1 FOR UPDATE")
+            .bind(setlist_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+    setlist
+        .map(|_| ())
+        .ok_or_else(|| MyAppError::new(StatusCode::NOT_FOUND, "Setlist with the ID not found !"))
+}
+
+///
+/// Numbers the pieces of a setlist 1, 2, 3 ... in their order
+/// returns the number of pieces
+///
+async fn renumber_setlist_items(
+    setlist_id: i32,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<i32, MyAppError> {
+    let result = sqlx::query(
+        "UPDATE setlist_items i SET position = numbered.rank \
+         FROM (SELECT id, row_number() OVER (ORDER BY position, id)::INT AS rank \
+               FROM setlist_items WHERE setlist_id = $1) numbered \
+         WHERE i.id = numbered.id",
+    )
+    .bind(setlist_id)
+    .execute(&mut *tx)
+    .await?;
+    Ok(result.rows_affected() as i32)
+}
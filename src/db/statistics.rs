@@ -0,0 +1,164 @@
+//! src/db/statistics.rs
+//!
+//! Statistics of the catalogue (the partitions in the trash are not counted)
+//! and of the loans, computed by the DB
+//!
+
+use sqlx::PgPool;
+
+use crate::errors::MyAppError;
+use crate::models::statistics::{CatalogueTotals, ChartValue};
+
+///
+/// **Returns the totals of the catalogue and of the loans**
+///
+pub async fn catalogue_totals(pool: &PgPool) -> Result<CatalogueTotals, MyAppError> {
+    let totals = sqlx::query_as::<_, CatalogueTotals>(
+        "SELECT \
+            (SELECT COUNT(*) FROM partitions WHERE deleted_at IS NULL) AS partitions, \
+            (SELECT COUNT(DISTINCT w.person_id) FROM ( \
+                SELECT person_id FROM partitions WHERE deleted_at IS NULL \
+                UNION SELECT pc.person_id FROM partition_contributors pc \
+                INNER JOIN partitions p ON p.id = pc.partition_id \
+                WHERE pc.role = 'composer' AND p.deleted_at IS NULL \
+             ) AS w \
+             INNER JOIN persons pe ON pe.id = w.person_id \
+             WHERE pe.deleted_at IS NULL) AS composers, \
+            (SELECT COUNT(*) FROM genres WHERE deleted_at IS NULL) AS genres, \
+            (SELECT COUNT(*) FROM loans WHERE returned_at IS NULL) AS open_loans, \
+            (SELECT COUNT(*) FROM loans \
+             WHERE returned_at IS NULL AND due_date < CURRENT_DATE) AS overdue_loans, \
+            (SELECT COUNT(*) FROM loans \
+             WHERE borrowed_at >= NOW() - INTERVAL '12 months') AS loans_year",
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(totals)
+}
+
+///
+/// **Returns the composers with the most partitions**<br>
+/// main musician or other musician with the role composer of the partitions
+///
+pub async fn top_composers(limit: i64, pool: &PgPool) -> Result<Vec<ChartValue>, MyAppError> {
+    let values = sqlx::query_as::<_, ChartValue>(
+        "SELECT pe.full_name AS label, COUNT(DISTINCT w.partition_id) AS value \
+         FROM ( \
+            SELECT id AS partition_id, person_id FROM partitions WHERE deleted_at IS NULL \
+            UNION SELECT pc.partition_id, pc.person_id FROM partition_contributors pc \
+            INNER JOIN partitions p ON p.id = pc.partition_id \
+            WHERE pc.role = 'composer' AND p.deleted_at IS NULL \
+         ) AS w \
+         INNER JOIN persons pe ON pe.id = w.person_id \
+         WHERE pe.deleted_at IS NULL \
+         GROUP BY pe.id, pe.full_name \
+         ORDER BY value DESC, label \
+         LIMIT $1",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(values)
+}
+
+///
+/// **Returns the number of partitions added per month**<br>
+/// the last `months` months, the current one included (a month without partition is 0)
+///
+pub async fn partitions_per_month(
+    months: i32,
+    pool: &PgPool,
+) -> Result<Vec<ChartValue>, MyAppError> {
+    let values = sqlx::query_as::<_, ChartValue>(
+        "SELECT to_char(m.month, 'MM/YY') AS label, COUNT(p.id) AS value \
+         FROM generate_series( \
+            date_trunc('month', NOW()) - make_interval(months => $1 - 1), \
+            date_trunc('month', NOW()), INTERVAL '1 month') AS m(month) \
+         LEFT JOIN partitions p \
+         ON date_trunc('month', p.created_at) = m.month AND p.deleted_at IS NULL \
+         GROUP BY m.month \
+         ORDER BY m.month",
+    )
+    .bind(months)
+    .fetch_all(pool)
+    .await?;
+    Ok(values)
+}
+
+///
+/// **Returns the number of partitions per main genre**<br>
+/// the partitions of the sub-genres are counted in their main genre
+///
+pub async fn partitions_per_genre(
+    limit: i64,
+    pool: &PgPool,
+) -> Result<Vec<ChartValue>, MyAppError> {
+    let values = sqlx::query_as::<_, ChartValue>(
+        "WITH RECURSIVE main_genres AS ( \
+            SELECT g.id, g.id AS main_id, g.name AS main_name \
+            FROM genres g \
+            LEFT JOIN genres parent ON parent.id = g.parent_id AND parent.deleted_at IS NULL \
+            WHERE g.deleted_at IS NULL AND parent.id IS NULL \
+            UNION ALL \
+            SELECT g.id, m.main_id, m.main_name \
+            FROM genres g \
+            INNER JOIN main_genres m ON g.parent_id = m.id \
+            WHERE g.deleted_at IS NULL \
+         ) \
+         SELECT m.main_name AS label, COUNT(*) AS value \
+         FROM partitions p \
+         INNER JOIN main_genres m ON m.id = p.genre_id \
+         WHERE p.deleted_at IS NULL \
+         GROUP BY m.main_id, m.main_name \
+         ORDER BY value DESC, label \
+         LIMIT $1",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(values)
+}
+
+///
+/// **Returns the number of loans per month**<br>
+/// the last `months` months, the current one included (a month without loan is 0)
+///
+pub async fn loans_per_month(months: i32, pool: &PgPool) -> Result<Vec<ChartValue>, MyAppError> {
+    let values = sqlx::query_as::<_, ChartValue>(
+        "SELECT to_char(m.month, 'MM/YY') AS label, COUNT(l.id) AS value \
+         FROM generate_series( \
+            date_trunc('month', NOW()) - make_interval(months => $1 - 1), \
+            date_trunc('month', NOW()), INTERVAL '1 month') AS m(month) \
+         LEFT JOIN loans l ON date_trunc('month', l.borrowed_at) = m.month \
+         GROUP BY m.month \
+         ORDER BY m.month",
+    )
+    .bind(months)
+    .fetch_all(pool)
+    .await?;
+    Ok(values)
+}
+
+///
+/// **Returns the partitions the most borrowed** during the last `months` months
+///
+pub async fn most_borrowed_partitions(
+    months: i32,
+    limit: i64,
+    pool: &PgPool,
+) -> Result<Vec<ChartValue>, MyAppError> {
+    let values = sqlx::query_as::<_, ChartValue>(
+        "SELECT p.title AS label, COUNT(*) AS value \
+         FROM loans l \
+         INNER JOIN partitions p ON p.id = l.partition_id \
+         WHERE p.deleted_at IS NULL AND l.borrowed_at >= NOW() - make_interval(months => $1) \
+         GROUP BY p.id, p.title \
+         ORDER BY value DESC, label \
+         LIMIT $2",
+    )
+    .bind(months)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(values)
+}
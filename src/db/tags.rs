@@ -0,0 +1,197 @@
+//! src/db/tags.rs
+//!
+//! Operations on the tags (table tags)
+//! and on the tags of the partitions (table partition_tags)
+//!
+
+use axum::http::StatusCode;
+use sqlx::{PgPool, Postgres, QueryBuilder, Transaction};
+
+use crate::errors::MyAppError;
+use crate::models::pagination::PageParams;
+use crate::models::tag::{PartitionTag, Tag};
+
+//*******************************************************************************************
+// CRUD Operations on tags
+//
+
+///
+/// **Adds a tag to the DB**<br>
+/// returns the added Tag or MyAppError (CONFLICT if the tag exists)
+///
+pub async fn add_tag(pool: &PgPool, tag_name: String) -> Result<Tag, MyAppError> {
+    let tag = sqlx::query_as!(
+        Tag,
+        "INSERT INTO tags (name) VALUES ( $1 ) RETURNING id, name;",
+        tag_name.trim()
+    )
+    .fetch_one(pool)
+    .await?;
+
+    tracing::info!("db : tag added : {:?}", &tag);
+    Ok(tag)
+}
+
+pub async fn update_tag(id: i32, tag_name: String, pool: &PgPool) -> Result<Tag, MyAppError> {
+    let tag = sqlx::query_as!(
+        Tag,
+        "UPDATE tags SET name = $1 WHERE id = $2 RETURNING id, name;",
+        tag_name.trim(),
+        id,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(tag)
+}
+
+///
+/// **Deletes a tag**<br>
+/// the tag is removed from the partitions (ON DELETE CASCADE)
+///
+pub async fn delete_tag(id: i32, pool: &PgPool) -> Result<String, MyAppError> {
+    let tag = find_tag_by_id(id, pool)
+        .await?
+        .ok_or_else(|| MyAppError::new(StatusCode::NOT_FOUND, "Tag with the ID not found !"))?;
+
+    sqlx::query("DELETE FROM tags WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    tracing::info!("db : Tag deleted : {}", &tag.name);
+    Ok(tag.name)
+}
+
+///
+/// Returns the list of all the tags ordered by name
+///
+pub async fn list_tags(pool: &PgPool) -> Result<Vec<Tag>, MyAppError> {
+    let tags = sqlx::query_as!(Tag, "SELECT id, name FROM tags ORDER BY lower(name);")
+        .fetch_all(pool)
+        .await?;
+    Ok(tags)
+}
+
+///
+/// Returns one page of the list of tags
+/// sorted by name (sort=name) or by id (sort=id)
+///
+pub async fn list_tags_page(page: &PageParams, pool: &PgPool) -> Result<Vec<Tag>, MyAppError> {
+    let column = page
+        .sort_column(&[("name", "lower(name)"), ("id", "id")])
+        .unwrap_or("lower(name)");
+    let mut query = QueryBuilder::<Postgres>::new("SELECT id, name FROM tags ORDER BY ");
+    query
+        .push(column)
+        .push(" ")
+        .push(page.sql_dir())
+        .push(", id LIMIT ")
+        .push_bind(page.per_page())
+        .push(" OFFSET ")
+        .push_bind(page.offset());
+    let tags = query.build_query_as::<Tag>().fetch_all(pool).await?;
+    Ok(tags)
+}
+
+///
+/// Returns the number of tags
+/// used for the pagination
+///
+pub async fn count_tags(pool: &PgPool) -> Result<i64, MyAppError> {
+    let total = sqlx::query_scalar!(r#"SELECT COUNT(*) as "count!" FROM tags"#)
+        .fetch_one(pool)
+        .await?;
+    Ok(total)
+}
+
+pub async fn find_tag_by_id(id: i32, pool: &PgPool) -> Result<Option<Tag>, MyAppError> {
+    let tag = sqlx::query_as!(Tag, "SELECT id, name FROM tags WHERE id = $1;", id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(tag)
+}
+
+///
+/// **find tag by name parts**<br>
+/// returns the tags beginning with the letters entered (case insensitive)
+///
+pub async fn find_tag_by_name_parts(name: String, pool: &PgPool) -> Result<Vec<Tag>, MyAppError> {
+    let mut part_name = name.trim().to_lowercase();
+    part_name.push('%');
+
+    let tags = sqlx::query_as!(
+        Tag,
+        "SELECT id, name FROM tags WHERE lower(name) LIKE $1 ORDER BY lower(name);",
+        part_name
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(tags)
+}
+
+//*******************************************************************************************
+// Tags of the partitions
+//
+
+///
+/// **Replaces the tags of a partition**<br>
+/// the tags that don't exist yet are created on the fly
+/// (the names are compared case insensitive : "gospel" is the tag "Gospel")
+///
+/// Used by add_partition and update_partition inside their transaction
+///
+pub async fn set_partition_tags(
+    partition_id: i32,
+    tag_names: &[String],
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<(), MyAppError> {
+    sqlx::query("DELETE FROM partition_tags WHERE partition_id = $1")
+        .bind(partition_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for name in tag_names {
+        // DO UPDATE (and not DO NOTHING) so that the id of an existing tag is returned
+        let tag_id: i32 = sqlx::query_scalar(
+            "INSERT INTO tags (name) VALUES ( $1 )
+            ON CONFLICT ((lower(name))) DO UPDATE SET name = tags.name
+            RETURNING id",
+        )
+        .bind(name.trim())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO partition_tags (partition_id, tag_id) VALUES ( $1, $2 )
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(partition_id)
+        .bind(tag_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    Ok(())
+}
+
+///
+/// **Returns the tags of a list of partitions**<br>
+/// used to show the tags next to the partitions
+///
+pub async fn list_tags_for_partitions(
+    partition_ids: &[i32],
+    pool: &PgPool,
+) -> Result<Vec<PartitionTag>, MyAppError> {
+    let tags = sqlx::query_as!(
+        PartitionTag,
+        "SELECT partition_tags.partition_id, partition_tags.tag_id, tags.name
+        FROM partition_tags
+        INNER JOIN tags
+        ON partition_tags.tag_id = tags.id
+        WHERE partition_tags.partition_id = ANY($1)
+        ORDER BY lower(tags.name);",
+        partition_ids
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(tags)
+}
@@ -0,0 +1,213 @@
+//! src/db/trash.rs
+//!
+//! Trash of the catalogue : the partitions, musicians and genres whose
+//! deleted_at is set (soft deletion)
+//!
+//! A record of the trash can be restored or purged (really deleted),
+//! the records older than the retention period are purged automatically
+//!
+
+use axum::http::StatusCode;
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::errors::MyAppError;
+use crate::models::audit::AuditEntity;
+use crate::models::trash::TrashItem;
+
+/// the records of the three tables in the trash, as TrashItems
+const TRASH_SELECT: &str = "
+    SELECT 'partition'::audit_entity AS entity, id, title AS name, deleted_at
+    FROM partitions WHERE deleted_at IS NOT NULL
+    UNION ALL
+    SELECT 'person'::audit_entity AS entity, id, full_name AS name, deleted_at
+    FROM persons WHERE deleted_at IS NOT NULL
+    UNION ALL
+    SELECT 'genre'::audit_entity AS entity, id, name, deleted_at
+    FROM genres WHERE deleted_at IS NOT NULL
+    ";
+
+///
+/// Returns the records in the trash, the last deleted first
+///
+pub async fn list_trash(pool: &PgPool) -> Result<Vec<TrashItem>, MyAppError> {
+    let items = sqlx::query_as::<_, TrashItem>(&format!(
+        "{TRASH_SELECT} ORDER BY deleted_at DESC, entity, id"
+    ))
+    .fetch_all(pool)
+    .await?;
+    Ok(items)
+}
+
+///
+/// **Restores a record from the trash**<br>
+/// a partition can only be restored when its musicians and its genre
+/// are not in the trash (MyAppError CONFLICT)<br>
+/// Returns the restored record or MyAppError (NOT_FOUND if it is not in the trash)
+///
+pub async fn restore_from_trash(
+    entity: AuditEntity,
+    id: i32,
+    pool: &PgPool,
+) -> Result<TrashItem, MyAppError> {
+    if entity == AuditEntity::Partition {
+        let deleted_references: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM partitions p \
+             WHERE p.id = $1 AND ( \
+                EXISTS (SELECT 1 FROM genres g \
+                    WHERE g.id = p.genre_id AND g.deleted_at IS NOT NULL) \
+                OR EXISTS (SELECT 1 FROM partition_contributors pc \
+                    INNER JOIN persons pe ON pe.id = pc.person_id \
+                    WHERE pc.partition_id = p.id AND pe.deleted_at IS NOT NULL) \
+                OR EXISTS (SELECT 1 FROM persons pe \
+                    WHERE pe.id = p.person_id AND pe.deleted_at IS NOT NULL))",
+        )
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+        if deleted_references > 0 {
+            return Err(MyAppError::new(
+                StatusCode::CONFLICT,
+                "un musicien ou le genre de la partition est dans la corbeille, \
+                 restaurez-le d'abord",
+            ));
+        }
+    }
+
+    let (table, name) = columns_of(entity);
+    let item = sqlx::query_as::<_, TrashItem>(&format!(
+        "UPDATE {table} SET deleted_at = NULL \
+         FROM (SELECT id, deleted_at FROM {table} WHERE id = $1) AS old \
+         WHERE {table}.id = old.id AND old.deleted_at IS NOT NULL \
+         RETURNING $2::audit_entity AS entity, {table}.id, {table}.{name} AS name, \
+            old.deleted_at"
+    ))
+    .bind(id)
+    .bind(entity)
+    .fetch_one(pool)
+    .await?;
+
+    tracing::info!("db : restored from trash : {:?}", &item);
+    Ok(item)
+}
+
+///
+/// **Purges a record from the trash : the record is deleted for good**<br>
+/// with a partition, its files (rows), copies, loans ... are deleted (ON DELETE CASCADE)<br>
+/// a musician or a genre still used by partitions of the trash can't be purged
+/// (MyAppError CONFLICT)
+///
+pub async fn purge_from_trash(
+    entity: AuditEntity,
+    id: i32,
+    pool: &PgPool,
+) -> Result<TrashItem, MyAppError> {
+    let mut tx = pool.begin().await?;
+    let item = delete_from_trash(entity, id, &mut tx).await?;
+    tx.commit().await?;
+
+    tracing::info!("db : purged from trash : {:?}", &item);
+    Ok(item)
+}
+
+///
+/// **Purges the records deleted more than `retention_days` days ago**<br>
+/// the partitions first, then the musicians and genres no partition uses any more
+///
+/// Returns the purged records
+///
+pub async fn purge_expired(
+    retention_days: i64,
+    pool: &PgPool,
+) -> Result<Vec<TrashItem>, MyAppError> {
+    let expired = sqlx::query_as::<_, TrashItem>(&format!(
+        "SELECT * FROM ({TRASH_SELECT}) AS trash \
+         WHERE deleted_at < NOW() - make_interval(days => $1::INT) \
+         ORDER BY (entity = 'partition') DESC, deleted_at"
+    ))
+    .bind(retention_days)
+    .fetch_all(pool)
+    .await?;
+
+    let mut tx = pool.begin().await?;
+    let mut purged = Vec::new();
+    for item in expired {
+        match delete_from_trash(item.entity, item.id, &mut tx).await {
+            Ok(item) => purged.push(item),
+            // still used by a partition of the trash : purged with the partition
+            Err(err) if err.code == StatusCode::CONFLICT => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    tx.commit().await?;
+
+    if !purged.is_empty() {
+        tracing::info!("db : {} record(s) purged from trash", purged.len());
+    }
+    Ok(purged)
+}
+
+//*******************************************************************************
+// Helpers functions
+//
+
+/// the table and the name column of a kind of record
+pub fn columns_of(entity: AuditEntity) -> (&'static str, &'static str) {
+    match entity {
+        AuditEntity::Partition => ("partitions", "title"),
+        AuditEntity::Person => ("persons", "full_name"),
+        AuditEntity::Genre => ("genres", "name"),
+    }
+}
+
+///
+/// Returns the number of partitions (in the trash or not) using a musician or a genre
+///
+async fn used_by_partitions(
+    entity: AuditEntity,
+    id: i32,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<i64, MyAppError> {
+    let query = match entity {
+        AuditEntity::Partition => return Ok(0),
+        AuditEntity::Person => {
+            "SELECT COUNT(*) FROM partitions p WHERE p.person_id = $1 OR EXISTS ( \
+                SELECT 1 FROM partition_contributors pc \
+                WHERE pc.partition_id = p.id AND pc.person_id = $1)"
+        }
+        AuditEntity::Genre => "SELECT COUNT(*) FROM partitions WHERE genre_id = $1",
+    };
+    let used: i64 = sqlx::query_scalar(query)
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+    Ok(used)
+}
+
+///
+/// Deletes a record of the trash, or MyAppError (NOT_FOUND if it is not in the trash,
+/// CONFLICT if partitions still use the musician or the genre)
+///
+async fn delete_from_trash(
+    entity: AuditEntity,
+    id: i32,
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<TrashItem, MyAppError> {
+    let used = used_by_partitions(entity, id, tx).await?;
+    if used > 0 {
+        return Err(MyAppError::new(
+            StatusCode::CONFLICT,
+            format!("encore utilisé par {used} partition(s) de la corbeille"),
+        ));
+    }
+
+    let (table, name) = columns_of(entity);
+    let item = sqlx::query_as::<_, TrashItem>(&format!(
+        "DELETE FROM {table} WHERE id = $1 AND deleted_at IS NOT NULL \
+         RETURNING $2::audit_entity AS entity, id, {name} AS name, deleted_at"
+    ))
+    .bind(id)
+    .bind(entity)
+    .fetch_one(&mut *tx)
+    .await?;
+    Ok(item)
+}
@@ -1,7 +1,9 @@
 //! src/errors.rs
 
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Redirect, Response};
+use axum::Json;
 use axum_flash::Flash;
 use password_auth::VerifyError;
 use redis::RedisError;
@@ -38,11 +40,26 @@ impl From<anyhow::Error> for MyAppError {
     }
 }
 
+///
+/// A record that is not found is a NOT_FOUND error,    
+/// a record still referenced by another table is a CONFLICT,    
+/// everything else is an INTERNAL_SERVER_ERROR
+///
 impl From<sqlx::Error> for MyAppError {
     fn from(value: sqlx::Error) -> Self {
         let error_message = value.to_string();
+        let code = match &value {
+            sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+            sqlx::Error::Database(db_err)
+                if db_err.code().as_deref() == Some("23503")
+                    || db_err.code().as_deref() == Some("23505") =>
+            {
+                StatusCode::CONFLICT
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
         Self {
-            code: StatusCode::INTERNAL_SERVER_ERROR,
+            code,
             message: error_message,
         }
     }
@@ -77,6 +94,16 @@ impl From<redis::RedisError> for MyAppError {
     }
 }
 
+impl From<std::io::Error> for MyAppError {
+    fn from(value: std::io::Error) -> Self {
+        let error_message = value.to_string();
+        Self {
+            code: StatusCode::INTERNAL_SERVER_ERROR,
+            message: error_message,
+        }
+    }
+}
+
 impl Display for MyAppError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", &self.message)
@@ -95,4 +122,59 @@ impl IntoResponse for MyAppError {
     }
 }
 
+///
+/// # ApiError
+/// Error returned by the JSON API (routes under /api/v1)    
+/// Same content as MyAppError but rendered as a JSON body
+/// instead of the ErrorTemplate HTML page :    
+/// { "status": "error", "code": 404, "message": "..." }
+///
+#[derive(Debug)]
+pub struct ApiError(pub MyAppError);
+
+impl ApiError {
+    pub fn new(code: StatusCode, message: impl Into<String>) -> Self {
+        Self(MyAppError::new(code, message))
+    }
+}
+
+impl From<MyAppError> for ApiError {
+    fn from(value: MyAppError) -> Self {
+        Self(value)
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(value: JsonRejection) -> Self {
+        Self(MyAppError::new(value.status(), value.body_text()))
+    }
+}
 
+impl From<PathRejection> for ApiError {
+    fn from(value: PathRejection) -> Self {
+        Self(MyAppError::new(value.status(), value.body_text()))
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(value: QueryRejection) -> Self {
+        Self(MyAppError::new(value.status(), value.body_text()))
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", &self.0.message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({
+            "status": "error",
+            "code": self.0.code.as_u16(),
+            "message": self.0.message,
+        }));
+        (self.0.code, body).into_response()
+    }
+}
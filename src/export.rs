@@ -0,0 +1,242 @@
+//! src/export.rs
+//!
+//! Export of the catalogue (partitions, persons, genres) in CSV or JSON
+//!
+//! The rows are read from the DB in batches and written in the response
+//! as they come (streamed body), a large catalogue is never held in memory.
+//! The CSV of the partitions has the columns of the CSV import.
+//!
+
+use axum::body::StreamBody;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use futures::stream::{self, BoxStream, Stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::MyAppError;
+use crate::models::genre::Genre;
+use crate::models::musician::Person;
+use crate::models::partition::{format_duration, ShowPartition};
+
+///
+/// Query string of the export routes
+/// * format : "csv" (default) or "json"
+/// * name : the first letters of the names (persons and genres, as the find forms)
+///
+/// the partitions take the criteria of the search (PartitionFilter) in the same query string
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExportParams {
+    pub format: Option<String>,
+    pub name: Option<String>,
+}
+
+impl ExportParams {
+    pub fn format(&self) -> ExportFormat {
+        match self.format.as_deref() {
+            Some(format) if format.eq_ignore_ascii_case("json") => ExportFormat::Json,
+            _ => ExportFormat::Csv,
+        }
+    }
+
+    ///
+    /// Pattern LIKE of the name filter ("BR" -> "BR%")
+    ///
+    pub fn name_pattern(&self) -> String {
+        format!("{}%", self.name.as_deref().unwrap_or_default().trim())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+///
+/// A row of an export : its CSV columns (the JSON is given by Serialize)
+///
+pub trait ExportRecord: Serialize {
+    const HEADER: &'static [&'static str];
+
+    fn csv_record(&self) -> Vec<String>;
+}
+
+impl ExportRecord for ShowPartition {
+    const HEADER: &'static [&'static str] = &[
+        "id",
+        "title",
+        "composer",
+        "genre",
+        "contributors",
+        "tags",
+        "musical_key",
+        "time_signature",
+        "tempo",
+        "duration",
+        "instrumentation",
+        "difficulty",
+    ];
+
+    fn csv_record(&self) -> Vec<String> {
+        let metadata = &self.metadata;
+        let contributors = self
+            .other_contributors()
+            .iter()
+            .map(|c| format!("{} : {}", c.role.label(), c.full_name))
+            .collect::<Vec<_>>()
+            .join(" ; ");
+        vec![
+            self.id.to_string(),
+            self.title.clone(),
+            self.full_name.clone(),
+            self.name.clone(),
+            contributors,
+            self.tags_text(),
+            metadata
+                .key
+                .map(|v| v.value().to_string())
+                .unwrap_or_default(),
+            metadata
+                .time_signature
+                .map(|v| v.value().to_string())
+                .unwrap_or_default(),
+            metadata
+                .tempo
+                .map(|v| v.value().to_string())
+                .unwrap_or_default(),
+            metadata
+                .duration_seconds
+                .map(format_duration)
+                .unwrap_or_default(),
+            metadata
+                .instrumentation
+                .map(|v| v.value().to_string())
+                .unwrap_or_default(),
+            metadata
+                .difficulty
+                .map(|v| v.value().to_string())
+                .unwrap_or_default(),
+        ]
+    }
+}
+
+impl ExportRecord for Person {
+    const HEADER: &'static [&'static str] = &["id", "full_name"];
+
+    fn csv_record(&self) -> Vec<String> {
+        vec![self.id.to_string(), self.full_name.clone()]
+    }
+}
+
+impl ExportRecord for Genre {
+    const HEADER: &'static [&'static str] = &["id", "name"];
+
+    fn csv_record(&self) -> Vec<String> {
+        vec![self.id.to_string(), self.name.clone()]
+    }
+}
+
+///
+/// **Builds the response of an export**<br>
+/// * batches : the rows read from the DB, batch after batch
+/// * name : the name of the downloaded file (without extension)
+///
+/// CSV : a header line then the rows (UTF-8 with BOM for the spreadsheets)<br>
+/// JSON : an array of the rows
+///
+/// An error of the DB while streaming interrupts the download
+///
+pub fn export_response<T, S>(batches: S, format: ExportFormat, name: &str) -> Response
+where
+    T: ExportRecord + Send + 'static,
+    S: Stream<Item = Result<Vec<T>, MyAppError>> + Send + 'static,
+{
+    let body: BoxStream<'static, Result<Vec<u8>, MyAppError>> = match format {
+        ExportFormat::Csv => {
+            let header = csv_lines(std::iter::once(T::HEADER.iter().map(|h| h.to_string()))).map(
+                |mut bytes| {
+                    bytes.splice(0..0, "\u{feff}".bytes());
+                    bytes
+                },
+            );
+            stream::once(async move { header })
+                .chain(batches.and_then(|batch| async move {
+                    csv_lines(batch.iter().map(|row| row.csv_record()))
+                }))
+                .boxed()
+        }
+        ExportFormat::Json => stream::once(async { Ok(b"[".to_vec()) })
+            .chain(
+                batches
+                    .enumerate()
+                    .map(|(index, batch)| json_items(index == 0, &batch?)),
+            )
+            .chain(stream::once(async { Ok(b"]".to_vec()) }))
+            .boxed(),
+    };
+    let file_name = name.to_string();
+    let body =
+        body.inspect_err(move |err| tracing::info!("error exporting {} : {}", file_name, err));
+
+    let disposition = format!("attachment; filename=\"{}.{}\"", name, format.extension());
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        StreamBody::new(body),
+    )
+        .into_response()
+}
+
+///
+/// Writes rows as CSV lines
+///
+fn csv_lines<R, I>(rows: R) -> Result<Vec<u8>, MyAppError>
+where
+    R: Iterator<Item = I>,
+    I: IntoIterator<Item = String>,
+{
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer
+            .write_record(row)
+            .map_err(|err| MyAppError::new(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+    }
+    writer
+        .into_inner()
+        .map_err(|err| MyAppError::new(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
+
+///
+/// Writes rows as items of a JSON array
+/// (separated by commas, the first batch doesn't start with a comma)
+///
+fn json_items<T: Serialize>(first_batch: bool, rows: &[T]) -> Result<Vec<u8>, MyAppError> {
+    let mut bytes = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        if index > 0 || !first_batch {
+            bytes.push(b',');
+        }
+        serde_json::to_writer(&mut bytes, row)
+            .map_err(|err| MyAppError::new(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,510 @@
+//! src/handlers/api_v1_handlers.rs
+//!
+//! JSON REST API for the catalogue (routes under /api/v1)
+//!
+//! Same operations as the HTML handlers (persons, genres, partitions)
+//! but the data is sent and received as JSON.
+//! The errors are returned as JSON bodies (ApiError) with the proper status code
+//! instead of the ErrorTemplate HTML page.
+//!
+
+use axum::async_trait;
+use axum::debug_handler;
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{Path, Query, State};
+use axum::http::request::Parts;
+use axum::http::{HeaderName, Request, StatusCode};
+use axum::{Extension, Json};
+use axum_core::extract::{FromRequest, FromRequestParts};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::db::audit::{partition_snapshot, record_audit, snapshot};
+use crate::db::genres::{add_genre, delete_genre, find_genre_by_id, list_genres, update_genre};
+use crate::db::musicians::{
+    add_person, delete_person, find_person_by_id, list_persons, update_person,
+};
+use crate::db::partitions::{
+    add_partition, count_partitions, delete_partition, find_partition_by_id, search_partitions,
+    update_partition,
+};
+use crate::errors::ApiError;
+use crate::models::audit::{AuditChange, AuditEntity};
+use crate::models::deletion::DeletePolicy;
+use crate::models::genre::{Genre, NewGenre};
+use crate::models::musician::{NewPerson, Person};
+use crate::models::pagination::PageParams;
+use crate::models::partition::{
+    MusicalMetadata, NewContributor, Partition, PartitionDetails, PartitionFilter, ShowPartition,
+};
+use crate::AppState;
+
+///
+/// **Extractor ApiJson**<br>
+/// Works like axum::Json but a malformed body is rejected
+/// with an ApiError (JSON body) instead of a plain text response
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+    Json<T>: FromRequest<S, B, Rejection = JsonRejection>,
+    B: Send + 'static,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        Ok(ApiJson(value))
+    }
+}
+
+///
+/// **Extractor ApiPath**<br>
+/// Works like axum::extract::Path but an invalid parameter ('/api/v1/persons/abc')
+/// is rejected with an ApiError
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiPath<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiPath<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+    Path<T>: FromRequestParts<S, Rejection = PathRejection>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(value) = Path::<T>::from_request_parts(parts, state).await?;
+        Ok(ApiPath(value))
+    }
+}
+
+///
+/// **Extractor ApiQuery**<br>
+/// Works like axum::extract::Query but an invalid query string ('?page=abc')
+/// is rejected with an ApiError
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiQuery<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+    Query<T>: FromRequestParts<S, Rejection = QueryRejection>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(value) = Query::<T>::from_request_parts(parts, state).await?;
+        Ok(ApiQuery(value))
+    }
+}
+
+///
+/// Returns an error if a mandatory text field is empty
+///
+fn check_not_empty(field: &str, value: &str) -> Result<(), ApiError> {
+    if value.trim().is_empty() {
+        Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("The field '{field}' can't be empty"),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+///
+/// Returns the main musician and the genre of a partition sent in a body
+/// or an error if one of them doesn't exist
+///
+async fn find_person_and_genre(
+    person_id: i32,
+    genre_id: i32,
+    state: &AppState,
+) -> Result<(Person, Genre), ApiError> {
+    let person = find_person_by_id(person_id, &state.pool)
+        .await?
+        .ok_or_else(|| ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "Unknown person_id"))?;
+    let genre = find_genre_by_id(genre_id, &state.pool)
+        .await?
+        .ok_or_else(|| ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "Unknown genre_id"))?;
+    Ok((person, genre))
+}
+
+///
+/// Returns an error if a musician given in the contributors doesn't exist
+///
+async fn check_contributors(
+    contributors: &[NewContributor],
+    state: &AppState,
+) -> Result<(), ApiError> {
+    for contributor in contributors {
+        find_person_by_id(contributor.person_id, &state.pool)
+            .await?
+            .ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!(
+                        "Unknown person_id in contributors : {}",
+                        contributor.person_id
+                    ),
+                )
+            })?;
+    }
+    Ok(())
+}
+
+//***********************************************************************************
+// Partitions
+//
+
+///
+/// Body of the requests to create or modify a partition
+/// * person_id : the main musician (composer)
+/// * contributors : the other musicians with their role (optional)
+/// * tags : names of the tags, created if they don't exist (optional)
+/// * metadata : the musical metadata (optional), e.g.
+///   { "key": "D minor", "time_signature": "3/4", "tempo": "andante",
+///   "duration_seconds": 225, "instrumentation": "satb", "difficulty": "3" }
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartitionInput {
+    pub title: String,
+    pub person_id: i32,
+    pub genre_id: i32,
+    #[serde(default)]
+    pub contributors: Vec<NewContributor>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub metadata: MusicalMetadata,
+}
+
+impl PartitionInput {
+    fn details(&self) -> PartitionDetails {
+        PartitionDetails {
+            contributors: self.contributors.clone(),
+            tags: self.tags.clone(),
+            metadata: self.metadata,
+        }
+    }
+}
+
+///
+/// # Handler
+/// **GET /api/v1/partitions**<br>
+/// Returns the list of the partitions (with the musician name and the genre name)<br>
+/// takes the same filters as the search page : *'?q=...'*
+/// and the pagination : *'?page=&per_page=&sort=&dir='*<br>
+/// the total number of partitions found is sent in the header X-Total-Count
+///
+#[debug_handler]
+pub async fn api_list_partitions_hdl(
+    State(state): State<AppState>,
+    ApiQuery(filter): ApiQuery<PartitionFilter>,
+    ApiQuery(params): ApiQuery<PageParams>,
+) -> Result<([(HeaderName, String); 1], Json<Vec<ShowPartition>>), ApiError> {
+    let partitions = search_partitions(&filter, &params, &state.pool).await?;
+    let total = count_partitions(&filter, &state.pool).await?;
+    Ok((
+        [(HeaderName::from_static("x-total-count"), total.to_string())],
+        Json(partitions),
+    ))
+}
+
+///
+/// # Handler
+/// **GET /api/v1/partitions/:id**<br>
+/// Returns one partition or 404
+///
+#[debug_handler]
+pub async fn api_get_partition_hdl(
+    State(state): State<AppState>,
+    ApiPath(id): ApiPath<i32>,
+) -> Result<Json<Partition>, ApiError> {
+    let partition = find_partition_by_id(id, &state.pool).await?;
+    Ok(Json(partition))
+}
+
+///
+/// # Handler
+/// **POST /api/v1/partitions**<br>
+/// body : { "title": "...", "person_id": 1, "genre_id": 1,
+/// "contributors": [{ "person_id": 2, "role": "arranger" }] }<br>
+/// Returns 201 and the created partition
+///
+#[debug_handler]
+pub async fn api_create_partition_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    ApiJson(input): ApiJson<PartitionInput>,
+) -> Result<(StatusCode, Json<Partition>), ApiError> {
+    check_not_empty("title", &input.title)?;
+    let (person, genre) = find_person_and_genre(input.person_id, input.genre_id, &state).await?;
+    check_contributors(&input.contributors, &state).await?;
+
+    let details = input.details();
+    let partition = add_partition(
+        input.title,
+        person.full_name,
+        genre.name,
+        &details,
+        &state.pool,
+    )
+    .await?;
+    if let Some(id) = partition.id {
+        let after = partition_snapshot(id, &state.pool).await;
+        let change = AuditChange::created(AuditEntity::Partition, id, after);
+        record_audit(&auth_jwt.user, change, &state.pool).await;
+    }
+    Ok((StatusCode::CREATED, Json(partition)))
+}
+
+///
+/// # Handler
+/// **PUT /api/v1/partitions/:id**<br>
+/// body : { "title": "...", "person_id": 1, "genre_id": 1, "contributors": [...] }<br>
+/// the contributors replace the existing ones<br>
+/// Returns the modified partition
+///
+#[debug_handler]
+pub async fn api_update_partition_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    ApiPath(id): ApiPath<i32>,
+    ApiJson(input): ApiJson<PartitionInput>,
+) -> Result<Json<Partition>, ApiError> {
+    check_not_empty("title", &input.title)?;
+    find_partition_by_id(id, &state.pool).await?;
+    find_person_and_genre(input.person_id, input.genre_id, &state).await?;
+    check_contributors(&input.contributors, &state).await?;
+    let details = input.details();
+    let before = partition_snapshot(id, &state.pool).await;
+    let partition = update_partition(
+        id,
+        input.title,
+        input.person_id,
+        input.genre_id,
+        &details,
+        &state.pool,
+    )
+    .await?;
+    let after = partition_snapshot(id, &state.pool).await;
+    let change = AuditChange::updated(AuditEntity::Partition, id, before, after);
+    record_audit(&auth_jwt.user, change, &state.pool).await;
+    Ok(Json(partition))
+}
+
+///
+/// # Handler
+/// **DELETE /api/v1/partitions/:id**<br>
+/// Returns 204 (no content)
+///
+#[debug_handler]
+pub async fn api_delete_partition_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    ApiPath(id): ApiPath<i32>,
+) -> Result<StatusCode, ApiError> {
+    let before = partition_snapshot(id, &state.pool).await;
+    delete_partition(id, &state.pool).await?;
+    let change = AuditChange::deleted(AuditEntity::Partition, id, before);
+    record_audit(&auth_jwt.user, change, &state.pool).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+//***********************************************************************************
+// Persons
+//
+
+///
+/// # Handler
+/// **GET /api/v1/persons**<br>
+/// Returns the list of all musicians
+///
+#[debug_handler]
+pub async fn api_list_persons_hdl(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Person>>, ApiError> {
+    let persons = list_persons(&state.pool).await?;
+    Ok(Json(persons))
+}
+
+///
+/// # Handler
+/// **GET /api/v1/persons/:id**<br>
+/// Returns one musician or 404
+///
+#[debug_handler]
+pub async fn api_get_person_hdl(
+    State(state): State<AppState>,
+    ApiPath(id): ApiPath<i32>,
+) -> Result<Json<Person>, ApiError> {
+    let person = find_person_by_id(id, &state.pool)
+        .await?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "Person not found"))?;
+    Ok(Json(person))
+}
+
+///
+/// # Handler
+/// **POST /api/v1/persons**<br>
+/// body : { "full_name": "..." }<br>
+/// Returns 201 and the created musician
+///
+#[debug_handler]
+pub async fn api_create_person_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    ApiJson(input): ApiJson<NewPerson>,
+) -> Result<(StatusCode, Json<Person>), ApiError> {
+    check_not_empty("full_name", &input.full_name)?;
+    let person = add_person(&state.pool, input.full_name).await?;
+    let change = AuditChange::created(AuditEntity::Person, person.id, snapshot(&person));
+    record_audit(&auth_jwt.user, change, &state.pool).await;
+    Ok((StatusCode::CREATED, Json(person)))
+}
+
+///
+/// # Handler
+/// **PUT /api/v1/persons/:id**<br>
+/// body : { "full_name": "..." }<br>
+/// Returns the modified musician
+///
+#[debug_handler]
+pub async fn api_update_person_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    ApiPath(id): ApiPath<i32>,
+    ApiJson(input): ApiJson<NewPerson>,
+) -> Result<Json<Person>, ApiError> {
+    check_not_empty("full_name", &input.full_name)?;
+    let before = find_person_by_id(id, &state.pool).await?;
+    let person = update_person(id, input.full_name, &state.pool).await?;
+    let before = before.and_then(|p| snapshot(&p));
+    let change = AuditChange::updated(AuditEntity::Person, id, before, snapshot(&person));
+    record_audit(&auth_jwt.user, change, &state.pool).await;
+    Ok(Json(person))
+}
+
+///
+/// # Handler
+/// **DELETE /api/v1/persons/:id**<br>
+/// Returns 204 (no content)<br>
+/// or 409 if partitions still refer to the musician
+///
+#[debug_handler]
+pub async fn api_delete_person_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    ApiPath(id): ApiPath<i32>,
+) -> Result<StatusCode, ApiError> {
+    let person = delete_person(id, DeletePolicy::Block, &state.pool).await?;
+    let change = AuditChange::deleted(AuditEntity::Person, id, snapshot(&person));
+    record_audit(&auth_jwt.user, change, &state.pool).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+//***********************************************************************************
+// Genres
+//
+
+///
+/// # Handler
+/// **GET /api/v1/genres**<br>
+/// Returns the list of all genres
+///
+#[debug_handler]
+pub async fn api_list_genres_hdl(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Genre>>, ApiError> {
+    let genres = list_genres(&state.pool).await?;
+    Ok(Json(genres))
+}
+
+///
+/// # Handler
+/// **GET /api/v1/genres/:id**<br>
+/// Returns one genre or 404
+///
+#[debug_handler]
+pub async fn api_get_genre_hdl(
+    State(state): State<AppState>,
+    ApiPath(id): ApiPath<i32>,
+) -> Result<Json<Genre>, ApiError> {
+    let genre = find_genre_by_id(id, &state.pool)
+        .await?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "Genre not found"))?;
+    Ok(Json(genre))
+}
+
+///
+/// # Handler
+/// **POST /api/v1/genres**<br>
+/// body : { "name": "..." }<br>
+/// Returns 201 and the created genre
+///
+#[debug_handler]
+pub async fn api_create_genre_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    ApiJson(input): ApiJson<NewGenre>,
+) -> Result<(StatusCode, Json<Genre>), ApiError> {
+    check_not_empty("name", &input.name)?;
+    let genre = add_genre(&state.pool, input.name).await?;
+    let change = AuditChange::created(AuditEntity::Genre, genre.id, snapshot(&genre));
+    record_audit(&auth_jwt.user, change, &state.pool).await;
+    Ok((StatusCode::CREATED, Json(genre)))
+}
+
+///
+/// # Handler
+/// **PUT /api/v1/genres/:id**<br>
+/// body : { "name": "..." }<br>
+/// Returns the modified genre
+///
+#[debug_handler]
+pub async fn api_update_genre_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    ApiPath(id): ApiPath<i32>,
+    ApiJson(input): ApiJson<NewGenre>,
+) -> Result<Json<Genre>, ApiError> {
+    check_not_empty("name", &input.name)?;
+    let before = find_genre_by_id(id, &state.pool).await?;
+    let genre = update_genre(id, input.name, &state.pool).await?;
+    let before = before.and_then(|g| snapshot(&g));
+    let change = AuditChange::updated(AuditEntity::Genre, id, before, snapshot(&genre));
+    record_audit(&auth_jwt.user, change, &state.pool).await;
+    Ok(Json(genre))
+}
+
+///
+/// # Handler
+/// **DELETE /api/v1/genres/:id**<br>
+/// Returns 204 (no content)<br>
+/// or 409 if partitions still refer to the genre
+///
+#[debug_handler]
+pub async fn api_delete_genre_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    ApiPath(id): ApiPath<i32>,
+) -> Result<StatusCode, ApiError> {
+    let before = find_genre_by_id(id, &state.pool).await?;
+    delete_genre(id, DeletePolicy::Block, &state.pool).await?;
+    let change = AuditChange::deleted(AuditEntity::Genre, id, before.and_then(|g| snapshot(&g)));
+    record_audit(&auth_jwt.user, change, &state.pool).await;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,55 @@
+//! src/handlers/audit_handlers.rs
+//!
+//! Audit log of the changes of the catalogue (administrators only)
+//!
+
+use axum::debug_handler;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::Extension;
+
+use crate::askama::askama_tpl::AuditTemplate;
+use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::db::audit::{count_audit, list_audit_page};
+use crate::db::users::list_users;
+use crate::errors::MyAppError;
+use crate::models::audit::AuditFilter;
+use crate::models::pagination::{Page, PageParams};
+use crate::AppState;
+
+///
+/// # Handler
+///
+/// **Shows the audit log** (administrators only)<br>
+/// the changes matching the criteria
+/// *'?actor_id=&action=&entity=&entity_id=&from=2023-07-01&to=2023-07-31'*,
+/// the last ones first, one page of the list *'&page=&per_page='*
+///
+/// Returns a HTML Page (Askama Template) or MyAppError (UNAUTHORIZED)
+///
+#[debug_handler]
+pub async fn audit_askama_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Query(filter): Query<AuditFilter>,
+    Query(params): Query<PageParams>,
+) -> Result<AuditTemplate, MyAppError> {
+    if auth_jwt.user.role != "Administrateur" {
+        return Err(MyAppError::new(
+            StatusCode::UNAUTHORIZED,
+            "Hey ! Page only for Administrators",
+        ));
+    }
+    let entries = list_audit_page(&filter, &params, &state.pool).await?;
+    let total = count_audit(&filter, &state.pool).await?;
+    let page = Page::new(&params, total, "/admin/audit").with_filters(filter.query_pairs());
+    let users = list_users(&state.pool).await?;
+
+    Ok(AuditTemplate {
+        title: "Journal des modifications".to_string(),
+        entries,
+        users,
+        filter,
+        page,
+    })
+}
@@ -0,0 +1,65 @@
+//! src/handlers/browse_handlers.rs
+//!
+//! Faceted browsing of the partitions : the results of the criteria with,
+//! for each facet (genre, composer, key, difficulty), the number of partitions
+//! per value counted in the DB
+//!
+
+use axum::debug_handler;
+use axum::extract::{Query, State};
+use axum_flash::IncomingFlashes;
+
+use crate::askama::askama_tpl::BrowsePartitionsTemplate;
+use crate::db::partitions::{count_partition_facet, count_partitions, search_partitions};
+use crate::errors::MyAppError;
+use crate::models::facet::{Facet, FacetKind, BROWSE_PATH};
+use crate::models::pagination::{Page, PageParams};
+use crate::models::partition::PartitionFilter;
+use crate::AppState;
+
+///
+/// # Handler
+///
+/// **Shows the browse page of the partitions** GET *'api/partitions/browse'*<br>
+/// the criteria are the ones of the search *'?q=&tag=&key=...'* and of the facets
+/// *'&genre=3&composer=12&key=d_minor&difficulty=2'* ; the results are paginated
+/// like the main partition page
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn browse_partitions_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Query(filter): Query<PartitionFilter>,
+    Query(params): Query<PageParams>,
+) -> Result<(IncomingFlashes, BrowsePartitionsTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let partitions = search_partitions(&filter, &params, &state.pool).await?;
+    let total = count_partitions(&filter, &state.pool).await?;
+    let mut facets = Vec::new();
+    for kind in FacetKind::ALL {
+        let counts = count_partition_facet(kind, &filter, &state.pool).await?;
+        facets.push(Facet::new(kind, counts, &filter));
+    }
+    let pairs = filter.query_pairs();
+    let page = Page::new(&params, total, BROWSE_PATH).with_filters(pairs.clone());
+
+    let template = BrowsePartitionsTemplate {
+        title: "Parcourir les Partitions".to_string(),
+        flash: Some(flash),
+        q: filter.q.clone().unwrap_or_default(),
+        hidden: pairs.into_iter().filter(|(name, _)| *name != "q").collect(),
+        criteria: filter.criteria_text(),
+        facets,
+        partitions,
+        page,
+    };
+    Ok((in_flash, template))
+}
@@ -0,0 +1,268 @@
+//! src/handlers/collections_handlers.rs
+//!
+//! Favourites and personal collections of the logged user :
+//! star a partition, create, modify, delete a collection, add and remove
+//! its partitions, and read the collections shared by the other users
+//!
+
+use axum::debug_handler;
+use axum::extract::{Form, Path, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum::Extension;
+use axum_flash::{Flash, IncomingFlashes};
+
+use crate::askama::askama_tpl::CollectionTemplate;
+use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::db::collections::*;
+use crate::db::setlists::list_partition_choices;
+use crate::errors::MyAppError;
+use crate::models::collection::{CollectionForm, CollectionItemForm};
+use crate::models::loan::BackForm;
+use crate::AppState;
+
+//***********************************************************************************
+// Favourites
+//
+
+///
+/// # Handler
+///
+/// **Stars or unstars a partition for the logged user**<br>
+/// the form field "back" gives the page to come back to
+///
+/// Returns a flash message and redirects to that page (default : the page of the user *'api/me'*)
+///
+#[debug_handler]
+pub async fn toggle_favourite_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Path(partition_id): Path<i32>,
+    Form(form): Form<BackForm>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&form.back_or("/api/me"));
+    match toggle_favourite(auth_jwt.user.id, partition_id, &state.pool).await {
+        Ok(true) => (flash.success("Partition ajoutée aux favoris"), redirect),
+        Ok(false) => (flash.success("Partition retirée des favoris"), redirect),
+        Err(err) => {
+            tracing::info!("error toggling favourite : {}", err);
+            let message = format!("Favoris pas modifiés : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+//***********************************************************************************
+// CRUD Operations on collections
+//
+
+///
+/// # Handler
+///
+/// **Creates a new collection of the logged user**
+///
+/// Returns a flash message and redirects to the page of the collection
+///
+#[debug_handler]
+pub async fn create_collection_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Form(form): Form<CollectionForm>,
+) -> (Flash, Redirect) {
+    let result = async {
+        let (name, shared) = form
+            .parse()
+            .map_err(|cause| MyAppError::new(StatusCode::UNPROCESSABLE_ENTITY, cause))?;
+        add_collection(auth_jwt.user.id, &name, shared, &state.pool).await
+    }
+    .await;
+
+    match result {
+        Ok(collection) => {
+            let message = format!("Collection créée : {}", collection.name);
+            let redirect = Redirect::to(&format!("/api/collections/{}", collection.id));
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error adding collection : {}", err);
+            let message = format!("Collection pas créée : {}", err.message);
+            (flash.error(message), Redirect::to("/api/me"))
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Renames a collection of the logged user and shares it or not**
+///
+/// Returns a flash message and redirects to the page of the collection
+///
+#[debug_handler]
+pub async fn update_collection_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Path(id): Path<i32>,
+    Form(form): Form<CollectionForm>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&format!("/api/collections/{id}"));
+    let result = async {
+        let (name, shared) = form
+            .parse()
+            .map_err(|cause| MyAppError::new(StatusCode::UNPROCESSABLE_ENTITY, cause))?;
+        update_collection(id, auth_jwt.user.id, &name, shared, &state.pool).await
+    }
+    .await;
+
+    match result {
+        Ok(collection) => {
+            let message = format!("Collection modifiée : {}", collection.name);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error modifying collection : {}", err);
+            let message = format!("Collection pas modifiée : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+#[debug_handler]
+pub async fn delete_collection_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Path(id): Path<i32>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to("/api/me");
+    match delete_collection(id, auth_jwt.user.id, &state.pool).await {
+        Ok(collection) => {
+            let message = format!("Collection effacée : {}", collection.name);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error deleting collection : {}", err);
+            let message = format!("Collection pas effacée : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+//***********************************************************************************
+// Partitions of the collections
+//
+
+///
+/// # Handler
+///
+/// **Adds a partition to a collection of the logged user**<br>
+/// the form field "back" gives the page to come back to
+///
+/// Returns a flash message and redirects to that page (default : the page of the collection)
+///
+#[debug_handler]
+pub async fn add_collection_partition_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Form(form): Form<CollectionItemForm>,
+) -> (Flash, Redirect) {
+    let back = BackForm { back: form.back };
+    let redirect = Redirect::to(&back.back_or(&format!("/api/collections/{}", form.collection_id)));
+    match add_collection_partition(
+        form.collection_id,
+        auth_jwt.user.id,
+        form.partition_id,
+        &state.pool,
+    )
+    .await
+    {
+        Ok(collection) => {
+            let message = format!("Partition ajoutée à la collection : {}", collection.name);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error adding partition to collection : {}", err);
+            let message = format!("Partition pas ajoutée : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Removes a partition from a collection of the logged user**
+///
+/// Returns a flash message and redirects to the page of the collection
+///
+#[debug_handler]
+pub async fn remove_collection_partition_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Path((id, partition_id)): Path<(i32, i32)>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&format!("/api/collections/{id}"));
+    match remove_collection_partition(id, auth_jwt.user.id, partition_id, &state.pool).await {
+        Ok(collection) => {
+            let message = format!("Partition retirée de la collection : {}", collection.name);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error removing partition from collection : {}", err);
+            let message = format!("Partition pas retirée : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+//*******************************************************************************
+// Page of a collection
+//
+
+///
+/// # Handler
+///
+/// **Shows a collection** : one of the collections of the logged user
+/// (with the forms to modify it) or a collection shared by another user (read-only)
+///
+/// Returns a HTML Page (Askama Template) or MyAppError (NOT_FOUND for a private collection
+/// of another user)
+///
+#[debug_handler]
+pub async fn collection_askama_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Path(id): Path<i32>,
+) -> Result<(IncomingFlashes, CollectionTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let collection = find_readable_collection(id, auth_jwt.user.id, &state.pool).await?;
+    let partitions = list_collection_partitions(id, &state.pool).await?;
+    let is_owner = collection.is_owner(auth_jwt.user.id);
+    let partitions_choices = if is_owner {
+        list_partition_choices(&state.pool).await?
+    } else {
+        Vec::new()
+    };
+
+    let template = CollectionTemplate {
+        title: format!("Collection : {}", collection.name),
+        flash: Some(flash),
+        collection,
+        partitions,
+        is_owner,
+        partitions_choices,
+    };
+    Ok((in_flash, template))
+}
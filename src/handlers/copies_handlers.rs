@@ -0,0 +1,372 @@
+//! src/handlers/copies_handlers.rs
+//!
+//! Printed copies of the partitions (location, condition, purchase date)
+//! and inventory audits : the copies seen on the shelves are marked,
+//! the report gives the copies that were not found
+//!
+
+use axum::debug_handler;
+use axum::extract::{Form, Path, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum::Extension;
+use axum_flash::{Flash, IncomingFlashes};
+
+use crate::askama::askama_tpl::{
+    InventoryReportTemplate, InventoryTemplate, PartitionCopiesTemplate,
+};
+use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::db::copies::*;
+use crate::errors::MyAppError;
+use crate::models::copy::{CopyCondition, CopyForm, SeenForm};
+use crate::AppState;
+
+///
+/// # Handler
+///
+/// **Shows the copies of a partition**<br>
+/// the form to add copies and a form to modify each copy
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn partition_copies_askama_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Path(id): Path<i32>,
+) -> Result<(IncomingFlashes, PartitionCopiesTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let availability = partition_availability(id, &state.pool).await?;
+    let copies = list_partition_copies(id, &state.pool).await?;
+
+    let template = PartitionCopiesTemplate {
+        title: format!("Exemplaires : {}", availability.title),
+        flash: Some(flash),
+        availability,
+        copies,
+        conditions: CopyCondition::ALL.to_vec(),
+    };
+    Ok((in_flash, template))
+}
+
+///
+/// # Handler
+///
+/// **Adds copies to a partition**<br>
+/// the field "count" gives the number of copies with the same values
+///
+/// Returns a flash message and redirects to the copies page of the partition
+///
+#[debug_handler]
+pub async fn add_copies_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path(id): Path<i32>,
+    Form(form): Form<CopyForm>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&format!("/api/copies/partition/{id}"));
+    let result = async {
+        let (values, count) = form
+            .values()
+            .and_then(|values| Ok((values, form.count()?)))
+            .map_err(|cause| MyAppError::new(StatusCode::UNPROCESSABLE_ENTITY, cause))?;
+        add_copies(id, count, &values, &state.pool).await
+    }
+    .await;
+
+    match result {
+        Ok(copies) => {
+            let numbers = copies
+                .iter()
+                .map(|copy| copy.id.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let message = format!("Exemplaire(s) ajouté(s) : n° {}", numbers);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error adding copies : {}", err);
+            let message = format!("Exemplaires pas ajoutés : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Modifies a copy** *'/api/copies/partition/:partition_id/:id'*<br>
+/// a lent copy can't be marked as missing
+///
+/// Returns a flash message and redirects to the copies page of the partition
+///
+#[debug_handler]
+pub async fn update_copy_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path((partition_id, id)): Path<(i32, i32)>,
+    Form(form): Form<CopyForm>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&format!("/api/copies/partition/{partition_id}"));
+    let result = async {
+        let values = form
+            .values()
+            .map_err(|cause| MyAppError::new(StatusCode::UNPROCESSABLE_ENTITY, cause))?;
+        update_copy(id, &values, &state.pool).await
+    }
+    .await;
+
+    match result {
+        Ok(copy) => {
+            let message = format!("Exemplaire n° {} modifié : {}", copy.id, copy.condition);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error updating copy : {}", err);
+            let message = format!("Exemplaire pas modifié : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Deletes a copy** *'/api/copies/partition/:partition_id/:id/delete'*<br>
+/// a lent copy can't be deleted
+///
+/// Returns a flash message and redirects to the copies page of the partition
+///
+#[debug_handler]
+pub async fn delete_copy_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path((partition_id, id)): Path<(i32, i32)>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&format!("/api/copies/partition/{partition_id}"));
+    match delete_copy(id, &state.pool).await {
+        Ok(copy) => {
+            let message = format!("Exemplaire n° {} effacé", copy.id);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error deleting copy : {}", err);
+            let message = format!("Exemplaire pas effacé : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+//*******************************************************************************************
+// Inventory audits
+//
+
+///
+/// # Handler
+///
+/// **Shows the inventory page**<br>
+/// without audit in progress : the button to start an audit and the previous audits<br>
+/// with an audit in progress : the form to mark copies as seen (inventory numbers)
+/// and the copies in the order of the shelves
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn inventory_askama_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+) -> Result<(IncomingFlashes, InventoryTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let audit = current_audit(&state.pool).await?;
+    let copies = match &audit {
+        Some(audit) => list_audit_copies(audit.id, false, &state.pool).await?,
+        None => Vec::new(),
+    };
+    let audits = list_audits(&state.pool).await?;
+
+    let template = InventoryTemplate {
+        title: "Inventaire des exemplaires".to_string(),
+        flash: Some(flash),
+        audit,
+        copies,
+        audits,
+    };
+    Ok((in_flash, template))
+}
+
+///
+/// # Handler
+///
+/// **Starts an inventory audit**<br>
+/// only one audit can be in progress
+///
+/// Returns a flash message and redirects to the inventory page
+///
+#[debug_handler]
+pub async fn start_audit_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to("/api/inventory");
+    match start_audit(auth_jwt.user.id, &state.pool).await {
+        Ok(audit) => {
+            let message = format!("Inventaire commencé : {} exemplaire(s) à voir", audit.total);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error starting audit : {}", err);
+            let message = format!("Inventaire pas commencé : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Marks copies as seen during the audit in progress**<br>
+/// the field "copy_ids" gives the inventory numbers ("12 13, 14"),
+/// the unknown numbers are given in a warning
+///
+/// Returns a flash message and redirects to the inventory page
+///
+#[debug_handler]
+pub async fn mark_seen_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Form(form): Form<SeenForm>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to("/api/inventory");
+    let result = async {
+        let copy_ids = form
+            .copy_ids()
+            .map_err(|cause| MyAppError::new(StatusCode::UNPROCESSABLE_ENTITY, cause))?;
+        let unknown = mark_copies_seen(&copy_ids, &state.pool).await?;
+        Ok::<_, MyAppError>((copy_ids.len() - unknown.len(), unknown))
+    }
+    .await;
+
+    match result {
+        Ok((seen, unknown)) if unknown.is_empty() => {
+            let message = format!("Exemplaire(s) vu(s) : {}", seen);
+            (flash.success(message), redirect)
+        }
+        Ok((seen, unknown)) => {
+            let numbers = unknown
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let message = format!(
+                "Exemplaire(s) vu(s) : {}, numéro(s) inconnu(s) : {}",
+                seen, numbers
+            );
+            (flash.warning(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error marking copies seen : {}", err);
+            let message = format!("Exemplaires pas marqués : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Closes the audit in progress**
+///
+/// Returns a flash message and redirects to the report of the audit
+///
+#[debug_handler]
+pub async fn close_audit_hdl(State(state): State<AppState>, flash: Flash) -> (Flash, Redirect) {
+    match close_audit(&state.pool).await {
+        Ok(audit) => {
+            let message = format!(
+                "Inventaire terminé : {} exemplaire(s) vu(s) sur {}",
+                audit.seen, audit.total
+            );
+            let redirect = Redirect::to(&format!("/api/inventory/{}", audit.id));
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error closing audit : {}", err);
+            let message = format!("Inventaire pas terminé : {}", err.message);
+            (flash.error(message), Redirect::to("/api/inventory"))
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Shows the report of an audit**<br>
+/// the copies that were not seen (the lent copies are shown apart : they are not lost)
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn audit_report_askama_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Path(id): Path<i32>,
+) -> Result<(IncomingFlashes, InventoryReportTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let audit = find_audit_by_id(id, &state.pool).await?;
+    let copies = list_audit_copies(id, true, &state.pool).await?;
+
+    let template = InventoryReportTemplate {
+        title: format!("Inventaire du {}", audit.started_text()),
+        flash: Some(flash),
+        audit,
+        copies,
+    };
+    Ok((in_flash, template))
+}
+
+///
+/// # Handler
+///
+/// **Marks as missing the copies not found by a closed audit**<br>
+/// the missing copies can't be lent any more
+///
+/// Returns a flash message and redirects to the report of the audit
+///
+#[debug_handler]
+pub async fn mark_missing_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path(id): Path<i32>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&format!("/api/inventory/{id}"));
+    match mark_not_found_missing(id, &state.pool).await {
+        Ok(count) => {
+            let message = format!("Exemplaire(s) marqué(s) manquant(s) : {}", count);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error marking copies missing : {}", err);
+            let message = format!("Exemplaires pas marqués : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
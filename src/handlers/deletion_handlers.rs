@@ -0,0 +1,169 @@
+//! src/handlers/deletion_handlers.rs
+//!
+//! Deletion of a musician or a genre still used by partitions :
+//! the confirmation page listing the partitions, loans and setlists affected,
+//! where the policy for the partitions is chosen (block, reassign, cascade)
+//!
+
+use axum::debug_handler;
+use axum::extract::{Path, State};
+use axum::Extension;
+use serde_json::Value;
+
+use crate::askama::askama_tpl::DeletionTemplate;
+use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::db::audit::{partition_snapshot, record_audit};
+use crate::db::deletion::deletion_impact;
+use crate::db::genres::list_genres;
+use crate::db::merge::partitions_using;
+use crate::db::musicians::list_persons;
+use crate::errors::MyAppError;
+use crate::models::audit::{AuditChange, AuditEntity};
+use crate::models::deletion::DeletePolicy;
+use crate::models::user::User;
+use crate::AppState;
+
+///
+/// # Handler
+///
+/// **Shows the impact of the deletion of a musician** *'/api/persons/delete/12'*<br>
+/// the partitions, open loans and setlists affected, and the choice of the policy
+/// (reassign and cascade for the administrators only)
+///
+/// Returns a HTML Page (Askama Template) or MyAppError (NOT_FOUND)
+///
+#[debug_handler]
+pub async fn preview_delete_person_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Path(id): Path<i32>,
+) -> Result<DeletionTemplate, MyAppError> {
+    let choices = list_persons(&state.pool)
+        .await?
+        .into_iter()
+        .filter(|person| person.id != id)
+        .map(|person| (person.id, person.full_name))
+        .collect();
+    let is_admin = auth_jwt.user.role == "Administrateur";
+    deletion_template(
+        AuditEntity::Person,
+        id,
+        choices,
+        is_admin,
+        "/api/persons",
+        &state,
+    )
+    .await
+}
+
+///
+/// # Handler
+///
+/// **Shows the impact of the deletion of a genre** *'/api/genres/delete/3'*<br>
+/// the partitions, open loans and setlists affected, and the choice of the policy
+/// (reassign and cascade for the administrators only)
+///
+/// Returns a HTML Page (Askama Template) or MyAppError (NOT_FOUND)
+///
+#[debug_handler]
+pub async fn preview_delete_genre_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Path(id): Path<i32>,
+) -> Result<DeletionTemplate, MyAppError> {
+    let choices = list_genres(&state.pool)
+        .await?
+        .into_iter()
+        .filter(|genre| genre.id != id)
+        .map(|genre| (genre.id, genre.name))
+        .collect();
+    let is_admin = auth_jwt.user.role == "Administrateur";
+    deletion_template(
+        AuditEntity::Genre,
+        id,
+        choices,
+        is_admin,
+        "/api/genres",
+        &state,
+    )
+    .await
+}
+
+///
+/// Checks that the user may apply the policy : reassigning the partitions or
+/// putting them in the trash is only for the administrators
+///
+pub fn check_delete_policy(policy: DeletePolicy, user: &User) -> Result<(), String> {
+    if policy.is_admin_only() && user.role != "Administrateur" {
+        return Err("Hey ! Action only for Administrators".to_string());
+    }
+    Ok(())
+}
+
+///
+/// Returns the partitions using a musician or a genre with their record
+/// before the deletion, for the audit log (no record for the partitions of the trash)
+///
+pub async fn partitions_before_deletion(
+    entity: AuditEntity,
+    id: i32,
+    state: &AppState,
+) -> Vec<(i32, Option<Value>)> {
+    let mut partitions = Vec::new();
+    for partition_id in partitions_using(entity, id, &state.pool)
+        .await
+        .unwrap_or_default()
+    {
+        let before = partition_snapshot(partition_id, &state.pool).await;
+        if before.is_some() {
+            partitions.push((partition_id, before));
+        }
+    }
+    partitions
+}
+
+///
+/// Records in the audit log what the policy did to the partitions :
+/// modified (reassigned) or deleted (cascade)
+///
+pub async fn record_partitions_deletion(
+    policy: DeletePolicy,
+    partitions: Vec<(i32, Option<Value>)>,
+    user: &User,
+    state: &AppState,
+) {
+    for (id, before) in partitions {
+        let change = match policy {
+            DeletePolicy::Block => continue,
+            DeletePolicy::Reassign(_) => {
+                let after = partition_snapshot(id, &state.pool).await;
+                AuditChange::updated(AuditEntity::Partition, id, before, after)
+            }
+            DeletePolicy::Cascade => AuditChange::deleted(AuditEntity::Partition, id, before),
+        };
+        record_audit(user, change, &state.pool).await;
+    }
+}
+
+//*******************************************************************************
+// Helpers functions
+//
+
+async fn deletion_template(
+    entity: AuditEntity,
+    id: i32,
+    choices: Vec<(i32, String)>,
+    is_admin: bool,
+    page: &str,
+    state: &AppState,
+) -> Result<DeletionTemplate, MyAppError> {
+    let impact = deletion_impact(entity, id, &state.pool).await?;
+    Ok(DeletionTemplate {
+        title: format!("Effacer : {}", impact.name),
+        impact,
+        choices,
+        is_admin,
+        action: format!("{page}/delete/{id}"),
+        cancel: page.to_string(),
+    })
+}
@@ -0,0 +1,131 @@
+//! src/handlers/duplicates_handlers.rs
+//!
+//! Report of the suspected duplicates of the catalogue and merge of two
+//! musicians or two genres (administrators only)
+//!
+
+use axum::debug_handler;
+use axum::extract::{Form, Path, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum::Extension;
+use axum_flash::{Flash, IncomingFlashes};
+
+use crate::askama::askama_tpl::DuplicatesTemplate;
+use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::db::audit::{partition_snapshot, record_audit, snapshot};
+use crate::db::duplicates::list_duplicate_clusters;
+use crate::db::merge::{merge_records, partitions_using};
+use crate::errors::MyAppError;
+use crate::models::audit::{AuditChange, AuditEntity};
+use crate::models::duplicate::MergeForm;
+use crate::AppState;
+
+///
+/// # Handler
+///
+/// **Shows the suspected duplicates** (administrators only)<br>
+/// the clusters of partitions, musicians and genres whose titles or names are close
+/// (trigram similarity), the largest clusters first
+///
+/// Returns a HTML Page (Askama Template) or MyAppError (UNAUTHORIZED)
+///
+#[debug_handler]
+pub async fn duplicates_askama_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    in_flash: IncomingFlashes,
+) -> Result<(IncomingFlashes, DuplicatesTemplate), MyAppError> {
+    if auth_jwt.user.role != "Administrateur" {
+        return Err(MyAppError::new(
+            StatusCode::UNAUTHORIZED,
+            "Hey ! Page only for Administrators",
+        ));
+    }
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let clusters = list_duplicate_clusters(&state.pool).await?;
+
+    let template = DuplicatesTemplate {
+        title: "Doublons possibles".to_string(),
+        flash: Some(flash),
+        clusters,
+    };
+    Ok((in_flash, template))
+}
+
+///
+/// # Handler
+///
+/// **Merges two musicians or two genres** (administrators only)<br>
+/// *'/admin/merge/person'* with the form fields loser_id and survivor_id :
+/// the partitions of the loser go to the survivor and the loser is deleted<br>
+/// the merge and the modified partitions are recorded in the audit log
+///
+/// Returns a flash message and redirects to the duplicates page *'admin/duplicates'*
+///
+#[debug_handler]
+pub async fn merge_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Path(entity): Path<String>,
+    Form(form): Form<MergeForm>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to("/admin/duplicates");
+    if auth_jwt.user.role != "Administrateur" {
+        let message = "Hey ! Action only for Administrators".to_string();
+        return (flash.error(message), redirect);
+    }
+    let entity = match entity.parse::<AuditEntity>() {
+        Ok(entity) => entity,
+        Err(message) => return (flash.error(message), redirect),
+    };
+
+    // the partitions before the merge, for the audit log
+    let mut partitions = Vec::new();
+    for id in partitions_using(entity, form.loser_id, &state.pool)
+        .await
+        .unwrap_or_default()
+    {
+        partitions.push((id, partition_snapshot(id, &state.pool).await));
+    }
+
+    match merge_records(entity, form.loser_id, form.survivor_id, &state.pool).await {
+        Ok(report) => {
+            for (id, before) in partitions {
+                // a partition of the trash has no snapshot : its change is not recorded
+                if before.is_some() {
+                    let after = partition_snapshot(id, &state.pool).await;
+                    let change = AuditChange::updated(AuditEntity::Partition, id, before, after);
+                    record_audit(&auth_jwt.user, change, &state.pool).await;
+                }
+            }
+            let change = AuditChange::merged(
+                entity,
+                report.loser.id,
+                snapshot(&report.loser),
+                snapshot(&report.survivor),
+            );
+            record_audit(&auth_jwt.user, change, &state.pool).await;
+            let message = format!(
+                "{} fusionné(e) : {} dans {} ({} partition(s) déplacée(s))",
+                entity.label(),
+                report.loser.name,
+                report.survivor.name,
+                report.partition_ids.len()
+            );
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error merging {:?} {} : {}", entity, form.loser_id, err);
+            let message = format!("Pas fusionné : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
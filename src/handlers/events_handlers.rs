@@ -0,0 +1,300 @@
+//! src/handlers/events_handlers.rs
+//!
+//! Events of the ensemble (rehearsals and concerts) : monthly calendar,
+//! create, modify, delete the events and the iCalendar feed of each user
+//!
+
+use axum::debug_handler;
+use axum::extract::{Form, Host, Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Extension;
+use axum_flash::{Flash, IncomingFlashes};
+use chrono::{Duration, NaiveTime};
+
+use crate::askama::askama_tpl::{CalendarTemplate, EventTemplate};
+use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::db::events::*;
+use crate::db::setlists::{list_setlist_items, list_setlists};
+use crate::errors::MyAppError;
+use crate::ics::{calendar_ics, CalendarEvent};
+use crate::models::event::{
+    first_day_of_next_month, CalendarMonth, EventForm, MonthParams, DEFAULT_EVENT_MINUTES,
+};
+use crate::models::loan::today;
+use crate::AppState;
+
+/// the past events kept in the iCalendar feeds (days)
+const FEED_PAST_DAYS: i64 = 90;
+
+//***********************************************************************************
+// CRUD Operations on events
+//
+
+///
+/// # Handler
+///
+/// **Creates a new event**
+///
+/// Returns a flash message and redirects to the calendar of the month of the event
+///
+#[debug_handler]
+pub async fn create_event_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Form(form): Form<EventForm>,
+) -> (Flash, Redirect) {
+    let result = async {
+        let values = form
+            .values()
+            .map_err(|cause| MyAppError::new(StatusCode::UNPROCESSABLE_ENTITY, cause))?;
+        add_event(&values, &state.pool).await
+    }
+    .await;
+
+    match result {
+        Ok(event) => {
+            let message = format!(
+                "Événement ajouté : {} le {}",
+                event.summary(),
+                event.date_text()
+            );
+            let redirect = Redirect::to(&format!("/api/events?month={}", event.month_value()));
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error adding event : {}", err);
+            let message = format!("Événement pas ajouté : {}", err.message);
+            (flash.error(message), Redirect::to("/api/events"))
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Modifies an event**
+///
+/// Returns a flash message and redirects to the calendar of the month of the event
+///
+#[debug_handler]
+pub async fn update_event_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path(id): Path<i32>,
+    Form(form): Form<EventForm>,
+) -> (Flash, Redirect) {
+    let result = async {
+        let values = form
+            .values()
+            .map_err(|cause| MyAppError::new(StatusCode::UNPROCESSABLE_ENTITY, cause))?;
+        update_event(id, &values, &state.pool).await
+    }
+    .await;
+
+    match result {
+        Ok(event) => {
+            let message = format!(
+                "Événement modifié : {} le {}",
+                event.summary(),
+                event.date_text()
+            );
+            let redirect = Redirect::to(&format!("/api/events?month={}", event.month_value()));
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error modifying event : {}", err);
+            let message = format!("Événement pas modifié : {}", err.message);
+            (
+                flash.error(message),
+                Redirect::to(&format!("/api/events/{id}")),
+            )
+        }
+    }
+}
+
+#[debug_handler]
+pub async fn delete_event_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path(id): Path<i32>,
+) -> (Flash, Redirect) {
+    match delete_event(id, &state.pool).await {
+        Ok(event) => {
+            let message = format!(
+                "Événement effacé : {} le {}",
+                event.summary(),
+                event.date_text()
+            );
+            let redirect = Redirect::to(&format!("/api/events?month={}", event.month_value()));
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error deleting event : {}", err);
+            let message = format!("Événement pas effacé : {}", err.message);
+            (flash.error(message), Redirect::to("/api/events"))
+        }
+    }
+}
+
+//*******************************************************************************
+// Pages of the events
+//
+
+///
+/// # Handler
+///
+/// **Shows the calendar of a month** *'?month=2023-07'* (default : the current month)<br>
+/// the events of the month in the days of the weeks and the form to add an event
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn calendar_askama_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Query(params): Query<MonthParams>,
+) -> Result<(IncomingFlashes, CalendarTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let first_day = params.first_day();
+    let events = list_events_between(
+        first_day.and_time(NaiveTime::MIN),
+        first_day_of_next_month(first_day).and_time(NaiveTime::MIN),
+        &state.pool,
+    )
+    .await?;
+    let month = CalendarMonth::new(first_day, &events);
+    let setlists = list_setlists(&state.pool).await?;
+    // the date proposed in the form : today in the current month, else the first day
+    let date = if month.first_day <= today() && today() < first_day_of_next_month(first_day) {
+        today()
+    } else {
+        first_day
+    };
+
+    let template = CalendarTemplate {
+        title: format!("Calendrier : {}", month.label()),
+        flash: Some(flash),
+        month,
+        events,
+        setlists,
+        date: date.format("%Y-%m-%d").to_string(),
+        duration: DEFAULT_EVENT_MINUTES,
+    };
+    Ok((in_flash, template))
+}
+
+///
+/// # Handler
+///
+/// **Shows an event** with the form to modify it
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn event_askama_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Path(id): Path<i32>,
+) -> Result<(IncomingFlashes, EventTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let event = find_event_by_id(id, &state.pool).await?;
+    let setlists = list_setlists(&state.pool).await?;
+
+    let template = EventTemplate {
+        title: format!("{} le {}", event.summary(), event.date_text()),
+        flash: Some(flash),
+        event,
+        setlists,
+    };
+    Ok((in_flash, template))
+}
+
+//*******************************************************************************
+// iCalendar feeds
+//
+
+///
+/// # Handler
+///
+/// **Sends the iCalendar feed of a user** *'/calendar/:token.ics'*<br>
+/// the route is outside the auth layer : the phones can't log in,
+/// the secret token of the user in the URL authenticates the feed
+/// (the events of the last 90 days and the next ones, with their programme)
+///
+/// Returns the calendar (text/calendar) or MyAppError (NOT_FOUND if the token is unknown)
+///
+#[debug_handler]
+pub async fn calendar_feed_hdl(
+    State(state): State<AppState>,
+    Host(host): Host,
+    Path(file): Path<String>,
+) -> Result<Response, MyAppError> {
+    let token = file.strip_suffix(".ics").unwrap_or(&file);
+    let user_name = find_calendar_token_user(token, &state.pool)
+        .await?
+        .ok_or_else(|| MyAppError::new(StatusCode::NOT_FOUND, "Calendar not found !"))?;
+
+    let since = (today() - Duration::days(FEED_PAST_DAYS)).and_time(NaiveTime::MIN);
+    let mut events = Vec::new();
+    for event in list_events_since(since, &state.pool).await? {
+        let items = match event.setlist_id {
+            Some(setlist_id) => list_setlist_items(setlist_id, &state.pool).await?,
+            None => Vec::new(),
+        };
+        events.push(CalendarEvent { event, items });
+    }
+    tracing::info!("calendar feed of {} : {} events", user_name, events.len());
+
+    // the port is not a part of the domain of the identifiers
+    let domain = host.split(':').next().unwrap_or("localhost");
+    let ics = calendar_ics(&state.env.ensemble_name, domain, &events);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        ics,
+    )
+        .into_response())
+}
+
+///
+/// # Handler
+///
+/// **Replaces the secret token of the feed of the logged user**<br>
+/// the calendars subscribed with the old URL are not updated any more
+///
+/// Returns a flash message and redirects to the page of the user
+///
+#[debug_handler]
+pub async fn renew_calendar_token_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to("/api/me");
+    match renew_calendar_token(auth_jwt.user.id, &state.pool).await {
+        Ok(_) => {
+            let message = "Nouvelle adresse du calendrier : l'ancienne ne fonctionne plus";
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error renewing calendar token : {}", err);
+            let message = format!("Adresse du calendrier pas changée : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
@@ -1,21 +1,31 @@
 //! src/handlers/genres_hdl.rs
 
 use axum::debug_handler;
-use axum::extract::{Form, Path, State};
+use axum::extract::{Form, Path, Query, State};
 use axum::http::StatusCode;
-use axum::response::Redirect;
+use axum::response::{Redirect, Response};
+use axum::Extension;
 use axum_flash::{Flash, IncomingFlashes};
 
 use crate::askama::askama_tpl::{HandleGenresTemplate, ListGenresTemplate};
+use crate::authentication::auth_layer::JWTAuthMiddleware;
 use crate::{/*db,*/ globals, AppState};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
+use crate::db::audit::{record_audit, snapshot};
 use crate::db::genres::*;
 use crate::errors::MyAppError;
+use crate::export::{export_response, ExportParams};
+use crate::handlers::deletion_handlers::{
+    check_delete_policy, partitions_before_deletion, record_partitions_deletion,
+};
 //use crate::handlers::musicians_handlers::get_filtered_list_persons_once_cell;
 
-use crate::models::genre::Genre;
+use crate::models::audit::{AuditChange, AuditEntity};
+use crate::models::deletion::DeleteForm;
+use crate::models::genre::{Genre, GenreNode, ParentForm};
+use crate::models::pagination::{Page, PageParams};
 //use crate::models::musician::Person;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -45,12 +55,15 @@ pub struct Payload {
 pub async fn create_genre_hdl(
     flash: Flash,
     State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
     Form(form): Form<Payload>,
 ) -> (Flash, Redirect) {
     let new_genre = form.name;
 
     if let Ok(genre) = add_genre(&state.pool, new_genre.clone()).await {
         tracing::info!("genre added : {:?}", genre);
+        let change = AuditChange::created(AuditEntity::Genre, genre.id, snapshot(&genre));
+        record_audit(&auth_jwt.user, change, &state.pool).await;
         let message = format!("Genre ajouté : {}", new_genre);
         (flash.success(message), Redirect::to("/genres"))
     } else {
@@ -64,12 +77,17 @@ pub async fn create_genre_hdl(
 pub async fn update_genre_hdl(
     State(state): State<AppState>,
     flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
     Path(id): Path<i32>,
     Form(form): Form<Payload>,
 ) -> (Flash, Redirect) {
     let updated_genre_name = form.name;
+    let before = find_genre_by_id(id, &state.pool).await.ok().flatten();
     if let Ok(genre) = update_genre(id, updated_genre_name, &state.pool).await {
         tracing::info!("genre modified : {:?}", genre);
+        let before = before.and_then(|g| snapshot(&g));
+        let change = AuditChange::updated(AuditEntity::Genre, id, before, snapshot(&genre));
+        record_audit(&auth_jwt.user, change, &state.pool).await;
         let message = format!("Genre modifié : {}", genre.name);
         (flash.success(message), Redirect::to("/api/genres"))
     } else {
@@ -79,18 +97,95 @@ pub async fn update_genre_hdl(
     }
 }
 
+///
+/// # Handler
+///
+/// **Moves a genre under another one** *'/api/genres/3/parent'*<br>
+/// an empty parent makes it a main genre ; a genre can't be moved under itself
+/// or one of its sub-genres
+///
+/// Returns a flash message and redirects to the genres page *'api/genres'*
+///
+#[debug_handler]
+pub async fn move_genre_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Path(id): Path<i32>,
+    Form(form): Form<ParentForm>,
+) -> (Flash, Redirect) {
+    let parent_id = match form.parent_id() {
+        Ok(parent_id) => parent_id,
+        Err(cause) => {
+            let message = format!("Genre pas déplacé : {cause}");
+            return (flash.error(message), Redirect::to("/api/genres"));
+        }
+    };
+    let before = find_genre_node(id, &state.pool).await;
+    match move_genre(id, parent_id, &state.pool).await {
+        Ok(genre) => {
+            let after = find_genre_node(id, &state.pool).await;
+            let change = AuditChange::updated(
+                AuditEntity::Genre,
+                id,
+                before.as_ref().and_then(snapshot),
+                after.as_ref().and_then(snapshot),
+            );
+            record_audit(&auth_jwt.user, change, &state.pool).await;
+            let path = after.map(|node| node.path).unwrap_or(genre.name);
+            let message = format!("Genre déplacé : {path}");
+            (flash.success(message), Redirect::to("/api/genres"))
+        }
+        Err(err) => {
+            let message = format!("Genre pas déplacé : {}", err.message);
+            (flash.error(message), Redirect::to("/api/genres"))
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Deletes a genre : the genre goes to the trash**<br>
+/// the partitions still using the genre are handled by the policy of the form
+/// (block, reassign to another genre, cascade to the trash),
+/// see the confirmation page *'/api/genres/delete/3'* (GET)
+///
+/// Returns a flash message and redirects to the genres page *'api/genres'*
+///
 #[debug_handler]
 pub async fn delete_genre_hdl(
     flash: Flash,
     State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
     Path(id): Path<i32>,
+    Form(form): Form<DeleteForm>,
 ) -> (Flash, Redirect) {
-    if let Ok(deleted_name) = delete_genre(id, &state.pool).await {
-        let message = format!("Genre effacé : {}", deleted_name);
-        (flash.success(message), Redirect::to("/api/genres"))
-    } else {
-        let message = "Genre pas effacé".to_string();
-        (flash.error(message), Redirect::to("/api/genres"))
+    let policy = match form.policy() {
+        Ok(policy) => policy,
+        Err(cause) => {
+            let message = format!("Genre pas effacé : {cause}");
+            return (flash.error(message), Redirect::to("/api/genres"));
+        }
+    };
+    if let Err(message) = check_delete_policy(policy, &auth_jwt.user) {
+        return (flash.error(message), Redirect::to("/api/genres"));
+    }
+    let before = find_genre_by_id(id, &state.pool).await.ok().flatten();
+    let partitions = partitions_before_deletion(AuditEntity::Genre, id, &state).await;
+    match delete_genre(id, policy, &state.pool).await {
+        Ok(deleted_name) => {
+            record_partitions_deletion(policy, partitions, &auth_jwt.user, &state).await;
+            let before = before.and_then(|g| snapshot(&g));
+            let change = AuditChange::deleted(AuditEntity::Genre, id, before);
+            record_audit(&auth_jwt.user, change, &state.pool).await;
+            let message = format!("Genre mis à la corbeille : {}", deleted_name);
+            (flash.success(message), Redirect::to("/api/genres"))
+        }
+        Err(err) => {
+            let message = format!("Genre pas effacé : {}", err.message);
+            (flash.error(message), Redirect::to("/api/genres"))
+        }
     }
 }
 
@@ -101,7 +196,8 @@ pub async fn delete_genre_hdl(
 ///
 /// # Handler
 ///
-/// **Shows the main page to manage the genres**
+/// **Shows the main page to manage the genres**<br>
+/// with one page of the list of genres *'?page=&per_page=&sort=&dir='* (sort : name)
 ///
 /// Returns a HTML Page (Askama Template) or MyAppError
 ///
@@ -109,6 +205,7 @@ pub async fn delete_genre_hdl(
 pub async fn manage_genres_askama_hdl(
     State(state): State<AppState>,
     in_flash: IncomingFlashes,
+    Query(params): Query<PageParams>,
 ) -> Result<(IncomingFlashes, HandleGenresTemplate), MyAppError> {
     let flash = in_flash
         .into_iter()
@@ -116,22 +213,26 @@ pub async fn manage_genres_askama_hdl(
         .collect::<Vec<_>>()
         .join(", ");
     tracing::info!("flash : {}", flash);
-    let genres = get_list_all_genres_one_cell(&state.pool)
-        .await
-        .map_err(|_| {
-            MyAppError::new(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Error getting global list genres",
-            )
-        })?;
+    let genres = list_genres_page(&params, &state.pool).await.map_err(|_| {
+        MyAppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error getting list genres",
+        )
+    })?;
+    let total = count_genres(&state.pool).await?;
+    let page = Page::new(&params, total, "/api/genres");
 
     let title = "Gestion des Genres".to_string();
     let flash = Some(flash);
 
+    let tree = list_genre_tree(&state.pool).await?;
+
     let template = HandleGenresTemplate {
         title,
         flash,
         genres,
+        page,
+        tree,
     };
 
     Ok((in_flash, template))
@@ -140,17 +241,41 @@ pub async fn manage_genres_askama_hdl(
 ///
 /// # Handler
 ///
-/// **Shows a printable list of Genres**
+/// **Shows a printable list of Genres**<br>
+/// one page of the list *'?page=&per_page=&sort=&dir='* (sort : name)
 ///
 /// Returns a HTML Page (Askama Template) or MyAppError
 ///
 #[debug_handler]
-pub async fn list_genres_askama_hdl() -> Result<ListGenresTemplate, MyAppError> {
-    let list_genres = get_existing_list_genres_one_cell().await?;
-    let template = ListGenresTemplate { list_genres };
+pub async fn list_genres_askama_hdl(
+    State(state): State<AppState>,
+    Query(params): Query<PageParams>,
+) -> Result<ListGenresTemplate, MyAppError> {
+    let list_genres = list_genres_page(&params, &state.pool).await?;
+    let total = count_genres(&state.pool).await?;
+    let page = Page::new(&params, total, "/api/genres/print");
+    let template = ListGenresTemplate { list_genres, page };
     Ok(template)
 }
 
+///
+/// # Handler
+///
+/// **Exports the genres in CSV or JSON**<br>
+/// *'?format=csv'* (or *'format=json'*) and *'&name='* : the first letters of the genres,
+/// as the find form
+///
+/// Returns the file (streamed)
+///
+#[debug_handler]
+pub async fn export_genres_hdl(
+    State(state): State<AppState>,
+    Query(params): Query<ExportParams>,
+) -> Response {
+    let genres = stream_genres(params.name_pattern(), state.pool.clone());
+    export_response(genres, params.format(), "genres")
+}
+
 //****************************************************************************************
 // Functions to find genres by different criteria
 //
@@ -181,13 +306,18 @@ pub async fn find_genre_by_name_hdl(
         .await
         .unwrap();
 
+    let page = Page::all(genres.len() as i64, "/api/genres");
     let title = "Genre(s) trouvé(s)".to_string();
     let flash = Some(flash);
 
+    let tree = list_genre_tree(&state.pool).await?;
+
     let template = HandleGenresTemplate {
         title,
         flash,
         genres,
+        page,
+        tree,
     };
     Ok(template)
 }
@@ -206,19 +336,12 @@ pub async fn get_filtered_list_genres_once_cell(
     let genres = globals::once_cell::get_static_vec_genres();
     Ok(genres)
 }
-///
-/// # Handler
-///
-/// **Returns a list of all genres**<br>
-/// Functions with OneCell crate
-///
-pub async fn get_list_all_genres_one_cell(pool: &PgPool) -> Result<Vec<Genre>, MyAppError> {
-    globals::once_cell::set_static_vec_genres(list_genres(pool).await.unwrap());
-    let genres = globals::once_cell::get_static_vec_genres();
-    Ok(genres)
-}
 
-pub async fn get_existing_list_genres_one_cell() -> Result<Vec<Genre>, MyAppError> {
-    let genres = globals::once_cell::get_static_vec_genres();
-    Ok(genres)
+/// the genre `id` in the tree of the genres (None if it is not found or in the trash)
+async fn find_genre_node(id: i32, pool: &PgPool) -> Option<GenreNode> {
+    list_genre_tree(pool)
+        .await
+        .ok()?
+        .into_iter()
+        .find(|node| node.id == id)
 }
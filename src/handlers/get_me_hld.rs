@@ -0,0 +1,64 @@
+//! src/handlers/get_me_hld.rs
+//!
+//! Page of the logged user : profile, loans, address of the calendar,
+//! favourites and personal collections
+//!
+
+use axum::debug_handler;
+use axum::extract::{Host, State};
+use axum::Extension;
+use axum_flash::IncomingFlashes;
+
+use crate::askama::askama_tpl::MeTemplate;
+use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::db::collections::{list_favourites, list_shared_collections, list_user_collections};
+use crate::db::events::get_calendar_token;
+use crate::db::loans::list_user_loans;
+use crate::errors::MyAppError;
+use crate::models::user::filter_user_record;
+use crate::AppState;
+
+///
+/// # Handler
+///
+/// **Shows the page of the logged user**<br>
+/// the profile and "my loans" : the partitions lent (the overdue ones are marked)
+/// then the history of the returned loans, and the address to subscribe
+/// to the calendar of the events (with the secret token of the user),
+/// the favourites, the collections of the user and the ones shared by the other users
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn get_me_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Host(host): Host,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+) -> Result<(IncomingFlashes, MeTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let user = filter_user_record(&auth_jwt.user);
+    let loans = list_user_loans(auth_jwt.user.id, &state.pool).await?;
+    let token = get_calendar_token(auth_jwt.user.id, &state.pool).await?;
+    let favourites = list_favourites(auth_jwt.user.id, &state.pool).await?;
+    let collections = list_user_collections(auth_jwt.user.id, &state.pool).await?;
+    let shared_collections = list_shared_collections(auth_jwt.user.id, &state.pool).await?;
+
+    let template = MeTemplate {
+        title: format!("Mon compte : {}", user.name),
+        flash: Some(flash),
+        user,
+        loans,
+        calendar_url: format!("webcal://{host}/calendar/{token}.ics"),
+        favourites,
+        collections,
+        shared_collections,
+    };
+    Ok((in_flash, template))
+}
@@ -0,0 +1,209 @@
+//! src/handlers/loans_handlers.rs
+//!
+//! Loans of the copies of the partitions to the users :
+//! lend, return, history of the loans and list of the overdue loans
+//!
+
+use axum::debug_handler;
+use axum::extract::{Form, Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum::Extension;
+use axum_flash::{Flash, IncomingFlashes};
+
+use crate::askama::askama_tpl::{
+    HandleLoansTemplate, OverdueLoansTemplate, PartitionLoansTemplate,
+};
+use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::db::copies::partition_availability;
+use crate::db::loans::*;
+use crate::db::users::list_users;
+use crate::errors::MyAppError;
+use crate::models::loan::{default_due_date, BackForm, LoanFilter, LoanForm};
+use crate::models::pagination::{Page, PageParams};
+use crate::AppState;
+
+///
+/// # Handler
+///
+/// **Shows the loans page**<br>
+/// the loans matching the criteria *'?status=open&user_id='* (status : open, overdue, returned)
+/// one page of the list *'&page=&per_page=&sort=&dir='* (sort : borrowed, due, title, user)
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn manage_loans_askama_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Query(filter): Query<LoanFilter>,
+    Query(params): Query<PageParams>,
+) -> Result<(IncomingFlashes, HandleLoansTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let loans = list_loans_page(&filter, &params, &state.pool).await?;
+    let total = count_loans(&filter, &state.pool).await?;
+    let page = Page::new(&params, total, "/api/loans").with_filters(filter.query_pairs());
+    let users = list_users(&state.pool).await?;
+
+    let template = HandleLoansTemplate {
+        title: "Prêts des Partitions".to_string(),
+        flash: Some(flash),
+        loans,
+        users,
+        filter,
+        page,
+    };
+    Ok((in_flash, template))
+}
+
+///
+/// # Handler
+///
+/// **Shows the loans of a partition**<br>
+/// the copies (total and available), the form to lend a copy
+/// and the history of the loans of the partition *'?page=&per_page='*
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn partition_loans_askama_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Path(id): Path<i32>,
+    Query(params): Query<PageParams>,
+) -> Result<(IncomingFlashes, PartitionLoansTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let availability = partition_availability(id, &state.pool).await?;
+    let filter = LoanFilter {
+        partition_id: Some(id.to_string()),
+        ..LoanFilter::default()
+    };
+    let loans = list_loans_page(&filter, &params, &state.pool).await?;
+    let total = count_loans(&filter, &state.pool).await?;
+    let page = Page::new(&params, total, &format!("/api/loans/partition/{id}"));
+    let users = list_users(&state.pool).await?;
+
+    let template = PartitionLoansTemplate {
+        title: format!("Prêts : {}", availability.title),
+        flash: Some(flash),
+        availability,
+        loans,
+        users,
+        current_user_id: auth_jwt.user.id.to_string(),
+        due_date: default_due_date(),
+        page,
+    };
+    Ok((in_flash, template))
+}
+
+///
+/// # Handler
+///
+/// **Lends a copy of a partition to a user**<br>
+/// refused if all the copies of the partition are lent or missing
+///
+/// Returns a flash message and redirects to the loans page of the partition
+///
+#[debug_handler]
+pub async fn create_loan_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Form(form): Form<LoanForm>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&format!("/api/loans/partition/{}", form.partition_id));
+    let result = async {
+        let (user_id, due_date) = form
+            .parse()
+            .map_err(|cause| MyAppError::new(StatusCode::UNPROCESSABLE_ENTITY, cause))?;
+        lend_partition(form.partition_id, user_id, due_date, &state.pool).await
+    }
+    .await;
+
+    match result {
+        Ok(loan) => {
+            tracing::info!("loan added : {:?}", loan);
+            let message = format!(
+                "Partition prêtée : {} à {} (retour le {})",
+                loan.title,
+                loan.user_name,
+                loan.due_text()
+            );
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error adding loan : {}", err);
+            let message = format!("Partition pas prêtée : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Returns a lent copy**<br>
+/// the form field "back" gives the page to come back to
+///
+/// Returns a flash message and redirects to that page (default : the loans page *'api/loans'*)
+///
+#[debug_handler]
+pub async fn return_loan_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path(id): Path<i32>,
+    Form(form): Form<BackForm>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&form.back_or("/api/loans"));
+    match return_loan(id, &state.pool).await {
+        Ok(loan) => {
+            tracing::info!("loan returned : {:?}", loan);
+            let message = format!("Partition rendue : {} par {}", loan.title, loan.user_name);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error returning loan : {}", err);
+            let message = format!("Retour pas enregistré : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Shows the overdue loans** (administrators only)<br>
+/// the copies not returned after their due date, the oldest first
+///
+/// Returns a HTML Page (Askama Template) or MyAppError (UNAUTHORIZED)
+///
+#[debug_handler]
+pub async fn overdue_loans_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+) -> Result<OverdueLoansTemplate, MyAppError> {
+    if auth_jwt.user.role != "Administrateur" {
+        return Err(MyAppError::new(
+            StatusCode::UNAUTHORIZED,
+            "Hey ! Page only for Administrators",
+        ));
+    }
+    let loans = list_overdue_loans(&state.pool).await?;
+    Ok(OverdueLoansTemplate {
+        title: "Prêts en retard".to_string(),
+        flash: None,
+        loans,
+    })
+}
@@ -1,10 +1,27 @@
 //! /src/handlers/mod.rs
 
 //pub mod axum_sessions_handlers;
+pub mod api_v1_handlers;
+pub mod audit_handlers;
+pub mod browse_handlers;
+pub mod collections_handlers;
+pub mod copies_handlers;
+pub mod deletion_handlers;
+pub mod duplicates_handlers;
+pub mod events_handlers;
 pub mod genres_handlers;
+pub mod get_me_hld;
+pub mod loans_handlers;
 pub mod login_handlers;
 pub mod logout_handlers;
 pub mod musicians_handlers;
+pub mod partition_files_handlers;
+pub mod partition_history_handlers;
+pub mod partition_import_handlers;
 pub mod partitions_handlers;
+pub mod setlists_handlers;
 pub mod signup_handlers;
+pub mod statistics_handlers;
+pub mod tags_handlers;
+pub mod trash_handlers;
 pub mod utils_handlers;
@@ -1,18 +1,32 @@
 //! src/handlers/musicians_handlers
 
-use crate::askama::askama_tpl::{HandlePersonsTemplate, /*HtmlTemplate, */ ListPersonsTemplate,};
+use crate::askama::askama_tpl::{
+    DuplicatesConfirmTemplate, HandlePersonsTemplate, /*HtmlTemplate, */ ListPersonsTemplate,
+    PersonTemplate,
+};
+use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::db::audit::{record_audit, snapshot};
+use crate::db::duplicates::find_similar_persons;
 use crate::db::musicians::{
-    add_person, delete_person, find_persons_by_name_parts, /*find_persons_by_name_strict,*/
-    list_persons, update_person,
+    add_person, count_persons, delete_person, find_person_by_id, find_person_profile,
+    find_persons_by_name_parts, /*find_persons_by_name_strict,*/ list_person_works,
+    list_persons_page, stream_persons, update_person, update_person_profile,
 };
 use crate::errors::MyAppError;
+use crate::export::{export_response, ExportParams};
 use crate::globals;
-use crate::models::musician::Person;
+use crate::handlers::deletion_handlers::{
+    check_delete_policy, partitions_before_deletion, record_partitions_deletion,
+};
+use crate::models::audit::{AuditChange, AuditEntity};
+use crate::models::deletion::DeleteForm;
+use crate::models::musician::{GenreWorks, Person, PersonProfileForm};
+use crate::models::pagination::{Page, PageParams};
 use crate::AppState;
 use axum::debug_handler;
-use axum::extract::{Path, State};
-use axum::response::Redirect;
-use axum::Form;
+use axum::extract::{Path, Query, State};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::{Extension, Form};
 use axum_flash::{Flash, IncomingFlashes};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
@@ -22,15 +36,23 @@ use sqlx::PgPool;
 pub struct Payload {
     pub name: String,
 }
+
+/// Form to add a musician : confirm is true when the user confirmed despite close names
+#[derive(Serialize, Deserialize)]
+pub struct NewPersonPayload {
+    pub name: String,
+    #[serde(default)]
+    pub confirm: bool,
+}
 ///
 /// # Handler
 ///
 /// **Manages the Printable Musician List Page**  
-/// Initiates the static Vec(Person) to show the list of musicians
-/// filtered or not
+/// Shows one page of the list of musicians
 ///
 /// ## Arguments
 /// * 'state' - the AppState with PgPool
+/// * 'params' - the pagination and the sort *'?page=&per_page=&sort=&dir='* (sort : name)
 ///
 /// ## Returns
 /// * Result with the Askama Template that handles
@@ -38,22 +60,45 @@ pub struct Payload {
 ///
 /// '''
 #[debug_handler]
-pub async fn list_persons_askama_hdl(//State(state): State<AppState>,
+pub async fn list_persons_askama_hdl(
+    State(state): State<AppState>,
+    Query(params): Query<PageParams>,
 ) -> Result<ListPersonsTemplate, MyAppError> {
-    let list_persons = get_existing_list_persons_one_cell().await.unwrap();
-    let template = ListPersonsTemplate { list_persons };
+    let list_persons = list_persons_page(&params, &state.pool).await?;
+    let total = count_persons(&state.pool).await?;
+    let page = Page::new(&params, total, "/api/persons/print");
+    let template = ListPersonsTemplate { list_persons, page };
     Ok(template)
 }
 
+///
+/// # Handler
+///
+/// **Exports the musicians in CSV or JSON**<br>
+/// *'?format=csv'* (or *'format=json'*) and *'&name='* : the first letters of the names,
+/// as the find form
+///
+/// Returns the file (streamed)
+///
+#[debug_handler]
+pub async fn export_persons_hdl(
+    State(state): State<AppState>,
+    Query(params): Query<ExportParams>,
+) -> Response {
+    let persons = stream_persons(params.name_pattern(), state.pool.clone());
+    export_response(persons, params.format(), "musiciens")
+}
+
 ///
 /// # Handler
 ///
 /// **Manages the Musician Page**  
-/// Initiates the static Vec<Person> to show the list of musicians
+/// Shows one page of the list of musicians
 ///
 /// ## Arguments
 /// * 'state' - the AppState with PgPool
 /// * 'in_flash' - An axum_flash IncomingFlash
+/// * 'params' - the pagination and the sort *'?page=&per_page=&sort=&dir='* (sort : name)
 /// ## Returns
 /// * Result with the IncomingFlashes and the Askama Template that handles
 /// the Musician Page
@@ -63,6 +108,7 @@ pub async fn list_persons_askama_hdl(//State(state): State<AppState>,
 pub async fn manage_persons_askama_hdl(
     State(state): State<AppState>,
     in_flash: IncomingFlashes,
+    Query(params): Query<PageParams>,
 ) -> Result<(IncomingFlashes, HandlePersonsTemplate), MyAppError> {
     let title = "Gestion des Musiciens".to_string();
     let flash = in_flash
@@ -73,13 +119,16 @@ pub async fn manage_persons_askama_hdl(
         .join(", ");
     tracing::info!("flash : {}", flash);
 
-    let persons = get_list_all_persons_one_cell(&state.pool).await.unwrap();
+    let persons = list_persons_page(&params, &state.pool).await?;
+    let total = count_persons(&state.pool).await?;
+    let page = Page::new(&params, total, "/api/persons");
 
     let flash = Some(flash);
     let template = HandlePersonsTemplate {
         title,
         flash,
         persons,
+        page,
     };
     // il faut retourner le flash pour qu'il soit enlevé du cookie
     Ok((in_flash, template))
@@ -92,30 +141,53 @@ pub async fn manage_persons_askama_hdl(
 /// ## Arguments
 /// * 'flash' - An axum_flash Flash
 /// * 'state' - the AppState with PgPool
-/// * 'form'  - the person name comes from a Form(Payload) where struct Payload has a field "value: String"<br>
+/// * 'form'  - the person name comes from a Form(NewPersonPayload) where struct NewPersonPayload has a field "name: String"<br>
+///             and a field "confirm" set when the user chose to add the musician despite close names<br>
 /// <br>
 ///             *Form must be placed as last argument because it consumes the request*
 /// ## Returns
 /// * the flash message
 /// * Redirects to /persons page with a flash message\
+/// * or the confirmation page listing the musicians with a close name
 /// '''
 #[debug_handler]
 pub async fn create_person_hdl(
     flash: Flash,
     State(state): State<AppState>,
-    Form(form): Form<Payload>,
-) -> (Flash, Redirect) {
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Form(form): Form<NewPersonPayload>,
+) -> Response {
     let new_person = form.name;
     tracing::info!("form : {}", new_person.clone());
 
+    if !form.confirm {
+        match find_similar_persons(&new_person, &state.pool).await {
+            Ok(matches) if !matches.is_empty() => {
+                let template = DuplicatesConfirmTemplate {
+                    title: "Ajouter un musicien".to_string(),
+                    name: new_person.clone(),
+                    matches,
+                    action: "/api/persons/add".to_string(),
+                    fields: vec![("name".to_string(), new_person)],
+                    cancel: "/api/persons".to_string(),
+                };
+                return template.into_response();
+            }
+            Ok(_) => {}
+            Err(err) => tracing::info!("error checking duplicates : {}", err),
+        }
+    }
+
     if let Ok(person) = add_person(&state.pool, new_person.clone()).await {
         tracing::info!("person added : {:?}", person);
+        let change = AuditChange::created(AuditEntity::Person, person.id, snapshot(&person));
+        record_audit(&auth_jwt.user, change, &state.pool).await;
         let message = format!("Musicien ajouté : {}", new_person);
-        (flash.success(message), Redirect::to("/api/persons"))
+        (flash.success(message), Redirect::to("/api/persons")).into_response()
     } else {
         tracing::info!("error adding person");
         let message = "Musicien pas ajouté".to_string();
-        (flash.error(message), Redirect::to("/api/persons"))
+        (flash.error(message), Redirect::to("/api/persons")).into_response()
     }
 }
 
@@ -123,12 +195,17 @@ pub async fn create_person_hdl(
 pub async fn update_person_hdl(
     State(state): State<AppState>,
     flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
     Path(id): Path<i32>,
     Form(form): Form<Payload>,
 ) -> (Flash, Redirect) {
     let updated_person_name = form.name;
+    let before = find_person_by_id(id, &state.pool).await.ok().flatten();
     if let Ok(person) = update_person(id, updated_person_name, &state.pool).await {
         tracing::info!("person modified : {:?}", person);
+        let before = before.and_then(|p| snapshot(&p));
+        let change = AuditChange::updated(AuditEntity::Person, id, before, snapshot(&person));
+        record_audit(&auth_jwt.user, change, &state.pool).await;
         let message = format!("Musicien modifié : {}", person.full_name);
         (flash.success(message), Redirect::to("/api/persons"))
     } else {
@@ -138,18 +215,125 @@ pub async fn update_person_hdl(
     }
 }
 
+///
+/// # Handler
+///
+/// **Deletes a person (musician) : the person goes to the trash**<br>
+/// the partitions still using the person are handled by the policy of the form
+/// (block, reassign to another person, cascade to the trash),
+/// see the confirmation page *'/api/persons/delete/12'* (GET)
+///
+/// Returns a flash message and redirects to the persons page *'api/persons'*
+///
 #[debug_handler]
 pub async fn delete_person_hdl(
     flash: Flash,
     State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
     Path(id): Path<i32>,
+    Form(form): Form<DeleteForm>,
 ) -> (Flash, Redirect) {
-    if let Ok(deleted_person) = delete_person(id, &state.pool).await {
-        let message = format!("Musicien effacé : {}", deleted_person.full_name);
-        (flash.success(message), Redirect::to("/api/persons"))
-    } else {
-        let message = "Erreur Musicien pas effacé".to_string();
-        (flash.error(message), Redirect::to("/api/persons"))
+    let policy = match form.policy() {
+        Ok(policy) => policy,
+        Err(cause) => {
+            let message = format!("Musicien pas effacé : {cause}");
+            return (flash.error(message), Redirect::to("/api/persons"));
+        }
+    };
+    if let Err(message) = check_delete_policy(policy, &auth_jwt.user) {
+        return (flash.error(message), Redirect::to("/api/persons"));
+    }
+    let partitions = partitions_before_deletion(AuditEntity::Person, id, &state).await;
+    match delete_person(id, policy, &state.pool).await {
+        Ok(deleted_person) => {
+            record_partitions_deletion(policy, partitions, &auth_jwt.user, &state).await;
+            let change = AuditChange::deleted(AuditEntity::Person, id, snapshot(&deleted_person));
+            record_audit(&auth_jwt.user, change, &state.pool).await;
+            let message = format!("Musicien mis à la corbeille : {}", deleted_person.full_name);
+            (flash.success(message), Redirect::to("/api/persons"))
+        }
+        Err(err) => {
+            let message = format!("Musicien pas effacé : {}", err.message);
+            (flash.error(message), Redirect::to("/api/persons"))
+        }
+    }
+}
+
+//****************************************************************************************
+// Profile of a musician
+//
+
+///
+/// # Handler
+///
+/// **Shows the profile of a musician and its partitions** *'/api/persons/12'*<br>
+/// the partitions (not in the trash) are grouped by genre, with the roles of the musician
+///
+/// Returns a HTML Page (Askama Template) or MyAppError (NOT_FOUND)
+///
+#[debug_handler]
+pub async fn person_askama_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Path(id): Path<i32>,
+) -> Result<(IncomingFlashes, PersonTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let profile = find_person_profile(id, &state.pool).await?;
+    let works = list_person_works(id, &state.pool).await?;
+
+    let template = PersonTemplate {
+        title: profile.full_name.clone(),
+        flash: Some(flash),
+        profile,
+        total: works.len(),
+        works: GenreWorks::group(works),
+    };
+    Ok((in_flash, template))
+}
+
+///
+/// # Handler
+///
+/// **Updates the profile of a musician** (years, nationality, biography, other spellings)
+///
+/// Returns a flash message and redirects to the profile page *'api/persons/12'*
+///
+#[debug_handler]
+pub async fn update_person_profile_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Path(id): Path<i32>,
+    Form(form): Form<PersonProfileForm>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&format!("/api/persons/{id}"));
+    let profile = match form.profile() {
+        Ok(profile) => profile,
+        Err(cause) => {
+            let message = format!("Profil pas modifié : {cause}");
+            return (flash.error(message), redirect);
+        }
+    };
+    let before = find_person_profile(id, &state.pool).await.ok();
+    match update_person_profile(id, &profile, &state.pool).await {
+        Ok(profile) => {
+            let before = before.and_then(|p| snapshot(&p));
+            let change = AuditChange::updated(AuditEntity::Person, id, before, snapshot(&profile));
+            record_audit(&auth_jwt.user, change, &state.pool).await;
+            let message = format!("Profil modifié : {}", profile.full_name);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error modifying person profile : {}", err);
+            let message = format!("Profil pas modifié : {}", err.message);
+            (flash.error(message), redirect)
+        }
     }
 }
 
@@ -181,12 +365,14 @@ pub async fn find_person_by_name_hdl(
     //.unwrap();
     tracing::info!("liste personnes trouvées :{:?}", persons);
 
+    let page = Page::all(persons.len() as i64, "/api/persons");
     let title = "Musicien(s) trouvé(s)".to_string();
     let flash = None;
     let template = HandlePersonsTemplate {
         title,
         flash,
         persons,
+        page,
     };
     Ok(template)
 }
@@ -205,14 +391,3 @@ pub async fn get_filtered_list_persons_once_cell(
     let persons = globals::once_cell::get_static_vec_persons();
     Ok(persons)
 }
-
-pub async fn get_list_all_persons_one_cell(pool: &PgPool) -> Result<Vec<Person>, MyAppError> {
-    globals::once_cell::set_static_vec_persons(list_persons(pool).await.unwrap());
-    let persons = globals::once_cell::get_static_vec_persons();
-    Ok(persons)
-}
-
-pub async fn get_existing_list_persons_one_cell() -> Result<Vec<Person>, MyAppError> {
-    let persons = globals::once_cell::get_static_vec_persons();
-    Ok(persons)
-}
@@ -0,0 +1,207 @@
+//! src/handlers/partition_files_handlers.rs
+//!
+//! Files (PDF, MusicXML, images) attached to the partitions
+//! upload, download and delete
+//!
+
+use axum::body::Bytes;
+use axum::debug_handler;
+use axum::extract::{Multipart, Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Redirect};
+use axum_flash::Flash;
+
+use crate::db::partition_files::{
+    add_partition_file, delete_partition_file, find_partition_file_by_id,
+};
+use crate::db::partitions::find_partition_by_id;
+use crate::errors::MyAppError;
+use crate::models::partition_file::PartitionFile;
+use crate::{storage, AppState};
+
+///
+/// # Handler
+///
+/// **Uploads one or several files for a partition**<br>
+/// multipart form with one or several fields "file"<br>
+/// accepted : PDF, MusicXML (.musicxml, .mxl, .xml) and images
+///
+/// Returns a flash message and redirects to the main partition page *'api/partitions'*
+///
+#[debug_handler]
+pub async fn upload_partition_files_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path(id): Path<i32>,
+    multipart: Multipart,
+) -> (Flash, Redirect) {
+    match store_uploaded_files(&state, id, multipart).await {
+        Ok(files) if files.is_empty() => {
+            let message = "Aucun fichier reçu".to_string();
+            (flash.error(message), Redirect::to("/api/partitions"))
+        }
+        Ok(files) => {
+            let names = files
+                .iter()
+                .map(|file| file.file_name.clone())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let message = format!("Fichier(s) ajouté(s) : {names}");
+            (flash.success(message), Redirect::to("/api/partitions"))
+        }
+        Err(err) => {
+            tracing::info!("error uploading partition file : {}", err);
+            let message = format!("Fichier pas ajouté : {}", err.message);
+            (flash.error(message), Redirect::to("/api/partitions"))
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Downloads a file attached to a partition**
+///
+/// Returns the file content or MyAppError
+///
+#[debug_handler]
+pub async fn download_partition_file_hdl(
+    State(state): State<AppState>,
+    Path((id, file_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, MyAppError> {
+    let file = find_file_of_partition(&state, id, file_id).await?;
+    let bytes = storage::read_file(&state.env.partition_files_root, id, &file.stored_name).await?;
+
+    let disposition = format!(
+        "attachment; filename=\"{}\"",
+        file.file_name.replace(['"', '\\'], "_")
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, file.mime_type),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        bytes,
+    ))
+}
+
+///
+/// # Handler
+///
+/// **Deletes a file attached to a partition**<br>
+/// from the DB and from the disk
+///
+/// Returns a flash message and redirects to the main partition page *'api/partitions'*
+///
+#[debug_handler]
+pub async fn delete_partition_file_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path((id, file_id)): Path<(i32, i32)>,
+) -> (Flash, Redirect) {
+    let result = async {
+        find_file_of_partition(&state, id, file_id).await?;
+        let file = delete_partition_file(file_id, &state.pool).await?;
+        storage::remove_file(&state.env.partition_files_root, id, &file.stored_name).await?;
+        Ok::<PartitionFile, MyAppError>(file)
+    }
+    .await;
+
+    match result {
+        Ok(file) => {
+            let message = format!("Fichier effacé : {}", file.file_name);
+            (flash.success(message), Redirect::to("/api/partitions"))
+        }
+        Err(err) => {
+            tracing::info!("error deleting partition file : {}", err);
+            let message = "Fichier pas effacé".to_string();
+            (flash.error(message), Redirect::to("/api/partitions"))
+        }
+    }
+}
+
+//*******************************************************************************
+// Helpers functions
+//
+
+///
+/// Reads the fields of the multipart form and checks all the files,
+/// then writes each file on the disk and its metadata in the DB
+/// (a file not accepted stops the upload before anything is written)
+///
+async fn store_uploaded_files(
+    state: &AppState,
+    partition_id: i32,
+    mut multipart: Multipart,
+) -> Result<Vec<PartitionFile>, MyAppError> {
+    // the partition must exist
+    find_partition_by_id(partition_id, &state.pool).await?;
+
+    let mut uploads: Vec<(String, &'static str, Bytes)> = Vec::new();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| MyAppError::new(err.status(), err.body_text()))?
+    {
+        // an empty file input is sent without file name
+        let file_name = match field.file_name() {
+            Some(name) if !name.trim().is_empty() => name.to_string(),
+            _ => continue,
+        };
+        let mime_type = storage::mime_type_from_file_name(&file_name).ok_or_else(|| {
+            MyAppError::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("type de fichier non accepté : {file_name}"),
+            )
+        })?;
+        let bytes = field
+            .bytes()
+            .await
+            .map_err(|err| MyAppError::new(err.status(), err.body_text()))?;
+        uploads.push((file_name, mime_type, bytes));
+    }
+
+    let root = &state.env.partition_files_root;
+    let mut files: Vec<PartitionFile> = Vec::new();
+    for (file_name, mime_type, bytes) in uploads {
+        let stored_name = storage::stored_name_for(&file_name);
+        storage::save_file(root, partition_id, &stored_name, &bytes).await?;
+
+        let added = add_partition_file(
+            partition_id,
+            file_name,
+            stored_name.clone(),
+            mime_type.to_string(),
+            bytes.len() as i64,
+            storage::checksum(&bytes),
+            &state.pool,
+        )
+        .await;
+        match added {
+            Ok(file) => files.push(file),
+            Err(err) => {
+                // without its metadata the file could never be downloaded nor removed
+                if let Err(io_err) = storage::remove_file(root, partition_id, &stored_name).await {
+                    tracing::info!("error removing the file {} : {}", stored_name, io_err);
+                }
+                return Err(err);
+            }
+        }
+    }
+    Ok(files)
+}
+
+///
+/// Returns the file if it belongs to the partition
+/// or a NOT_FOUND MyAppError
+///
+async fn find_file_of_partition(
+    state: &AppState,
+    partition_id: i32,
+    file_id: i32,
+) -> Result<PartitionFile, MyAppError> {
+    find_partition_file_by_id(file_id, &state.pool)
+        .await?
+        .filter(|file| file.partition_id == partition_id)
+        .ok_or_else(|| MyAppError::new(StatusCode::NOT_FOUND, "File not found"))
+}
@@ -0,0 +1,122 @@
+//! src/handlers/partition_history_handlers.rs
+//!
+//! Revision history of a partition : its versions (read in the audit log),
+//! the field by field differences between two versions and the revert
+//! to an earlier version
+//!
+
+use axum::debug_handler;
+use axum::extract::{Path, Query, State};
+use axum::response::Redirect;
+use axum::Extension;
+use axum_flash::{Flash, IncomingFlashes};
+
+use crate::askama::askama_tpl::PartitionHistoryTemplate;
+use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::db::audit::list_partition_audit;
+use crate::db::partitions::find_partition_by_id;
+use crate::errors::MyAppError;
+use crate::handlers::partitions_handlers::save_partition_form;
+use crate::models::revision::{PartitionRevision, RevisionParams};
+use crate::AppState;
+
+///
+/// # Handler
+///
+/// **Shows the revision history of a partition**<br>
+/// its versions, the last one first, and the differences between two versions
+/// *'?from=2&to=5'* (default : the last two versions)
+///
+/// Returns a HTML Page (Askama Template) or MyAppError (NOT_FOUND)
+///
+#[debug_handler]
+pub async fn partition_history_askama_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Path(id): Path<i32>,
+    Query(params): Query<RevisionParams>,
+) -> Result<(IncomingFlashes, PartitionHistoryTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let partition = find_partition_by_id(id, &state.pool).await?;
+    let entries = list_partition_audit(id, &state.pool).await?;
+    let revisions = PartitionRevision::from_entries(&entries);
+
+    let compared = params.pair(revisions.len());
+    let changes = compared
+        .map(|(from, to)| {
+            revisions[from - 1]
+                .snapshot
+                .diff(&revisions[to - 1].snapshot)
+        })
+        .unwrap_or_default();
+    let (from, to) = compared.unwrap_or((0, 0));
+
+    let template = PartitionHistoryTemplate {
+        title: format!("Historique : {}", partition.title),
+        flash: Some(flash),
+        partition_id: id,
+        partition_title: partition.title,
+        revisions: revisions.into_iter().rev().collect(),
+        from,
+        to,
+        changes,
+    };
+    Ok((in_flash, template))
+}
+
+///
+/// # Handler
+///
+/// **Reverts a partition to one of its versions**<br>
+/// the version is saved like a modification of the partition form
+/// (same checks as update_partition_hdl : the musicians and the genre must still exist),
+/// so the revert is a new version of the history
+///
+/// Returns a flash message and redirects to the history of the partition
+///
+#[debug_handler]
+pub async fn revert_partition_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Path((id, number)): Path<(i32, usize)>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&format!("/api/partitions/{id}/history"));
+    let revision = match list_partition_audit(id, &state.pool).await {
+        Ok(entries) => PartitionRevision::from_entries(&entries)
+            .into_iter()
+            .find(|revision| revision.number == number),
+        Err(err) => {
+            let message = format!("Partition pas rétablie : {}", err.message);
+            return (flash.error(message), redirect);
+        }
+    };
+    let revision = match revision {
+        Some(revision) => revision,
+        None => {
+            let message = format!("Partition pas rétablie : version {number} inconnue");
+            return (flash.error(message), redirect);
+        }
+    };
+
+    let fields = revision.snapshot.form_fields();
+    match save_partition_form(id, fields, &auth_jwt.user, &state).await {
+        Ok(partition) => {
+            let message = format!(
+                "Partition rétablie à la version {number} : {}",
+                partition.title
+            );
+            (flash.success(message), redirect)
+        }
+        Err(cause) => {
+            let message = format!("Partition pas rétablie : {cause}");
+            (flash.error(message), redirect)
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! src/handlers/partition_import_handlers.rs
+//!
+//! Bulk import of partitions from a CSV file
+//! upload -> dry-run preview with the errors of each row -> confirmation
+//!
+//! The CSV text is sent back with the confirmation form,
+//! the rows are read and checked again before the import
+//!
+
+use axum::debug_handler;
+use axum::extract::{Form, Multipart, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum::Extension;
+use axum_flash::{Flash, IncomingFlashes};
+use serde::Deserialize;
+
+use crate::askama::askama_tpl::ImportPartitionsTemplate;
+use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::db::audit::{record_audit, snapshot};
+use crate::db::partition_import::{import_partitions, preview_import};
+use crate::errors::MyAppError;
+use crate::models::audit::AuditChange;
+use crate::models::partition_import::{parse_import_csv, ImportOptions, ImportReport};
+use crate::AppState;
+
+///
+/// Confirmation form of the import : the CSV text and the options of the preview
+///
+#[derive(Deserialize, Debug, Clone)]
+pub struct ImportPayload {
+    pub csv: String,
+    #[serde(flatten)]
+    pub options: ImportOptions,
+}
+
+///
+/// # Handler
+///
+/// **Shows the page to upload a CSV file of partitions**
+///
+/// Returns a HTML Page (Askama Template)
+///
+#[debug_handler(state = AppState)]
+pub async fn import_partitions_askama_hdl(
+    in_flash: IncomingFlashes,
+) -> (IncomingFlashes, ImportPartitionsTemplate) {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let template = ImportPartitionsTemplate {
+        title: "Importer des Partitions".to_string(),
+        flash: Some(flash),
+        report: None,
+        csv: String::new(),
+        options: ImportOptions::default(),
+    };
+    (in_flash, template)
+}
+
+///
+/// # Handler
+///
+/// **Dry-run of the import of a CSV file**<br>
+/// multipart form with the field "file" and the checkboxes
+/// "create_persons" and "create_genres"<br>
+/// nothing is written in the DB
+///
+/// Returns the import page with the preview of the rows and their errors
+/// or a flash message and redirects to the import page *'api/partitions/import'*
+///
+#[debug_handler]
+pub async fn preview_import_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    multipart: Multipart,
+) -> Result<ImportPartitionsTemplate, (Flash, Redirect)> {
+    let result = async {
+        let (csv, options) = read_import_form(multipart).await?;
+        let report = check_import(&csv, options, &state).await?;
+        Ok::<_, MyAppError>((csv, options, report))
+    }
+    .await;
+
+    match result {
+        Ok((csv, options, report)) => {
+            let flash = if report.is_valid() {
+                format!("{} partition(s) prête(s) à importer", report.rows.len())
+            } else {
+                format!(
+                    "{} ligne(s) en erreur : corriger le fichier et recommencer",
+                    report.error_count()
+                )
+            };
+            Ok(ImportPartitionsTemplate {
+                title: "Importer des Partitions : vérification".to_string(),
+                flash: Some(flash),
+                report: Some(report),
+                csv,
+                options,
+            })
+        }
+        Err(err) => {
+            tracing::info!("error reading import file : {}", err);
+            let message = format!("Fichier pas lu : {}", err.message);
+            Err((flash.error(message), Redirect::to("/api/partitions/import")))
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Imports the partitions of the CSV file after the confirmation**<br>
+/// the rows are checked again, then all the partitions (and the musicians
+/// and genres to create) are added in one transaction
+///
+/// Returns a flash message and redirects to the main partition page *'api/partitions'*
+/// or to the import page if nothing was imported
+///
+#[debug_handler]
+pub async fn confirm_import_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Form(form): Form<ImportPayload>,
+) -> (Flash, Redirect) {
+    let result = async {
+        let report = check_import(&form.csv, form.options, &state).await?;
+        import_partitions(&report, form.options, &state.pool).await
+    }
+    .await;
+
+    match result {
+        Ok(summary) => {
+            tracing::info!("partitions imported : {:?}", summary);
+            let change = AuditChange::imported(snapshot(&summary));
+            record_audit(&auth_jwt.user, change, &state.pool).await;
+            let message = format!(
+                "Partitions importées : {} (musiciens ajoutés : {}, genres ajoutés : {})",
+                summary.partitions, summary.persons, summary.genres
+            );
+            (flash.success(message), Redirect::to("/api/partitions"))
+        }
+        Err(err) => {
+            tracing::info!("error importing partitions : {}", err);
+            let message = format!("Partitions pas importées : {}", err.message);
+            (flash.error(message), Redirect::to("/api/partitions/import"))
+        }
+    }
+}
+
+//*******************************************************************************
+// Helpers functions
+//
+
+///
+/// Reads the CSV file (UTF-8) and the options of the multipart form
+///
+async fn read_import_form(mut multipart: Multipart) -> Result<(String, ImportOptions), MyAppError> {
+    let mut csv: Option<String> = None;
+    let mut options = ImportOptions::default();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| MyAppError::new(err.status(), err.body_text()))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+        let text = field
+            .bytes()
+            .await
+            .map_err(|err| MyAppError::new(err.status(), err.body_text()))?;
+        match name.as_str() {
+            "file" if !text.is_empty() => {
+                let text = String::from_utf8(text.to_vec()).map_err(|_| {
+                    MyAppError::new(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        "le fichier doit être un CSV en UTF-8",
+                    )
+                })?;
+                csv = Some(text);
+            }
+            "create_persons" => options.create_persons = true,
+            "create_genres" => options.create_genres = true,
+            _ => {}
+        }
+    }
+    let csv = csv.ok_or_else(|| MyAppError::new(StatusCode::BAD_REQUEST, "aucun fichier reçu"))?;
+    Ok((csv, options))
+}
+
+///
+/// Reads the rows of the CSV text and resolves their musicians and genres (dry-run)
+///
+async fn check_import(
+    csv: &str,
+    options: ImportOptions,
+    state: &AppState,
+) -> Result<ImportReport, MyAppError> {
+    let rows = parse_import_csv(csv)
+        .map_err(|cause| MyAppError::new(StatusCode::UNPROCESSABLE_ENTITY, cause))?;
+    preview_import(rows, options, &state.pool).await
+}
@@ -1,17 +1,39 @@
 //! src/handlers/partitions_handlers.rs
 
 use axum::debug_handler;
-use axum::extract::{Form, Path, State};
-use axum::response::Redirect;
+use axum::extract::{Form, Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Extension;
+use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
 use axum_flash::{Flash, IncomingFlashes};
 
-use crate::askama::askama_tpl::{HandlePartitionsTemplate, ListPartitionsTemplate};
+use crate::askama::askama_tpl::{
+    DuplicatesConfirmTemplate, HandlePartitionsTemplate, ListPartitionsTemplate,
+};
+use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::catalogue_pdf::{catalogue_pdf, Catalogue, CatalogueParams};
+use crate::db::audit::{partition_snapshot, record_audit};
+use crate::db::collections::{list_favourite_ids, list_user_collections};
+use crate::db::copies::list_availability_for_partitions;
+use crate::db::duplicates::find_similar_partitions;
+use crate::db::partition_files::list_files_for_partitions;
+use crate::db::tags::list_tags;
 use crate::db::{genres::*, musicians::*, partitions::update_partition, partitions::*};
 use crate::errors::MyAppError;
-use crate::models::partition::ShowPartition;
+use crate::export::{export_response, ExportParams};
+use crate::models::audit::{AuditChange, AuditEntity};
+use crate::models::duplicate::take_confirmation;
+use crate::models::loan::PartitionAvailability;
+use crate::models::pagination::{Page, PageParams};
+use crate::models::partition::{
+    ContributorRole, Partition, PartitionFilter, PartitionForm, ShowPartition,
+};
+use crate::models::partition_file::PartitionFile;
+use crate::models::user::User;
 use crate::{globals, AppState};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -19,6 +41,17 @@ pub struct Payload {
     pub name: String,
 }
 
+///
+/// Form to find the partitions of a musician
+/// * role : "composer", "lyricist", "arranger", "editor" or empty for all the roles
+///
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AuthorPayload {
+    pub name: String,
+    #[serde(default)]
+    pub role: String,
+}
+
 //***********************************************************************************
 // CRUD Operations
 //
@@ -26,6 +59,10 @@ pub struct Payload {
 /// # Handler
 ///
 /// **Creates a new partition in the partitions table**<br>
+/// with its main musician, the other musicians (pairs of fields role/contributor),
+/// its tags (created if they don't exist) and its musical metadata<br>
+/// when partitions with a close title exist, a confirmation page lists them first
+/// (the form is posted again with confirm=true to add the partition anyway)<br>
 /// Shows the list of all partitions
 ///
 /// Returns a flash message and redirects to the main partition page *'api/partitions'*
@@ -34,22 +71,59 @@ pub struct Payload {
 pub async fn create_partition_hdl(
     State(state): State<AppState>,
     flash: Flash,
-    Form(form): Form<ShowPartition>,
-) -> (Flash, Redirect) {
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Form(mut fields): Form<Vec<(String, String)>>,
+) -> Response {
+    let confirmed = take_confirmation(&mut fields);
+    let form = match PartitionForm::from_fields(fields.clone()) {
+        Ok(form) => form,
+        Err(cause) => {
+            let message = format!("Partition pas ajoutée : {cause}");
+            return (flash.error(message), Redirect::to("/api/partitions")).into_response();
+        }
+    };
+    if !confirmed {
+        match find_similar_partitions(&form.title, &state.pool).await {
+            Ok(matches) if !matches.is_empty() => {
+                let template = DuplicatesConfirmTemplate {
+                    title: "Ajouter une partition".to_string(),
+                    name: form.title,
+                    matches,
+                    action: "/api/partitions/add".to_string(),
+                    fields,
+                    cancel: "/api/partitions".to_string(),
+                };
+                return template.into_response();
+            }
+            Ok(_) => {}
+            Err(err) => tracing::info!("error checking duplicates : {}", err),
+        }
+    }
     let partition_title = form.title;
     let person_name = form.full_name;
     let genre_name = form.name;
 
-    if let Ok(partition) =
-        add_partition(partition_title, person_name, genre_name, &state.pool).await
+    if let Ok(partition) = add_partition(
+        partition_title,
+        person_name,
+        genre_name,
+        &form.details,
+        &state.pool,
+    )
+    .await
     {
         tracing::info!("partition added : {:?}", partition);
+        if let Some(id) = partition.id {
+            let after = partition_snapshot(id, &state.pool).await;
+            let change = AuditChange::created(AuditEntity::Partition, id, after);
+            record_audit(&auth_jwt.user, change, &state.pool).await;
+        }
         let message = format!("Partition ajoutée : {}", partition.title);
-        (flash.success(message), Redirect::to("/api/partitions"))
+        (flash.success(message), Redirect::to("/api/partitions")).into_response()
     } else {
         tracing::info!("error adding partition");
         let message = "Partition pas ajoutée".to_string();
-        (flash.error(message), Redirect::to("/api/partitions"))
+        (flash.error(message), Redirect::to("/api/partitions")).into_response()
     }
 }
 
@@ -57,6 +131,8 @@ pub async fn create_partition_hdl(
 /// # Handler
 ///
 /// **Updates a partition in the partitions table**<br>
+/// the musicians, the tags and the musical metadata of the partition
+/// are replaced by the ones of the form<br>
 /// Shows the list of all partitions
 ///
 /// Returns a flash message and redirects to the main partition page *'api/partitions'*
@@ -65,51 +141,75 @@ pub async fn create_partition_hdl(
 pub async fn update_partition_hdl(
     State(state): State<AppState>,
     flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
     Path(id): Path<i32>,
-    Form(form): Form<ShowPartition>,
+    Form(fields): Form<Vec<(String, String)>>,
 ) -> (Flash, Redirect) {
-    let partition_title = form.title;
-
-    let person = find_persons_by_name_strict(form.full_name, &state.pool)
-        .await
-        .map_err(|err| {
-            let cause = err.message;
-            let message = format!("Error : {cause}");
-            (
-                flash.clone().error(message),
-                Redirect::to("/api/partitions"),
-            )
-        });
-    if person.is_err() {
-        let message = "Error retreiving person";
-        (flash.error(message), Redirect::to("/api/partitions"))
-    } else {
-        let person = person.unwrap();
-        let person_id = person.id;
-
-        let genre = find_genre_by_name_parts(form.name, &state.pool)
-            .await
-            .unwrap();
-        let genre_id = genre[0].id;
-
-        if let Ok(partition) =
-            update_partition(id, partition_title, person_id, genre_id, &state.pool).await
-        {
-            tracing::info!("partition modified : {:?}", partition);
+    match save_partition_form(id, fields, &auth_jwt.user, &state).await {
+        Ok(partition) => {
             let message = format!("Partition modifiée : {}", partition.title);
             (flash.success(message), Redirect::to("/api/partitions"))
-        } else {
-            tracing::info!("error modifying partition");
-            let message = "Partition pas modifiée".to_string();
+        }
+        Err(cause) => {
+            let message = format!("Partition pas modifiée : {cause}");
             (flash.error(message), Redirect::to("/api/partitions"))
         }
     }
 }
 
+///
+/// **Saves the fields of the partition form in an existing partition**<br>
+/// the fields are checked (metadata values, musician and genre must exist)
+/// and the change is recorded in the audit log<br>
+/// used by update_partition_hdl and by the revert of the revision history
+///
+/// Returns the modified partition or the cause of the error
+///
+pub async fn save_partition_form(
+    id: i32,
+    fields: Vec<(String, String)>,
+    user: &User,
+    state: &AppState,
+) -> Result<Partition, String> {
+    let form = PartitionForm::from_fields(fields)?;
+
+    let person = find_persons_by_name_strict(form.full_name, &state.pool)
+        .await
+        .map_err(|err| format!("Error retreiving person : {}", err.message))?;
+    let genres = find_genre_by_name_parts(form.name, &state.pool)
+        .await
+        .map_err(|err| err.message)?;
+    let genre = genres
+        .first()
+        .ok_or_else(|| "genre inconnu".to_string())?;
+
+    let before = partition_snapshot(id, &state.pool).await;
+    let partition = update_partition(
+        id,
+        form.title,
+        person.id,
+        genre.id,
+        &form.details,
+        &state.pool,
+    )
+    .await
+    .map_err(|err| {
+        tracing::info!("error modifying partition : {}", err);
+        err.message
+    })?;
+    tracing::info!("partition modified : {:?}", partition);
+
+    let after = partition_snapshot(id, &state.pool).await;
+    let change = AuditChange::updated(AuditEntity::Partition, id, before, after);
+    record_audit(user, change, &state.pool).await;
+    Ok(partition)
+}
+
 ///
 /// # Handler
 ///
 /// **Deletes a partition in the partitions table**<br>
+/// the partition goes to the trash (it can be restored from *'admin/trash'*)<br>
 /// Shows the list of all partitions
 ///
 /// Returns a flash message and redirects to the main partition page *'api/partitions'*
@@ -117,11 +217,16 @@ pub async fn update_partition_hdl(
 #[debug_handler]
 pub async fn delete_partition_hdl(
     State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
     Path(id): Path<i32>,
     flash: Flash,
 ) -> (Flash, Redirect) {
+    let before = partition_snapshot(id, &state.pool).await;
     if let Ok(deleted_partition) = delete_partition(id, &state.pool).await {
-        let message = format!("Partition effacée : {deleted_partition}");
+        let change = AuditChange::deleted(AuditEntity::Partition, id, before);
+        record_audit(&auth_jwt.user, change, &state.pool).await;
+        // the partition is in the trash : its files are kept until it is purged
+        let message = format!("Partition mise à la corbeille : {deleted_partition}");
         (flash.success(message), Redirect::to("/api/partitions"))
     } else {
         let message = "Partition pas effacée".to_string();
@@ -137,7 +242,8 @@ pub async fn delete_partition_hdl(
 /// # Handler
 ///
 /// **Shows the main partition page**<br>
-/// with the list of partitions via ShowPartition
+/// with one page of the list of partitions via ShowPartition<br>
+/// *'?page=&per_page=&sort=&dir='* (sort : title, composer, genre, difficulty or duration)
 ///
 /// Returns a HTML Page or AppError
 ///
@@ -145,6 +251,8 @@ pub async fn delete_partition_hdl(
 pub async fn manage_partitions_hdl(
     State(state): State<AppState>,
     in_flash: IncomingFlashes,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Query(params): Query<PageParams>,
 ) -> Result<(IncomingFlashes, HandlePartitionsTemplate), MyAppError> {
     let flash = in_flash
         .into_iter()
@@ -153,9 +261,16 @@ pub async fn manage_partitions_hdl(
         .join(", ");
     tracing::info!("flash : {}", flash);
 
-    let partitions = get_list_all_partitions_one_cell(&state.pool).await;
+    let partitions = list_show_partitions(&params, &state.pool).await?;
+    let total = count_partitions(&PartitionFilter::default(), &state.pool).await?;
+    let page = Page::new(&params, total, "/api/partitions");
     let persons = list_persons(&state.pool).await?;
     let genres = list_genres(&state.pool).await?;
+    let tags = list_tags(&state.pool).await?;
+    let files = get_files_of_partitions(&state.pool, &partitions).await?;
+    let copies = get_copies_of_partitions(&state.pool, &partitions).await?;
+    let favourites = get_favourites_of_partitions(&state.pool, &auth_jwt, &partitions).await?;
+    let collections = list_user_collections(auth_jwt.user.id, &state.pool).await?;
     let title = "Gestion des Partitions".to_string();
     let flash = Some(flash);
 
@@ -165,25 +280,41 @@ pub async fn manage_partitions_hdl(
         partitions,
         persons,
         genres,
+        tags,
+        files,
+        copies,
+        favourites,
+        collections,
+        page,
     };
     Ok((in_flash, template))
 }
 ///
 /// # Handler
 ///
-/// **Shows a printable list of all partitions in the db**<br>
-/// under the form of ShowPartitions
+/// **Shows a printable list of the partitions in the db**<br>
+/// under the form of ShowPartitions<br>
+/// takes the same filters as the search (*'?q=&tag=&key=...'*) and the pagination
+/// (*'?page=&per_page=&sort=&dir='*)
 ///
 /// Returns a HTML Page or AppError
 ///
 #[debug_handler]
-pub async fn print_list_partitions_hdl() -> Result<ListPartitionsTemplate, MyAppError> {
-    let list_partitions = get_existing_list_partitions_one_cell().await;
+pub async fn print_list_partitions_hdl(
+    State(state): State<AppState>,
+    Query(filter): Query<PartitionFilter>,
+    Query(params): Query<PageParams>,
+) -> Result<ListPartitionsTemplate, MyAppError> {
+    let list_partitions = search_partitions(&filter, &params, &state.pool).await?;
+    let total = count_partitions(&filter, &state.pool).await?;
+    let page =
+        Page::new(&params, total, "/api/partitions/print").with_filters(filter.query_pairs());
 
     let title = "liste des partitions".to_string();
     let template = ListPartitionsTemplate {
         title,
         list_partitions,
+        page,
     };
     Ok(template)
 }
@@ -202,6 +333,7 @@ pub async fn print_list_partitions_hdl() -> Result<ListPartitionsTemplate, MyApp
 pub async fn find_partition_title_hdl(
     State(state): State<AppState>,
     in_flash: IncomingFlashes,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
     Form(form): Form<Payload>,
 ) -> Result<(IncomingFlashes, HandlePartitionsTemplate), MyAppError> {
     let flash = in_flash
@@ -213,6 +345,12 @@ pub async fn find_partition_title_hdl(
     let partitions = get_list_partitions_by_title_once_cell(&state.pool, form.name).await;
     let persons = list_persons(&state.pool).await?;
     let genres = list_genres(&state.pool).await?;
+    let tags = list_tags(&state.pool).await?;
+    let files = get_files_of_partitions(&state.pool, &partitions).await?;
+    let copies = get_copies_of_partitions(&state.pool, &partitions).await?;
+    let favourites = get_favourites_of_partitions(&state.pool, &auth_jwt, &partitions).await?;
+    let collections = list_user_collections(auth_jwt.user.id, &state.pool).await?;
+    let page = Page::all(partitions.len() as i64, "/api/partitions");
     let title = "Partition(s) trouvée(s)".to_string();
     let flash = Some(flash);
     let template = HandlePartitionsTemplate {
@@ -221,6 +359,12 @@ pub async fn find_partition_title_hdl(
         partitions,
         persons,
         genres,
+        tags,
+        files,
+        copies,
+        favourites,
+        collections,
+        page,
     };
     Ok((in_flash, template))
 }
@@ -229,6 +373,7 @@ pub async fn find_partition_title_hdl(
 pub async fn find_partition_genre_hdl(
     State(state): State<AppState>,
     in_flash: IncomingFlashes,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
     Form(form): Form<Payload>,
 ) -> Result<(IncomingFlashes, HandlePartitionsTemplate), MyAppError> {
     let title = "Partition(s) trouvée(s)".to_string();
@@ -247,6 +392,12 @@ pub async fn find_partition_genre_hdl(
     let partitions = get_list_partitions_by_genre_once_cell(&state.pool, form.name).await;
     let persons = list_persons(&state.pool).await?;
     let genres = list_genres(&state.pool).await?;
+    let tags = list_tags(&state.pool).await?;
+    let files = get_files_of_partitions(&state.pool, &partitions).await?;
+    let copies = get_copies_of_partitions(&state.pool, &partitions).await?;
+    let favourites = get_favourites_of_partitions(&state.pool, &auth_jwt, &partitions).await?;
+    let collections = list_user_collections(auth_jwt.user.id, &state.pool).await?;
+    let page = Page::all(partitions.len() as i64, "/api/partitions");
     let flash = Some(flash);
 
     let template = HandlePartitionsTemplate {
@@ -255,15 +406,29 @@ pub async fn find_partition_genre_hdl(
         partitions,
         persons,
         genres,
+        tags,
+        files,
+        copies,
+        favourites,
+        collections,
+        page,
     };
     Ok((in_flash, template))
 }
 
+///
+/// # Handler
+/// **finds partition(s) by musician**<br>
+/// with an optional role ("arranged by X"), all the roles if the role is empty
+///
+/// returns partitions page with partition(s) found
+///
 #[debug_handler]
 pub async fn find_partition_author_hdl(
     State(state): State<AppState>,
     in_flash: IncomingFlashes,
-    Form(form): Form<Payload>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Form(form): Form<AuthorPayload>,
 ) -> Result<(IncomingFlashes, HandlePartitionsTemplate), MyAppError> {
     let title = "Partition(s) trouvée(s)".to_string();
     let flash = in_flash
@@ -280,9 +445,68 @@ pub async fn find_partition_author_hdl(
     //}
     //set_static_vec_partitions(show_partitions);
     //let show_partitions = get_static_vec_partitions();
-    let partitions = get_list_partitions_by_author_once_cell(&state.pool, form.name).await;
+    let role = form.role.parse::<ContributorRole>().ok();
+    let partitions = get_list_partitions_by_author_once_cell(&state.pool, form.name, role).await;
+    let persons = list_persons(&state.pool).await?;
+    let genres = list_genres(&state.pool).await?;
+    let tags = list_tags(&state.pool).await?;
+    let files = get_files_of_partitions(&state.pool, &partitions).await?;
+    let copies = get_copies_of_partitions(&state.pool, &partitions).await?;
+    let favourites = get_favourites_of_partitions(&state.pool, &auth_jwt, &partitions).await?;
+    let collections = list_user_collections(auth_jwt.user.id, &state.pool).await?;
+    let page = Page::all(partitions.len() as i64, "/api/partitions");
+    let flash = Some(flash);
+    let template = HandlePartitionsTemplate {
+        title,
+        flash,
+        partitions,
+        persons,
+        genres,
+        tags,
+        files,
+        copies,
+        favourites,
+        collections,
+        page,
+    };
+    Ok((in_flash, template))
+}
+///
+/// # Handler
+/// **searches partitions with one search box**
+///
+/// Full-text search on the title, the musician and the genre : GET *'api/partitions/search?q=...'*<br>
+/// and filters on a tag and the musical metadata :
+/// *'&tag=&key=&time_signature=&tempo=&instrumentation=&max_difficulty='*<br>
+/// returns partitions page with the partitions found, the most relevant first<br>
+/// paginated like the main partition page
+///
+#[debug_handler]
+pub async fn search_partitions_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Query(filter): Query<PartitionFilter>,
+    Query(params): Query<PageParams>,
+) -> Result<(IncomingFlashes, HandlePartitionsTemplate), MyAppError> {
+    let title = "Partition(s) trouvée(s)".to_string();
+    let flash = in_flash
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+    tracing::info!("flash : {}", flash);
+    let partitions = search_partitions(&filter, &params, &state.pool).await?;
+    let total = count_partitions(&filter, &state.pool).await?;
+    let page =
+        Page::new(&params, total, "/api/partitions/search").with_filters(filter.query_pairs());
     let persons = list_persons(&state.pool).await?;
     let genres = list_genres(&state.pool).await?;
+    let tags = list_tags(&state.pool).await?;
+    let files = get_files_of_partitions(&state.pool, &partitions).await?;
+    let copies = get_copies_of_partitions(&state.pool, &partitions).await?;
+    let favourites = get_favourites_of_partitions(&state.pool, &auth_jwt, &partitions).await?;
+    let collections = list_user_collections(auth_jwt.user.id, &state.pool).await?;
     let flash = Some(flash);
     let template = HandlePartitionsTemplate {
         title,
@@ -290,9 +514,82 @@ pub async fn find_partition_author_hdl(
         partitions,
         persons,
         genres,
+        tags,
+        files,
+        copies,
+        favourites,
+        collections,
+        page,
     };
     Ok((in_flash, template))
 }
+
+///
+/// # Handler
+/// **Exports the partitions in CSV or JSON**
+///
+/// GET *'api/partitions/export?format=csv'* (or *'format=json'*)
+/// with the same criteria as the search *'&q=&tag=&key=...'*<br>
+/// the partitions with their musicians, genre, tags and musical metadata,
+/// ordered by title
+///
+/// Returns the file (streamed)
+///
+#[debug_handler]
+pub async fn export_partitions_hdl(
+    State(state): State<AppState>,
+    Query(filter): Query<PartitionFilter>,
+    Query(params): Query<ExportParams>,
+) -> Response {
+    let partitions = stream_show_partitions(filter, state.pool.clone());
+    export_response(partitions, params.format(), "partitions")
+}
+
+///
+/// # Handler
+/// **Generates the catalogue of the partitions in PDF**
+///
+/// GET *'api/partitions/print.pdf?group=genre'* (or *'group=composer'*)
+/// with the same criteria as the search *'&q=&tag=&key=...'*<br>
+/// a chapter per genre (or composer), a table of contents,
+/// the name of the ensemble in the header and the page numbers
+///
+/// Returns the PDF file (shown by the browser)
+///
+#[debug_handler]
+pub async fn print_pdf_partitions_hdl(
+    State(state): State<AppState>,
+    Query(filter): Query<PartitionFilter>,
+    Query(params): Query<CatalogueParams>,
+) -> Result<Response, MyAppError> {
+    let criteria = filter.criteria_text();
+    let partitions = stream_show_partitions(filter, state.pool.clone())
+        .try_concat()
+        .await?;
+    let catalogue = Catalogue::new(
+        state.env.ensemble_name.clone(),
+        criteria,
+        params.grouping(),
+        partitions,
+    );
+    // the layout of a large catalogue takes time : out of the async threads
+    let pdf = tokio::task::spawn_blocking(move || catalogue_pdf(&catalogue))
+        .await
+        .map_err(|err| MyAppError::new(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf"),
+            (
+                header::CONTENT_DISPOSITION,
+                "inline; filename=\"catalogue.pdf\"",
+            ),
+        ],
+        pdf,
+    )
+        .into_response())
+}
+
 ///
 /// # Helpers functions
 ///
@@ -334,8 +631,9 @@ async fn get_list_partitions_by_genre_once_cell(
 async fn get_list_partitions_by_author_once_cell(
     pool: &PgPool,
     partition_author: String,
+    role: Option<ContributorRole>,
 ) -> Vec<ShowPartition> {
-    let partitions = find_partition_by_author(partition_author, pool)
+    let partitions = find_partition_by_author(partition_author, role, pool)
         .await
         .unwrap();
     let mut show_partitions: Vec<ShowPartition> = Vec::new();
@@ -347,11 +645,36 @@ async fn get_list_partitions_by_author_once_cell(
     globals::once_cell::get_static_vec_partitions()
 }
 
-async fn get_list_all_partitions_one_cell(pool: &PgPool) -> Vec<ShowPartition> {
-    globals::once_cell::set_static_vec_partitions(list_show_partitions(pool).await.unwrap());
-    globals::once_cell::get_static_vec_partitions()
+///
+/// Returns the files attached to the partitions shown in the page
+///
+async fn get_files_of_partitions(
+    pool: &PgPool,
+    partitions: &[ShowPartition],
+) -> Result<Vec<PartitionFile>, MyAppError> {
+    let ids = partitions.iter().map(|p| p.id).collect::<Vec<i32>>();
+    list_files_for_partitions(&ids, pool).await
 }
 
-async fn get_existing_list_partitions_one_cell() -> Vec<ShowPartition> {
-    globals::once_cell::get_static_vec_partitions()
+///
+/// Returns the copies (total and available) of the partitions shown in the page
+///
+async fn get_copies_of_partitions(
+    pool: &PgPool,
+    partitions: &[ShowPartition],
+) -> Result<Vec<PartitionAvailability>, MyAppError> {
+    let ids = partitions.iter().map(|p| p.id).collect::<Vec<i32>>();
+    list_availability_for_partitions(&ids, pool).await
+}
+
+///
+/// Returns the ids of the partitions of the page starred by the logged user
+///
+async fn get_favourites_of_partitions(
+    pool: &PgPool,
+    auth_jwt: &JWTAuthMiddleware,
+    partitions: &[ShowPartition],
+) -> Result<Vec<i32>, MyAppError> {
+    let ids = partitions.iter().map(|p| p.id).collect::<Vec<i32>>();
+    list_favourite_ids(auth_jwt.user.id, &ids, pool).await
 }
@@ -0,0 +1,344 @@
+//! src/handlers/setlists_handlers.rs
+//!
+//! Setlists : create, rename, delete the setlists,
+//! add, move, annotate and remove their pieces, print the programme
+//!
+
+use axum::debug_handler;
+use axum::extract::{Form, Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum_flash::{Flash, IncomingFlashes};
+
+use crate::askama::askama_tpl::{
+    HandleSetlistsTemplate, SetlistProgrammeTemplate, SetlistTemplate,
+};
+use crate::db::setlists::*;
+use crate::errors::MyAppError;
+use crate::models::pagination::{Page, PageParams};
+use crate::models::setlist::{MoveForm, NoteForm, SetlistForm, SetlistItemForm};
+use crate::AppState;
+
+//***********************************************************************************
+// CRUD Operations on setlists
+//
+
+///
+/// # Handler
+///
+/// **Creates a new setlist** (name and date of the concert)
+///
+/// Returns a flash message and redirects to the page of the new setlist
+///
+#[debug_handler]
+pub async fn create_setlist_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Form(form): Form<SetlistForm>,
+) -> (Flash, Redirect) {
+    let result = async {
+        let (name, date) = form
+            .parse()
+            .map_err(|cause| MyAppError::new(StatusCode::UNPROCESSABLE_ENTITY, cause))?;
+        add_setlist(&name, date, &state.pool).await
+    }
+    .await;
+
+    match result {
+        Ok(setlist) => {
+            let message = format!(
+                "Setlist ajoutée : {} ({})",
+                setlist.name,
+                setlist.date_text()
+            );
+            let redirect = Redirect::to(&format!("/api/setlists/{}", setlist.id));
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error adding setlist : {}", err);
+            let message = format!("Setlist pas ajoutée : {}", err.message);
+            (flash.error(message), Redirect::to("/api/setlists"))
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Modifies the name and the date of a setlist**
+///
+/// Returns a flash message and redirects to the setlists page
+///
+#[debug_handler]
+pub async fn update_setlist_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path(id): Path<i32>,
+    Form(form): Form<SetlistForm>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to("/api/setlists");
+    let result = async {
+        let (name, date) = form
+            .parse()
+            .map_err(|cause| MyAppError::new(StatusCode::UNPROCESSABLE_ENTITY, cause))?;
+        update_setlist(id, &name, date, &state.pool).await
+    }
+    .await;
+
+    match result {
+        Ok(setlist) => {
+            let message = format!(
+                "Setlist modifiée : {} ({})",
+                setlist.name,
+                setlist.date_text()
+            );
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error modifying setlist : {}", err);
+            let message = format!("Setlist pas modifiée : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+#[debug_handler]
+pub async fn delete_setlist_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path(id): Path<i32>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to("/api/setlists");
+    match delete_setlist(id, &state.pool).await {
+        Ok(name) => {
+            let message = format!("Setlist effacée : {}", name);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error deleting setlist : {}", err);
+            let message = format!("Setlist pas effacée : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+//*******************************************************************************
+// Pages of the setlists
+//
+
+///
+/// # Handler
+///
+/// **Shows the main page to manage the setlists**<br>
+/// with one page of the list of setlists *'?page=&per_page=&sort=&dir='* (sort : date, name)
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn manage_setlists_askama_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Query(params): Query<PageParams>,
+) -> Result<(IncomingFlashes, HandleSetlistsTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let setlists = list_setlists_page(&params, &state.pool).await?;
+    let total = count_setlists(&state.pool).await?;
+    let page = Page::new(&params, total, "/api/setlists");
+
+    let template = HandleSetlistsTemplate {
+        title: "Gestion des Setlists".to_string(),
+        flash: Some(flash),
+        setlists,
+        page,
+    };
+    Ok((in_flash, template))
+}
+
+///
+/// # Handler
+///
+/// **Shows a setlist**<br>
+/// the pieces in order with the forms to move, annotate and remove them,
+/// and the form to add a partition at the end
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn setlist_askama_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Path(id): Path<i32>,
+) -> Result<(IncomingFlashes, SetlistTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let setlist = find_setlist_by_id(id, &state.pool).await?;
+    let items = list_setlist_items(id, &state.pool).await?;
+    let partitions = list_partition_choices(&state.pool).await?;
+
+    let template = SetlistTemplate {
+        title: format!("Setlist : {} ({})", setlist.name, setlist.date_text()),
+        flash: Some(flash),
+        setlist,
+        items,
+        partitions,
+    };
+    Ok((in_flash, template))
+}
+
+///
+/// # Handler
+///
+/// **Shows the printable programme of a setlist**<br>
+/// the pieces with their composer and duration, and the total duration
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn setlist_programme_hdl(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<SetlistProgrammeTemplate, MyAppError> {
+    let setlist = find_setlist_by_id(id, &state.pool).await?;
+    let items = list_setlist_items(id, &state.pool).await?;
+
+    Ok(SetlistProgrammeTemplate {
+        title: format!("Programme : {}", setlist.name),
+        ensemble: state.env.ensemble_name.clone(),
+        setlist,
+        items,
+    })
+}
+
+//*******************************************************************************
+// Pieces of a setlist
+//
+
+///
+/// # Handler
+///
+/// **Adds a partition at the end of a setlist**
+///
+/// Returns a flash message and redirects to the page of the setlist
+///
+#[debug_handler]
+pub async fn add_setlist_item_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path(id): Path<i32>,
+    Form(form): Form<SetlistItemForm>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&format!("/api/setlists/{id}"));
+    match add_setlist_item(id, form.partition_id, form.note.trim(), &state.pool).await {
+        Ok(item) => {
+            let message = format!("Morceau ajouté : {}. {}", item.position, item.title);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error adding setlist item : {}", err);
+            let message = format!("Morceau pas ajouté : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Modifies the note of a piece** *'/api/setlists/:id/items/:item_id'*
+///
+/// Returns a flash message and redirects to the page of the setlist
+///
+#[debug_handler]
+pub async fn update_setlist_item_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path((id, item_id)): Path<(i32, i32)>,
+    Form(form): Form<NoteForm>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&format!("/api/setlists/{id}"));
+    match update_setlist_item_note(item_id, form.note.trim(), &state.pool).await {
+        Ok(item) => {
+            let message = format!("Note modifiée : {}", item.title);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error modifying setlist item : {}", err);
+            let message = format!("Note pas modifiée : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Moves a piece to a new position** *'/api/setlists/:id/items/:item_id/move'*
+///
+/// Returns a flash message and redirects to the page of the setlist
+///
+#[debug_handler]
+pub async fn move_setlist_item_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path((id, item_id)): Path<(i32, i32)>,
+    Form(form): Form<MoveForm>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&format!("/api/setlists/{id}"));
+    let result = async {
+        let position = form
+            .position()
+            .map_err(|cause| MyAppError::new(StatusCode::UNPROCESSABLE_ENTITY, cause))?;
+        move_setlist_item(item_id, position, &state.pool).await
+    }
+    .await;
+
+    match result {
+        Ok(item) => {
+            let message = format!("Morceau déplacé : {}. {}", item.position, item.title);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error moving setlist item : {}", err);
+            let message = format!("Morceau pas déplacé : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Removes a piece from a setlist** *'/api/setlists/:id/items/:item_id/delete'*
+///
+/// Returns a flash message and redirects to the page of the setlist
+///
+#[debug_handler]
+pub async fn remove_setlist_item_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path((id, item_id)): Path<(i32, i32)>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to(&format!("/api/setlists/{id}"));
+    match remove_setlist_item(item_id, &state.pool).await {
+        Ok(item) => {
+            let message = format!("Morceau retiré : {}", item.title);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error removing setlist item : {}", err);
+            let message = format!("Morceau pas retiré : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! src/handlers/statistics_handlers.rs
+//!
+//! Statistics page of the catalogue : the totals, the top composers,
+//! the partitions added per month, the genres and the loans,
+//! with bar charts drawn in SVG on the server
+//!
+
+use axum::debug_handler;
+use axum::extract::State;
+
+use crate::askama::askama_tpl::StatisticsTemplate;
+use crate::db::statistics::{
+    catalogue_totals, loans_per_month, most_borrowed_partitions, partitions_per_genre,
+    partitions_per_month, top_composers,
+};
+use crate::errors::MyAppError;
+use crate::models::statistics::{BarChart, Statistics};
+use crate::AppState;
+
+/// the number of months of the charts per month
+const STATISTICS_MONTHS: i32 = 12;
+/// the number of composers, genres and partitions of the rankings
+const RANKING_LIMIT: i64 = 10;
+
+///
+/// # Handler
+///
+/// **Shows the statistics of the catalogue** GET *'api/statistics'*
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn statistics_askama_hdl(
+    State(state): State<AppState>,
+) -> Result<StatisticsTemplate, MyAppError> {
+    let pool = &state.pool;
+    let totals = catalogue_totals(pool).await?;
+    let composers = top_composers(RANKING_LIMIT, pool).await?;
+    let added = partitions_per_month(STATISTICS_MONTHS, pool).await?;
+    let genres = partitions_per_genre(RANKING_LIMIT, pool).await?;
+    let loans = loans_per_month(STATISTICS_MONTHS, pool).await?;
+    let borrowed = most_borrowed_partitions(STATISTICS_MONTHS, RANKING_LIMIT, pool).await?;
+
+    let statistics = Statistics {
+        top_composers: BarChart::rows(&composers, None),
+        partitions_per_month: BarChart::columns(&added),
+        genres: BarChart::rows(&genres, Some(totals.partitions)),
+        loans_per_month: BarChart::columns(&loans),
+        most_borrowed: BarChart::rows(&borrowed, None),
+        totals,
+    };
+    let template = StatisticsTemplate {
+        title: "Statistiques du Catalogue".to_string(),
+        statistics,
+    };
+    Ok(template)
+}
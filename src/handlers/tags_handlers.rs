@@ -0,0 +1,184 @@
+//! src/handlers/tags_handlers.rs
+//!
+//! Pages to manage the tags of the partitions
+//! the tags are also created on the fly when a partition is saved
+//!
+
+use axum::debug_handler;
+use axum::extract::{Form, Path, Query, State};
+use axum::response::Redirect;
+use axum_flash::{Flash, IncomingFlashes};
+use serde::{Deserialize, Serialize};
+
+use crate::askama::askama_tpl::{HandleTagsTemplate, ListTagsTemplate};
+use crate::db::tags::*;
+use crate::errors::MyAppError;
+use crate::models::pagination::{Page, PageParams};
+use crate::AppState;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Payload {
+    pub name: String,
+}
+
+//***********************************************************************************
+// CRUD Operations
+//
+
+///
+/// # Handler
+///
+/// **Shows the main page to manage the tags**<br>
+/// with one page of the list of tags *'?page=&per_page=&sort=&dir='* (sort : name)
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn manage_tags_askama_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Query(params): Query<PageParams>,
+) -> Result<(IncomingFlashes, HandleTagsTemplate), MyAppError> {
+    let flash = in_flash
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+    tracing::info!("flash : {}", flash);
+
+    let tags = list_tags_page(&params, &state.pool).await?;
+    let total = count_tags(&state.pool).await?;
+    let page = Page::new(&params, total, "/api/tags");
+
+    let title = "Gestion des Tags".to_string();
+    let flash = Some(flash);
+
+    let template = HandleTagsTemplate {
+        title,
+        flash,
+        tags,
+        page,
+    };
+    Ok((in_flash, template))
+}
+
+///
+/// # Handler
+///
+/// **Creates a new tag in the DB**
+///
+/// Returns a flash message and redirects to the tags page *'api/tags'*
+///
+#[debug_handler]
+pub async fn create_tag_hdl(
+    flash: Flash,
+    State(state): State<AppState>,
+    Form(form): Form<Payload>,
+) -> (Flash, Redirect) {
+    let new_tag = form.name;
+    if new_tag.trim().is_empty() {
+        let message = "Tag vide pas ajouté".to_string();
+        return (flash.error(message), Redirect::to("/api/tags"));
+    }
+
+    if let Ok(tag) = add_tag(&state.pool, new_tag).await {
+        tracing::info!("tag added : {:?}", tag);
+        let message = format!("Tag ajouté : {}", tag.name);
+        (flash.success(message), Redirect::to("/api/tags"))
+    } else {
+        tracing::info!("Error adding tag");
+        let message = "Tag pas ajouté".to_string();
+        (flash.error(message), Redirect::to("/api/tags"))
+    }
+}
+
+#[debug_handler]
+pub async fn update_tag_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Path(id): Path<i32>,
+    Form(form): Form<Payload>,
+) -> (Flash, Redirect) {
+    if let Ok(tag) = update_tag(id, form.name, &state.pool).await {
+        tracing::info!("tag modified : {:?}", tag);
+        let message = format!("Tag modifié : {}", tag.name);
+        (flash.success(message), Redirect::to("/api/tags"))
+    } else {
+        tracing::info!("error modifying tag");
+        let message = "Tag pas modifié".to_string();
+        (flash.error(message), Redirect::to("/api/tags"))
+    }
+}
+
+#[debug_handler]
+pub async fn delete_tag_hdl(
+    flash: Flash,
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> (Flash, Redirect) {
+    if let Ok(deleted_name) = delete_tag(id, &state.pool).await {
+        let message = format!("Tag effacé : {}", deleted_name);
+        (flash.success(message), Redirect::to("/api/tags"))
+    } else {
+        let message = "Tag pas effacé".to_string();
+        (flash.error(message), Redirect::to("/api/tags"))
+    }
+}
+
+//*******************************************************************************
+// Functions to show or print list of tags
+//
+
+///
+/// # Handler
+///
+/// **Shows a printable list of Tags**<br>
+/// one page of the list *'?page=&per_page=&sort=&dir='* (sort : name)
+///
+/// Returns a HTML Page (Askama Template) or MyAppError
+///
+#[debug_handler]
+pub async fn list_tags_askama_hdl(
+    State(state): State<AppState>,
+    Query(params): Query<PageParams>,
+) -> Result<ListTagsTemplate, MyAppError> {
+    let list_tags = list_tags_page(&params, &state.pool).await?;
+    let total = count_tags(&state.pool).await?;
+    let page = Page::new(&params, total, "/api/tags/print");
+    let template = ListTagsTemplate { list_tags, page };
+    Ok(template)
+}
+
+///
+/// # Handler
+///
+/// **find_tag_by_name**
+///
+/// returns the tags page with the tags beginning with the letters entered
+///
+#[debug_handler]
+pub async fn find_tag_by_name_hdl(
+    State(state): State<AppState>,
+    in_flash: IncomingFlashes,
+    Form(form): Form<Payload>,
+) -> Result<(IncomingFlashes, HandleTagsTemplate), MyAppError> {
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let tags = find_tag_by_name_parts(form.name, &state.pool).await?;
+    let page = Page::all(tags.len() as i64, "/api/tags");
+    let title = "Tag(s) trouvé(s)".to_string();
+    let flash = Some(flash);
+
+    let template = HandleTagsTemplate {
+        title,
+        flash,
+        tags,
+        page,
+    };
+    Ok((in_flash, template))
+}
@@ -0,0 +1,190 @@
+//! src/handlers/trash_handlers.rs
+//!
+//! Trash of the catalogue (administrators only) :
+//! the deleted partitions, musicians and genres can be restored or purged
+//!
+
+use axum::debug_handler;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Redirect;
+use axum::Extension;
+use axum_flash::{Flash, IncomingFlashes};
+use std::time::Duration;
+
+use crate::askama::askama_tpl::TrashTemplate;
+use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::db::audit::{partition_snapshot, record_audit, record_system_audit, snapshot};
+use crate::db::trash::{list_trash, purge_expired, purge_from_trash, restore_from_trash};
+use crate::errors::MyAppError;
+use crate::models::audit::{AuditChange, AuditEntity};
+use crate::{storage, AppState};
+
+///
+/// # Handler
+///
+/// **Shows the trash** (administrators only)<br>
+/// the deleted records, the last deleted first, with the day of their automatic purge
+///
+/// Returns a HTML Page (Askama Template) or MyAppError (UNAUTHORIZED)
+///
+#[debug_handler]
+pub async fn trash_askama_hdl(
+    State(state): State<AppState>,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    in_flash: IncomingFlashes,
+) -> Result<(IncomingFlashes, TrashTemplate), MyAppError> {
+    if auth_jwt.user.role != "Administrateur" {
+        return Err(MyAppError::new(
+            StatusCode::UNAUTHORIZED,
+            "Hey ! Page only for Administrators",
+        ));
+    }
+    let flash = in_flash
+        .clone()
+        .into_iter()
+        .map(|(level, text)| format!("{:?}: {}", level, text))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let items = list_trash(&state.pool).await?;
+
+    let template = TrashTemplate {
+        title: "Corbeille".to_string(),
+        flash: Some(flash),
+        items,
+        retention_days: state.env.trash_retention_days,
+    };
+    Ok((in_flash, template))
+}
+
+///
+/// # Handler
+///
+/// **Restores a record from the trash** (administrators only)<br>
+/// *'/admin/trash/partition/12/restore'* (partition, person or genre)
+///
+/// Returns a flash message and redirects to the trash page *'admin/trash'*
+///
+#[debug_handler]
+pub async fn restore_trash_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Path((entity, id)): Path<(String, i32)>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to("/admin/trash");
+    let entity = match check_trash_request(&auth_jwt, &entity) {
+        Ok(entity) => entity,
+        Err(message) => return (flash.error(message), redirect),
+    };
+    match restore_from_trash(entity, id, &state.pool).await {
+        Ok(item) => {
+            let after = match entity {
+                AuditEntity::Partition => partition_snapshot(id, &state.pool).await,
+                _ => snapshot(&item),
+            };
+            let change = AuditChange::restored(entity, id, after);
+            record_audit(&auth_jwt.user, change, &state.pool).await;
+            let message = format!("{} restauré(e) : {}", entity.label(), item.name);
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error restoring {:?} {} : {}", entity, id, err);
+            let message = format!("Pas restauré : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+///
+/// # Handler
+///
+/// **Purges a record from the trash : it is deleted for good** (administrators only)<br>
+/// *'/admin/trash/partition/12/purge'* (partition, person or genre)<br>
+/// the files attached to a purged partition are removed from the disk
+///
+/// Returns a flash message and redirects to the trash page *'admin/trash'*
+///
+#[debug_handler]
+pub async fn purge_trash_hdl(
+    State(state): State<AppState>,
+    flash: Flash,
+    Extension(auth_jwt): Extension<JWTAuthMiddleware>,
+    Path((entity, id)): Path<(String, i32)>,
+) -> (Flash, Redirect) {
+    let redirect = Redirect::to("/admin/trash");
+    let entity = match check_trash_request(&auth_jwt, &entity) {
+        Ok(entity) => entity,
+        Err(message) => return (flash.error(message), redirect),
+    };
+    match purge_from_trash(entity, id, &state.pool).await {
+        Ok(item) => {
+            if entity == AuditEntity::Partition {
+                remove_partition_files(&state, id).await;
+            }
+            let change = AuditChange::purged(entity, id, snapshot(&item));
+            record_audit(&auth_jwt.user, change, &state.pool).await;
+            let message = format!(
+                "{} supprimé(e) définitivement : {}",
+                entity.label(),
+                item.name
+            );
+            (flash.success(message), redirect)
+        }
+        Err(err) => {
+            tracing::info!("error purging {:?} {} : {}", entity, id, err);
+            let message = format!("Pas supprimé : {}", err.message);
+            (flash.error(message), redirect)
+        }
+    }
+}
+
+///
+/// **Purges the trash automatically**<br>
+/// every hour, the records deleted more than TRASH_RETENTION_DAYS days ago
+/// are deleted for good (spawned by main)
+///
+pub async fn purge_expired_trash_task(state: AppState) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        match purge_expired(state.env.trash_retention_days, &state.pool).await {
+            Ok(items) => {
+                for item in items {
+                    if item.entity == AuditEntity::Partition {
+                        remove_partition_files(&state, item.id).await;
+                    }
+                    let change = AuditChange::purged(item.entity, item.id, snapshot(&item));
+                    record_system_audit(change, &state.pool).await;
+                }
+            }
+            Err(err) => tracing::info!("error purging the trash : {}", err),
+        }
+    }
+}
+
+/// time between two automatic purges of the trash
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+//*******************************************************************************
+// Helpers functions
+//
+
+///
+/// The DB rows of the attached files are deleted with the partition (ON DELETE CASCADE)
+/// but the files themselves must be removed from the disk
+///
+async fn remove_partition_files(state: &AppState, id: i32) {
+    if let Err(err) = storage::remove_partition_dir(&state.env.partition_files_root, id).await {
+        tracing::info!("error removing files of partition {} : {}", id, err);
+    }
+}
+
+/// the user must be an administrator and the kind of record known
+fn check_trash_request(auth_jwt: &JWTAuthMiddleware, entity: &str) -> Result<AuditEntity, String> {
+    if auth_jwt.user.role != "Administrateur" {
+        return Err("Hey ! Action only for Administrators".to_string());
+    }
+    entity.parse::<AuditEntity>()
+}
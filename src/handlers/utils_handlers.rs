@@ -5,6 +5,7 @@ use crate::askama::askama_tpl::{
     WelcomeTemplate,
 };
 use crate::authentication::auth_layer::JWTAuthMiddleware;
+use crate::db::statistics::catalogue_totals;
 use crate::db::users::list_users;
 use crate::errors::MyAppError;
 use crate::AppState;
@@ -59,7 +60,7 @@ pub async fn start_hdl() -> Result<StartTemplate, MyAppError> {
 ///
 #[debug_handler]
 pub async fn welcome_hdl(
-    State(_app): State<AppState>,
+    State(app): State<AppState>,
     in_flash: IncomingFlashes,
 ) -> Result<(IncomingFlashes, WelcomeTemplate), MyAppError> {
     let mut flash = String::new();
@@ -68,7 +69,12 @@ pub async fn welcome_hdl(
     }
     let flash = Some(flash);
     let title = "Commencer à travailler".to_string();
-    let template = WelcomeTemplate { title, flash };
+    let totals = catalogue_totals(&app.pool).await?;
+    let template = WelcomeTemplate {
+        title,
+        flash,
+        totals,
+    };
     Ok((in_flash, template))
 }
 
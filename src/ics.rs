@@ -0,0 +1,182 @@
+//! src/ics.rs
+//!
+//! iCalendar feed of the events (RFC 5545)
+//!
+//! The times of the events are the local times of the ensemble :
+//! they are written as "floating" times (no time zone), the calendar
+//! of the phone shows them at the same hour wherever it is.
+//! The lines are ended by CRLF and folded at 75 octets.
+//!
+
+use chrono::{NaiveDateTime, Utc};
+
+use crate::models::event::Event;
+use crate::models::setlist::SetlistItem;
+
+/// product identifier of the feed
+const PRODID: &str = "-//axum_simple//Calendrier de l'ensemble//FR";
+
+///
+/// An event of the feed with the pieces of its setlist (empty without setlist)
+///
+pub struct CalendarEvent {
+    pub event: Event,
+    pub items: Vec<SetlistItem>,
+}
+
+///
+/// **Writes the iCalendar of the events**<br>
+/// * name : the name of the calendar shown by the phones (X-WR-CALNAME)
+/// * host : the domain of the unique identifiers of the events
+///
+pub fn calendar_ics(name: &str, host: &str, events: &[CalendarEvent]) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut ics = String::new();
+    push_line(&mut ics, "BEGIN:VCALENDAR");
+    push_line(&mut ics, "VERSION:2.0");
+    push_line(&mut ics, &format!("PRODID:{PRODID}"));
+    push_line(&mut ics, "CALSCALE:GREGORIAN");
+    push_line(&mut ics, "METHOD:PUBLISH");
+    push_line(&mut ics, &format!("X-WR-CALNAME:{}", escape_text(name)));
+
+    for CalendarEvent { event, items } in events {
+        push_line(&mut ics, "BEGIN:VEVENT");
+        push_line(&mut ics, &format!("UID:event-{}@{}", event.id, host));
+        push_line(&mut ics, &format!("DTSTAMP:{stamp}"));
+        push_line(&mut ics, &format!("DTSTART:{}", ics_time(event.starts_at)));
+        push_line(&mut ics, &format!("DTEND:{}", ics_time(event.ends_at())));
+        push_line(
+            &mut ics,
+            &format!("SUMMARY:{}", escape_text(&event.summary())),
+        );
+        if !event.place.is_empty() {
+            push_line(&mut ics, &format!("LOCATION:{}", escape_text(&event.place)));
+        }
+        if let Some(description) = description(event, items) {
+            push_line(
+                &mut ics,
+                &format!("DESCRIPTION:{}", escape_text(&description)),
+            );
+        }
+        push_line(&mut ics, "END:VEVENT");
+    }
+    push_line(&mut ics, "END:VCALENDAR");
+    ics
+}
+
+///
+/// The programme of the event : the name of the setlist and its pieces
+///
+fn description(event: &Event, items: &[SetlistItem]) -> Option<String> {
+    let setlist_name = event.setlist_name.as_deref()?;
+    let mut lines = vec![format!("Programme : {setlist_name}")];
+    lines.extend(
+        items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| format!("{}. {} ({})", index + 1, item.title, item.full_name)),
+    );
+    Some(lines.join("\n"))
+}
+
+/// floating local time : 20230714T200000
+fn ics_time(time: NaiveDateTime) -> String {
+    time.format("%Y%m%dT%H%M%S").to_string()
+}
+
+///
+/// Escapes a TEXT value : backslash, semicolon, comma and new lines
+///
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+///
+/// Adds a content line folded at 75 octets (the next lines start with a space)
+/// a line is never cut inside a multi-bytes character
+///
+fn push_line(ics: &mut String, line: &str) {
+    let mut length = 0;
+    for c in line.chars() {
+        // the space at the start of a folded line is counted
+        if length + c.len_utf8() > 75 {
+            ics.push_str("\r\n ");
+            length = 1;
+        }
+        ics.push(c);
+        length += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pushed(line: &str) -> String {
+        let mut ics = String::new();
+        push_line(&mut ics, line);
+        ics
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(escape_text("Concert de Noël"), "Concert de Noël");
+        assert_eq!(
+            escape_text(r"Salle 2; église, place\nord"),
+            r"Salle 2\; église\, place\\nord"
+        );
+        assert_eq!(
+            escape_text("Kyrie\r\nGloria\nSanctus"),
+            "Kyrie\\nGloria\\nSanctus"
+        );
+    }
+
+    #[test]
+    fn short_lines_are_not_folded() {
+        assert_eq!(pushed("BEGIN:VEVENT"), "BEGIN:VEVENT\r\n");
+        let line = "X".repeat(75);
+        assert_eq!(pushed(&line), format!("{line}\r\n"));
+    }
+
+    #[test]
+    fn long_lines_are_folded_at_75_octets() {
+        let line = format!("SUMMARY:{}", "a".repeat(200));
+        let ics = pushed(&line);
+        let lines: Vec<&str> = ics.trim_end_matches("\r\n").split("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].len(), 75);
+        assert!(lines[1..].iter().all(|folded| folded.starts_with(' ')));
+        assert!(lines.iter().all(|folded| folded.len() <= 75));
+        assert_eq!(ics.replace("\r\n ", ""), format!("{line}\r\n"));
+    }
+
+    #[test]
+    fn multi_bytes_characters_are_not_cut() {
+        let line = format!("LOCATION:{}", "é".repeat(60));
+        let ics = pushed(&line);
+        for folded in ics.trim_end_matches("\r\n").split("\r\n") {
+            assert!(folded.len() <= 75);
+        }
+        // 9 octets of "LOCATION:" and 33 "é" of 2 octets : 75 octets
+        assert!(ics.starts_with(&format!("LOCATION:{}\r\n é", "é".repeat(33))));
+        assert_eq!(ics.replace("\r\n ", ""), format!("{line}\r\n"));
+    }
+
+    #[test]
+    fn floating_local_time() {
+        let time = NaiveDateTime::parse_from_str("2023-07-14 20:00", "%Y-%m-%d %H:%M").unwrap();
+        assert_eq!(ics_time(time), "20230714T200000");
+    }
+}
@@ -10,6 +10,7 @@ use axum_flash::Key;
 
 use crate::config::Config;
 use crate::db::connect::{create_pg_pool, create_redis_client};
+use crate::handlers::trash_handlers::purge_expired_trash_task;
 use crate::routers::create_routers;
 use axum_core::response::Response;
 use redis::Client;
@@ -19,16 +20,20 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 pub mod askama;
 pub mod authentication;
+mod catalogue_pdf;
 mod config;
 mod ctx;
 mod db;
 mod errors;
+mod export;
 mod globals;
 mod handlers;
+mod ics;
 mod log;
 mod models;
 mod print_req_res;
 mod routers;
+mod storage;
 //mod sessions;
 
 #[tokio::main]
@@ -88,6 +93,11 @@ async fn main() -> anyhow::Result<()> {
         redis_client,
     };
 
+    //*******************************************************
+    // Purging the trash (the records deleted a long time ago)
+    // in the background
+    tokio::spawn(purge_expired_trash_task(state.clone()));
+
     //*******************************************************
     // Creating the routers
     let app = create_routers(state);
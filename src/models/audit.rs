@@ -0,0 +1,407 @@
+//! src/models/audit.rs
+//!
+//! Audit log of the changes of the catalogue (partitions, musicians, genres) :
+//! who made the change, when, and the record before and after it (JSON)
+//!
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+///
+/// # AuditAction
+/// What was done (Postgres enum audit_action)
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, sqlx::Type)]
+#[sqlx(type_name = "audit_action", rename_all = "lowercase")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Import,
+    Restore,
+    Purge,
+    Merge,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 7] = [
+        AuditAction::Create,
+        AuditAction::Update,
+        AuditAction::Delete,
+        AuditAction::Import,
+        AuditAction::Restore,
+        AuditAction::Purge,
+        AuditAction::Merge,
+    ];
+
+    /// value used in the DB and the forms
+    pub fn value(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Import => "import",
+            AuditAction::Restore => "restore",
+            AuditAction::Purge => "purge",
+            AuditAction::Merge => "merge",
+        }
+    }
+
+    /// label shown in the templates
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "Création",
+            AuditAction::Update => "Modification",
+            AuditAction::Delete => "Suppression",
+            AuditAction::Import => "Import CSV",
+            AuditAction::Restore => "Restauration",
+            AuditAction::Purge => "Suppression définitive",
+            AuditAction::Merge => "Fusion",
+        }
+    }
+
+    /// used in the templates to select the current option
+    pub fn is(&self, current: Option<AuditAction>) -> bool {
+        current == Some(*self)
+    }
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+impl FromStr for AuditAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AuditAction::ALL
+            .into_iter()
+            .find(|action| action.value() == s.trim().to_lowercase())
+            .ok_or_else(|| format!("action inconnue : {s}"))
+    }
+}
+
+///
+/// # AuditEntity
+/// The kind of record changed (Postgres enum audit_entity)
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "audit_entity", rename_all = "lowercase")]
+pub enum AuditEntity {
+    Partition,
+    Person,
+    Genre,
+}
+
+impl AuditEntity {
+    pub const ALL: [AuditEntity; 3] = [
+        AuditEntity::Partition,
+        AuditEntity::Person,
+        AuditEntity::Genre,
+    ];
+
+    /// value used in the DB and the forms
+    pub fn value(&self) -> &'static str {
+        match self {
+            AuditEntity::Partition => "partition",
+            AuditEntity::Person => "person",
+            AuditEntity::Genre => "genre",
+        }
+    }
+
+    /// label shown in the templates
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditEntity::Partition => "Partition",
+            AuditEntity::Person => "Musicien",
+            AuditEntity::Genre => "Genre",
+        }
+    }
+
+    /// used in the templates to select the current option
+    pub fn is(&self, current: Option<AuditEntity>) -> bool {
+        current == Some(*self)
+    }
+}
+
+impl fmt::Display for AuditEntity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+impl FromStr for AuditEntity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AuditEntity::ALL
+            .into_iter()
+            .find(|entity| entity.value() == s.trim().to_lowercase())
+            .ok_or_else(|| format!("type d'enregistrement inconnu : {s}"))
+    }
+}
+
+///
+/// # AuditChange
+/// A change of the catalogue to record
+/// * entity_id : None when the change is not about one record (an import)
+/// * before, after : the record before and after the change
+///   (no before for a creation, no after for a deletion)
+///
+#[derive(Debug, Clone)]
+pub struct AuditChange {
+    pub action: AuditAction,
+    pub entity: AuditEntity,
+    pub entity_id: Option<i32>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl AuditChange {
+    pub fn created(entity: AuditEntity, entity_id: i32, after: Option<Value>) -> Self {
+        Self {
+            action: AuditAction::Create,
+            entity,
+            entity_id: Some(entity_id),
+            before: None,
+            after,
+        }
+    }
+
+    pub fn updated(
+        entity: AuditEntity,
+        entity_id: i32,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) -> Self {
+        Self {
+            action: AuditAction::Update,
+            entity,
+            entity_id: Some(entity_id),
+            before,
+            after,
+        }
+    }
+
+    pub fn deleted(entity: AuditEntity, entity_id: i32, before: Option<Value>) -> Self {
+        Self {
+            action: AuditAction::Delete,
+            entity,
+            entity_id: Some(entity_id),
+            before,
+            after: None,
+        }
+    }
+
+    /// a record restored from the trash
+    pub fn restored(entity: AuditEntity, entity_id: i32, after: Option<Value>) -> Self {
+        Self {
+            action: AuditAction::Restore,
+            entity,
+            entity_id: Some(entity_id),
+            before: None,
+            after,
+        }
+    }
+
+    /// a record purged from the trash (deleted for good)
+    pub fn purged(entity: AuditEntity, entity_id: i32, before: Option<Value>) -> Self {
+        Self {
+            action: AuditAction::Purge,
+            entity,
+            entity_id: Some(entity_id),
+            before,
+            after: None,
+        }
+    }
+
+    ///
+    /// a musician or a genre merged into another one and deleted :
+    /// before is the merged record, after the one that remains
+    ///
+    pub fn merged(
+        entity: AuditEntity,
+        entity_id: i32,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) -> Self {
+        Self {
+            action: AuditAction::Merge,
+            entity,
+            entity_id: Some(entity_id),
+            before,
+            after,
+        }
+    }
+
+    /// an import of partitions from a CSV file : after is the summary of the import
+    pub fn imported(after: Option<Value>) -> Self {
+        Self {
+            action: AuditAction::Import,
+            entity: AuditEntity::Partition,
+            entity_id: None,
+            before: None,
+            after,
+        }
+    }
+}
+
+///
+/// # AuditEntry
+/// A recorded change
+///
+/// * actor_id : the user who made the change (None if the user was deleted since)
+/// * actor_name : the name of the user at the time of the change
+/// * before, after : the JSON of the record (as text)
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor_id: Option<Uuid>,
+    pub actor_name: String,
+    pub action: AuditAction,
+    pub entity: AuditEntity,
+    pub entity_id: Option<i32>,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditEntry {
+    pub fn created_text(&self) -> String {
+        self.created_at.format("%d/%m/%Y %H:%M:%S").to_string()
+    }
+
+    ///
+    /// Returns the fields changed between before and after (the first level
+    /// of the JSON objects), with their values before and after ("" if absent)
+    ///
+    pub fn changes(&self) -> Vec<FieldChange> {
+        let before = parse_object(self.before.as_deref());
+        let after = parse_object(self.after.as_deref());
+        let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+        fields.sort();
+        fields.dedup();
+        fields
+            .into_iter()
+            .filter(|field| before.get(*field) != after.get(*field))
+            .map(|field| FieldChange {
+                field: field.clone(),
+                before: before.get(field).map(value_text).unwrap_or_default(),
+                after: after.get(field).map(value_text).unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+///
+/// A field changed by a change : its values before and after as text
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// the JSON object of a record (empty if there is no record or it is not an object)
+fn parse_object(json: Option<&str>) -> serde_json::Map<String, Value> {
+    json.and_then(|json| serde_json::from_str::<Value>(json).ok())
+        .and_then(|value| match value {
+            Value::Object(object) => Some(object),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// a value shown in the page : the strings without their quotes
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+///
+/// # AuditFilter
+/// Criteria of the audit page (query string)
+///
+/// * actor_id : the changes of a user
+/// * action, entity : "update", "partition" ... empty for all
+/// * entity_id : the changes of one record
+/// * from, to : the days of the changes ("2023-07-14", both included)
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuditFilter {
+    pub actor_id: Option<String>,
+    pub action: Option<String>,
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl AuditFilter {
+    pub fn actor_id(&self) -> Option<Uuid> {
+        Uuid::try_parse(self.actor_id.as_deref()?.trim()).ok()
+    }
+
+    pub fn action(&self) -> Option<AuditAction> {
+        self.action.as_deref()?.parse().ok()
+    }
+
+    pub fn entity(&self) -> Option<AuditEntity> {
+        self.entity.as_deref()?.parse().ok()
+    }
+
+    pub fn entity_id(&self) -> Option<i32> {
+        self.entity_id.as_deref()?.trim().parse().ok()
+    }
+
+    pub fn from(&self) -> Option<NaiveDate> {
+        parse_date(self.from.as_deref())
+    }
+
+    pub fn to(&self) -> Option<NaiveDate> {
+        parse_date(self.to.as_deref())
+    }
+
+    ///
+    /// Returns the criteria as (name, value) to keep them in the links of the page
+    ///
+    pub fn query_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if let Some(actor_id) = self.actor_id() {
+            pairs.push(("actor_id", actor_id.to_string()));
+        }
+        if let Some(action) = self.action() {
+            pairs.push(("action", action.value().to_string()));
+        }
+        if let Some(entity) = self.entity() {
+            pairs.push(("entity", entity.value().to_string()));
+        }
+        if let Some(entity_id) = self.entity_id() {
+            pairs.push(("entity_id", entity_id.to_string()));
+        }
+        if let Some(from) = self.from() {
+            pairs.push(("from", from.format("%Y-%m-%d").to_string()));
+        }
+        if let Some(to) = self.to() {
+            pairs.push(("to", to.format("%Y-%m-%d").to_string()));
+        }
+        pairs
+    }
+}
+
+fn parse_date(date: Option<&str>) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date?.trim(), "%Y-%m-%d").ok()
+}
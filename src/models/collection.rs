@@ -0,0 +1,95 @@
+//! src/models/collection.rs
+//!
+//! Favourites (the partitions starred by a user) and personal collections :
+//! named lists of partitions of a user ("mes solos", "à apprendre" ...),
+//! a shared collection can be read by the other users
+//!
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+///
+/// # Collection
+/// A named list of partitions of a user
+///
+/// * owner_name : the name of the user (joined for the pages)
+/// * shared : the other users can read it (not modify it)
+/// * partitions_count : the number of partitions in the collection
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct Collection {
+    pub id: i32,
+    pub user_id: Uuid,
+    pub owner_name: String,
+    pub name: String,
+    pub shared: bool,
+    pub partitions_count: i64,
+}
+
+impl Collection {
+    pub fn is_owner(&self, user_id: Uuid) -> bool {
+        self.user_id == user_id
+    }
+}
+
+///
+/// # CollectionPartition
+/// A partition in the favourites or in a collection
+/// * added_at : when the partition was starred or added to the collection
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct CollectionPartition {
+    pub partition_id: i32,
+    pub title: String,
+    pub full_name: String,
+    pub genre: String,
+    pub added_at: DateTime<Utc>,
+}
+
+impl CollectionPartition {
+    pub fn added_text(&self) -> String {
+        self.added_at.format("%d/%m/%Y").to_string()
+    }
+}
+
+///
+/// # CollectionForm
+/// Fields of the forms to create or modify a collection
+/// * shared : the checkbox is only sent when it is checked
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct CollectionForm {
+    pub name: String,
+    pub shared: Option<String>,
+}
+
+impl CollectionForm {
+    ///
+    /// Reads the name and the sharing
+    /// returns an error message if the name is empty or too long
+    ///
+    pub fn parse(&self) -> Result<(String, bool), String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("le nom est vide".to_string());
+        }
+        if name.chars().count() > 100 {
+            return Err("le nom est trop long (100 caractères au plus)".to_string());
+        }
+        Ok((name.to_string(), self.shared.is_some()))
+    }
+}
+
+///
+/// Form to add a partition to a collection
+/// (the collection is chosen in a select of the partitions page)
+/// * back : the page to come back to
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct CollectionItemForm {
+    pub collection_id: i32,
+    pub partition_id: i32,
+    pub back: Option<String>,
+}
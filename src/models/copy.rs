@@ -0,0 +1,251 @@
+//! src/models/copy.rs
+//!
+//! Printed copies of the partitions (location, condition, purchase date)
+//! and inventory audits of the copies
+//!
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::fmt;
+use std::str::FromStr;
+
+///
+/// # CopyCondition
+/// Condition of a copy (Postgres enum copy_condition)
+/// a missing copy can't be lent
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "copy_condition", rename_all = "lowercase")]
+pub enum CopyCondition {
+    Good,
+    Damaged,
+    Missing,
+}
+
+impl CopyCondition {
+    pub const ALL: [CopyCondition; 3] = [
+        CopyCondition::Good,
+        CopyCondition::Damaged,
+        CopyCondition::Missing,
+    ];
+
+    /// value used in the DB and the forms
+    pub fn value(&self) -> &'static str {
+        match self {
+            CopyCondition::Good => "good",
+            CopyCondition::Damaged => "damaged",
+            CopyCondition::Missing => "missing",
+        }
+    }
+
+    /// label shown in the templates
+    pub fn label(&self) -> &'static str {
+        match self {
+            CopyCondition::Good => "bon état",
+            CopyCondition::Damaged => "abîmé",
+            CopyCondition::Missing => "manquant",
+        }
+    }
+}
+
+impl fmt::Display for CopyCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+impl FromStr for CopyCondition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CopyCondition::ALL
+            .into_iter()
+            .find(|condition| condition.value() == s.trim().to_lowercase())
+            .ok_or_else(|| format!("état inconnu : {s}"))
+    }
+}
+
+///
+/// # PartitionCopy
+/// A printed copy of a partition
+///
+/// * id : the inventory number written on the copy
+/// * title : the title of the partition (joined for the pages)
+/// * location : the shelf or the box ("Étagère A / Boîte 12")
+/// * lent : true if the copy is lent (open loan)
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct PartitionCopy {
+    pub id: i32,
+    pub partition_id: i32,
+    pub title: String,
+    pub location: String,
+    pub condition: CopyCondition,
+    pub purchase_date: Option<NaiveDate>,
+    pub lent: bool,
+}
+
+impl PartitionCopy {
+    /// a copy can be lent if it's not lent nor missing
+    pub fn is_available(&self) -> bool {
+        !self.lent && self.condition != CopyCondition::Missing
+    }
+
+    /// the date for the input type="date" of the forms ("2023-07-14")
+    pub fn purchase_value(&self) -> String {
+        self.purchase_date
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    }
+}
+
+///
+/// # CopyForm
+/// Fields of the forms to add or modify copies
+/// * condition : "good", "damaged" or "missing"
+/// * purchase_date : "2023-07-14" (input type="date"), empty if unknown
+/// * count : the number of copies to add with these values (add form only)
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct CopyForm {
+    pub location: String,
+    pub condition: String,
+    pub purchase_date: String,
+    pub count: Option<String>,
+}
+
+///
+/// The values of a CopyForm, checked
+///
+#[derive(Debug, Clone)]
+pub struct CopyValues {
+    pub location: String,
+    pub condition: CopyCondition,
+    pub purchase_date: Option<NaiveDate>,
+}
+
+/// most copies added in one time
+pub const MAX_COPIES_ADDED: i32 = 100;
+
+impl CopyForm {
+    ///
+    /// Reads the values of the form
+    /// returns an error message if a field is invalid
+    ///
+    pub fn values(&self) -> Result<CopyValues, String> {
+        let purchase_date = match self.purchase_date.trim() {
+            "" => None,
+            date => Some(
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_| format!("date d'achat invalide : {date}"))?,
+            ),
+        };
+        Ok(CopyValues {
+            location: self.location.trim().to_string(),
+            condition: self.condition.parse()?,
+            purchase_date,
+        })
+    }
+
+    /// the number of copies to add : 1 if the field is empty
+    pub fn count(&self) -> Result<i32, String> {
+        match self.count.as_deref().map(str::trim) {
+            None | Some("") => Ok(1),
+            Some(count) => count
+                .parse()
+                .ok()
+                .filter(|count| (1..=MAX_COPIES_ADDED).contains(count))
+                .ok_or_else(|| {
+                    format!("nombre d'exemplaires invalide : {count} (1 à {MAX_COPIES_ADDED})")
+                }),
+        }
+    }
+}
+
+//*******************************************************************************
+// Inventory audits
+//
+
+///
+/// # InventoryAudit
+/// An inventory of the copies : the librarian marks the copies seen on the shelves
+///
+/// * started_by : the name of the user who started the audit
+/// * closed_at : None while the audit is in progress
+/// * seen : the number of copies seen
+/// * total : the number of copies (when the audit was closed)
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct InventoryAudit {
+    pub id: i32,
+    pub started_by: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub seen: i64,
+    pub total: i64,
+}
+
+impl InventoryAudit {
+    pub fn is_closed(&self) -> bool {
+        self.closed_at.is_some()
+    }
+
+    pub fn started_text(&self) -> String {
+        self.started_at.format("%d/%m/%Y %H:%M").to_string()
+    }
+
+    pub fn closed_text(&self) -> String {
+        self.closed_at
+            .map(|date| date.format("%d/%m/%Y %H:%M").to_string())
+            .unwrap_or_default()
+    }
+}
+
+///
+/// # AuditCopy
+/// A copy in an inventory audit
+/// * seen : true if the copy was marked as seen during the audit
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct AuditCopy {
+    pub id: i32,
+    pub partition_id: i32,
+    pub title: String,
+    pub location: String,
+    pub condition: CopyCondition,
+    pub lent: bool,
+    pub seen: bool,
+}
+
+///
+/// Form of the audit : the inventory numbers of the copies seen
+/// typed or scanned, separated by spaces or commas ("12 13, 14")
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeenForm {
+    pub copy_ids: String,
+}
+
+impl SeenForm {
+    ///
+    /// Returns the inventory numbers of the form (a number typed twice is kept once)
+    /// or an error message with the first invalid number
+    ///
+    pub fn copy_ids(&self) -> Result<Vec<i32>, String> {
+        let mut ids = self
+            .copy_ids
+            .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+            .filter(|id| !id.is_empty())
+            .map(|id| {
+                id.trim_start_matches('#')
+                    .parse()
+                    .map_err(|_| format!("numéro d'exemplaire invalide : {id}"))
+            })
+            .collect::<Result<Vec<i32>, String>>()?;
+        ids.sort_unstable();
+        ids.dedup();
+        Ok(ids)
+    }
+}
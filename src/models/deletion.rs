@@ -0,0 +1,144 @@
+//! src/models/deletion.rs
+//!
+//! Deletion of a musician or a genre still used by partitions :
+//! what would be affected and what to do with the partitions
+//!
+
+use serde::Deserialize;
+use sqlx::FromRow;
+
+use crate::models::audit::AuditEntity;
+use crate::models::loan::Loan;
+use crate::models::setlist::Setlist;
+
+///
+/// # DeletePolicy
+/// What to do with the partitions of a deleted musician or genre
+///
+/// * Block : the deletion is refused while partitions use the record
+/// * Reassign : the partitions go to another musician or genre (its id)
+/// * Cascade : the partitions go to the trash with the record
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeletePolicy {
+    Block,
+    Reassign(i32),
+    Cascade,
+}
+
+impl DeletePolicy {
+    /// the policies changing or deleting partitions are only for the administrators
+    pub fn is_admin_only(&self) -> bool {
+        !matches!(self, DeletePolicy::Block)
+    }
+}
+
+///
+/// # DeleteForm
+/// Form of the deletion page : policy is "block", "reassign" or "cascade"
+/// (empty : block), reassign_to is the id of the other musician or genre
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DeleteForm {
+    #[serde(default)]
+    pub policy: String,
+    #[serde(default)]
+    pub reassign_to: String,
+}
+
+impl DeleteForm {
+    pub fn policy(&self) -> Result<DeletePolicy, String> {
+        match self.policy.trim() {
+            "" | "block" => Ok(DeletePolicy::Block),
+            "cascade" => Ok(DeletePolicy::Cascade),
+            "reassign" => self
+                .reassign_to
+                .trim()
+                .parse()
+                .map(DeletePolicy::Reassign)
+                .map_err(|_| "choisissez à qui réattribuer les partitions".to_string()),
+            other => Err(format!("politique de suppression inconnue : {other}")),
+        }
+    }
+}
+
+///
+/// # ImpactPartition
+/// A partition using the record to delete
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct ImpactPartition {
+    pub id: i32,
+    pub title: String,
+}
+
+///
+/// # DeletionImpact
+/// What the deletion of a musician or a genre would affect :
+/// its partitions (not in the trash), their open loans and the setlists playing them
+///
+#[derive(Debug, Clone)]
+pub struct DeletionImpact {
+    pub entity: AuditEntity,
+    pub id: i32,
+    pub name: String,
+    pub partitions: Vec<ImpactPartition>,
+    pub loans: Vec<Loan>,
+    pub setlists: Vec<Setlist>,
+}
+
+impl DeletionImpact {
+    /// no partition uses the record : it can be deleted without choosing a policy
+    pub fn is_empty(&self) -> bool {
+        self.partitions.is_empty()
+    }
+
+    pub fn partition_ids(&self) -> Vec<i32> {
+        self.partitions
+            .iter()
+            .map(|partition| partition.id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form(policy: &str, reassign_to: &str) -> DeleteForm {
+        DeleteForm {
+            policy: policy.to_string(),
+            reassign_to: reassign_to.to_string(),
+        }
+    }
+
+    #[test]
+    fn policies_of_the_form() {
+        assert_eq!(form("", "").policy(), Ok(DeletePolicy::Block));
+        assert_eq!(form("block", "7").policy(), Ok(DeletePolicy::Block));
+        assert_eq!(form(" cascade ", "").policy(), Ok(DeletePolicy::Cascade));
+        assert_eq!(
+            form("reassign", " 7 ").policy(),
+            Ok(DeletePolicy::Reassign(7))
+        );
+    }
+
+    #[test]
+    fn invalid_policies() {
+        assert_eq!(
+            form("reassign", "").policy(),
+            Err("choisissez à qui réattribuer les partitions".to_string())
+        );
+        assert_eq!(
+            form("delete", "").policy(),
+            Err("politique de suppression inconnue : delete".to_string())
+        );
+    }
+
+    #[test]
+    fn only_block_is_for_every_user() {
+        assert!(!DeletePolicy::Block.is_admin_only());
+        assert!(DeletePolicy::Reassign(7).is_admin_only());
+        assert!(DeletePolicy::Cascade.is_admin_only());
+    }
+}
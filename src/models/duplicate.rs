@@ -0,0 +1,268 @@
+//! src/models/duplicate.rs
+//!
+//! Duplicate detection : the partitions, musicians and genres whose title or
+//! name is close to another one (trigram similarity of pg_trgm)
+//!
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashMap;
+
+use crate::models::audit::AuditEntity;
+
+/// the similarity (0 to 1) from which two titles or names are suspected duplicates
+pub const DUPLICATE_THRESHOLD: f64 = 0.5;
+
+///
+/// # DuplicateMatch
+/// A record close to the title or the name being added
+///
+/// * detail : the main musician of a partition (None for a musician or a genre)
+/// * similarity : 1 for the same words
+///
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct DuplicateMatch {
+    pub id: i32,
+    pub name: String,
+    pub detail: Option<String>,
+    pub similarity: f64,
+}
+
+impl DuplicateMatch {
+    pub fn similarity_text(&self) -> String {
+        format!("{:.0} %", self.similarity * 100.0)
+    }
+}
+
+///
+/// # DuplicatePair
+/// Two records of the same table suspected to be duplicates (first_id < second_id)
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct DuplicatePair {
+    pub first_id: i32,
+    pub first_name: String,
+    pub first_detail: Option<String>,
+    pub second_id: i32,
+    pub second_name: String,
+    pub second_detail: Option<String>,
+    pub similarity: f64,
+}
+
+///
+/// # DuplicateCluster
+/// Records linked by suspected duplicate pairs
+/// ("A" close to "B" and "B" close to "C" : one cluster A, B, C)
+///
+/// * members : sorted by id (the oldest record first)
+/// * similarity : the highest similarity of the pairs of the cluster
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateCluster {
+    pub entity: AuditEntity,
+    pub members: Vec<DuplicateMember>,
+    pub similarity: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateMember {
+    pub id: i32,
+    pub name: String,
+    pub detail: Option<String>,
+}
+
+impl DuplicateCluster {
+    ///
+    /// Groups the pairs of one table into clusters (union-find on the ids),
+    /// the largest clusters first
+    ///
+    pub fn from_pairs(entity: AuditEntity, pairs: Vec<DuplicatePair>) -> Vec<DuplicateCluster> {
+        let mut parents: HashMap<i32, i32> = HashMap::new();
+        let mut members: HashMap<i32, DuplicateMember> = HashMap::new();
+        for pair in &pairs {
+            let first = find_root(&mut parents, pair.first_id);
+            let second = find_root(&mut parents, pair.second_id);
+            if first != second {
+                parents.insert(first.max(second), first.min(second));
+            }
+            for (id, name, detail) in [
+                (pair.first_id, &pair.first_name, &pair.first_detail),
+                (pair.second_id, &pair.second_name, &pair.second_detail),
+            ] {
+                members.entry(id).or_insert_with(|| DuplicateMember {
+                    id,
+                    name: name.clone(),
+                    detail: detail.clone(),
+                });
+            }
+        }
+
+        let mut clusters: HashMap<i32, DuplicateCluster> = HashMap::new();
+        let mut ids: Vec<i32> = members.keys().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            let root = find_root(&mut parents, id);
+            let cluster = clusters.entry(root).or_insert_with(|| DuplicateCluster {
+                entity,
+                members: Vec::new(),
+                similarity: 0.0,
+            });
+            if let Some(member) = members.remove(&id) {
+                cluster.members.push(member);
+            }
+        }
+        for pair in &pairs {
+            let root = find_root(&mut parents, pair.first_id);
+            if let Some(cluster) = clusters.get_mut(&root) {
+                cluster.similarity = cluster.similarity.max(pair.similarity);
+            }
+        }
+
+        let mut clusters: Vec<DuplicateCluster> = clusters.into_values().collect();
+        clusters.sort_by(|a, b| {
+            b.members
+                .len()
+                .cmp(&a.members.len())
+                .then(a.members[0].id.cmp(&b.members[0].id))
+        });
+        clusters
+    }
+
+    pub fn similarity_text(&self) -> String {
+        format!("{:.0} %", self.similarity * 100.0)
+    }
+}
+
+///
+/// # MergeForm
+/// Form to merge two musicians or two genres :
+/// the partitions of the loser go to the survivor, then the loser is deleted
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct MergeForm {
+    pub loser_id: i32,
+    pub survivor_id: i32,
+}
+
+///
+/// # MergedRecord
+/// A musician or a genre of a merge (id and name)
+///
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct MergedRecord {
+    pub id: i32,
+    pub name: String,
+}
+
+///
+/// # MergeReport
+/// The result of a merge
+/// * partition_ids : the partitions moved from the loser to the survivor
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeReport {
+    pub entity: AuditEntity,
+    pub loser: MergedRecord,
+    pub survivor: MergedRecord,
+    pub partition_ids: Vec<i32>,
+}
+
+///
+/// Removes the field "confirm" of a form and returns true if it was there :
+/// the user chose to add the record despite the suspected duplicates
+///
+pub fn take_confirmation(fields: &mut Vec<(String, String)>) -> bool {
+    let count = fields.len();
+    fields.retain(|(key, _)| key != "confirm");
+    fields.len() != count
+}
+
+/// the root of the cluster of an id (with path compression)
+fn find_root(parents: &mut HashMap<i32, i32>, id: i32) -> i32 {
+    let parent = *parents.entry(id).or_insert(id);
+    if parent == id {
+        return id;
+    }
+    let root = find_root(parents, parent);
+    parents.insert(id, root);
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(first_id: i32, second_id: i32, similarity: f64) -> DuplicatePair {
+        DuplicatePair {
+            first_id,
+            first_name: format!("nom {first_id}"),
+            first_detail: None,
+            second_id,
+            second_name: format!("nom {second_id}"),
+            second_detail: Some(format!("détail {second_id}")),
+            similarity,
+        }
+    }
+
+    fn ids(cluster: &DuplicateCluster) -> Vec<i32> {
+        cluster.members.iter().map(|member| member.id).collect()
+    }
+
+    #[test]
+    fn no_pairs_no_clusters() {
+        assert!(DuplicateCluster::from_pairs(AuditEntity::Person, Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn linked_pairs_make_one_cluster() {
+        let pairs = vec![
+            pair(7, 8, 0.6),
+            pair(1, 2, 0.55),
+            pair(2, 3, 0.9),
+            pair(4, 9, 0.7),
+            pair(3, 9, 0.5),
+        ];
+        let clusters = DuplicateCluster::from_pairs(AuditEntity::Genre, pairs);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(ids(&clusters[0]), vec![1, 2, 3, 4, 9]);
+        assert_eq!(clusters[0].similarity, 0.9);
+        assert_eq!(clusters[0].entity, AuditEntity::Genre);
+        assert_eq!(ids(&clusters[1]), vec![7, 8]);
+        assert_eq!(clusters[1].similarity_text(), "60 %");
+    }
+
+    #[test]
+    fn clusters_of_the_same_size_are_sorted_by_oldest_id() {
+        let pairs = vec![pair(10, 11, 0.8), pair(3, 5, 0.6), pair(5, 10, 0.5)];
+        let clusters = DuplicateCluster::from_pairs(AuditEntity::Partition, pairs);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(ids(&clusters[0]), vec![3, 5, 10, 11]);
+
+        let pairs = vec![pair(10, 11, 0.8), pair(3, 5, 0.6)];
+        let clusters = DuplicateCluster::from_pairs(AuditEntity::Partition, pairs);
+        assert_eq!(ids(&clusters[0]), vec![3, 5]);
+        assert_eq!(ids(&clusters[1]), vec![10, 11]);
+    }
+
+    #[test]
+    fn members_keep_their_name_and_detail() {
+        let clusters = DuplicateCluster::from_pairs(AuditEntity::Partition, vec![pair(1, 2, 0.7)]);
+        let members = &clusters[0].members;
+        assert_eq!(members[0].name, "nom 1");
+        assert_eq!(members[0].detail, None);
+        assert_eq!(members[1].name, "nom 2");
+        assert_eq!(members[1].detail.as_deref(), Some("détail 2"));
+    }
+
+    #[test]
+    fn confirmation_is_taken_from_the_form() {
+        let mut fields = vec![
+            ("title".to_string(), "Ave Maria".to_string()),
+            ("confirm".to_string(), "1".to_string()),
+        ];
+        assert!(take_confirmation(&mut fields));
+        assert_eq!(fields, vec![("title".to_string(), "Ave Maria".to_string())]);
+        assert!(!take_confirmation(&mut fields));
+        assert_eq!(fields.len(), 1);
+    }
+}
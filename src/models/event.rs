@@ -0,0 +1,334 @@
+//! src/models/event.rs
+//!
+//! Events of the ensemble (rehearsals and concerts)
+//! and the monthly calendar of the events
+//!
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::Deserialize;
+use sqlx::FromRow;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::models::loan::today;
+
+/// duration proposed in the event form (minutes)
+pub const DEFAULT_EVENT_MINUTES: i32 = 120;
+
+///
+/// # EventKind
+/// Kind of an event (Postgres enum event_kind)
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, sqlx::Type)]
+#[sqlx(type_name = "event_kind", rename_all = "lowercase")]
+pub enum EventKind {
+    Rehearsal,
+    Concert,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 2] = [EventKind::Rehearsal, EventKind::Concert];
+
+    /// value used in the DB and the forms
+    pub fn value(&self) -> &'static str {
+        match self {
+            EventKind::Rehearsal => "rehearsal",
+            EventKind::Concert => "concert",
+        }
+    }
+
+    /// label shown in the templates and the calendars
+    pub fn label(&self) -> &'static str {
+        match self {
+            EventKind::Rehearsal => "Répétition",
+            EventKind::Concert => "Concert",
+        }
+    }
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+impl FromStr for EventKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EventKind::ALL
+            .into_iter()
+            .find(|kind| kind.value() == s.trim().to_lowercase())
+            .ok_or_else(|| format!("type d'événement inconnu : {s}"))
+    }
+}
+
+///
+/// # Event
+/// A rehearsal or a concert
+///
+/// * title : optional ("Concert de Noël"), the kind is shown when empty
+/// * starts_at : the local time of the ensemble
+/// * setlist_id, setlist_name : the setlist played (joined for the pages)
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct Event {
+    pub id: i32,
+    pub kind: EventKind,
+    pub title: String,
+    pub starts_at: NaiveDateTime,
+    pub duration_minutes: i32,
+    pub place: String,
+    pub setlist_id: Option<i32>,
+    pub setlist_name: Option<String>,
+}
+
+impl Event {
+    pub fn ends_at(&self) -> NaiveDateTime {
+        self.starts_at + Duration::minutes(self.duration_minutes as i64)
+    }
+
+    /// "Concert : Concert de Noël" or "Répétition"
+    pub fn summary(&self) -> String {
+        if self.title.is_empty() {
+            self.kind.label().to_string()
+        } else {
+            format!("{} : {}", self.kind.label(), self.title)
+        }
+    }
+
+    pub fn date_text(&self) -> String {
+        self.starts_at.format("%d/%m/%Y").to_string()
+    }
+
+    /// "20:00 - 22:00"
+    pub fn hours_text(&self) -> String {
+        format!(
+            "{} - {}",
+            self.starts_at.format("%H:%M"),
+            self.ends_at().format("%H:%M")
+        )
+    }
+
+    /// the date for the input type="date" of the forms ("2023-07-14")
+    pub fn date_value(&self) -> String {
+        self.starts_at.format("%Y-%m-%d").to_string()
+    }
+
+    /// the time for the input type="time" of the forms ("20:00")
+    pub fn time_value(&self) -> String {
+        self.starts_at.format("%H:%M").to_string()
+    }
+
+    /// the month of the event for the links to the calendar ("2023-07")
+    pub fn month_value(&self) -> String {
+        self.starts_at.format("%Y-%m").to_string()
+    }
+}
+
+///
+/// # EventForm
+/// Fields of the forms to create or modify an event
+/// * kind : "rehearsal" or "concert"
+/// * date : "2023-07-14" (input type="date"), time : "20:00" (input type="time")
+/// * duration : in minutes
+/// * setlist_id : empty for no setlist
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventForm {
+    pub kind: String,
+    pub title: String,
+    pub date: String,
+    pub time: String,
+    pub duration: String,
+    pub place: String,
+    pub setlist_id: String,
+}
+
+///
+/// The values of an EventForm, checked
+///
+#[derive(Debug, Clone)]
+pub struct EventValues {
+    pub kind: EventKind,
+    pub title: String,
+    pub starts_at: NaiveDateTime,
+    pub duration_minutes: i32,
+    pub place: String,
+    pub setlist_id: Option<i32>,
+}
+
+impl EventForm {
+    ///
+    /// Reads the values of the form
+    /// returns an error message if a field is invalid
+    ///
+    pub fn values(&self) -> Result<EventValues, String> {
+        let date = NaiveDate::parse_from_str(self.date.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("date invalide : {}", self.date))?;
+        let time = NaiveTime::parse_from_str(self.time.trim(), "%H:%M")
+            .map_err(|_| format!("heure invalide : {}", self.time))?;
+        let duration_minutes = self
+            .duration
+            .trim()
+            .parse()
+            .ok()
+            .filter(|minutes| *minutes > 0)
+            .ok_or_else(|| format!("durée invalide : {} (en minutes)", self.duration))?;
+        let setlist_id = match self.setlist_id.trim() {
+            "" => None,
+            id => Some(id.parse().map_err(|_| format!("setlist inconnue : {id}"))?),
+        };
+        Ok(EventValues {
+            kind: self.kind.parse()?,
+            title: self.title.trim().to_string(),
+            starts_at: date.and_time(time),
+            duration_minutes,
+            place: self.place.trim().to_string(),
+            setlist_id,
+        })
+    }
+}
+
+//*******************************************************************************
+// Monthly calendar
+//
+
+const MONTHS: [&str; 12] = [
+    "janvier",
+    "février",
+    "mars",
+    "avril",
+    "mai",
+    "juin",
+    "juillet",
+    "août",
+    "septembre",
+    "octobre",
+    "novembre",
+    "décembre",
+];
+
+///
+/// The month asked in the query string of the calendar : *'?month=2023-07'*
+/// (default : the current month)
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MonthParams {
+    pub month: Option<String>,
+}
+
+impl MonthParams {
+    /// the first day of the month
+    pub fn first_day(&self) -> NaiveDate {
+        self.month
+            .as_deref()
+            .and_then(|month| {
+                NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d").ok()
+            })
+            .unwrap_or_else(|| today().with_day(1).unwrap_or_else(today))
+    }
+}
+
+///
+/// # CalendarDay
+/// A day of the calendar with its events
+/// * in_month : false for the days of the previous and next months
+///   shown to complete the first and last weeks
+///
+#[derive(Debug, Clone)]
+pub struct CalendarDay {
+    pub date: NaiveDate,
+    pub in_month: bool,
+    pub is_today: bool,
+    pub events: Vec<Event>,
+}
+
+impl CalendarDay {
+    pub fn day(&self) -> u32 {
+        self.date.day()
+    }
+}
+
+///
+/// # CalendarMonth
+/// The weeks of a month, from monday to sunday
+///
+#[derive(Debug, Clone)]
+pub struct CalendarMonth {
+    pub first_day: NaiveDate,
+    pub weeks: Vec<Vec<CalendarDay>>,
+}
+
+impl CalendarMonth {
+    ///
+    /// Builds the weeks of the month of first_day
+    /// with the events of the month (sorted by date)
+    ///
+    pub fn new(first_day: NaiveDate, events: &[Event]) -> CalendarMonth {
+        let start = first_day - Duration::days(first_day.weekday().num_days_from_monday() as i64);
+        let next_month = first_day_of_next_month(first_day);
+        let today = today();
+
+        let mut weeks = Vec::new();
+        let mut date = start;
+        while date < next_month {
+            let week = (0..7)
+                .map(|offset| {
+                    let day = date + Duration::days(offset);
+                    CalendarDay {
+                        date: day,
+                        in_month: day.month() == first_day.month(),
+                        is_today: day == today,
+                        events: events
+                            .iter()
+                            .filter(|event| event.starts_at.date() == day)
+                            .cloned()
+                            .collect(),
+                    }
+                })
+                .collect();
+            weeks.push(week);
+            date += Duration::days(7);
+        }
+        CalendarMonth { first_day, weeks }
+    }
+
+    /// "juillet 2023"
+    pub fn label(&self) -> String {
+        format!(
+            "{} {}",
+            MONTHS[self.first_day.month0() as usize],
+            self.first_day.year()
+        )
+    }
+
+    /// the month for the links and the queries ("2023-07")
+    pub fn value(&self) -> String {
+        self.first_day.format("%Y-%m").to_string()
+    }
+
+    pub fn previous_value(&self) -> String {
+        (self.first_day - Duration::days(1))
+            .format("%Y-%m")
+            .to_string()
+    }
+
+    pub fn next_value(&self) -> String {
+        first_day_of_next_month(self.first_day)
+            .format("%Y-%m")
+            .to_string()
+    }
+}
+
+///
+/// Returns the first day of the month after the month of the date
+///
+pub fn first_day_of_next_month(date: NaiveDate) -> NaiveDate {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(date)
+}
@@ -0,0 +1,178 @@
+//! src/models/facet.rs
+//!
+//! Faceted browsing of the partitions : the number of partitions per genre,
+//! composer, key and difficulty among the partitions of the current criteria ;
+//! each value is a link that narrows the criteria (a second click removes it)
+//!
+
+use serde::Serialize;
+use sqlx::FromRow;
+
+use crate::models::pagination::encode_query_value;
+use crate::models::partition::{Difficulty, MusicalKey, PartitionFilter};
+
+/// the url of the browse page
+pub const BROWSE_PATH: &str = "/api/partitions/browse";
+
+///
+/// # FacetKind
+/// The criteria of the browse page counted by value
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FacetKind {
+    Genre,
+    Composer,
+    Key,
+    Difficulty,
+}
+
+impl FacetKind {
+    pub const ALL: [FacetKind; 4] = [
+        FacetKind::Genre,
+        FacetKind::Composer,
+        FacetKind::Key,
+        FacetKind::Difficulty,
+    ];
+
+    /// the name of the criterion in the query string (PartitionFilter)
+    pub fn param(&self) -> &'static str {
+        match self {
+            FacetKind::Genre => "genre",
+            FacetKind::Composer => "composer",
+            FacetKind::Key => "key",
+            FacetKind::Difficulty => "difficulty",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FacetKind::Genre => "Genre",
+            FacetKind::Composer => "Compositeur",
+            FacetKind::Key => "Tonalité",
+            FacetKind::Difficulty => "Niveau",
+        }
+    }
+
+    /// the value of the criterion chosen in the filter (None : all the values)
+    pub fn selected(&self, filter: &PartitionFilter) -> Option<String> {
+        match self {
+            FacetKind::Genre => filter.genre().map(|id| id.to_string()),
+            FacetKind::Composer => filter.composer().map(|id| id.to_string()),
+            FacetKind::Key => filter.key().map(|key| key.value().to_string()),
+            FacetKind::Difficulty => filter.difficulty().map(|d| d.value().to_string()),
+        }
+    }
+
+    /// the filter with another value of the criterion (None : without the criterion)
+    pub fn with_value(&self, filter: &PartitionFilter, value: Option<String>) -> PartitionFilter {
+        let mut filter = filter.clone();
+        match self {
+            FacetKind::Genre => filter.genre = value,
+            FacetKind::Composer => filter.composer = value,
+            FacetKind::Key => filter.key = value,
+            FacetKind::Difficulty => filter.difficulty = value,
+        }
+        filter
+    }
+
+    /// the text shown for a value : the name read in the DB or the label of the metadata
+    fn value_label(&self, count: &FacetCount) -> String {
+        let label = match self {
+            FacetKind::Key => count.value.parse::<MusicalKey>().ok().map(|k| k.label()),
+            FacetKind::Difficulty => count.value.parse::<Difficulty>().ok().map(|d| d.label()),
+            FacetKind::Genre | FacetKind::Composer => None,
+        };
+        label
+            .map(str::to_string)
+            .unwrap_or_else(|| count.name.clone())
+    }
+}
+
+///
+/// # FacetCount
+/// The number of partitions for one value of a facet (counted in the DB)
+///
+/// * value : the value of the criterion in the query string (id, key, grade)
+/// * name : the name of the genre or the composer (the value for the metadata)
+///
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct FacetCount {
+    pub value: String,
+    pub name: String,
+    pub count: i64,
+}
+
+///
+/// # FacetLink
+/// A value of a facet on the browse page
+/// * href : the browse page with this value chosen (or removed if it is the chosen one)
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct FacetLink {
+    pub label: String,
+    pub count: i64,
+    pub href: String,
+    pub selected: bool,
+}
+
+///
+/// # Facet
+/// A sidebar of the browse page : the values of a criterion with their counts
+/// * clear_href : the browse page without this criterion (None if it is not chosen)
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct Facet {
+    pub kind: FacetKind,
+    pub links: Vec<FacetLink>,
+    pub clear_href: Option<String>,
+}
+
+impl Facet {
+    pub fn new(kind: FacetKind, counts: Vec<FacetCount>, filter: &PartitionFilter) -> Self {
+        let selected = kind.selected(filter);
+        let links = counts
+            .iter()
+            .map(|count| {
+                let is_selected = selected.as_deref() == Some(count.value.as_str());
+                let value = if is_selected {
+                    None
+                } else {
+                    Some(count.value.clone())
+                };
+                FacetLink {
+                    label: kind.value_label(count),
+                    count: count.count,
+                    href: browse_link(&kind.with_value(filter, value)),
+                    selected: is_selected,
+                }
+            })
+            .collect();
+        let clear_href = selected.map(|_| browse_link(&kind.with_value(filter, None)));
+        Self {
+            kind,
+            links,
+            clear_href,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        self.kind.label()
+    }
+}
+
+///
+/// Returns the link of the browse page for the criteria of a filter (first page)
+///
+pub fn browse_link(filter: &PartitionFilter) -> String {
+    let query = filter
+        .query_pairs()
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, encode_query_value(value)))
+        .collect::<Vec<String>>()
+        .join("&");
+    if query.is_empty() {
+        BROWSE_PATH.to_string()
+    } else {
+        format!("{BROWSE_PATH}?{query}")
+    }
+}
@@ -14,3 +14,55 @@ pub struct Genre {
 pub struct NewGenre {
     pub name: String,
 }
+
+///
+/// # GenreNode
+/// A genre in the tree of the genres ("Classique > Baroque > Cantate")
+///
+/// * parent_id : None for a main genre (or when the parent is in the trash)
+/// * depth : 0 for a main genre
+/// * path : the names from the main genre, separated by " > "
+///
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct GenreNode {
+    pub id: i32,
+    pub name: String,
+    pub parent_id: Option<i32>,
+    pub depth: i32,
+    pub path: String,
+}
+
+impl GenreNode {
+    /// the left margin of the genre in the tree view (rem)
+    pub fn indent(&self) -> i32 {
+        self.depth * 2
+    }
+
+    /// used in the templates to select the current parent
+    pub fn is_parent_of(&self, genre: &GenreNode) -> bool {
+        genre.parent_id == Some(self.id)
+    }
+}
+
+///
+/// # ParentForm
+/// Form to move a genre : parent_id is the id of the new parent, empty for a main genre
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ParentForm {
+    #[serde(default)]
+    pub parent_id: String,
+}
+
+impl ParentForm {
+    pub fn parent_id(&self) -> Result<Option<i32>, String> {
+        let value = self.parent_id.trim();
+        if value.is_empty() {
+            return Ok(None);
+        }
+        value
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("genre parent invalide : {value}"))
+    }
+}
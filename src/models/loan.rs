@@ -0,0 +1,343 @@
+//! src/models/loan.rs
+//!
+//! Loans of the physical copies of the partitions to the users
+//!
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Deserialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// loan period proposed in the lend form
+pub const DEFAULT_LOAN_DAYS: i64 = 21;
+
+///
+/// A loan of a copy of a partition to a user
+///
+/// * copy_id : the inventory number of the lent copy
+///   (None for the loans returned before the copies were numbered)
+/// * title, user_name : the partition and the borrower (joined for the pages)
+/// * due_date : the date the copy must be returned
+/// * returned_at : None while the copy is lent
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct Loan {
+    pub id: i32,
+    pub partition_id: i32,
+    pub copy_id: Option<i32>,
+    pub title: String,
+    pub user_id: Uuid,
+    pub user_name: String,
+    pub borrowed_at: DateTime<Utc>,
+    pub due_date: NaiveDate,
+    pub returned_at: Option<DateTime<Utc>>,
+}
+
+impl Loan {
+    pub fn is_open(&self) -> bool {
+        self.returned_at.is_none()
+    }
+
+    pub fn is_overdue(&self) -> bool {
+        self.is_open() && self.due_date < today()
+    }
+
+    /// number of days since the due date (0 if not overdue)
+    pub fn days_overdue(&self) -> i64 {
+        if self.is_overdue() {
+            (today() - self.due_date).num_days()
+        } else {
+            0
+        }
+    }
+
+    /// "en cours", "en retard" or "rendu", used in the templates
+    pub fn status_label(&self) -> &'static str {
+        if !self.is_open() {
+            "rendu"
+        } else if self.is_overdue() {
+            "en retard"
+        } else {
+            "en cours"
+        }
+    }
+
+    pub fn borrowed_text(&self) -> String {
+        self.borrowed_at.format("%d/%m/%Y").to_string()
+    }
+
+    pub fn due_text(&self) -> String {
+        self.due_date.format("%d/%m/%Y").to_string()
+    }
+
+    pub fn returned_text(&self) -> String {
+        self.returned_at
+            .map(|date| date.format("%d/%m/%Y").to_string())
+            .unwrap_or_default()
+    }
+}
+
+///
+/// # PartitionAvailability
+/// The copies of a partition : how many the library has (the missing ones
+/// are not counted), how many are lent
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct PartitionAvailability {
+    pub partition_id: i32,
+    pub title: String,
+    pub copies: i64,
+    pub lent: i64,
+}
+
+impl PartitionAvailability {
+    pub fn available(&self) -> i64 {
+        (self.copies - self.lent).max(0)
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.available() > 0
+    }
+}
+
+///
+/// # LoanForm
+/// Fields of the form to lend a partition
+/// * user_id : the borrower (uuid of the user)
+/// * due_date : "2023-07-14" (input type="date")
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoanForm {
+    pub partition_id: i32,
+    pub user_id: String,
+    pub due_date: String,
+}
+
+impl LoanForm {
+    ///
+    /// Reads the borrower and the due date
+    /// returns an error message if a field is invalid or the date is past
+    ///
+    pub fn parse(&self) -> Result<(Uuid, NaiveDate), String> {
+        let user_id =
+            Uuid::try_parse(self.user_id.trim()).map_err(|_| "emprunteur inconnu".to_string())?;
+        let due_date = NaiveDate::parse_from_str(self.due_date.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("date de retour invalide : {}", self.due_date))?;
+        if due_date < today() {
+            return Err(format!(
+                "date de retour passée : {}",
+                due_date.format("%d/%m/%Y")
+            ));
+        }
+        Ok((user_id, due_date))
+    }
+}
+
+///
+/// # LoanFilter
+/// Criteria of the list of loans (query string of the loans page)
+///
+/// * status : "open" (the copies lent), "overdue" or "returned", empty for all the loans
+/// * user_id : the loans of a user
+/// * partition_id : the loans of a partition
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LoanFilter {
+    pub status: Option<String>,
+    pub user_id: Option<String>,
+    pub partition_id: Option<String>,
+}
+
+impl LoanFilter {
+    pub fn status(&self) -> Option<&str> {
+        self.status
+            .as_deref()
+            .filter(|status| ["open", "overdue", "returned"].contains(status))
+    }
+
+    pub fn user_id(&self) -> Option<Uuid> {
+        Uuid::try_parse(self.user_id.as_deref()?.trim()).ok()
+    }
+
+    pub fn partition_id(&self) -> Option<i32> {
+        self.partition_id.as_deref()?.trim().parse().ok()
+    }
+
+    ///
+    /// Returns the criteria as (name, value) to keep them in the links of the page
+    ///
+    pub fn query_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if let Some(status) = self.status() {
+            pairs.push(("status", status.to_string()));
+        }
+        if let Some(user_id) = self.user_id() {
+            pairs.push(("user_id", user_id.to_string()));
+        }
+        if let Some(partition_id) = self.partition_id() {
+            pairs.push(("partition_id", partition_id.to_string()));
+        }
+        pairs
+    }
+}
+
+///
+/// Form with the page to come back to after an action on a loan
+/// (the return buttons are on several pages)
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BackForm {
+    pub back: Option<String>,
+}
+
+impl BackForm {
+    ///
+    /// Returns the page to come back to : only a path of the site is accepted
+    /// ("//host" and "/\host" are read as another site by the browsers)
+    ///
+    pub fn back_or(&self, default: &str) -> String {
+        match self.back.as_deref() {
+            Some(back)
+                if back.starts_with('/')
+                    && !back.starts_with("//")
+                    && !back.contains('\\')
+                    && !back.chars().any(char::is_control) =>
+            {
+                back.to_string()
+            }
+            _ => default.to_string(),
+        }
+    }
+}
+
+/// the date of the day (UTC)
+pub fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+/// the due date proposed in the lend form ("2023-07-14")
+pub fn default_due_date() -> String {
+    (today() + Duration::days(DEFAULT_LOAN_DAYS))
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loan(due_in_days: i64, returned: bool) -> Loan {
+        Loan {
+            id: 1,
+            partition_id: 2,
+            copy_id: Some(3),
+            title: "Requiem".to_string(),
+            user_id: Uuid::nil(),
+            user_name: "Jean".to_string(),
+            borrowed_at: Utc::now() - Duration::days(30),
+            due_date: today() + Duration::days(due_in_days),
+            returned_at: returned.then(Utc::now),
+        }
+    }
+
+    fn form(user_id: &str, due_date: &str) -> LoanForm {
+        LoanForm {
+            partition_id: 2,
+            user_id: user_id.to_string(),
+            due_date: due_date.to_string(),
+        }
+    }
+
+    #[test]
+    fn loan_due_today_is_not_overdue() {
+        let loan = loan(0, false);
+        assert!(!loan.is_overdue());
+        assert_eq!(loan.days_overdue(), 0);
+        assert_eq!(loan.status_label(), "en cours");
+    }
+
+    #[test]
+    fn loan_past_the_due_date_is_overdue() {
+        let loan = loan(-3, false);
+        assert!(loan.is_overdue());
+        assert_eq!(loan.days_overdue(), 3);
+        assert_eq!(loan.status_label(), "en retard");
+    }
+
+    #[test]
+    fn returned_loan_is_never_overdue() {
+        let loan = loan(-3, true);
+        assert!(!loan.is_open());
+        assert!(!loan.is_overdue());
+        assert_eq!(loan.days_overdue(), 0);
+        assert_eq!(loan.status_label(), "rendu");
+    }
+
+    #[test]
+    fn default_due_date_is_in_the_loan_period() {
+        let due_date = NaiveDate::parse_from_str(&default_due_date(), "%Y-%m-%d").unwrap();
+        assert_eq!((due_date - today()).num_days(), DEFAULT_LOAN_DAYS);
+    }
+
+    #[test]
+    fn lend_form_is_parsed() {
+        let user_id = Uuid::new_v4();
+        let due_date = today() + Duration::days(7);
+        let lend = form(
+            &format!(" {user_id} "),
+            &due_date.format("%Y-%m-%d").to_string(),
+        );
+        assert_eq!(lend.parse(), Ok((user_id, due_date)));
+        assert!(form(&user_id.to_string(), &default_due_date())
+            .parse()
+            .is_ok());
+    }
+
+    #[test]
+    fn lend_form_errors() {
+        let user_id = Uuid::new_v4().to_string();
+        assert_eq!(
+            form("Jean", &default_due_date()).parse(),
+            Err("emprunteur inconnu".to_string())
+        );
+        assert_eq!(
+            form(&user_id, "14/07/2023").parse(),
+            Err("date de retour invalide : 14/07/2023".to_string())
+        );
+        let yesterday = today() - Duration::days(1);
+        assert_eq!(
+            form(&user_id, &yesterday.format("%Y-%m-%d").to_string()).parse(),
+            Err(format!(
+                "date de retour passée : {}",
+                yesterday.format("%d/%m/%Y")
+            ))
+        );
+    }
+
+    #[test]
+    fn back_is_a_path_of_the_site() {
+        let back = |back: &str| BackForm {
+            back: Some(back.to_string()),
+        };
+        assert_eq!(
+            back("/api/loans?page=2").back_or("/api/loans"),
+            "/api/loans?page=2"
+        );
+        for outside in [
+            "https://evil.example",
+            "//evil.example",
+            "/\\evil.example",
+            "/api/\\..\\evil",
+            "/api\r\nLocation: //evil.example",
+            "/\tevil.example",
+            "",
+        ] {
+            assert_eq!(
+                back(outside).back_or("/api/loans"),
+                "/api/loans",
+                "{outside}"
+            );
+        }
+        assert_eq!(BackForm::default().back_or("/api/me"), "/api/me");
+    }
+}
@@ -1,5 +1,21 @@
 //! src/models/mod.rs
+pub mod audit;
+pub mod collection;
+pub mod copy;
+pub mod deletion;
+pub mod duplicate;
+pub mod event;
+pub mod facet;
 pub mod genre;
+pub mod loan;
 pub mod musician;
+pub mod pagination;
 pub mod partition;
+pub mod partition_file;
+pub mod partition_import;
+pub mod revision;
+pub mod setlist;
+pub mod statistics;
+pub mod tag;
+pub mod trash;
 pub mod user;
@@ -2,6 +2,8 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
+use crate::models::partition::ContributorRole;
+
 // this struct will be used to represent database record
 #[derive(Clone, Serialize, Deserialize, FromRow, Debug, Eq, PartialEq)]
 pub struct Person {
@@ -13,3 +15,162 @@ pub struct Person {
 pub struct NewPerson {
     pub full_name: String,
 }
+
+///
+/// # PersonProfile
+/// The profile of a musician (detail page *'/api/persons/:id'*)
+///
+/// * birth_year, death_year : None if unknown (or still alive)
+/// * alternative_names : the other spellings of the name ("Brel, Jacques")
+///
+#[derive(Clone, Serialize, Deserialize, FromRow, Debug, Default, Eq, PartialEq)]
+pub struct PersonProfile {
+    pub id: i32,
+    pub full_name: String,
+    pub birth_year: Option<i32>,
+    pub death_year: Option<i32>,
+    pub nationality: String,
+    pub biography: String,
+    pub alternative_names: Vec<String>,
+}
+
+impl PersonProfile {
+    /// "1929 – 1978", "né en 1951", "" if the years are unknown
+    pub fn years_text(&self) -> String {
+        match (self.birth_year, self.death_year) {
+            (Some(birth), Some(death)) => format!("{birth} – {death}"),
+            (Some(birth), None) => format!("né(e) en {birth}"),
+            (None, Some(death)) => format!("† {death}"),
+            (None, None) => String::new(),
+        }
+    }
+
+    pub fn birth_year_text(&self) -> String {
+        self.birth_year.map(|year| year.to_string()).unwrap_or_default()
+    }
+
+    pub fn death_year_text(&self) -> String {
+        self.death_year.map(|year| year.to_string()).unwrap_or_default()
+    }
+
+    /// the other spellings, one per line (textarea of the profile form)
+    pub fn alternative_names_text(&self) -> String {
+        self.alternative_names.join("\n")
+    }
+}
+
+///
+/// # PersonProfileForm
+/// Form of the profile of a musician : the years are empty when unknown,
+/// the other spellings are separated by new lines or semicolons
+///
+#[derive(Clone, Deserialize, Debug, Default)]
+pub struct PersonProfileForm {
+    #[serde(default)]
+    pub birth_year: String,
+    #[serde(default)]
+    pub death_year: String,
+    #[serde(default)]
+    pub nationality: String,
+    #[serde(default)]
+    pub biography: String,
+    #[serde(default)]
+    pub alternative_names: String,
+}
+
+impl PersonProfileForm {
+    ///
+    /// Returns the profile of the form (id and full_name are not changed by the form)
+    /// or an error message if a year is not a number or the death is before the birth
+    ///
+    pub fn profile(&self) -> Result<PersonProfile, String> {
+        let birth_year = parse_year(&self.birth_year)?;
+        let death_year = parse_year(&self.death_year)?;
+        if let (Some(birth), Some(death)) = (birth_year, death_year) {
+            if death < birth {
+                return Err(format!("décès ({death}) avant la naissance ({birth})"));
+            }
+        }
+        let mut alternative_names: Vec<String> = Vec::new();
+        for name in self.alternative_names.split(['\n', ';']).map(str::trim) {
+            if !name.is_empty() && !alternative_names.iter().any(|n| n == name) {
+                alternative_names.push(name.to_string());
+            }
+        }
+        Ok(PersonProfile {
+            birth_year,
+            death_year,
+            nationality: self.nationality.trim().to_string(),
+            biography: self.biography.trim().to_string(),
+            alternative_names,
+            ..PersonProfile::default()
+        })
+    }
+}
+
+/// a year of the form : None if empty
+fn parse_year(value: &str) -> Result<Option<i32>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("année invalide : {value}"))
+}
+
+///
+/// # PersonWork
+/// A partition of a musician (not in the trash)
+/// * roles : the roles of the musician in the partition, separated by commas
+///   ("composer,arranger"), empty when the musician is only the main musician
+///
+#[derive(Clone, Serialize, FromRow, Debug)]
+pub struct PersonWork {
+    pub id: i32,
+    pub title: String,
+    pub genre: String,
+    pub roles: String,
+}
+
+impl PersonWork {
+    /// "Compositeur, Arrangeur"
+    pub fn roles_text(&self) -> String {
+        self.roles
+            .split(',')
+            .filter_map(|role| role.parse::<ContributorRole>().ok())
+            .map(|role| role.label())
+            .collect::<Vec<&str>>()
+            .join(", ")
+    }
+}
+
+///
+/// # GenreWorks
+/// The partitions of a musician in one genre
+///
+#[derive(Clone, Serialize, Debug)]
+pub struct GenreWorks {
+    pub genre: String,
+    pub works: Vec<PersonWork>,
+}
+
+impl GenreWorks {
+    ///
+    /// Groups the partitions by genre, the partitions must be sorted by genre
+    ///
+    pub fn group(works: Vec<PersonWork>) -> Vec<GenreWorks> {
+        let mut groups: Vec<GenreWorks> = Vec::new();
+        for work in works {
+            match groups.last_mut() {
+                Some(group) if group.genre == work.genre => group.works.push(work),
+                _ => groups.push(GenreWorks {
+                    genre: work.genre.clone(),
+                    works: vec![work],
+                }),
+            }
+        }
+        groups
+    }
+}
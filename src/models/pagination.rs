@@ -0,0 +1,341 @@
+//! src/models/pagination.rs
+//!
+//! Pagination and sorting of the lists (persons, genres, partitions)
+//!
+//! PageParams is read from the query string : ?page=2&per_page=50&sort=title&dir=desc
+//! Page is given to the templates to show the total and the links
+//! to the previous and next pages.
+//!
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PER_PAGE: i64 = 50;
+pub const MAX_PER_PAGE: i64 = 500;
+/// the highest page number asked in the query string is kept below this one
+pub const MAX_PAGE: i64 = 1_000_000;
+
+///
+/// # PageParams
+/// Pagination and sorting asked in the query string
+/// all the fields are optional
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PageParams {
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+    pub sort: Option<String>,
+    pub dir: Option<String>,
+}
+
+impl PageParams {
+    /// number of the page, starting at 1 (at most MAX_PAGE)
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(1).clamp(1, MAX_PAGE)
+    }
+
+    /// number of rows per page (between 1 and MAX_PER_PAGE)
+    pub fn per_page(&self) -> i64 {
+        self.per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE)
+    }
+
+    /// number of rows to skip (OFFSET)
+    pub fn offset(&self) -> i64 {
+        (self.page() - 1).saturating_mul(self.per_page())
+    }
+
+    /// true if the sort is descending (dir=desc)
+    pub fn is_desc(&self) -> bool {
+        self.dir.as_deref() == Some("desc")
+    }
+
+    /// SQL direction of the sort
+    pub fn sql_dir(&self) -> &'static str {
+        if self.is_desc() {
+            "DESC"
+        } else {
+            "ASC"
+        }
+    }
+
+    ///
+    /// Returns the SQL column corresponding to the asked sort
+    /// among the allowed columns : [(sort key, SQL column)]
+    ///
+    /// Only the SQL columns of the list can end up in the query,
+    /// an unknown sort key gives None.
+    ///
+    pub fn sort_column(&self, allowed: &[(&str, &'static str)]) -> Option<&'static str> {
+        let sort = self.sort.as_deref()?;
+        allowed
+            .iter()
+            .find(|(key, _)| *key == sort)
+            .map(|(_, column)| *column)
+    }
+}
+
+///
+/// # Page
+/// Data used by the templates to show the pagination of a list :
+/// total, links to the previous/next pages and sort links
+///
+/// * path : the page url without the query string, e.g. "/api/partitions"
+/// * filter_query : the query string of the filters to keep in the links, e.g. "q=noct&"
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct Page {
+    pub number: i64,
+    pub per_page: i64,
+    pub total: i64,
+    pub sort: String,
+    pub dir: String,
+    pub path: String,
+    pub filter_query: String,
+}
+
+impl Page {
+    pub fn new(params: &PageParams, total: i64, path: &str) -> Self {
+        Self {
+            number: params.page(),
+            per_page: params.per_page(),
+            total,
+            sort: params.sort.clone().unwrap_or_default(),
+            dir: if params.is_desc() { "desc" } else { "asc" }.to_string(),
+            path: path.to_string(),
+            filter_query: String::new(),
+        }
+    }
+
+    ///
+    /// A page showing a complete list in one time
+    /// (e.g. the results of the find forms)
+    ///
+    pub fn all(total: i64, path: &str) -> Self {
+        Self {
+            number: 1,
+            per_page: total.max(1),
+            total,
+            sort: String::new(),
+            dir: "asc".to_string(),
+            path: path.to_string(),
+            filter_query: String::new(),
+        }
+    }
+
+    ///
+    /// Keeps a filter in the links of the page
+    ///
+    pub fn with_filter(mut self, name: &str, value: &str) -> Self {
+        if !value.is_empty() {
+            self.filter_query
+                .push_str(&format!("{}={}&", name, encode_query_value(value)));
+        }
+        self
+    }
+
+    ///
+    /// Keeps several filters in the links of the page
+    ///
+    pub fn with_filters(self, filters: Vec<(&str, String)>) -> Self {
+        filters
+            .iter()
+            .fold(self, |page, (name, value)| page.with_filter(name, value))
+    }
+
+    pub fn total_pages(&self) -> i64 {
+        ((self.total + self.per_page - 1) / self.per_page).max(1)
+    }
+
+    pub fn has_previous(&self) -> bool {
+        self.number > 1
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.number < self.total_pages()
+    }
+
+    /// number of the first row shown (for "51 - 100 sur 1234")
+    pub fn first_row(&self) -> i64 {
+        if self.total == 0 {
+            0
+        } else {
+            (self.number - 1).saturating_mul(self.per_page) + 1
+        }
+    }
+
+    /// number of the last row shown
+    pub fn last_row(&self) -> i64 {
+        self.number.saturating_mul(self.per_page).min(self.total)
+    }
+
+    /// query string (without '?') of a page, keeping filters and sort
+    pub fn query(&self, number: i64) -> String {
+        let mut query = format!(
+            "{}page={}&per_page={}",
+            self.filter_query, number, self.per_page
+        );
+        if !self.sort.is_empty() {
+            query.push_str(&format!(
+                "&sort={}&dir={}",
+                encode_query_value(&self.sort),
+                self.dir
+            ));
+        }
+        query
+    }
+
+    /// query string of the current page (e.g. for the print link)
+    pub fn current_query(&self) -> String {
+        self.query(self.number)
+    }
+
+    pub fn link(&self, number: i64) -> String {
+        format!("{}?{}", self.path, self.query(number))
+    }
+
+    /// link of the current page (e.g. the page to come back to after an action)
+    pub fn current_link(&self) -> String {
+        self.link(self.number)
+    }
+
+    pub fn previous_link(&self) -> String {
+        self.link(self.number - 1)
+    }
+
+    pub fn next_link(&self) -> String {
+        self.link(self.number + 1)
+    }
+
+    ///
+    /// Link to sort the list on a column (back to the first page)
+    /// a second click on the sorted column reverses the direction
+    ///
+    pub fn sort_link(&self, sort: &str) -> String {
+        let dir = if self.sort == sort && self.dir == "asc" {
+            "desc"
+        } else {
+            "asc"
+        };
+        format!(
+            "{}?{}page=1&per_page={}&sort={}&dir={}",
+            self.path,
+            self.filter_query,
+            self.per_page,
+            encode_query_value(sort),
+            dir
+        )
+    }
+
+    /// arrow shown next to the sorted column
+    pub fn sort_mark(&self, sort: &str) -> &'static str {
+        match (self.sort == sort, self.dir.as_str()) {
+            (true, "desc") => "▼",
+            (true, _) => "▲",
+            _ => "",
+        }
+    }
+}
+
+///
+/// Percent-encodes a value of the query string
+///
+pub fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(page: Option<i64>, per_page: Option<i64>) -> PageParams {
+        PageParams {
+            page,
+            per_page,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn offset_of_the_pages() {
+        assert_eq!(params(None, None).offset(), 0);
+        assert_eq!(params(Some(3), Some(20)).offset(), 40);
+        assert_eq!(params(Some(0), Some(20)).offset(), 0);
+        assert_eq!(params(Some(-5), None).offset(), 0);
+    }
+
+    #[test]
+    fn per_page_is_bounded() {
+        assert_eq!(params(None, Some(0)).per_page(), 1);
+        assert_eq!(params(None, Some(10_000)).per_page(), MAX_PER_PAGE);
+    }
+
+    #[test]
+    fn huge_page_number_does_not_overflow() {
+        let huge = params(Some(i64::MAX), Some(MAX_PER_PAGE));
+        assert_eq!(huge.page(), MAX_PAGE);
+        assert_eq!(huge.offset(), (MAX_PAGE - 1) * MAX_PER_PAGE);
+
+        let page = Page::new(&huge, 10, "/api/partitions");
+        assert_eq!(page.last_row(), 10);
+        assert!(page.first_row() > 0);
+        assert!(!page.has_next());
+    }
+
+    #[test]
+    fn total_pages_and_rows() {
+        let page = Page::new(&params(Some(2), Some(50)), 120, "/api/persons");
+        assert_eq!(page.total_pages(), 3);
+        assert_eq!(page.first_row(), 51);
+        assert_eq!(page.last_row(), 100);
+        assert!(page.has_previous());
+        assert!(page.has_next());
+
+        let empty = Page::new(&params(None, None), 0, "/api/persons");
+        assert_eq!(empty.total_pages(), 1);
+        assert_eq!(empty.first_row(), 0);
+        assert_eq!(empty.last_row(), 0);
+    }
+
+    #[test]
+    fn links_keep_filters_and_sort() {
+        let mut page_params = params(Some(2), Some(10));
+        page_params.sort = Some("title".to_string());
+        page_params.dir = Some("desc".to_string());
+        let page = Page::new(&page_params, 100, "/api/partitions/search")
+            .with_filter("q", "noël & co")
+            .with_filter("tag", "");
+
+        assert_eq!(
+            page.next_link(),
+            "/api/partitions/search?q=no%C3%ABl%20%26%20co&page=3&per_page=10&sort=title&dir=desc"
+        );
+        assert_eq!(
+            page.sort_link("title"),
+            "/api/partitions/search?q=no%C3%ABl%20%26%20co&page=1&per_page=10&sort=title&dir=asc"
+        );
+        assert_eq!(page.sort_mark("title"), "▼");
+        assert_eq!(page.sort_mark("genre"), "");
+    }
+
+    #[test]
+    fn sort_is_encoded_in_the_links() {
+        let mut page_params = params(None, None);
+        page_params.sort = Some("\"><script>".to_string());
+        let page = Page::new(&page_params, 1, "/api/genres");
+
+        assert!(page
+            .current_query()
+            .contains("sort=%22%3E%3Cscript%3E&dir=asc"));
+        assert!(!page.link(1).contains('<'));
+        assert!(!page.sort_link("a b").contains(' '));
+    }
+}
@@ -2,6 +2,10 @@
 
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::models::tag::{parse_tag_names, PartitionTag};
 
 #[derive(Debug, Clone, Deserialize, Serialize, FromRow)]
 pub struct Partition {
@@ -25,6 +29,17 @@ pub struct ShowPartition {
     pub name: String,
 }
 */
+///
+/// une struct pour présenter les partitions avec les
+/// éléments des différentes tables
+///
+/// * full_name : the main musician of the partition (partitions.person_id)
+/// * name : the genre
+/// * contributors : all the musicians of the partition with their role,
+///   the main musician included (as composer)
+/// * tags : the free-form tags of the partition
+/// * metadata : key, time signature, tempo, duration, instrumentation and difficulty
+///
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShowPartition {
     #[serde(skip_deserializing)]
@@ -32,4 +47,737 @@ pub struct ShowPartition {
     pub title: String,
     pub full_name: String,
     pub name: String,
+    #[serde(default, skip_deserializing)]
+    pub contributors: Vec<Contributor>,
+    #[serde(default, skip_deserializing)]
+    pub tags: Vec<PartitionTag>,
+    #[serde(default, skip_deserializing)]
+    pub metadata: MusicalMetadata,
+}
+
+impl ShowPartition {
+    ///
+    /// Returns the contributors other than the main musician (as composer)
+    /// i.e. the lyricists, arrangers, editors and the other composers
+    ///
+    pub fn other_contributors(&self) -> Vec<&Contributor> {
+        self.contributors
+            .iter()
+            .filter(|c| !c.is_main_musician(&self.full_name))
+            .collect()
+    }
+
+    ///
+    /// Returns the tags as shown in the field of the form : "Noël, Gospel"
+    ///
+    pub fn tags_text(&self) -> String {
+        self.tags
+            .iter()
+            .map(|tag| tag.name.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ")
+    }
+}
+
+//*******************************************************************************
+// Contributors of a partition (table partition_contributors)
+//
+
+///
+/// # ContributorRole
+/// Role of a musician in a partition (Postgres enum contributor_role)
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "contributor_role", rename_all = "lowercase")]
+pub enum ContributorRole {
+    Composer,
+    Lyricist,
+    Arranger,
+    Editor,
+}
+
+impl ContributorRole {
+    pub const ALL: [ContributorRole; 4] = [
+        ContributorRole::Composer,
+        ContributorRole::Lyricist,
+        ContributorRole::Arranger,
+        ContributorRole::Editor,
+    ];
+
+    /// value used in the DB, the query strings and the forms
+    pub fn value(&self) -> &'static str {
+        match self {
+            ContributorRole::Composer => "composer",
+            ContributorRole::Lyricist => "lyricist",
+            ContributorRole::Arranger => "arranger",
+            ContributorRole::Editor => "editor",
+        }
+    }
+
+    /// label shown in the templates
+    pub fn label(&self) -> &'static str {
+        match self {
+            ContributorRole::Composer => "compositeur",
+            ContributorRole::Lyricist => "parolier",
+            ContributorRole::Arranger => "arrangeur",
+            ContributorRole::Editor => "éditeur",
+        }
+    }
+}
+
+impl fmt::Display for ContributorRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+impl FromStr for ContributorRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ContributorRole::ALL
+            .into_iter()
+            .find(|role| role.value() == s.trim().to_lowercase())
+            .ok_or_else(|| format!("rôle inconnu : {s}"))
+    }
+}
+
+///
+/// # Contributor
+/// A musician of a partition with his role
+///
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Contributor {
+    pub partition_id: i32,
+    pub person_id: i32,
+    pub full_name: String,
+    pub role: ContributorRole,
+}
+
+impl Contributor {
+    ///
+    /// Returns true for the main musician of the partition recorded as composer :
+    /// it is already shown as the musician of the partition
+    ///
+    pub fn is_main_musician(&self, main_full_name: &str) -> bool {
+        self.role == ContributorRole::Composer && self.full_name == main_full_name
+    }
+}
+
+///
+/// # NewContributor
+/// A musician to add to a partition with his role
+/// (used by add_partition and update_partition)
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NewContributor {
+    pub person_id: i32,
+    pub role: ContributorRole,
+}
+
+//*******************************************************************************
+// Musical metadata of a partition
+//
+
+///
+/// Generates an enum of the musical metadata with :
+/// * ALL : the list of the values (for the selects of the forms)
+/// * value() : the value stored in the DB and used in the forms and the query strings
+/// * label() : the text shown in the templates
+/// * FromStr (case insensitive) : only the known values are accepted
+///
+/// The enum is (de)serialized as its value : "D minor", "4/4", "satb" ...
+///
+macro_rules! metadata_enum {
+    (
+        $(#[$meta:meta])*
+        $name:ident, $what:literal {
+            $($variant:ident => ($value:literal, $label:literal)),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(try_from = "String", into = "String")]
+        pub enum $name {
+            $($variant),+
+        }
+
+        impl $name {
+            pub const ALL: &'static [$name] = &[$($name::$variant),+];
+
+            pub fn value(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $value),+
+                }
+            }
+
+            pub fn label(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $label),+
+                }
+            }
+
+            /// true if the value is the current one (to select it in the forms)
+            pub fn is(&self, current: &Option<$name>) -> bool {
+                *current == Some(*self)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.label())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $name::ALL
+                    .iter()
+                    .copied()
+                    .find(|v| v.value().eq_ignore_ascii_case(s.trim()))
+                    .ok_or_else(|| format!("{} inconnu(e) : {}", $what, s))
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = String;
+
+            fn try_from(s: String) -> Result<Self, Self::Error> {
+                s.parse()
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(v: $name) -> String {
+                v.value().to_string()
+            }
+        }
+    };
+}
+
+metadata_enum! {
+    /// Key of the partition (the 30 key signatures, major and minor)
+    MusicalKey, "tonalité" {
+        CMajor => ("C major", "do majeur"),
+        CMinor => ("C minor", "do mineur"),
+        CSharpMajor => ("C# major", "do# majeur"),
+        CSharpMinor => ("C# minor", "do# mineur"),
+        CFlatMajor => ("Cb major", "dob majeur"),
+        DMajor => ("D major", "ré majeur"),
+        DMinor => ("D minor", "ré mineur"),
+        DFlatMajor => ("Db major", "réb majeur"),
+        DSharpMinor => ("D# minor", "ré# mineur"),
+        EMajor => ("E major", "mi majeur"),
+        EMinor => ("E minor", "mi mineur"),
+        EFlatMajor => ("Eb major", "mib majeur"),
+        EFlatMinor => ("Eb minor", "mib mineur"),
+        FMajor => ("F major", "fa majeur"),
+        FMinor => ("F minor", "fa mineur"),
+        FSharpMajor => ("F# major", "fa# majeur"),
+        FSharpMinor => ("F# minor", "fa# mineur"),
+        GMajor => ("G major", "sol majeur"),
+        GMinor => ("G minor", "sol mineur"),
+        GFlatMajor => ("Gb major", "solb majeur"),
+        GSharpMinor => ("G# minor", "sol# mineur"),
+        AMajor => ("A major", "la majeur"),
+        AMinor => ("A minor", "la mineur"),
+        AFlatMajor => ("Ab major", "lab majeur"),
+        AFlatMinor => ("Ab minor", "lab mineur"),
+        ASharpMinor => ("A# minor", "la# mineur"),
+        BMajor => ("B major", "si majeur"),
+        BMinor => ("B minor", "si mineur"),
+        BFlatMajor => ("Bb major", "sib majeur"),
+        BFlatMinor => ("Bb minor", "sib mineur"),
+    }
+}
+
+metadata_enum! {
+    /// Time signature of the partition
+    TimeSignature, "mesure" {
+        TwoTwo => ("2/2", "2/2 (alla breve)"),
+        ThreeTwo => ("3/2", "3/2"),
+        TwoFour => ("2/4", "2/4"),
+        ThreeFour => ("3/4", "3/4"),
+        FourFour => ("4/4", "4/4"),
+        FiveFour => ("5/4", "5/4"),
+        SixFour => ("6/4", "6/4"),
+        ThreeEight => ("3/8", "3/8"),
+        FiveEight => ("5/8", "5/8"),
+        SixEight => ("6/8", "6/8"),
+        SevenEight => ("7/8", "7/8"),
+        NineEight => ("9/8", "9/8"),
+        TwelveEight => ("12/8", "12/8"),
+        Free => ("free", "libre"),
+    }
+}
+
+metadata_enum! {
+    /// Tempo marking of the partition (from the slowest to the fastest)
+    Tempo, "tempo" {
+        Grave => ("grave", "Grave"),
+        Largo => ("largo", "Largo"),
+        Lento => ("lento", "Lento"),
+        Adagio => ("adagio", "Adagio"),
+        Andante => ("andante", "Andante"),
+        Moderato => ("moderato", "Moderato"),
+        Allegretto => ("allegretto", "Allegretto"),
+        Allegro => ("allegro", "Allegro"),
+        Vivace => ("vivace", "Vivace"),
+        Presto => ("presto", "Presto"),
+        Prestissimo => ("prestissimo", "Prestissimo"),
+    }
+}
+
+metadata_enum! {
+    /// Instrumentation or voicing of the partition
+    Instrumentation, "instrumentation" {
+        Satb => ("satb", "SATB"),
+        Ssa => ("ssa", "SSA"),
+        Ssaa => ("ssaa", "SSAA"),
+        Ttbb => ("ttbb", "TTBB"),
+        Sab => ("sab", "SAB"),
+        Unison => ("unison", "unisson"),
+        SoloVoice => ("solo_voice", "voix seule"),
+        VoicePiano => ("voice_piano", "voix et piano"),
+        Piano => ("piano", "piano"),
+        Organ => ("organ", "orgue"),
+        StringQuartet => ("string_quartet", "quatuor à cordes"),
+        StringOrchestra => ("string_orchestra", "orchestre à cordes"),
+        WindEnsemble => ("wind_ensemble", "ensemble à vents"),
+        BrassEnsemble => ("brass_ensemble", "ensemble de cuivres"),
+        Orchestra => ("orchestra", "orchestre"),
+        Other => ("other", "autre"),
+    }
+}
+
+metadata_enum! {
+    /// Difficulty grade of the partition, from 1 (very easy) to 5 (very difficult)
+    Difficulty, "niveau" {
+        Grade1 => ("1", "1 - très facile"),
+        Grade2 => ("2", "2 - facile"),
+        Grade3 => ("3", "3 - moyen"),
+        Grade4 => ("4", "4 - difficile"),
+        Grade5 => ("5", "5 - très difficile"),
+    }
+}
+
+impl Difficulty {
+    /// grade stored in the DB (SMALLINT between 1 and 5)
+    pub fn grade(&self) -> i16 {
+        match self {
+            Difficulty::Grade1 => 1,
+            Difficulty::Grade2 => 2,
+            Difficulty::Grade3 => 3,
+            Difficulty::Grade4 => 4,
+            Difficulty::Grade5 => 5,
+        }
+    }
+
+    pub fn from_grade(grade: i16) -> Option<Self> {
+        Difficulty::ALL.iter().copied().find(|d| d.grade() == grade)
+    }
+}
+
+///
+/// # MusicalMetadata
+/// Musical information of a partition, all the fields are optional
+///
+/// * duration_seconds : duration of the piece, entered as "m:ss" in the forms
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MusicalMetadata {
+    pub key: Option<MusicalKey>,
+    pub time_signature: Option<TimeSignature>,
+    pub tempo: Option<Tempo>,
+    pub duration_seconds: Option<i32>,
+    pub instrumentation: Option<Instrumentation>,
+    pub difficulty: Option<Difficulty>,
+}
+
+impl MusicalMetadata {
+    ///
+    /// Returns the duration as shown in the forms : 225 -> "3:45"
+    ///
+    pub fn duration_text(&self) -> String {
+        self.duration_seconds
+            .map(format_duration)
+            .unwrap_or_default()
+    }
+
+    ///
+    /// Returns the metadata in one line : "ré mineur · 3/4 · Andante · 3:45 · SATB · niveau 3"
+    ///
+    pub fn summary(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        if let Some(key) = self.key {
+            parts.push(key.label().to_string());
+        }
+        if let Some(time_signature) = self.time_signature {
+            parts.push(time_signature.label().to_string());
+        }
+        if let Some(tempo) = self.tempo {
+            parts.push(tempo.label().to_string());
+        }
+        if self.duration_seconds.is_some() {
+            parts.push(self.duration_text());
+        }
+        if let Some(instrumentation) = self.instrumentation {
+            parts.push(instrumentation.label().to_string());
+        }
+        if let Some(difficulty) = self.difficulty {
+            parts.push(format!("niveau {}", difficulty.value()));
+        }
+        parts.join(" · ")
+    }
+}
+
+///
+/// Formats a duration in seconds : 225 -> "3:45"
+///
+pub fn format_duration(seconds: i32) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+///
+/// Parses a duration entered in a form : "3:45" (minutes:seconds) or "4" (minutes)
+/// returns the number of seconds or an error message
+///
+pub fn parse_duration(text: &str) -> Result<i32, String> {
+    let error = || format!("durée invalide : {text} (format m:ss)");
+    let text = text.trim();
+    match text.split_once(':') {
+        Some((minutes, seconds)) => {
+            let minutes: i32 = minutes.trim().parse().map_err(|_| error())?;
+            let seconds: i32 = seconds.trim().parse().map_err(|_| error())?;
+            if minutes < 0 || !(0..60).contains(&seconds) {
+                return Err(error());
+            }
+            minutes
+                .checked_mul(60)
+                .and_then(|total| total.checked_add(seconds))
+                .ok_or_else(error)
+        }
+        None => {
+            let minutes: i32 = text.parse().map_err(|_| error())?;
+            if minutes < 0 {
+                return Err(error());
+            }
+            minutes.checked_mul(60).ok_or_else(error)
+        }
+    }
+}
+
+///
+/// Parses an optional field of a form : an empty field gives None
+///
+fn parse_optional<T: FromStr<Err = String>>(value: &str) -> Result<Option<T>, String> {
+    if value.trim().is_empty() {
+        Ok(None)
+    } else {
+        value.parse().map(Some)
+    }
+}
+
+///
+/// # PartitionDetails
+/// Everything that is saved with a partition besides the title,
+/// the main musician and the genre (used by add_partition and update_partition)
+///
+/// * contributors : the other musicians with their role
+/// * tags : the names of the tags (created on the fly if needed)
+/// * metadata : the musical metadata
+///
+#[derive(Debug, Clone, Default)]
+pub struct PartitionDetails {
+    pub contributors: Vec<NewContributor>,
+    pub tags: Vec<String>,
+    pub metadata: MusicalMetadata,
+}
+
+///
+/// # PartitionForm
+/// Fields of the HTML form to add or modify a partition :
+/// * title, full_name (main musician), name (genre)
+/// * the other musicians : pairs of fields "role" and "contributor" (person id)
+///   repeated in the form, a pair with an empty contributor is ignored
+/// * tags : the tags separated by commas ("Noël, Gospel")
+/// * musical_key, time_signature, tempo, duration ("m:ss"), instrumentation, difficulty :
+///   the musical metadata, an empty field means unknown
+///
+/// The form is read as a list of (key, value) because the pairs
+/// of fields "role" / "contributor" can be repeated.
+///
+#[derive(Debug, Clone, Default)]
+pub struct PartitionForm {
+    pub title: String,
+    pub full_name: String,
+    pub name: String,
+    pub details: PartitionDetails,
+}
+
+impl PartitionForm {
+    ///
+    /// Reads the fields of the form
+    /// returns an error message if a metadata field has an unknown value
+    ///
+    pub fn from_fields(fields: Vec<(String, String)>) -> Result<Self, String> {
+        let mut form = PartitionForm::default();
+        let details = &mut form.details;
+        let metadata = &mut details.metadata;
+        let mut roles: Vec<String> = Vec::new();
+        let mut persons: Vec<String> = Vec::new();
+        for (key, value) in fields {
+            match key.as_str() {
+                "title" => form.title = value,
+                "full_name" => form.full_name = value,
+                "name" => form.name = value,
+                "role" => roles.push(value),
+                "contributor" => persons.push(value),
+                "tags" => details.tags = parse_tag_names(&value),
+                "musical_key" => metadata.key = parse_optional(&value)?,
+                "time_signature" => metadata.time_signature = parse_optional(&value)?,
+                "tempo" => metadata.tempo = parse_optional(&value)?,
+                "instrumentation" => metadata.instrumentation = parse_optional(&value)?,
+                "difficulty" => metadata.difficulty = parse_optional(&value)?,
+                "duration" if !value.trim().is_empty() => {
+                    metadata.duration_seconds = Some(parse_duration(&value)?)
+                }
+                _ => {}
+            }
+        }
+        details.contributors = roles
+            .iter()
+            .zip(persons.iter())
+            .filter_map(|(role, person)| {
+                Some(NewContributor {
+                    person_id: person.parse().ok()?,
+                    role: role.parse().ok()?,
+                })
+            })
+            .collect();
+        Ok(form)
+    }
+}
+
+///
+/// # PartitionFilter
+/// Criteria of the partitions search (query string of the search routes)
+///
+/// * q : words searched in the title, the musician name and the genre name
+/// * tag : name of a tag the partitions must have
+/// * key, time_signature, tempo, instrumentation : values of the musical metadata
+/// * max_difficulty : the highest difficulty grade ("grade ≤ 3")
+/// * person : the id of a musician (main musician or other musician of the partitions)
+/// * genre : the id of a genre (the partitions of its sub-genres included)
+/// * composer : the id of the main musician of the partitions
+/// * difficulty : one difficulty grade ("grade = 3")
+///
+/// The fields are strings because the selects of the search form send
+/// an empty string for "all", an unknown value is ignored.
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PartitionFilter {
+    pub q: Option<String>,
+    pub tag: Option<String>,
+    pub key: Option<String>,
+    pub time_signature: Option<String>,
+    pub tempo: Option<String>,
+    pub instrumentation: Option<String>,
+    pub max_difficulty: Option<String>,
+    pub person: Option<String>,
+    pub genre: Option<String>,
+    pub composer: Option<String>,
+    pub difficulty: Option<String>,
+}
+
+impl PartitionFilter {
+    pub fn key(&self) -> Option<MusicalKey> {
+        self.key.as_deref()?.parse().ok()
+    }
+
+    pub fn time_signature(&self) -> Option<TimeSignature> {
+        self.time_signature.as_deref()?.parse().ok()
+    }
+
+    pub fn tempo(&self) -> Option<Tempo> {
+        self.tempo.as_deref()?.parse().ok()
+    }
+
+    pub fn instrumentation(&self) -> Option<Instrumentation> {
+        self.instrumentation.as_deref()?.parse().ok()
+    }
+
+    pub fn max_difficulty(&self) -> Option<Difficulty> {
+        self.max_difficulty.as_deref()?.parse().ok()
+    }
+
+    pub fn person(&self) -> Option<i32> {
+        self.person.as_deref()?.trim().parse().ok()
+    }
+
+    pub fn genre(&self) -> Option<i32> {
+        self.genre.as_deref()?.trim().parse().ok()
+    }
+
+    pub fn composer(&self) -> Option<i32> {
+        self.composer.as_deref()?.trim().parse().ok()
+    }
+
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        self.difficulty.as_deref()?.parse().ok()
+    }
+
+    ///
+    /// Returns the criteria of the filter as (name, value)
+    /// to keep them in the links of the pages (pagination, sort, print)
+    ///
+    pub fn query_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if let Some(q) = self.q.as_deref().filter(|q| !q.trim().is_empty()) {
+            pairs.push(("q", q.to_string()));
+        }
+        if let Some(tag) = self.tag() {
+            pairs.push(("tag", tag.to_string()));
+        }
+        if let Some(key) = self.key() {
+            pairs.push(("key", key.value().to_string()));
+        }
+        if let Some(time_signature) = self.time_signature() {
+            pairs.push(("time_signature", time_signature.value().to_string()));
+        }
+        if let Some(tempo) = self.tempo() {
+            pairs.push(("tempo", tempo.value().to_string()));
+        }
+        if let Some(instrumentation) = self.instrumentation() {
+            pairs.push(("instrumentation", instrumentation.value().to_string()));
+        }
+        if let Some(difficulty) = self.max_difficulty() {
+            pairs.push(("max_difficulty", difficulty.value().to_string()));
+        }
+        if let Some(person) = self.person() {
+            pairs.push(("person", person.to_string()));
+        }
+        if let Some(genre) = self.genre() {
+            pairs.push(("genre", genre.to_string()));
+        }
+        if let Some(composer) = self.composer() {
+            pairs.push(("composer", composer.to_string()));
+        }
+        if let Some(difficulty) = self.difficulty() {
+            pairs.push(("difficulty", difficulty.value().to_string()));
+        }
+        pairs
+    }
+
+    ///
+    /// Returns the criteria of the filter in words (first page of the PDF catalogue)
+    /// e.g. "« noël » · tag : Gospel · SATB · niveau max 3", empty without criteria
+    ///
+    pub fn criteria_text(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(q) = self.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+            parts.push(format!("« {} »", q));
+        }
+        if let Some(tag) = self.tag() {
+            parts.push(format!("tag : {}", tag));
+        }
+        if let Some(key) = self.key() {
+            parts.push(key.label().to_string());
+        }
+        if let Some(time_signature) = self.time_signature() {
+            parts.push(time_signature.label().to_string());
+        }
+        if let Some(tempo) = self.tempo() {
+            parts.push(tempo.label().to_string());
+        }
+        if let Some(instrumentation) = self.instrumentation() {
+            parts.push(instrumentation.label().to_string());
+        }
+        if let Some(difficulty) = self.max_difficulty() {
+            parts.push(format!("niveau max {}", difficulty.value()));
+        }
+        if let Some(person) = self.person() {
+            parts.push(format!("musicien n° {}", person));
+        }
+        if let Some(genre) = self.genre() {
+            parts.push(format!("genre n° {}", genre));
+        }
+        if let Some(composer) = self.composer() {
+            parts.push(format!("compositeur n° {}", composer));
+        }
+        if let Some(difficulty) = self.difficulty() {
+            parts.push(difficulty.label().to_string());
+        }
+        parts.join(" · ")
+    }
+
+    ///
+    /// Returns the tag asked or None if the field is empty
+    ///
+    pub fn tag(&self) -> Option<&str> {
+        self.tag
+            .as_deref()
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+    }
+
+    ///
+    /// Returns the full-text query (to_tsquery syntax) built from the words of `q`
+    /// or None if there is nothing to search.
+    ///
+    /// Every word is a prefix ("noct" finds "Nocturne") and all the words
+    /// must be found : "chopin noct" -> "chopin:* & noct:*"
+    ///
+    pub fn ts_query(&self) -> Option<String> {
+        let words = self
+            .q
+            .as_deref()
+            .unwrap_or_default()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| format!("{}:*", word.to_lowercase()))
+            .collect::<Vec<String>>();
+        if words.is_empty() {
+            None
+        } else {
+            Some(words.join(" & "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_in_minutes_and_seconds() {
+        assert_eq!(parse_duration("3:45"), Ok(225));
+        assert_eq!(parse_duration(" 0:07 "), Ok(7));
+        assert_eq!(parse_duration("12 : 00"), Ok(720));
+        assert_eq!(parse_duration("4"), Ok(240));
+    }
+
+    #[test]
+    fn invalid_durations() {
+        for text in [
+            "", "abc", "3:60", "3:-1", "-2", "-1:30", "3:4:5", "3,5", "99999999",
+        ] {
+            assert!(parse_duration(text).is_err(), "{text}");
+        }
+        assert_eq!(
+            parse_duration("x"),
+            Err("durée invalide : x (format m:ss)".to_string())
+        );
+    }
+
+    #[test]
+    fn duration_is_formatted_back() {
+        assert_eq!(format_duration(225), "3:45");
+        assert_eq!(format_duration(7), "0:07");
+        assert_eq!(parse_duration(&format_duration(3_725)), Ok(3_725));
+    }
 }
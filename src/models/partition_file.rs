@@ -0,0 +1,36 @@
+//! src/models/partition_file.rs
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+///
+/// A file (PDF, MusicXML, image) attached to a partition
+///
+/// * file_name : the name of the file when uploaded
+/// * stored_name : the name of the file on the disk
+/// * checksum : SHA-256 of the content (hexadecimal)
+///
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PartitionFile {
+    pub id: i32,
+    pub partition_id: i32,
+    pub file_name: String,
+    pub stored_name: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    pub checksum: String,
+}
+
+impl PartitionFile {
+    ///
+    /// Returns the size in a readable form (ko or Mo)
+    /// used in the templates
+    ///
+    pub fn readable_size(&self) -> String {
+        if self.size_bytes < 1024 * 1024 {
+            format!("{:.1} ko", self.size_bytes as f64 / 1024.0)
+        } else {
+            format!("{:.1} Mo", self.size_bytes as f64 / (1024.0 * 1024.0))
+        }
+    }
+}
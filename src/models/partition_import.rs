@@ -0,0 +1,341 @@
+//! src/models/partition_import.rs
+//!
+//! Bulk import of partitions from a CSV file (spreadsheet export)
+//! the rows are read here, the musicians and genres are resolved in db::partition_import
+//!
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::partition::{PartitionDetails, PartitionForm};
+
+///
+/// Columns of the CSV file : the header row gives the name of each column
+/// (case insensitive, the French names are accepted) and the corresponding
+/// field of the partition form<br>
+/// title, composer and genre are required, the other columns are optional
+///
+const IMPORT_COLUMNS: [(&str, &str); 20] = [
+    ("title", "title"),
+    ("titre", "title"),
+    ("composer", "full_name"),
+    ("compositeur", "full_name"),
+    ("musicien", "full_name"),
+    ("genre", "name"),
+    ("tags", "tags"),
+    ("musical_key", "musical_key"),
+    ("tonalite", "musical_key"),
+    ("tonalité", "musical_key"),
+    ("time_signature", "time_signature"),
+    ("mesure", "time_signature"),
+    ("tempo", "tempo"),
+    ("duration", "duration"),
+    ("duree", "duration"),
+    ("durée", "duration"),
+    ("instrumentation", "instrumentation"),
+    ("difficulty", "difficulty"),
+    ("difficulte", "difficulty"),
+    ("difficulté", "difficulty"),
+];
+
+///
+/// Options of the import (checkboxes of the upload form)
+/// * create_persons : the unknown musicians are created
+/// * create_genres : the unknown genres are created
+///
+/// Without the option an unknown musician or genre is an error of the row
+///
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ImportOptions {
+    #[serde(default, deserialize_with = "checkbox")]
+    pub create_persons: bool,
+    #[serde(default, deserialize_with = "checkbox")]
+    pub create_genres: bool,
+}
+
+///
+/// A checked checkbox sends "on", an unchecked one sends nothing
+///
+fn checkbox<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Ok(!value.is_empty() && value != "off")
+}
+
+///
+/// # ImportRow
+/// One row of the CSV file
+///
+/// * line : the number of the row in the file (the header is row 1)
+/// * title, full_name (main musician), name (genre) and details : as in the partition form
+/// * new_person, new_genre : the musician or the genre will be created by the import
+/// * errors : why the row cannot be imported, empty if the row is valid
+///
+#[derive(Debug, Clone, Default)]
+pub struct ImportRow {
+    pub line: usize,
+    pub title: String,
+    pub full_name: String,
+    pub name: String,
+    pub details: PartitionDetails,
+    pub new_person: bool,
+    pub new_genre: bool,
+    pub errors: Vec<String>,
+}
+
+impl ImportRow {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn tags_text(&self) -> String {
+        self.details.tags.join(", ")
+    }
+
+    pub fn errors_text(&self) -> String {
+        self.errors.join(" ; ")
+    }
+}
+
+///
+/// # ImportReport
+/// Result of the dry-run : the rows with their errors
+/// and the musicians and genres that the import will create
+///
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    pub rows: Vec<ImportRow>,
+    pub new_persons: Vec<String>,
+    pub new_genres: Vec<String>,
+}
+
+impl ImportReport {
+    ///
+    /// The import is possible only if there are rows and all the rows are valid
+    ///
+    pub fn is_valid(&self) -> bool {
+        !self.rows.is_empty() && self.rows.iter().all(ImportRow::is_valid)
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.rows.iter().filter(|row| !row.is_valid()).count()
+    }
+}
+
+///
+/// # Summary of a committed import
+///
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ImportSummary {
+    pub partitions: usize,
+    pub persons: usize,
+    pub genres: usize,
+}
+
+///
+/// **Reads the CSV file**<br>
+/// the separator is ";" (French spreadsheets) or "," : the one found the most in the header<br>
+/// returns the rows, with the errors of their values (missing title, unknown key, ...),
+/// or an error message if the header is wrong
+///
+pub fn parse_import_csv(text: &str) -> Result<Vec<ImportRow>, String> {
+    let text = text.trim_start_matches('\u{feff}');
+    let header = text.lines().next().unwrap_or_default();
+    let delimiter = if header.matches(';').count() > header.matches(',').count() {
+        b';'
+    } else {
+        b','
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+
+    let columns = reader
+        .headers()
+        .map_err(|err| format!("en-tête illisible : {err}"))?
+        .iter()
+        .map(|name| {
+            let name = name.to_lowercase();
+            IMPORT_COLUMNS
+                .iter()
+                .find(|(column, _)| *column == name)
+                .map(|(_, field)| *field)
+        })
+        .collect::<Vec<_>>();
+    for required in ["title", "full_name", "name"] {
+        if !columns.contains(&Some(required)) {
+            return Err(
+                "colonnes obligatoires : title (titre), composer (compositeur), genre".to_string(),
+            );
+        }
+    }
+
+    let new_lines: Vec<usize> = text.match_indices('\n').map(|(offset, _)| offset).collect();
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        // a quoted value can contain new lines and the empty lines are not records :
+        // the line of the row is counted in the file up to its first byte
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                let line = err
+                    .position()
+                    .map_or(index + 2, |p| line_of(text, &new_lines, p));
+                rows.push(ImportRow {
+                    line,
+                    errors: vec![format!("ligne illisible : {err}")],
+                    ..Default::default()
+                });
+                continue;
+            }
+        };
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+        let line = record
+            .position()
+            .map_or(index + 2, |p| line_of(text, &new_lines, p));
+        let fields = columns
+            .iter()
+            .zip(record.iter())
+            .filter_map(|(field, value)| Some((field.as_ref()?.to_string(), value.to_string())))
+            .collect::<Vec<(String, String)>>();
+        rows.push(import_row_from_fields(line, fields));
+    }
+    Ok(rows)
+}
+
+///
+/// Builds a row with the validation of the partition form
+///
+fn import_row_from_fields(line: usize, fields: Vec<(String, String)>) -> ImportRow {
+    let value = |name: &str| {
+        fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    };
+    let mut row = ImportRow {
+        line,
+        title: value("title"),
+        full_name: value("full_name"),
+        name: value("name"),
+        ..Default::default()
+    };
+    if row.title.is_empty() {
+        row.errors.push("titre manquant".to_string());
+    }
+    if row.full_name.is_empty() {
+        row.errors.push("compositeur manquant".to_string());
+    }
+    if row.name.is_empty() {
+        row.errors.push("genre manquant".to_string());
+    }
+    match PartitionForm::from_fields(fields) {
+        Ok(form) => row.details = form.details,
+        Err(cause) => row.errors.push(cause),
+    }
+    row
+}
+
+///
+/// Returns the line (from 1) of a record in the file, from the offsets of its new lines
+/// (the position given by the reader is before the empty lines skipped)
+///
+fn line_of(text: &str, new_lines: &[usize], position: &csv::Position) -> usize {
+    let start = position.byte() as usize;
+    let skipped = text
+        .get(start..)
+        .unwrap_or_default()
+        .bytes()
+        .take_while(|b| matches!(b, b'\r' | b'\n'))
+        .count();
+    new_lines.partition_point(|&offset| offset < start + skipped) + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::partition::{Difficulty, MusicalKey};
+
+    fn lines(rows: &[ImportRow]) -> Vec<usize> {
+        rows.iter().map(|row| row.line).collect()
+    }
+
+    #[test]
+    fn semicolons_and_french_header() {
+        let text = "Titre;Compositeur;Genre;Tonalité;Durée;Difficulté\n\
+                    Ave Maria;Schubert;Sacré;D minor;3:45;3\n";
+        let rows = parse_import_csv(text).unwrap();
+        assert_eq!(rows.len(), 1);
+        let row = &rows[0];
+        assert!(row.is_valid(), "{}", row.errors_text());
+        assert_eq!(row.line, 2);
+        assert_eq!(row.title, "Ave Maria");
+        assert_eq!(row.full_name, "Schubert");
+        assert_eq!(row.name, "Sacré");
+        assert_eq!(row.details.metadata.key, Some(MusicalKey::DMinor));
+        assert_eq!(row.details.metadata.duration_seconds, Some(225));
+        assert_eq!(row.details.metadata.difficulty, Some(Difficulty::Grade3));
+    }
+
+    #[test]
+    fn commas_and_quoted_values() {
+        let text = "TITLE,composer,genre,tags,unknown\n\
+                    \"Noël, nouvelet\", Anonyme ,Noël,\"Noël, Gospel\",ignored\n";
+        let rows = parse_import_csv(text).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].title, "Noël, nouvelet");
+        assert_eq!(rows[0].full_name, "Anonyme");
+        assert_eq!(rows[0].tags_text(), "Noël, Gospel");
+    }
+
+    #[test]
+    fn byte_order_mark_is_ignored() {
+        let text = "\u{feff}titre;compositeur;genre\nRequiem;Fauré;Sacré\n";
+        let rows = parse_import_csv(text).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].title, "Requiem");
+    }
+
+    #[test]
+    fn required_columns() {
+        for text in ["title,composer\nRequiem,Fauré\n", "", "titre;genre;tags\n"] {
+            assert_eq!(
+                parse_import_csv(text).unwrap_err(),
+                "colonnes obligatoires : title (titre), composer (compositeur), genre"
+            );
+        }
+    }
+
+    #[test]
+    fn blank_rows_are_skipped() {
+        let text = "title,composer,genre\nRequiem,Fauré,Sacré\n,,\n\nMessiah,Haendel,Oratorio\n";
+        let rows = parse_import_csv(text).unwrap();
+        assert_eq!(lines(&rows), vec![2, 5]);
+    }
+
+    #[test]
+    fn multi_line_quoted_values() {
+        let text = "title,composer,genre\n\
+                    \"Ave\nverum\",Mozart,Sacré\n\
+                    ,Fauré,Sacré\n";
+        let rows = parse_import_csv(text).unwrap();
+        assert_eq!(lines(&rows), vec![2, 4]);
+        assert_eq!(rows[0].title, "Ave\nverum");
+        assert!(rows[0].is_valid());
+        assert_eq!(rows[1].errors, vec!["titre manquant".to_string()]);
+    }
+
+    #[test]
+    fn errors_of_the_rows() {
+        let text = "title;composer;genre;musical_key;duration\n\
+                    Requiem;;Sacré;H major;abc\n";
+        let rows = parse_import_csv(text).unwrap();
+        assert!(!rows[0].is_valid());
+        assert_eq!(rows[0].errors[0], "compositeur manquant");
+        assert_eq!(rows[0].errors.len(), 2);
+    }
+}
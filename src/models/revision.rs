@@ -0,0 +1,407 @@
+//! src/models/revision.rs
+//!
+//! Revision history of the partitions : the versions of a partition are read
+//! in the audit log (the record after each change), they can be compared
+//! field by field and a partition can be reverted to one of them
+//!
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::models::audit::{AuditAction, AuditEntry, FieldChange};
+use crate::models::partition::{format_duration, Contributor, MusicalMetadata};
+use crate::models::tag::PartitionTag;
+
+///
+/// # PartitionSnapshot
+/// A partition as recorded in the audit log (JSON of a ShowPartition)
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PartitionSnapshot {
+    pub title: String,
+    #[serde(default)]
+    pub full_name: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub contributors: Vec<Contributor>,
+    #[serde(default)]
+    pub tags: Vec<PartitionTag>,
+    #[serde(default)]
+    pub metadata: MusicalMetadata,
+}
+
+impl PartitionSnapshot {
+    /// reads the JSON of the audit log (None if it is not a partition)
+    pub fn parse(json: Option<&str>) -> Option<Self> {
+        serde_json::from_str(json?).ok()
+    }
+
+    /// the contributors other than the main musician (as composer)
+    fn other_contributors(&self) -> Vec<&Contributor> {
+        self.contributors
+            .iter()
+            .filter(|c| !c.is_main_musician(&self.full_name))
+            .collect()
+    }
+
+    ///
+    /// Returns the fields of the version as (label, value) in the order of the form
+    /// (an unknown value is "")
+    ///
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let metadata = &self.metadata;
+        let label = |value: Option<&'static str>| value.unwrap_or_default().to_string();
+        vec![
+            ("Titre", self.title.clone()),
+            ("Musicien", self.full_name.clone()),
+            ("Genre", self.name.clone()),
+            (
+                "Autres musiciens",
+                self.other_contributors()
+                    .iter()
+                    .map(|c| format!("{} ({})", c.full_name, c.role.label()))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            ),
+            (
+                "Tags",
+                self.tags
+                    .iter()
+                    .map(|tag| tag.name.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", "),
+            ),
+            ("Tonalité", label(metadata.key.map(|v| v.label()))),
+            ("Mesure", label(metadata.time_signature.map(|v| v.label()))),
+            ("Tempo", label(metadata.tempo.map(|v| v.label()))),
+            (
+                "Durée",
+                metadata
+                    .duration_seconds
+                    .map(format_duration)
+                    .unwrap_or_default(),
+            ),
+            (
+                "Instrumentation",
+                label(metadata.instrumentation.map(|v| v.label())),
+            ),
+            ("Difficulté", label(metadata.difficulty.map(|v| v.label()))),
+        ]
+    }
+
+    ///
+    /// Returns the fields that differ between this version and another one
+    /// (before : this version, after : the other one)
+    ///
+    pub fn diff(&self, other: &PartitionSnapshot) -> Vec<FieldChange> {
+        self.fields()
+            .into_iter()
+            .zip(other.fields())
+            .filter(|((_, before), (_, after))| before != after)
+            .map(|((field, before), (_, after))| FieldChange {
+                field: field.to_string(),
+                before,
+                after,
+            })
+            .collect()
+    }
+
+    ///
+    /// Returns the version as the fields of the partition form
+    /// so that a revert is checked like a modification (PartitionForm::from_fields)
+    ///
+    pub fn form_fields(&self) -> Vec<(String, String)> {
+        let metadata = &self.metadata;
+        let mut fields = vec![
+            ("title".to_string(), self.title.clone()),
+            ("full_name".to_string(), self.full_name.clone()),
+            ("name".to_string(), self.name.clone()),
+        ];
+        for contributor in self.other_contributors() {
+            fields.push(("role".to_string(), contributor.role.value().to_string()));
+            fields.push(("contributor".to_string(), contributor.person_id.to_string()));
+        }
+        let value = |value: Option<&'static str>| value.unwrap_or_default().to_string();
+        fields.extend([
+            (
+                "tags".to_string(),
+                self.tags
+                    .iter()
+                    .map(|tag| tag.name.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", "),
+            ),
+            (
+                "musical_key".to_string(),
+                value(metadata.key.map(|v| v.value())),
+            ),
+            (
+                "time_signature".to_string(),
+                value(metadata.time_signature.map(|v| v.value())),
+            ),
+            (
+                "tempo".to_string(),
+                value(metadata.tempo.map(|v| v.value())),
+            ),
+            (
+                "duration".to_string(),
+                metadata
+                    .duration_seconds
+                    .map(format_duration)
+                    .unwrap_or_default(),
+            ),
+            (
+                "instrumentation".to_string(),
+                value(metadata.instrumentation.map(|v| v.value())),
+            ),
+            (
+                "difficulty".to_string(),
+                value(metadata.difficulty.map(|v| v.value())),
+            ),
+        ]);
+        fields
+    }
+}
+
+///
+/// # PartitionRevision
+/// A version of a partition
+///
+/// * number : 1 for the oldest version known
+/// * action, actor_name, created_at : the change that gave this version
+///   (None for the version before the first change recorded : a partition
+///   created before the audit log existed)
+///
+#[derive(Debug, Clone)]
+pub struct PartitionRevision {
+    pub number: usize,
+    pub action: Option<AuditAction>,
+    pub actor_name: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub snapshot: PartitionSnapshot,
+}
+
+impl PartitionRevision {
+    ///
+    /// Builds the versions of a partition from its audit entries (the oldest first) :
+    /// the record after each creation, modification or restoration, and the record
+    /// before the first change if the creation was not recorded
+    ///
+    pub fn from_entries(entries: &[AuditEntry]) -> Vec<PartitionRevision> {
+        let mut snapshots: Vec<(Option<&AuditEntry>, PartitionSnapshot)> = Vec::new();
+        if let Some(first) = entries.first() {
+            if let Some(before) = PartitionSnapshot::parse(first.before.as_deref()) {
+                snapshots.push((None, before));
+            }
+        }
+        for entry in entries {
+            if let Some(after) = PartitionSnapshot::parse(entry.after.as_deref()) {
+                snapshots.push((Some(entry), after));
+            }
+        }
+        snapshots
+            .into_iter()
+            .enumerate()
+            .map(|(index, (entry, snapshot))| PartitionRevision {
+                number: index + 1,
+                action: entry.map(|e| e.action),
+                actor_name: entry.map(|e| e.actor_name.clone()).unwrap_or_default(),
+                created_at: entry.map(|e| e.created_at),
+                snapshot,
+            })
+            .collect()
+    }
+
+    pub fn created_text(&self) -> String {
+        match self.created_at {
+            Some(created_at) => created_at.format("%d/%m/%Y %H:%M:%S").to_string(),
+            None => "avant le journal".to_string(),
+        }
+    }
+
+    pub fn action_label(&self) -> &'static str {
+        self.action.map(|a| a.label()).unwrap_or("État initial")
+    }
+}
+
+///
+/// # RevisionParams
+/// The two versions compared on the history page *'?from=2&to=5'*
+/// (default : the last two versions)
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RevisionParams {
+    pub from: Option<usize>,
+    pub to: Option<usize>,
+}
+
+impl RevisionParams {
+    ///
+    /// Returns the numbers of the versions to compare among `count` versions
+    /// (None when there is less than two versions)
+    ///
+    pub fn pair(&self, count: usize) -> Option<(usize, usize)> {
+        if count < 2 {
+            return None;
+        }
+        let valid = |n: &usize| (1..=count).contains(n);
+        let from = self.from.filter(valid).unwrap_or(count - 1);
+        let to = self.to.filter(valid).unwrap_or(count);
+        Some((from, to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    use crate::models::audit::AuditEntity;
+    use crate::models::partition::{ContributorRole, NewContributor, PartitionForm};
+
+    const FIRST: &str = r#"{
+        "title": "Ave Maria",
+        "full_name": "Schubert",
+        "name": "Sacré",
+        "contributors": [
+            { "partition_id": 1, "person_id": 1, "full_name": "Schubert", "role": "composer" }
+        ],
+        "tags": [],
+        "metadata": {}
+    }"#;
+
+    const SECOND: &str = r#"{
+        "title": "Ave Maria (D 839)",
+        "full_name": "Schubert",
+        "name": "Sacré",
+        "contributors": [
+            { "partition_id": 1, "person_id": 1, "full_name": "Schubert", "role": "composer" },
+            { "partition_id": 1, "person_id": 2, "full_name": "Liszt", "role": "arranger" }
+        ],
+        "tags": [{ "partition_id": 1, "tag_id": 3, "name": "Noël" }],
+        "metadata": { "key": "D minor", "duration_seconds": 225, "difficulty": "3" }
+    }"#;
+
+    fn entry(action: AuditAction, before: Option<&str>, after: Option<&str>) -> AuditEntry {
+        AuditEntry {
+            id: 1,
+            actor_id: None,
+            actor_name: "Jean".to_string(),
+            action,
+            entity: AuditEntity::Partition,
+            entity_id: Some(1),
+            before: before.map(str::to_string),
+            after: after.map(str::to_string),
+            created_at: Utc.with_ymd_and_hms(2023, 7, 14, 20, 0, 0).unwrap(),
+        }
+    }
+
+    fn snapshot(json: &str) -> PartitionSnapshot {
+        PartitionSnapshot::parse(Some(json)).unwrap()
+    }
+
+    #[test]
+    fn versions_from_the_creation() {
+        let entries = [
+            entry(AuditAction::Create, None, Some(FIRST)),
+            entry(AuditAction::Update, Some(FIRST), Some(SECOND)),
+        ];
+        let revisions = PartitionRevision::from_entries(&entries);
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0].number, 1);
+        assert_eq!(revisions[0].action, Some(AuditAction::Create));
+        assert_eq!(revisions[0].snapshot.title, "Ave Maria");
+        assert_eq!(revisions[1].number, 2);
+        assert_eq!(revisions[1].action_label(), "Modification");
+        assert_eq!(revisions[1].created_text(), "14/07/2023 20:00:00");
+        assert_eq!(revisions[1].snapshot.title, "Ave Maria (D 839)");
+    }
+
+    #[test]
+    fn version_before_the_first_recorded_change() {
+        let entries = [entry(AuditAction::Update, Some(FIRST), Some(SECOND))];
+        let revisions = PartitionRevision::from_entries(&entries);
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0].action, None);
+        assert_eq!(revisions[0].actor_name, "");
+        assert_eq!(revisions[0].action_label(), "État initial");
+        assert_eq!(revisions[0].created_text(), "avant le journal");
+        assert_eq!(revisions[0].snapshot.title, "Ave Maria");
+        assert_eq!(revisions[1].action, Some(AuditAction::Update));
+        assert_eq!(revisions[1].actor_name, "Jean");
+    }
+
+    #[test]
+    fn deletions_are_not_versions() {
+        let entries = [
+            entry(AuditAction::Create, None, Some(FIRST)),
+            entry(AuditAction::Delete, Some(FIRST), None),
+            entry(AuditAction::Restore, None, Some(FIRST)),
+        ];
+        let revisions = PartitionRevision::from_entries(&entries);
+        let actions: Vec<Option<AuditAction>> = revisions.iter().map(|r| r.action).collect();
+        assert_eq!(
+            actions,
+            vec![Some(AuditAction::Create), Some(AuditAction::Restore)]
+        );
+        assert_eq!(revisions[1].number, 2);
+        assert!(PartitionRevision::from_entries(&[]).is_empty());
+    }
+
+    #[test]
+    fn diff_of_two_versions() {
+        let changes = snapshot(FIRST).diff(&snapshot(SECOND));
+        let fields: Vec<&str> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "Titre",
+                "Autres musiciens",
+                "Tags",
+                "Tonalité",
+                "Durée",
+                "Difficulté"
+            ]
+        );
+        assert_eq!(
+            changes[1],
+            FieldChange {
+                field: "Autres musiciens".to_string(),
+                before: String::new(),
+                after: "Liszt (arrangeur)".to_string(),
+            }
+        );
+        assert_eq!(changes[4].after, "3:45");
+        assert!(snapshot(SECOND).diff(&snapshot(SECOND)).is_empty());
+    }
+
+    #[test]
+    fn version_as_form_fields() {
+        let version = snapshot(SECOND);
+        let form = PartitionForm::from_fields(version.form_fields()).unwrap();
+        assert_eq!(form.title, "Ave Maria (D 839)");
+        assert_eq!(form.full_name, "Schubert");
+        assert_eq!(form.name, "Sacré");
+        // the main musician is not one of the other musicians
+        assert_eq!(
+            form.details.contributors,
+            vec![NewContributor {
+                person_id: 2,
+                role: ContributorRole::Arranger,
+            }]
+        );
+        assert_eq!(form.details.tags, vec!["Noël".to_string()]);
+        assert_eq!(form.details.metadata, version.metadata);
+    }
+
+    #[test]
+    fn versions_compared() {
+        let params = |from, to| RevisionParams { from, to };
+        assert_eq!(params(None, None).pair(0), None);
+        assert_eq!(params(Some(1), Some(1)).pair(1), None);
+        assert_eq!(params(None, None).pair(5), Some((4, 5)));
+        assert_eq!(params(Some(2), Some(4)).pair(5), Some((2, 4)));
+        assert_eq!(params(Some(0), Some(9)).pair(5), Some((4, 5)));
+    }
+}
@@ -0,0 +1,156 @@
+//! src/models/setlist.rs
+//!
+//! Setlists : the partitions played in a concert, in order,
+//! with a note for each piece (soloist, repeat, cut ...)
+//!
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+use sqlx::FromRow;
+
+use crate::models::partition::format_duration;
+
+///
+/// # Setlist
+/// A named and dated list of partitions
+///
+/// * items : the number of pieces
+/// * total_seconds : the sum of the known durations of the pieces
+/// * unknown_durations : the number of pieces without duration
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct Setlist {
+    pub id: i32,
+    pub name: String,
+    pub performance_date: NaiveDate,
+    pub items: i64,
+    pub total_seconds: i64,
+    pub unknown_durations: i64,
+}
+
+impl Setlist {
+    pub fn date_text(&self) -> String {
+        self.performance_date.format("%d/%m/%Y").to_string()
+    }
+
+    /// the date for the input type="date" of the forms ("2023-07-14")
+    pub fn date_value(&self) -> String {
+        self.performance_date.format("%Y-%m-%d").to_string()
+    }
+
+    /// the total duration of the pieces : "1:02:30" or "45:10"
+    pub fn total_duration_text(&self) -> String {
+        let (hours, minutes, seconds) = (
+            self.total_seconds / 3600,
+            self.total_seconds % 3600 / 60,
+            self.total_seconds % 60,
+        );
+        if hours > 0 {
+            format!("{}:{:02}:{:02}", hours, minutes, seconds)
+        } else {
+            format!("{}:{:02}", minutes, seconds)
+        }
+    }
+}
+
+///
+/// # SetlistItem
+/// A piece of a setlist with what the pages and the programme show
+///
+/// * position : the order in the setlist, starting at 1
+/// * full_name : the composer of the partition
+/// * note : free text ("avec soliste", "sans la reprise" ...)
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct SetlistItem {
+    pub id: i32,
+    pub setlist_id: i32,
+    pub position: i32,
+    pub partition_id: i32,
+    pub title: String,
+    pub full_name: String,
+    pub duration_seconds: Option<i32>,
+    pub note: String,
+}
+
+impl SetlistItem {
+    /// the duration of the piece ("3:45"), empty if unknown
+    pub fn duration_text(&self) -> String {
+        self.duration_seconds
+            .map(format_duration)
+            .unwrap_or_default()
+    }
+}
+
+///
+/// # PartitionChoice
+/// A partition proposed in the form to add a piece to a setlist
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct PartitionChoice {
+    pub id: i32,
+    pub title: String,
+    pub full_name: String,
+}
+
+///
+/// # SetlistForm
+/// Fields of the forms to create or modify a setlist
+/// * performance_date : "2023-07-14" (input type="date")
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetlistForm {
+    pub name: String,
+    pub performance_date: String,
+}
+
+impl SetlistForm {
+    ///
+    /// Reads the name and the date
+    /// returns an error message if a field is empty or invalid
+    ///
+    pub fn parse(&self) -> Result<(String, NaiveDate), String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("le nom est vide".to_string());
+        }
+        let date = NaiveDate::parse_from_str(self.performance_date.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("date invalide : {}", self.performance_date))?;
+        Ok((name.to_string(), date))
+    }
+}
+
+///
+/// Form to add a partition at the end of a setlist
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetlistItemForm {
+    pub partition_id: i32,
+    pub note: String,
+}
+
+///
+/// Form to modify the note of a piece
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct NoteForm {
+    pub note: String,
+}
+
+///
+/// Form to move a piece : the new position (the buttons up and down
+/// send the position before or after the current one)
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct MoveForm {
+    pub position: String,
+}
+
+impl MoveForm {
+    pub fn position(&self) -> Result<i32, String> {
+        self.position
+            .trim()
+            .parse()
+            .map_err(|_| format!("position invalide : {}", self.position))
+    }
+}
@@ -0,0 +1,308 @@
+//! src/models/statistics.rs
+//!
+//! Statistics of the catalogue and their bar charts, drawn in SVG by the templates :
+//! the position and the size of every bar are computed here, so the page
+//! needs no JavaScript
+//!
+
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// the width of the charts (SVG units)
+const CHART_WIDTH: i32 = 600;
+/// the height of the bars of the vertical charts
+const COLUMNS_HEIGHT: i32 = 180;
+/// the room under the bars of the vertical charts (labels)
+const COLUMNS_BOTTOM: i32 = 24;
+/// the room above the bars of the vertical charts (values)
+const COLUMNS_TOP: i32 = 16;
+/// the height of a row of the horizontal charts
+const ROW_HEIGHT: i32 = 22;
+/// the width of the labels of the horizontal charts
+const ROW_LABEL_WIDTH: i32 = 200;
+/// the room right of the bars of the horizontal charts (values)
+const ROW_VALUE_WIDTH: i32 = 80;
+/// the number of characters of the labels of the horizontal charts
+const ROW_LABEL_CHARS: usize = 30;
+
+///
+/// # CatalogueTotals
+/// The numbers shown at the top of the statistics page and on the welcome page
+///
+/// * composers : the musicians who composed at least one partition
+///   (main musician or other musician with the role composer)
+/// * loans_year : the loans of the last twelve months
+///
+#[derive(Debug, Clone, Default, Serialize, FromRow)]
+pub struct CatalogueTotals {
+    pub partitions: i64,
+    pub composers: i64,
+    pub genres: i64,
+    pub open_loans: i64,
+    pub overdue_loans: i64,
+    pub loans_year: i64,
+}
+
+///
+/// # ChartValue
+/// A value of a chart read in the DB : a month, a composer, a genre ...
+///
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ChartValue {
+    pub label: String,
+    pub value: i64,
+}
+
+///
+/// # Bar
+/// A bar of a chart, in SVG units
+///
+/// * short_label : the label shown, the whole label is the tooltip of the bar
+/// * label_x, label_y : the position of the label (under the bar or left of it)
+/// * value_x, value_y : the position of the value (above the bar or right of it)
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct Bar {
+    pub label: String,
+    pub short_label: String,
+    pub value: i64,
+    pub value_text: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub label_x: i32,
+    pub label_y: i32,
+    pub value_x: i32,
+    pub value_y: i32,
+}
+
+///
+/// # BarChart
+/// A chart ready to be drawn : the size of the SVG and its bars
+///
+/// * label_anchor, value_anchor : the SVG text-anchor of the labels and of the values
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct BarChart {
+    pub width: i32,
+    pub height: i32,
+    pub bars: Vec<Bar>,
+    pub label_anchor: &'static str,
+    pub value_anchor: &'static str,
+}
+
+impl BarChart {
+    ///
+    /// A chart with vertical bars, the labels under the bars (e.g. one bar per month)
+    ///
+    pub fn columns(values: &[ChartValue]) -> Self {
+        let max = max_value(values);
+        let slot = CHART_WIDTH / values.len().max(1) as i32;
+        let width = (slot * 7 / 10).max(1);
+        let bottom = COLUMNS_TOP + COLUMNS_HEIGHT;
+        let bars = values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let height = scale(value.value, max, COLUMNS_HEIGHT);
+                let x = index as i32 * slot + (slot - width) / 2;
+                Bar {
+                    label: value.label.clone(),
+                    short_label: value.label.clone(),
+                    value: value.value,
+                    value_text: value.value.to_string(),
+                    x,
+                    y: bottom - height,
+                    width,
+                    height,
+                    label_x: x + width / 2,
+                    label_y: bottom + COLUMNS_BOTTOM - 8,
+                    value_x: x + width / 2,
+                    value_y: bottom - height - 4,
+                }
+            })
+            .collect();
+        Self {
+            width: CHART_WIDTH,
+            height: bottom + COLUMNS_BOTTOM,
+            bars,
+            label_anchor: "middle",
+            value_anchor: "middle",
+        }
+    }
+
+    ///
+    /// A chart with horizontal bars, the labels left of the bars (e.g. a ranking)<br>
+    /// with a total, the values show their share of it : "12 (34 %)"
+    ///
+    pub fn rows(values: &[ChartValue], total: Option<i64>) -> Self {
+        let max = max_value(values);
+        let bar_width = CHART_WIDTH - ROW_LABEL_WIDTH - ROW_VALUE_WIDTH;
+        let bars = values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let width = scale(value.value, max, bar_width);
+                let y = index as i32 * ROW_HEIGHT + 2;
+                let value_text = match total.filter(|total| *total > 0) {
+                    Some(total) => format!("{} ({:.0} %)", value.value, share(value.value, total)),
+                    None => value.value.to_string(),
+                };
+                Bar {
+                    label: value.label.clone(),
+                    short_label: shorten(&value.label, ROW_LABEL_CHARS),
+                    value: value.value,
+                    value_text,
+                    x: ROW_LABEL_WIDTH,
+                    y,
+                    width,
+                    height: ROW_HEIGHT - 6,
+                    label_x: ROW_LABEL_WIDTH - 6,
+                    label_y: y + ROW_HEIGHT - 10,
+                    value_x: ROW_LABEL_WIDTH + width + 6,
+                    value_y: y + ROW_HEIGHT - 10,
+                }
+            })
+            .collect::<Vec<Bar>>();
+        Self {
+            width: CHART_WIDTH,
+            height: (values.len() as i32 * ROW_HEIGHT).max(ROW_HEIGHT) + 4,
+            bars,
+            label_anchor: "end",
+            value_anchor: "start",
+        }
+    }
+
+    /// true if there is nothing to draw (no value or only zeros)
+    pub fn is_empty(&self) -> bool {
+        self.bars.iter().all(|bar| bar.value == 0)
+    }
+
+    /// the SVG viewBox of the chart
+    pub fn view_box(&self) -> String {
+        format!("0 0 {} {}", self.width, self.height)
+    }
+}
+
+///
+/// # Statistics
+/// The statistics page : the totals and the charts
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct Statistics {
+    pub totals: CatalogueTotals,
+    pub top_composers: BarChart,
+    pub partitions_per_month: BarChart,
+    pub genres: BarChart,
+    pub loans_per_month: BarChart,
+    pub most_borrowed: BarChart,
+}
+
+/// the highest value of a chart (at least 1)
+fn max_value(values: &[ChartValue]) -> i64 {
+    values.iter().map(|v| v.value).max().unwrap_or(0).max(1)
+}
+
+/// the length of a bar : `value` on a scale where `max` is `length`
+fn scale(value: i64, max: i64, length: i32) -> i32 {
+    (value.max(0) * length as i64 / max) as i32
+}
+
+/// the first characters of a label, "…" if it is cut
+fn shorten(label: &str, chars: usize) -> String {
+    if label.chars().count() <= chars {
+        return label.to_string();
+    }
+    let mut short: String = label.chars().take(chars - 1).collect();
+    short.push('…');
+    short
+}
+
+/// the share of a value in a total (percent)
+fn share(value: i64, total: i64) -> f64 {
+    value as f64 * 100.0 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(values: &[(&str, i64)]) -> Vec<ChartValue> {
+        values
+            .iter()
+            .map(|(label, value)| ChartValue {
+                label: label.to_string(),
+                value: *value,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty_charts() {
+        let columns = BarChart::columns(&[]);
+        assert!(columns.bars.is_empty());
+        assert!(columns.is_empty());
+        assert_eq!(columns.view_box(), "0 0 600 220");
+
+        let rows = BarChart::rows(&[], Some(0));
+        assert!(rows.bars.is_empty());
+        assert!(rows.is_empty());
+        assert_eq!(rows.height, ROW_HEIGHT + 4);
+    }
+
+    #[test]
+    fn only_zeros() {
+        let chart = BarChart::rows(&values(&[("Fauré", 0), ("Bach", 0)]), Some(0));
+        assert!(chart.is_empty());
+        assert!(chart.bars.iter().all(|bar| bar.width == 0));
+        assert_eq!(chart.bars[0].value_text, "0");
+
+        let chart = BarChart::columns(&values(&[("2023-06", 0)]));
+        assert!(chart.is_empty());
+        assert_eq!(chart.bars[0].height, 0);
+        assert_eq!(chart.bars[0].y, COLUMNS_TOP + COLUMNS_HEIGHT);
+    }
+
+    #[test]
+    fn bars_scaled_on_the_highest_value() {
+        let chart = BarChart::columns(&values(&[("06", 4), ("07", 2), ("08", 1), ("09", 0)]));
+        assert!(!chart.is_empty());
+        let heights: Vec<i32> = chart.bars.iter().map(|bar| bar.height).collect();
+        assert_eq!(heights, vec![180, 90, 45, 0]);
+        // one slot of 150 per bar, the bar is 70 % of it and centred
+        assert_eq!(chart.bars[1].x, 150 + 22);
+        assert_eq!(chart.bars[1].width, 105);
+        assert_eq!(
+            chart.bars[1].y + chart.bars[1].height,
+            COLUMNS_TOP + COLUMNS_HEIGHT
+        );
+
+        let chart = BarChart::rows(&values(&[("Bach", 10), ("Fauré", 5)]), None);
+        assert_eq!(chart.bars[0].width, 320);
+        assert_eq!(chart.bars[1].width, 160);
+        assert_eq!(chart.bars[1].y, ROW_HEIGHT + 2);
+        assert_eq!(chart.height, 2 * ROW_HEIGHT + 4);
+    }
+
+    #[test]
+    fn share_of_the_total() {
+        let chart = BarChart::rows(&values(&[("Sacré", 1), ("Noël", 2)]), Some(3));
+        assert_eq!(chart.bars[0].value_text, "1 (33 %)");
+        assert_eq!(chart.bars[1].value_text, "2 (67 %)");
+        assert_eq!(share(1, 4), 25.0);
+    }
+
+    #[test]
+    fn long_labels_are_cut() {
+        assert_eq!(shorten("Requiem", 30), "Requiem");
+        assert_eq!(shorten("Gabriel Fauré", 13), "Gabriel Fauré");
+        assert_eq!(shorten("Gabriel Fauré", 8), "Gabriel…");
+
+        let label = "Johann Sebastian Bach (arrangement pour chœur)";
+        let chart = BarChart::rows(&values(&[(label, 1)]), None);
+        assert_eq!(chart.bars[0].label, label);
+        assert_eq!(chart.bars[0].short_label.chars().count(), ROW_LABEL_CHARS);
+        assert!(chart.bars[0].short_label.ends_with('…'));
+    }
+}
@@ -0,0 +1,43 @@
+//! src/models/tag.rs
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+///
+/// A free-form tag of the partitions ("Noël", "Gospel", ...)
+/// a partition has one genre but can have several tags
+///
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, FromRow)]
+pub struct Tag {
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    pub name: String,
+}
+
+///
+/// A tag of a partition
+/// used to show the tags next to the partitions
+///
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, FromRow)]
+pub struct PartitionTag {
+    pub partition_id: i32,
+    pub tag_id: i32,
+    pub name: String,
+}
+
+///
+/// Returns the tag names of a text field : "Noël, Gospel" -> ["Noël", "Gospel"]
+/// the empty names and the duplicates (case insensitive) are removed
+///
+pub fn parse_tag_names(text: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for name in text.split(',').map(str::trim) {
+        let is_new = !names
+            .iter()
+            .any(|n| n.to_lowercase() == name.to_lowercase());
+        if !name.is_empty() && is_new {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
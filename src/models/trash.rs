@@ -0,0 +1,40 @@
+//! src/models/trash.rs
+//!
+//! Trash of the catalogue : the partitions, musicians and genres deleted
+//! (soft deletion) that can still be restored
+//!
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+use crate::models::audit::AuditEntity;
+
+///
+/// # TrashItem
+/// A record in the trash
+///
+/// * entity : the kind of record (partition, person, genre)
+/// * name : the title of the partition or the name of the musician / genre
+/// * deleted_at : when the record was deleted
+///
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct TrashItem {
+    pub entity: AuditEntity,
+    pub id: i32,
+    pub name: String,
+    pub deleted_at: DateTime<Utc>,
+}
+
+impl TrashItem {
+    pub fn deleted_text(&self) -> String {
+        self.deleted_at.format("%d/%m/%Y %H:%M").to_string()
+    }
+
+    /// the day the record will be purged automatically
+    pub fn purge_text(&self, retention_days: i64) -> String {
+        (self.deleted_at + Duration::days(retention_days))
+            .format("%d/%m/%Y")
+            .to_string()
+    }
+}
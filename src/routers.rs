@@ -1,31 +1,82 @@
 //! /src/routers.rs
 
 use crate::authentication::auth_layer;
+use crate::handlers::api_v1_handlers::{
+    api_create_genre_hdl, api_create_partition_hdl, api_create_person_hdl, api_delete_genre_hdl,
+    api_delete_partition_hdl, api_delete_person_hdl, api_get_genre_hdl, api_get_partition_hdl,
+    api_get_person_hdl, api_list_genres_hdl, api_list_partitions_hdl, api_list_persons_hdl,
+    api_update_genre_hdl, api_update_partition_hdl, api_update_person_hdl,
+};
+use crate::handlers::audit_handlers::audit_askama_hdl;
+use crate::handlers::browse_handlers::browse_partitions_hdl;
+use crate::handlers::collections_handlers::{
+    add_collection_partition_hdl, collection_askama_hdl, create_collection_hdl,
+    delete_collection_hdl, remove_collection_partition_hdl, toggle_favourite_hdl,
+    update_collection_hdl,
+};
+use crate::handlers::copies_handlers::{
+    add_copies_hdl, audit_report_askama_hdl, close_audit_hdl, delete_copy_hdl,
+    inventory_askama_hdl, mark_missing_hdl, mark_seen_hdl, partition_copies_askama_hdl,
+    start_audit_hdl, update_copy_hdl,
+};
+use crate::handlers::deletion_handlers::{preview_delete_genre_hdl, preview_delete_person_hdl};
+use crate::handlers::duplicates_handlers::{duplicates_askama_hdl, merge_hdl};
+use crate::handlers::events_handlers::{
+    calendar_askama_hdl, calendar_feed_hdl, create_event_hdl, delete_event_hdl,
+    event_askama_hdl, renew_calendar_token_hdl, update_event_hdl,
+};
 use crate::handlers::genres_handlers::{
-    create_genre_hdl, delete_genre_hdl, find_genre_by_name_hdl, list_genres_askama_hdl,
-    manage_genres_askama_hdl, update_genre_hdl,
+    create_genre_hdl, delete_genre_hdl, export_genres_hdl, find_genre_by_name_hdl,
+    list_genres_askama_hdl, manage_genres_askama_hdl, move_genre_hdl, update_genre_hdl,
 };
 use crate::handlers::get_me_hld::get_me_hdl;
+use crate::handlers::loans_handlers::{
+    create_loan_hdl, manage_loans_askama_hdl, overdue_loans_hdl, partition_loans_askama_hdl,
+    return_loan_hdl,
+};
 use crate::handlers::login_handlers::{login_form_askama_hdl, post_login_hdl};
 use crate::handlers::logout_handlers::{logout_handler, logout_page};
 use crate::handlers::musicians_handlers::{
-    create_person_hdl, delete_person_hdl, find_person_by_name_hdl, list_persons_askama_hdl,
-    manage_persons_askama_hdl, update_person_hdl,
+    create_person_hdl, delete_person_hdl, export_persons_hdl, find_person_by_name_hdl,
+    list_persons_askama_hdl, manage_persons_askama_hdl, person_askama_hdl, update_person_hdl,
+    update_person_profile_hdl,
+};
+use crate::handlers::partition_files_handlers::{
+    delete_partition_file_hdl, download_partition_file_hdl, upload_partition_files_hdl,
+};
+use crate::handlers::partition_history_handlers::{
+    partition_history_askama_hdl, revert_partition_hdl,
+};
+use crate::handlers::partition_import_handlers::{
+    confirm_import_hdl, import_partitions_askama_hdl, preview_import_hdl,
 };
 use crate::handlers::partitions_handlers::{
-    create_partition_hdl, delete_partition_hdl, find_partition_author_hdl,
+    create_partition_hdl, delete_partition_hdl, export_partitions_hdl, find_partition_author_hdl,
     find_partition_genre_hdl, find_partition_title_hdl, manage_partitions_hdl,
-    print_list_partitions_hdl, update_partition_hdl,
+    print_list_partitions_hdl, print_pdf_partitions_hdl, search_partitions_hdl,
+    update_partition_hdl,
+};
+use crate::handlers::setlists_handlers::{
+    add_setlist_item_hdl, create_setlist_hdl, delete_setlist_hdl, manage_setlists_askama_hdl,
+    move_setlist_item_hdl, remove_setlist_item_hdl, setlist_askama_hdl, setlist_programme_hdl,
+    update_setlist_hdl, update_setlist_item_hdl,
 };
 use crate::handlers::signup_handlers::{post_signup_hdl, signup_form_askama_hdl};
+use crate::handlers::statistics_handlers::statistics_askama_hdl;
+use crate::handlers::tags_handlers::{
+    create_tag_hdl, delete_tag_hdl, find_tag_by_name_hdl, list_tags_askama_hdl,
+    manage_tags_askama_hdl, update_tag_hdl,
+};
+use crate::handlers::trash_handlers::{purge_trash_hdl, restore_trash_hdl, trash_askama_hdl};
 use crate::handlers::utils_handlers::{
     about_hdl, favicon, handler_404, hello_name_askama_hdl, list_users_askama_hdl,
     list_users_with_extension, start_hdl, welcome_hdl,
 };
 use crate::main_response_mapper;
 use crate::print_req_res::print_cookies_askama;
+use crate::storage::MAX_UPLOAD_SIZE;
 use crate::AppState;
-use axum::extract::MatchedPath;
+use axum::extract::{DefaultBodyLimit, MatchedPath};
 use axum::http::Request;
 use axum::routing::{get, post};
 use axum::{middleware, Router};
@@ -65,6 +116,16 @@ pub fn create_routers(app_state: AppState) -> Router {
     //
     let admin_routes = Router::new()
         .route("/users", get(list_users_with_extension)) // the easiest way
+        .route("/loans/overdue", get(overdue_loans_hdl))
+        .route("/audit", get(audit_askama_hdl))
+        // suspected duplicates of the catalogue
+        .route("/duplicates", get(duplicates_askama_hdl))
+        // merge of two musicians or two genres
+        .route("/merge/:entity", post(merge_hdl))
+        // deleted partitions, musicians and genres : restore or purge
+        .route("/trash", get(trash_askama_hdl))
+        .route("/trash/:entity/:id/restore", post(restore_trash_hdl))
+        .route("/trash/:entity/:id/purge", post(purge_trash_hdl))
         // the less easy way :
         //.route("/users", get(list_users_askama_hdl))
         //.route_layer(middleware::from_fn_with_state(
@@ -101,31 +162,162 @@ pub fn create_routers(app_state: AppState) -> Router {
     let persons_routes = Router::new()
         .route("/", get(manage_persons_askama_hdl))
         .route("/add", post(create_person_hdl))
-        .route("/delete/:id", post(delete_person_hdl))
-        .route("/:id", post(update_person_hdl))
+        .route(
+            "/delete/:id",
+            get(preview_delete_person_hdl).post(delete_person_hdl),
+        )
+        .route("/:id", get(person_askama_hdl).post(update_person_hdl))
+        .route("/:id/profile", post(update_person_profile_hdl))
         .route("/print", get(list_persons_askama_hdl))
+        .route("/export", get(export_persons_hdl))
         .route("/find", post(find_person_by_name_hdl));
 
     let genres_routes = Router::new()
         .route("/", get(manage_genres_askama_hdl))
         .route("/add", post(create_genre_hdl))
-        .route("/delete/:id", post(delete_genre_hdl))
+        .route(
+            "/delete/:id",
+            get(preview_delete_genre_hdl).post(delete_genre_hdl),
+        )
         .route("/:id", post(update_genre_hdl))
+        .route("/:id/parent", post(move_genre_hdl))
         .route("/print", get(list_genres_askama_hdl))
+        .route("/export", get(export_genres_hdl))
         .route("/find", post(find_genre_by_name_hdl));
 
+    let tags_routes = Router::new()
+        .route("/", get(manage_tags_askama_hdl))
+        .route("/add", post(create_tag_hdl))
+        .route("/delete/:id", post(delete_tag_hdl))
+        .route("/:id", post(update_tag_hdl))
+        .route("/print", get(list_tags_askama_hdl))
+        .route("/find", post(find_tag_by_name_hdl));
+
     let partitions_routes = Router::new()
         .route("/", get(manage_partitions_hdl))
         .route("/add", post(create_partition_hdl))
         .route("/delete/:id", post(delete_partition_hdl))
         .route("/:id", post(update_partition_hdl))
         .route("/print", get(print_list_partitions_hdl))
+        // PDF catalogue grouped by genre or composer with the criteria of the search
+        .route("/print.pdf", get(print_pdf_partitions_hdl))
+        // export in CSV or JSON with the criteria of the search (streamed)
+        .route("/export", get(export_partitions_hdl))
         .route("/find/title", post(find_partition_title_hdl))
         .route("/find/author", post(find_partition_author_hdl))
-        .route("/find/genre", post(find_partition_genre_hdl));
+        .route("/find/genre", post(find_partition_genre_hdl))
+        .route("/search", get(search_partitions_hdl))
+        .route("/browse", get(browse_partitions_hdl))
+        // bulk import from a CSV file : upload and dry-run, then confirmation
+        .route(
+            "/import",
+            get(import_partitions_askama_hdl)
+                .post(preview_import_hdl)
+                .layer(DefaultBodyLimit::max(MAX_UPLOAD_SIZE)),
+        )
+        .route(
+            "/import/confirm",
+            post(confirm_import_hdl).layer(DefaultBodyLimit::max(MAX_UPLOAD_SIZE)),
+        )
+        // files attached to a partition (PDF, MusicXML, images)
+        .route(
+            "/:id/files",
+            post(upload_partition_files_hdl).layer(DefaultBodyLimit::max(MAX_UPLOAD_SIZE)),
+        )
+        .route("/:id/files/:file_id", get(download_partition_file_hdl))
+        // revision history : versions, differences and revert
+        .route("/:id/history", get(partition_history_askama_hdl))
+        .route("/:id/history/:number/revert", post(revert_partition_hdl))
+        .route("/:id/files/:file_id/delete", post(delete_partition_file_hdl));
+
+    // loans of the copies of the partitions to the users
+    let loans_routes = Router::new()
+        .route("/", get(manage_loans_askama_hdl))
+        .route("/add", post(create_loan_hdl))
+        .route("/:id/return", post(return_loan_hdl))
+        .route("/partition/:id", get(partition_loans_askama_hdl));
+
+    // printed copies of the partitions
+    let copies_routes = Router::new()
+        .route("/partition/:id", get(partition_copies_askama_hdl))
+        .route("/partition/:id/add", post(add_copies_hdl))
+        .route("/partition/:partition_id/:id", post(update_copy_hdl))
+        .route("/partition/:partition_id/:id/delete", post(delete_copy_hdl));
+
+    // inventory audits of the copies
+    let inventory_routes = Router::new()
+        .route("/", get(inventory_askama_hdl))
+        .route("/start", post(start_audit_hdl))
+        .route("/seen", post(mark_seen_hdl))
+        .route("/close", post(close_audit_hdl))
+        .route("/:id", get(audit_report_askama_hdl))
+        .route("/:id/missing", post(mark_missing_hdl));
+
+    // setlists : the partitions played in a concert, in order
+    let setlists_routes = Router::new()
+        .route("/", get(manage_setlists_askama_hdl))
+        .route("/add", post(create_setlist_hdl))
+        .route("/:id", get(setlist_askama_hdl).post(update_setlist_hdl))
+        .route("/delete/:id", post(delete_setlist_hdl))
+        .route("/:id/programme", get(setlist_programme_hdl))
+        .route("/:id/items", post(add_setlist_item_hdl))
+        .route("/:id/items/:item_id", post(update_setlist_item_hdl))
+        .route("/:id/items/:item_id/move", post(move_setlist_item_hdl))
+        .route("/:id/items/:item_id/delete", post(remove_setlist_item_hdl));
+
+    // rehearsals and concerts : monthly calendar
+    let events_routes = Router::new()
+        .route("/", get(calendar_askama_hdl))
+        .route("/add", post(create_event_hdl))
+        .route("/:id", get(event_askama_hdl).post(update_event_hdl))
+        .route("/delete/:id", post(delete_event_hdl));
+
+    // personal collections of partitions of the users (read-only when shared)
+    let collections_routes = Router::new()
+        .route("/add", post(create_collection_hdl))
+        .route("/:id", get(collection_askama_hdl).post(update_collection_hdl))
+        .route("/delete/:id", post(delete_collection_hdl))
+        .route("/partitions", post(add_collection_partition_hdl))
+        .route(
+            "/:id/partitions/:partition_id/delete",
+            post(remove_collection_partition_hdl),
+        );
 
     let welcome_route = Router::new().route("/", get(welcome_hdl));
 
+    // JSON REST API (version 1) for the catalogue
+    // same data as the persons, genres and partitions routes
+    // but JSON in and out, with JSON error bodies
+    // the route "/partitions" correspond to "/api/v1/partitions"
+    let api_v1_routes = Router::new()
+        .route(
+            "/partitions",
+            get(api_list_partitions_hdl).post(api_create_partition_hdl),
+        )
+        .route(
+            "/partitions/:id",
+            get(api_get_partition_hdl)
+                .put(api_update_partition_hdl)
+                .delete(api_delete_partition_hdl),
+        )
+        .route(
+            "/persons",
+            get(api_list_persons_hdl).post(api_create_person_hdl),
+        )
+        .route(
+            "/persons/:id",
+            get(api_get_person_hdl)
+                .put(api_update_person_hdl)
+                .delete(api_delete_person_hdl),
+        )
+        .route("/genres", get(api_list_genres_hdl).post(api_create_genre_hdl))
+        .route(
+            "/genres/:id",
+            get(api_get_genre_hdl)
+                .put(api_update_genre_hdl)
+                .delete(api_delete_genre_hdl),
+        );
+
     // api routes only for logged users
     // whatever their role.
     // the logout route is here (one must be logged in to logout)
@@ -134,9 +326,20 @@ pub fn create_routers(app_state: AppState) -> Router {
         .nest("/welcome", welcome_route)
         .nest("/persons", persons_routes)
         .nest("/genres", genres_routes)
+        .nest("/tags", tags_routes)
         .nest("/partitions", partitions_routes)
+        .nest("/loans", loans_routes)
+        .nest("/copies", copies_routes)
+        .nest("/inventory", inventory_routes)
+        .nest("/setlists", setlists_routes)
+        .nest("/events", events_routes)
+        .nest("/collections", collections_routes)
+        .nest("/v1", api_v1_routes)
         .route("/logout", get(logout_page).post(logout_handler))
         .route("/me", get(get_me_hdl))
+        .route("/statistics", get(statistics_askama_hdl))
+        .route("/me/calendar", post(renew_calendar_token_hdl))
+        .route("/me/favourites/:partition_id", post(toggle_favourite_hdl))
         .route_layer(middleware::from_fn_with_state(
             app_state.clone(),
             auth_layer::auth,
@@ -153,6 +356,8 @@ pub fn create_routers(app_state: AppState) -> Router {
         .nest("/hello", hello_routes)
         .nest("/admin", admin_routes)
         .route("/favicon.png", get(favicon))
+        // iCalendar feed of a user : the secret token of the user replaces the auth_token cookie
+        .route("/calendar/:file", get(calendar_feed_hdl))
         .fallback(handler_404)
         .layer(
             ServiceBuilder::new()
@@ -0,0 +1,108 @@
+//! src/storage.rs
+//!
+//! Storage of the partition files (PDF, MusicXML, images) on the local filesystem
+//!
+//! The files are stored under the root directory given by the configuration
+//! (PARTITION_FILES_ROOT in the .env file) :
+//! <root>/<partition_id>/<stored_name>
+//! the stored name is a generated uuid with the original extension,
+//! so two files with the same name can be attached to the same partition.
+//!
+
+use sha2::{Digest, Sha256};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+/// Maximum size of an uploaded file (20 Mo)
+pub const MAX_UPLOAD_SIZE: usize = 20 * 1024 * 1024;
+
+///
+/// Returns the MIME type of an accepted file based on its extension
+/// or None if the file type is not accepted
+///
+/// The browsers send 'application/octet-stream' for MusicXML files,
+/// that's why the extension is used and not the content type of the request.
+///
+pub fn mime_type_from_file_name(file_name: &str) -> Option<&'static str> {
+    let extension = file_name.rsplit_once('.')?.1.to_lowercase();
+    match extension.as_str() {
+        "pdf" => Some("application/pdf"),
+        "musicxml" => Some("application/vnd.recordare.musicxml+xml"),
+        "mxl" => Some("application/vnd.recordare.musicxml"),
+        "xml" => Some("application/xml"),
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "tif" | "tiff" => Some("image/tiff"),
+        _ => None,
+    }
+}
+
+///
+/// Returns the SHA-256 checksum of the content in hexadecimal
+///
+pub fn checksum(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+///
+/// Generates a unique name to store the file on the disk
+/// keeping the extension of the original file
+///
+pub fn stored_name_for(file_name: &str) -> String {
+    match file_name.rsplit_once('.') {
+        Some((_, extension)) => format!("{}.{}", uuid::Uuid::new_v4(), extension.to_lowercase()),
+        None => uuid::Uuid::new_v4().to_string(),
+    }
+}
+
+fn partition_dir(root: &str, partition_id: i32) -> PathBuf {
+    PathBuf::from(root).join(partition_id.to_string())
+}
+
+///
+/// Writes the content of a file in the directory of the partition
+///
+pub async fn save_file(
+    root: &str,
+    partition_id: i32,
+    stored_name: &str,
+    bytes: &[u8],
+) -> std::io::Result<()> {
+    let dir = partition_dir(root, partition_id);
+    tokio::fs::create_dir_all(&dir).await?;
+    tokio::fs::write(dir.join(stored_name), bytes).await
+}
+
+///
+/// Reads the content of a stored file
+///
+pub async fn read_file(
+    root: &str,
+    partition_id: i32,
+    stored_name: &str,
+) -> std::io::Result<Vec<u8>> {
+    tokio::fs::read(partition_dir(root, partition_id).join(stored_name)).await
+}
+
+///
+/// Removes a stored file
+/// a file that is already missing on the disk is not an error
+///
+pub async fn remove_file(root: &str, partition_id: i32, stored_name: &str) -> std::io::Result<()> {
+    match tokio::fs::remove_file(partition_dir(root, partition_id).join(stored_name)).await {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+///
+/// Removes the directory of a partition with all its files
+/// used when the partition is deleted
+///
+pub async fn remove_partition_dir(root: &str, partition_id: i32) -> std::io::Result<()> {
+    match tokio::fs::remove_dir_all(partition_dir(root, partition_id)).await {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}